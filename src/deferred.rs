@@ -0,0 +1,124 @@
+//! Deferred debriefing
+//!
+//! Slack gives slash commands 3 seconds to respond, which isn't enough time
+//! to hear back from Jira and PagerDuty. The gateway acknowledges the command
+//! right away and asynchronously re-invokes this function with a copy of the
+//! original (still signed) request, flagged so that it does the actual work.
+//! The flag is our countersignature of the body, so a replayed request can't
+//! claim to be a copy and skip straight past dedup, reuse and rate limiting.
+//! Outside of lambda, i.e. in server mode, the copy is handled in a thread and
+//! flagged in process instead.
+
+// Std lib
+use std::collections::HashMap;
+use std::env;
+use std::thread;
+
+// Third party
+use lambda_http::{Body, Request};
+use rusoto_core::Region;
+use rusoto_lambda::{InvocationRequest, Lambda, LambdaClient};
+use serde_json::Value;
+
+// Ours
+use standup::{log, slack};
+
+/// header countersigning a request as a deferred re-invocation
+pub const DEFERRED_HEADER: &str = "X-Standup-Deferred";
+
+/// extension marking a request as a deferred copy in server mode
+struct Deferred;
+
+/// returns true if this request should perform the debrief
+pub fn is_deferred(request: &Request, secret: &str) -> bool {
+    request.extensions().get::<Deferred>().is_some()
+        || slack::countersigned(
+            secret,
+            request
+                .headers()
+                .get(DEFERRED_HEADER)
+                .and_then(|value| value.to_str().ok()),
+            request.body().as_ref(),
+        )
+}
+
+/// asynchronously re-invokes the current function with a deferred copy of `request`
+pub fn defer(request: &Request, request_id: &str, secret: &str) -> Result<(), String> {
+    let function_name = match env::var("AWS_LAMBDA_FUNCTION_NAME") {
+        Ok(function_name) => function_name,
+        Err(_) => return spawn(request, request_id),
+    };
+    LambdaClient::new(Region::default())
+        .invoke(InvocationRequest {
+            function_name,
+            invocation_type: Some("Event".into()),
+            payload: Some(event(request, secret).to_string().into_bytes()),
+            ..Default::default()
+        })
+        .sync()
+        .map(|_| ())
+        .map_err(|err| format!("failed to defer debrief: {}", err))
+}
+
+/// handles a deferred copy of `request` in a thread of this process
+fn spawn(request: &Request, request_id: &str) -> Result<(), String> {
+    let mut deferred = Request::new(Body::from(request.body().as_ref()));
+    *deferred.method_mut() = request.method().clone();
+    *deferred.uri_mut() = request.uri().clone();
+    *deferred.headers_mut() = request.headers().clone();
+    deferred.headers_mut().remove(DEFERRED_HEADER);
+    deferred.extensions_mut().insert(Deferred);
+    let request_id = request_id.to_owned();
+    thread::Builder::new()
+        .name("deferred".into())
+        .spawn(move || {
+            if let Err(err) = ::respond(&deferred, &request_id) {
+                log::error("failed to debrief", json!({ "error": err }));
+            }
+        })
+        .map(|_| ())
+        .map_err(|err| format!("failed to defer debrief: {}", err))
+}
+
+/// re-serializes a request as an api gateway proxy event, countersigned
+fn event(request: &Request, secret: &str) -> Value {
+    let mut headers = request
+        .headers()
+        .iter()
+        // any flag the caller sent along is replaced with ours
+        .filter(|(name, _)| !name.as_str().eq_ignore_ascii_case(DEFERRED_HEADER))
+        .filter_map(|(name, value)| {
+            value
+                .to_str()
+                .ok()
+                .map(|value| (name.as_str().to_owned(), value.to_owned()))
+        })
+        .collect::<HashMap<_, _>>();
+    headers.insert(
+        DEFERRED_HEADER.into(),
+        slack::countersign(secret, request.body().as_ref()),
+    );
+    // only the shape of the request context matters to `lambda_http`
+    json!({
+        "path": request.uri().path(),
+        "httpMethod": request.method().as_str(),
+        "headers": headers,
+        "queryStringParameters": null,
+        "pathParameters": null,
+        "stageVariables": null,
+        "body": String::from_utf8_lossy(request.body().as_ref()),
+        "isBase64Encoded": false,
+        "requestContext": {
+            "accountId": "",
+            "resourceId": "",
+            "stage": "",
+            "requestId": "",
+            "resourcePath": request.uri().path(),
+            "httpMethod": request.method().as_str(),
+            "apiId": "",
+            "identity": {
+                "sourceIp": ""
+            }
+        }
+    })
+}
@@ -0,0 +1,109 @@
+//! Slack events
+//!
+//! Slack delivers subscribed events to the app's event request url as json,
+//! after verifying the url with a challenge. We only subscribe to direct
+//! messages, which answer check-in questions, see `standup::checkin`.
+//! Events slack redelivers share their `event_id`, see `standup::deliveries`.
+//!
+//! see https://api.slack.com/apis/connections/events-api
+
+// Third party
+use lambda_http::{Request, RequestExt};
+
+// Ours
+use standup::deliveries;
+
+/// header slack sets when redelivering an event we were slow to acknowledge
+const RETRY_HEADER: &str = "X-Slack-Retry-Num";
+
+/// An event api request
+/// only the fields we're using are represented
+#[derive(Deserialize, Debug)]
+struct Envelope {
+    #[serde(rename = "type")]
+    kind: String,
+    challenge: Option<String>,
+    team_id: Option<String>,
+    event_id: Option<String>,
+    event: Option<Event>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Event {
+    #[serde(rename = "type")]
+    kind: String,
+    channel_type: Option<String>,
+    user: Option<String>,
+    #[serde(default)]
+    text: String,
+    /// set for messages posted by bots, including our own questions
+    bot_id: Option<String>,
+    /// set for edits, deletions and other changes to messages
+    subtype: Option<String>,
+}
+
+/// What an event api request asked of us
+#[derive(Debug, PartialEq)]
+pub enum Received {
+    /// echo the challenge to verify the request url
+    Challenge(String),
+    /// a user's direct message to the bot, and the key its deliveries share
+    DirectMessage {
+        user: String,
+        text: String,
+        delivery: Option<String>,
+    },
+    /// events we don't handle or have already handled
+    Ignored,
+}
+
+/// returns what was received if this request is from the events api
+pub fn received(request: &Request) -> Option<Received> {
+    let envelope = request.payload::<Envelope>().ok().and_then(|json| json)?;
+    Some(match envelope {
+        Envelope {
+            ref kind,
+            challenge: Some(ref challenge),
+            ..
+        } if kind == "url_verification" => Received::Challenge(challenge.clone()),
+        Envelope {
+            ref kind,
+            event: Some(ref event),
+            ..
+        } if kind == "event_callback" => direct_message(
+            event,
+            match (&envelope.team_id, &envelope.event_id) {
+                (&Some(ref team_id), &Some(ref event_id)) => {
+                    Some(deliveries::key(team_id, event_id))
+                }
+                _ => None,
+            },
+        ),
+        _ => Received::Ignored,
+    })
+}
+
+/// returns true if slack is redelivering an event
+pub fn is_retry(request: &Request) -> bool {
+    request.headers().contains_key(RETRY_HEADER)
+}
+
+/// a direct message from a person, rather than a bot or an edit
+fn direct_message(event: &Event, delivery: Option<String>) -> Received {
+    match (
+        event.kind.as_str(),
+        event.channel_type.as_ref(),
+        &event.user,
+    ) {
+        ("message", Some(channel_type), &Some(ref user))
+            if channel_type == "im" && event.bot_id.is_none() && event.subtype.is_none() =>
+        {
+            Received::DirectMessage {
+                user: user.clone(),
+                text: event.text.clone(),
+                delivery,
+            }
+        }
+        _ => Received::Ignored,
+    }
+}
@@ -0,0 +1,75 @@
+//! Interactive standups
+//!
+//! Slack delivers button clicks to the app's interactivity request url as a
+//! form with a json `payload`. Our buttons carry the arguments of the command
+//! they re-run, so clicks are handled much like slash commands.
+//!
+//! see https://api.slack.com/reference/interaction-payloads/block-actions
+
+// Third party
+use lambda_http::{Request, RequestExt};
+use serde_json;
+
+// Ours
+use standup::log;
+use CommandRequest;
+
+#[derive(Deserialize, Debug)]
+struct InteractionRequest {
+    payload: String,
+}
+
+/// A `block_actions` payload
+/// only the fields we're using are represented
+#[derive(Deserialize, Debug)]
+struct BlockActions {
+    #[serde(rename = "type")]
+    kind: String,
+    team: Id,
+    channel: Id,
+    user: Id,
+    response_url: String,
+    trigger_id: Option<String>,
+    actions: Vec<Action>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Id {
+    id: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct Action {
+    #[serde(default)]
+    value: String,
+}
+
+/// returns the command a button click asked to run, if this request is a button click
+pub fn command(request: &Request) -> Option<CommandRequest> {
+    let payload = request
+        .payload::<InteractionRequest>()
+        .ok()
+        .and_then(|form| form)?
+        .payload;
+    let actions = match serde_json::from_str::<BlockActions>(&payload) {
+        Ok(ref actions) if actions.kind != "block_actions" => return None,
+        Ok(actions) => actions,
+        Err(err) => {
+            log::warn(
+                "ignoring unknown interaction",
+                json!({ "error": err.to_string() }),
+            );
+            return None;
+        }
+    };
+    let text = actions.actions.into_iter().next()?.value;
+    Some(CommandRequest {
+        response_url: actions.response_url,
+        team_id: actions.team.id,
+        channel_id: actions.channel.id,
+        user_id: actions.user.id,
+        text,
+        trigger_id: actions.trigger_id,
+        interactive: true,
+    })
+}
@@ -0,0 +1,456 @@
+//! Lambda handler
+//!
+//! A thin adapter between api gateway, slack and the `standup` library, run
+//! by the lambda runtime as the function's `bootstrap`, or with the `server`
+//! feature as a plain http server, see `server`
+
+extern crate chrono;
+#[cfg(feature = "server")]
+extern crate futures;
+extern crate http;
+#[cfg(feature = "server")]
+extern crate hyper;
+extern crate lambda_http;
+extern crate lambda_runtime;
+extern crate rusoto_core;
+extern crate rusoto_lambda;
+extern crate rusoto_sqs;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate serde_json;
+extern crate standup;
+
+mod deferred;
+mod events;
+mod interaction;
+mod schedule;
+#[cfg(feature = "server")]
+mod server;
+// only queueing is needed in server mode
+#[cfg_attr(feature = "server", allow(dead_code))]
+mod worker;
+
+// Std lib
+use std::collections::BTreeMap;
+
+// Third party
+use chrono::Utc;
+use http::header::{HeaderValue, AUTHORIZATION, CONTENT_TYPE};
+use http::StatusCode;
+use lambda_http::{Body, Request, RequestExt, Response};
+#[cfg(not(feature = "server"))]
+use lambda_runtime::error::HandlerError;
+#[cfg(not(feature = "server"))]
+use lambda_runtime::Context;
+
+// Ours
+use events::Received;
+use schedule::Schedule;
+use standup::admin::{self, Subcommand};
+use standup::checkin::{self, Checkins};
+use standup::deliveries::{self, Deliveries};
+use standup::facilitator::{self, Rotations};
+use standup::limits::{self, Limits};
+use standup::sink::{Chat, Sink, Webhook};
+use standup::tenants::Tenants;
+use standup::{command, health, log, slack, Command, Config, Origin};
+
+/// path reporting which upstreams are failing, for monitoring, see `health`
+const HEALTH_PATH: &str = "/health";
+
+/// Slack request payload for commands
+/// only the fields we're using are represented
+/// more are availbale
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct CommandRequest {
+    response_url: String,
+    team_id: String,
+    channel_id: String,
+    user_id: String,
+    #[serde(default)]
+    text: String,
+    /// identifies the interaction, which redeliveries share
+    trigger_id: Option<String>,
+    /// set for button clicks on an interactive standup, see `interaction`
+    #[serde(skip)]
+    interactive: bool,
+}
+
+#[cfg(not(feature = "server"))]
+fn main() {
+    if worker::is_worker() {
+        lambda_runtime::start(worker::handler, None)
+    } else {
+        lambda_http::start(handler, None)
+    }
+}
+
+#[cfg(feature = "server")]
+fn main() {
+    server::serve()
+}
+
+#[cfg(not(feature = "server"))]
+fn handler(request: Request, context: Context) -> Result<Response<Body>, HandlerError> {
+    standup::trace::start(Some(&context.xray_trace_id));
+    respond(&request, &context.aws_request_id).map_err(|err| HandlerError::from(err.as_str()))
+}
+
+fn respond(request: &Request, request_id: &str) -> Result<Response<Body>, String> {
+    log::reset();
+    log::correlate(json!({ "request_id": request_id }));
+    let config = Config::from_env()?;
+    if request.uri().path() == HEALTH_PATH {
+        let authorization = request
+            .headers()
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok());
+        // unauthorized requests can't tell the route's there
+        if !health::authorized(
+            config.health_token.as_ref().map(String::as_str),
+            authorization,
+        ) {
+            return Ok(status(StatusCode::NOT_FOUND));
+        }
+        return Ok(health(&config));
+    }
+    if let Some(schedule) = schedule::scheduled(request) {
+        log::correlate(json!({ "schedule": format!("{:?}", schedule) }));
+        // check-ins are asked for and chased up over DM
+        if schedule == Schedule::Checkin || schedule == Schedule::CheckinReminder {
+            match Checkins::configured(&config) {
+                Some(checkins) => {
+                    let result = if schedule == Schedule::Checkin {
+                        checkin::ask(&config, &checkins)
+                    } else {
+                        checkin::remind(&config, &checkins)
+                    };
+                    if let Err(err) = result {
+                        log::error("failed to message check-ins", json!({ "error": err }));
+                    }
+                }
+                None => log::warn("skipping check-ins: not configured", json!({})),
+            }
+            return Ok(Response::new(Body::Empty));
+        }
+        match schedule::sink(&config, &schedule) {
+            Some((channel, sink)) => {
+                log::correlate(json!({ "channel_id": channel }));
+                let result = match schedule {
+                    Schedule::Daily => {
+                        let command = Command::all(&config.standup_sections);
+                        standup::run(config, command, channel, Origin::Scheduled, sink.as_ref())
+                    }
+                    Schedule::Weekly => standup::weekly(config, channel, sink.as_ref()),
+                    Schedule::Checkin | Schedule::CheckinReminder | Schedule::CheckinSummary => {
+                        match Checkins::configured(&config) {
+                            Some(checkins) => checkin::summarize(&config, &checkins, sink.as_ref()),
+                            None => Err("check-ins aren't configured".into()),
+                        }
+                    }
+                };
+                if let Err(err) = result {
+                    log::error("failed to debrief", json!({ "error": err }));
+                }
+            }
+            None => log::warn(
+                "skipping scheduled standup: no destination configured",
+                json!({}),
+            ),
+        }
+        return Ok(Response::new(Body::Empty));
+    }
+    let header = |name: &'static str| {
+        request
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+    };
+    if let Err(err) = slack::verify(
+        &config.slack_signing_secret,
+        header("X-Slack-Request-Timestamp"),
+        header("X-Slack-Signature"),
+        request.body().as_ref(),
+        Utc::now().timestamp(),
+    ) {
+        log::warn("rejecting request", json!({ "error": err.to_string() }));
+        return Ok(status(StatusCode::UNAUTHORIZED));
+    }
+    match events::received(request) {
+        Some(Received::Challenge(challenge)) => {
+            return Ok(Response::new(Body::from(challenge)));
+        }
+        Some(Received::DirectMessage {
+            user,
+            text,
+            delivery,
+        }) => {
+            log::correlate(json!({ "user_id": user }));
+            // without a table to claim it in, redeliveries are dropped outright
+            let first = delivery
+                .and_then(|key| claimed(&config, &key))
+                .unwrap_or_else(|| !events::is_retry(request));
+            if !first {
+                log::info("skipping redelivered event", json!({}));
+                return Ok(Response::new(Body::Empty));
+            }
+            if let Some(checkins) = Checkins::configured(&config) {
+                if let Err(err) = checkin::answer(&config, &checkins, &user, &text) {
+                    log::error("failed to record check-in", json!({ "error": err }));
+                }
+            }
+            return Ok(Response::new(Body::Empty));
+        }
+        Some(Received::Ignored) => return Ok(Response::new(Body::Empty)),
+        None => (),
+    }
+    let payload = match interaction::command(request) {
+        Some(payload) => payload,
+        None => match request
+            .payload::<CommandRequest>()
+            .map_err(|err| err.to_string())?
+        {
+            Some(payload) => payload,
+            None => return Ok(status(StatusCode::BAD_REQUEST)),
+        },
+    };
+    log::correlate(json!({
+        "team_id": payload.team_id,
+        "channel_id": payload.channel_id,
+        "user_id": payload.user_id,
+    }));
+    let config = channel_config(config, &payload)?;
+    if !slack::authorized(
+        &config.slack_allowed_channels,
+        &config.slack_allowed_users,
+        &payload.channel_id,
+        &payload.user_id,
+    ) {
+        log::warn("rejecting unauthorized command", json!({}));
+        return Ok(ephemeral("🙅 You're not authorized to run standups here"));
+    }
+    if admin::is_config(&payload.text) {
+        let subcommand = Subcommand::parse(&payload.text);
+        if !subcommand.permitted(&config.slack_admin_users, &payload.user_id) {
+            log::warn("rejecting unauthorized config change", json!({}));
+            return Ok(ephemeral(
+                "🙅 Only admins can change this channel's configuration",
+            ));
+        }
+        return Ok(ephemeral(&match config.channel_config_table.clone() {
+            Some(table) => admin::run(
+                &Tenants::new(table),
+                &payload.team_id,
+                &payload.channel_id,
+                &subcommand,
+            ),
+            None => "Channel configuration isn't enabled".into(),
+        }));
+    }
+    let command = match Command::parse(&payload.text, &config.standup_sections) {
+        Ok(command) => command,
+        Err(err) => {
+            return Ok(ephemeral(&format!(
+                "{}\n{}",
+                err,
+                command::usage(&config.standup_sections)
+            )))
+        }
+    };
+    if let Some(ooo) = command.ooo {
+        return Ok(ephemeral(&match Checkins::configured(&config) {
+            Some(checkins) => checkin::mark(&config, &checkins, &payload.user_id, ooo)
+                .unwrap_or_else(|err| format!("😵 {}", err)),
+            None => "Check-ins aren't enabled".into(),
+        }));
+    }
+    if command.skip_me {
+        return Ok(ephemeral(&match Rotations::configured(&config) {
+            Some(rotations) => facilitator::skip(
+                &config,
+                &rotations,
+                &payload.channel_id,
+                &payload.user_id,
+                Utc::now()
+                    .with_timezone(&config.timezone)
+                    .date()
+                    .naive_local(),
+            )
+            .unwrap_or_else(|err| format!("😵 {}", err)),
+            None => "Facilitator rotation isn't enabled".into(),
+        }));
+    }
+    if deferred::is_deferred(request, &config.slack_signing_secret) {
+        if let Err(err) = debrief(config, command, payload) {
+            log::error("failed to debrief", json!({ "error": err }));
+        }
+        return Ok(Response::new(Body::Empty));
+    }
+    let key = payload
+        .trigger_id
+        .as_ref()
+        .map(|trigger_id| deliveries::key(&payload.team_id, trigger_id));
+    if key.and_then(|key| claimed(&config, &key)) == Some(false) {
+        log::info("skipping redelivered request", json!({}));
+        return Ok(Response::new(Body::Empty));
+    }
+    let rerun = command.fresh
+        || command.preview
+        || command.weekly
+        || command.stats
+        || command.release_notes.is_some()
+        || payload.interactive;
+    if !rerun {
+        if let Some(text) = limits::reusable(&config, &payload.channel_id, &command, Utc::now()) {
+            return Ok(ephemeral(&text));
+        }
+    }
+    // previews are only shown to whoever ran them
+    if let Some(limits) = Limits::configured(&config).filter(|_| !command.preview) {
+        match limits.acquire(&payload.team_id, &payload.channel_id, Utc::now()) {
+            Ok(true) => (),
+            Ok(false) => {
+                log::info("rate limiting standup", json!({}));
+                return Ok(ephemeral(&limits::refusal(
+                    &config,
+                    &payload.channel_id,
+                    Utc::now(),
+                )));
+            }
+            Err(err) => log::error("failed to rate limit standup", json!({ "error": err })),
+        }
+    }
+    let deferral = match config.job_queue_url.clone() {
+        Some(queue_url) => worker::enqueue(&queue_url, &payload),
+        None => deferred::defer(request, request_id, &config.slack_signing_secret),
+    };
+    if let Err(err) = deferral {
+        log::error("failed to defer", json!({ "error": err }));
+        return Ok(ephemeral("😵 Failed to start your standup, try again"));
+    }
+    if payload.interactive {
+        // the clicked standup is replaced once the new one is ready
+        return Ok(Response::new(Body::Empty));
+    }
+    Ok(ephemeral(match command.release_notes {
+        Some(_) => "Gathering release notes…",
+        None => "Building your standup…",
+    }))
+}
+
+/// the config of the channel a command was run in, with its overrides
+fn channel_config(config: Config, payload: &CommandRequest) -> Result<Config, String> {
+    Ok(match config.channel_config_table.clone() {
+        Some(table) => match Tenants::new(table).overrides(&payload.team_id, &payload.channel_id) {
+            Ok(ref overrides) if overrides.is_empty() => config,
+            Ok(overrides) => Config::with_overrides(&overrides)?,
+            Err(err) => {
+                log::error("failed to load channel config", json!({ "error": err }));
+                config
+            }
+        },
+        None => config,
+    })
+}
+
+/// runs a command, delivering the result to where it was run
+fn debrief(config: Config, command: Command, payload: CommandRequest) -> Result<(), String> {
+    // threads need a message ts, which only chat.postMessage returns.
+    // webhooks can't thread, so previews and refreshes are delivered whole
+    let sink: Box<dyn Sink> = match config.slack_bot_token.clone() {
+        _ if command.preview => Box::new(Webhook::ephemeral(payload.response_url)),
+        _ if payload.interactive && !command.post => {
+            Box::new(Webhook::replacing(payload.response_url))
+        }
+        Some(ref token) if config.slack_threaded => Box::new(Chat::new(
+            token.clone(),
+            payload.channel_id.clone(),
+            config.http_client(),
+        )),
+        _ if command.post => Box::new(Webhook::posting(payload.response_url)),
+        _ => Box::new(Webhook::new(payload.response_url)),
+    };
+    if let Some(ref version) = command.release_notes {
+        standup::release_notes(config, version, sink.as_ref())
+    } else if command.weekly {
+        standup::weekly(config, payload.channel_id, sink.as_ref())
+    } else if command.stats {
+        standup::stats(config, payload.channel_id, sink.as_ref())
+    } else {
+        standup::run(
+            config,
+            command,
+            payload.channel_id,
+            Origin::Requested,
+            sink.as_ref(),
+        )
+    }
+}
+
+/// claims a delivery, returning whether it's the first of its request, when
+/// there's a table to claim it in
+fn claimed(config: &Config, key: &str) -> Option<bool> {
+    let deliveries = Deliveries::configured(config)?;
+    Some(deliveries.claim(key).unwrap_or_else(|err| {
+        // better a duplicate than nothing at all
+        log::error("failed to deduplicate request", json!({ "error": err }));
+        true
+    }))
+}
+
+/// which upstreams are failing, unavailable if any are. why they're failing
+/// is logged rather than returned
+fn health(config: &Config) -> Response<Body> {
+    let checks = health::check(config);
+    let healthy = checks.iter().all(|check| check.result.is_ok());
+    let upstreams = checks
+        .into_iter()
+        .map(|check| {
+            let state = match check.result {
+                Ok(()) => "ok",
+                Err(err) => {
+                    log::warn(
+                        "upstream unhealthy",
+                        json!({ "upstream": check.upstream, "error": err }),
+                    );
+                    "failing"
+                }
+            };
+            (check.upstream.to_lowercase(), state)
+        })
+        .collect::<BTreeMap<_, _>>();
+    let mut response = Response::new(Body::from(
+        json!({ "healthy": healthy, "upstreams": upstreams }).to_string(),
+    ));
+    if !healthy {
+        *response.status_mut() = StatusCode::SERVICE_UNAVAILABLE;
+    }
+    response
+        .headers_mut()
+        .insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    response
+}
+
+/// an empty response with an error status
+fn status(status: StatusCode) -> Response<Body> {
+    let mut response = Response::new(Body::Empty);
+    *response.status_mut() = status;
+    response
+}
+
+/// a slash command response only visible to the invoking user
+fn ephemeral(text: &str) -> Response<Body> {
+    let mut response = Response::new(Body::from(
+        json!({
+            "response_type": "ephemeral",
+            "text": text
+        })
+        .to_string(),
+    ));
+    response
+        .headers_mut()
+        .insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    response
+}
@@ -0,0 +1,137 @@
+//! Scheduled standups
+//!
+//! `lambda_http` only understands api gateway proxy events, so the schedule
+//! rules in serverless.yml deliver a constant, proxy-shaped input addressed
+//! to one of the paths below. Api gateway only routes `/` to this function,
+//! so a request for these paths can only originate from a direct invocation.
+
+// Third party
+use chrono::Utc;
+use lambda_http::Request;
+
+// Ours
+use standup::config::{ConfluencePublishing, Destination};
+use standup::sink::{Chat, Confluence, Discord, Email, Fanout, Sink, Teams, Webhook};
+use standup::Config;
+
+const DAILY_PATH: &str = "/scheduled";
+const WEEKLY_PATH: &str = "/scheduled/weekly";
+const CHECKIN_PATH: &str = "/scheduled/checkin";
+const CHECKIN_REMINDER_PATH: &str = "/scheduled/checkin/reminder";
+const CHECKIN_SUMMARY_PATH: &str = "/scheduled/checkin/summary";
+
+/// history channel of standups posted to the configured webhook
+const SCHEDULED_CHANNEL: &str = "scheduled";
+
+/// What a schedule rule asked for
+#[derive(Debug, PartialEq)]
+pub enum Schedule {
+    /// the daily standup
+    Daily,
+    /// a digest of the week's standups
+    Weekly,
+    /// DMs asking for today's check-ins
+    Checkin,
+    /// reminders for those who haven't checked in yet
+    CheckinReminder,
+    /// a summary of today's check-ins
+    CheckinSummary,
+}
+
+/// returns the kind of run requested if this request was delivered by a schedule rule
+pub fn scheduled(request: &Request) -> Option<Schedule> {
+    match request.uri().path() {
+        DAILY_PATH => Some(Schedule::Daily),
+        WEEKLY_PATH => Some(Schedule::Weekly),
+        CHECKIN_PATH => Some(Schedule::Checkin),
+        CHECKIN_REMINDER_PATH => Some(Schedule::CheckinReminder),
+        CHECKIN_SUMMARY_PATH => Some(Schedule::CheckinSummary),
+        _ => None,
+    }
+}
+
+/// where scheduled standups are posted, and the history channel they're recorded in
+///
+/// Standups are also emailed when a sender and recipients are configured,
+/// and published to Confluence when `confluence_standups` is enabled.
+pub fn sink(config: &Config, schedule: &Schedule) -> Option<(String, Box<dyn Sink>)> {
+    let (channel, sink) = destination(config)?;
+    let mut sinks = vec![sink];
+    if let Some(sender) = config
+        .email_sender
+        .clone()
+        .filter(|_| !config.email_recipients.is_empty())
+    {
+        sinks.push(Box::new(Email::new(
+            sender,
+            config.email_recipients.clone(),
+        )));
+    }
+    if let Some(confluence) = confluence(config, schedule) {
+        sinks.push(Box::new(confluence));
+    }
+    if sinks.len() == 1 {
+        sinks.pop().map(|sink| (channel, sink))
+    } else {
+        Some((channel, Box::new(Fanout::new(sinks)) as Box<dyn Sink>))
+    }
+}
+
+/// Confluence, titled with the date so each day's page, or section of the running page, is distinct
+fn confluence(config: &Config, schedule: &Schedule) -> Option<Confluence> {
+    let title = format!(
+        "{} {}",
+        match *schedule {
+            Schedule::Daily => "Standup",
+            Schedule::Weekly => "Weekly digest",
+            Schedule::Checkin | Schedule::CheckinReminder | Schedule::CheckinSummary => {
+                "Check-ins"
+            }
+        },
+        Utc::now()
+            .with_timezone(&config.timezone)
+            .format("%Y-%m-%d")
+    );
+    let confluence = match config.confluence_standups {
+        ConfluencePublishing::Off => return None,
+        ConfluencePublishing::Append => {
+            Confluence::configured(config)?.appending_to(config.confluence_page_id.clone()?)
+        }
+        ConfluencePublishing::Pages => Confluence::configured(config)?,
+    };
+    Some(confluence.titled(title))
+}
+
+/// In slack, posting to `slack_channel` with the bot token is preferred over the webhook.
+fn destination(config: &Config) -> Option<(String, Box<dyn Sink>)> {
+    let client = config.http_client();
+    match config.standup_destination {
+        Destination::Slack => match (
+            config.slack_bot_token.clone(),
+            config.slack_channel.clone(),
+            config.slack_webhook_url.clone(),
+        ) {
+            (Some(token), Some(channel), _) => Some((
+                channel.clone(),
+                Box::new(Chat::new(token, channel, client)) as Box<dyn Sink>,
+            )),
+            (_, _, Some(url)) => Some((
+                SCHEDULED_CHANNEL.into(),
+                Box::new(Webhook::new(url)) as Box<dyn Sink>,
+            )),
+            _ => None,
+        },
+        Destination::Teams => config.teams_webhook_url.clone().map(|url| {
+            (
+                SCHEDULED_CHANNEL.into(),
+                Box::new(Teams::new(url, client)) as Box<dyn Sink>,
+            )
+        }),
+        Destination::Discord => config.discord_webhook_url.clone().map(|url| {
+            (
+                SCHEDULED_CHANNEL.into(),
+                Box::new(Discord::new(url, client)) as Box<dyn Sink>,
+            )
+        }),
+    }
+}
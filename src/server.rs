@@ -0,0 +1,84 @@
+//! Server mode
+//!
+//! Serves the slash command, interactivity and events endpoint over plain
+//! http, for running outside of lambda, i.e. in a container. Requests are
+//! handled just as they are on lambda, each in its own thread, since handling
+//! them blocks on upstreams. Only `/` and `/health` are served, so schedules,
+//! which lambda delivers to other paths, need running with the `standup`
+//! binary instead. Log correlation is kept per thread, so concurrent requests
+//! each log with their own.
+
+// Std lib
+use std::env;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+// Third party
+use futures::sync::oneshot;
+use futures::{Future, Stream};
+use http::StatusCode;
+use hyper::service::service_fn;
+use hyper::{self, Server};
+use lambda_http::{Body, Request};
+
+// Ours
+use standup::log;
+
+/// port served without a `PORT`
+const DEFAULT_PORT: u16 = 3000;
+
+/// identifies requests in logs, in place of lambda's request ids
+static REQUESTS: AtomicUsize = AtomicUsize::new(0);
+
+/// serves requests on `PORT` until the process is stopped
+pub fn serve() {
+    let port = env::var("PORT")
+        .ok()
+        .and_then(|port| port.parse().ok())
+        .unwrap_or(DEFAULT_PORT);
+    let addr = SocketAddr::from(([0, 0, 0, 0], port));
+    let server = Server::bind(&addr)
+        .serve(|| service_fn(handle))
+        .map_err(|err| log::error("server failed", json!({ "error": err.to_string() })));
+    log::info("serving", json!({ "port": port }));
+    hyper::rt::run(server);
+}
+
+fn handle(
+    request: hyper::Request<hyper::Body>,
+) -> Box<dyn Future<Item = hyper::Response<hyper::Body>, Error = hyper::Error> + Send> {
+    if request.uri().path() != "/" && request.uri().path() != ::HEALTH_PATH {
+        return Box::new(futures::future::ok(empty(StatusCode::NOT_FOUND)));
+    }
+    let (mut parts, body) = request.into_parts();
+    // deferred copies are only ever flagged in process here
+    parts.headers.remove(::deferred::DEFERRED_HEADER);
+    Box::new(body.concat2().and_then(move |body| {
+        let request = Request::from_parts(parts, Body::from(body.to_vec()));
+        let request_id = REQUESTS.fetch_add(1, Ordering::SeqCst).to_string();
+        let (sender, receiver) = oneshot::channel();
+        thread::spawn(move || {
+            let _ = sender.send(::respond(&request, &request_id));
+        });
+        receiver.then(|responded| {
+            Ok(match responded {
+                Ok(Ok(response)) => {
+                    let (parts, body) = response.into_parts();
+                    hyper::Response::from_parts(parts, hyper::Body::from(body.as_ref().to_vec()))
+                }
+                Ok(Err(err)) => {
+                    log::error("failed to respond", json!({ "error": err }));
+                    empty(StatusCode::INTERNAL_SERVER_ERROR)
+                }
+                Err(_) => empty(StatusCode::INTERNAL_SERVER_ERROR),
+            })
+        })
+    }))
+}
+
+fn empty(status: StatusCode) -> hyper::Response<hyper::Body> {
+    let mut response = hyper::Response::new(hyper::Body::empty());
+    *response.status_mut() = status;
+    response
+}
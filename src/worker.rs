@@ -0,0 +1,132 @@
+//! Queued debriefing
+//!
+//! With a `job_queue_url`, commands are queued in SQS rather than deferred
+//! to an async re-invocation, see `deferred`. Requests are verified before
+//! they're queued, so jobs carry the command rather than the signed request,
+//! and the channel it was run in, which keys its config. A worker function,
+//! marked by `STANDUP_WORKER`, consumes the queue. Jobs which can't be
+//! started are received again, and moved to the queue's dead letter queue
+//! after a few attempts. Once started, a job may have delivered some of what
+//! it's for, so it's claimed like any delivery, see `standup::deliveries`,
+//! and its failures are logged rather than retried.
+
+// Std lib
+use std::env;
+
+// Third party
+use lambda_runtime::error::HandlerError;
+use lambda_runtime::Context;
+use rusoto_core::Region;
+use rusoto_sqs::{SendMessageRequest, Sqs, SqsClient};
+use serde_json;
+
+// Ours
+use standup::{deliveries, log, trace, Command, Config};
+use CommandRequest;
+
+/// env var marking the worker function
+const WORKER_VAR: &str = "STANDUP_WORKER";
+
+/// A command waiting to be run
+#[derive(Serialize, Deserialize, Debug)]
+struct Job {
+    request: CommandRequest,
+    /// `CommandRequest` doesn't carry this itself, as requests mustn't set it
+    interactive: bool,
+}
+
+/// An SQS event
+/// only the fields we're using are represented
+#[derive(Deserialize, Debug)]
+pub struct Event {
+    #[serde(rename = "Records")]
+    records: Vec<Record>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Record {
+    #[serde(rename = "messageId")]
+    message_id: String,
+    body: String,
+}
+
+/// returns true if this function consumes the job queue
+pub fn is_worker() -> bool {
+    env::var(WORKER_VAR)
+        .map(|value| value == "true")
+        .unwrap_or_default()
+}
+
+/// queues a command, which the worker runs
+pub fn enqueue(queue_url: &str, request: &CommandRequest) -> Result<(), String> {
+    let job = serde_json::to_string(&Job {
+        request: request.clone(),
+        interactive: request.interactive,
+    })
+    .map_err(|err| err.to_string())?;
+    SqsClient::new(Region::default())
+        .send_message(SendMessageRequest {
+            queue_url: queue_url.to_owned(),
+            message_body: job,
+            ..Default::default()
+        })
+        .sync()
+        .map(|_| ())
+        .map_err(|err| format!("failed to queue debrief: {}", err))
+}
+
+/// runs queued commands, failing the batch if any can't be started so they're received again
+pub fn handler(event: Event, context: Context) -> Result<(), HandlerError> {
+    trace::start(Some(&context.xray_trace_id));
+    let failed = event
+        .records
+        .into_iter()
+        .filter_map(|record| {
+            run(&record.message_id, &record.body, &context.aws_request_id)
+                .map_err(|err| {
+                    log::error(
+                        "failed to start debrief",
+                        json!({ "error": err, "message_id": record.message_id }),
+                    )
+                })
+                .err()
+        })
+        .count();
+    if failed > 0 {
+        return Err(HandlerError::from(
+            format!("{} queued debriefs failed to start", failed).as_str(),
+        ));
+    }
+    Ok(())
+}
+
+fn run(message_id: &str, body: &str, request_id: &str) -> Result<(), String> {
+    let Job {
+        mut request,
+        interactive,
+    } = serde_json::from_str::<Job>(body).map_err(|err| format!("invalid job: {}", err))?;
+    request.interactive = interactive;
+    log::reset();
+    log::correlate(json!({
+        "request_id": request_id,
+        "team_id": request.team_id,
+        "channel_id": request.channel_id,
+        "user_id": request.user_id,
+    }));
+    let config = ::channel_config(Config::from_env()?, &request)?;
+    let command = Command::parse(&request.text, &config.standup_sections)?;
+    if ::claimed(&config, &deliveries::key(&request.team_id, message_id)) == Some(false) {
+        log::info(
+            "skipping redelivered job",
+            json!({ "message_id": message_id }),
+        );
+        return Ok(());
+    }
+    if let Err(err) = ::debrief(config, command, request) {
+        log::error(
+            "failed to debrief",
+            json!({ "error": err, "message_id": message_id }),
+        );
+    }
+    Ok(())
+}
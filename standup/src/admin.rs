@@ -0,0 +1,226 @@
+//! `/standup config` subcommands which manage a channel's configuration
+
+// Ours
+use config::Config;
+use secrets;
+use tenants::{Overrides, Tenants};
+
+/// settings a channel may change, and the env variables they override
+///
+/// secrets and deployment wiring are deliberately left out
+const SETTINGS: &[(&str, &str)] = &[
+    ("sections", "STANDUP_SECTIONS"),
+    ("timezone", "TIMEZONE"),
+    ("holidays", "HOLIDAYS"),
+    ("lookback", "LOOKBACK_DAYS"),
+    ("reuse_within_secs", "REUSE_WITHIN_SECS"),
+    ("project", "JIRA_PROJECTS"),
+    ("labels", "JIRA_LABELS"),
+    ("components", "JIRA_COMPONENTS"),
+    ("statuses", "JIRA_IN_FLIGHT_STATUSES"),
+    ("resolutions", "JIRA_SHIPPED_RESOLUTIONS"),
+    ("emoji", "JIRA_STATUS_EMOJI"),
+    ("type_emoji", "JIRA_TYPE_EMOJI"),
+    ("priority_emoji", "JIRA_PRIORITY_EMOJI"),
+    ("order", "JIRA_STATUS_ORDER"),
+    ("board", "JIRA_BOARD_ID"),
+    ("stale", "JIRA_STALE_DAYS"),
+    ("epic_field", "JIRA_EPIC_LINK_FIELD"),
+    ("wip", "JIRA_WIP_LIMITS"),
+    ("fields", "JIRA_DISPLAY_FIELDS"),
+    ("status_warning", "JIRA_STATUS_WARNING_DAYS"),
+    ("limit", "JIRA_GROUP_LIMIT"),
+    ("due_this_week", "JIRA_DUE_THIS_WEEK"),
+    ("issue_order", "JIRA_ISSUE_ORDER"),
+    ("subtasks", "JIRA_SUBTASKS"),
+    ("review_statuses", "JIRA_REVIEW_STATUSES"),
+    ("filter", "JIRA_FILTER_ID"),
+    ("shipped_filter", "JIRA_SHIPPED_FILTER"),
+    ("in_flight_filter", "JIRA_IN_FLIGHT_FILTER"),
+    ("release", "JIRA_FIX_VERSION"),
+    ("repos", "GITHUB_REPOS"),
+    ("org", "GITHUB_ORG"),
+    ("ci", "CI_PROVIDER"),
+    ("ci_repos", "CI_REPOS"),
+    ("ci_branch", "CI_BRANCH"),
+    ("ci_red_hours", "CI_RED_HOURS"),
+    ("deploys", "DEPLOY_SOURCE"),
+    ("deploy_environment", "DEPLOY_ENVIRONMENT"),
+    ("deploy_repos", "DEPLOY_REPOS"),
+    ("calendar", "GOOGLE_CALENDAR_ID"),
+    ("ooo_keywords", "CALENDAR_OOO_KEYWORDS"),
+    ("pd_teams", "PD_TEAM_IDS"),
+    ("pd_policies", "PD_ESCALATION_POLICY_IDS"),
+    ("pd_services", "PD_SERVICE_IDS"),
+    ("pd_urgencies", "PD_URGENCIES"),
+    ("pd_analytics", "PD_ANALYTICS"),
+    ("brief", "SLACK_BRIEF"),
+    ("incidents", "INCIDENT_SOURCE"),
+    ("opsgenie_teams", "OPSGENIE_TEAMS"),
+    ("opsgenie_schedules", "OPSGENIE_SCHEDULES"),
+    ("facilitators", "FACILITATOR_ROSTER"),
+    ("escalation_channel", "ESCALATION_CHANNEL"),
+    ("escalation_leads", "ESCALATION_LEADS"),
+];
+
+/// returns true if the command text is a config subcommand
+pub fn is_config(text: &str) -> bool {
+    text.split_whitespace().next() == Some("config")
+}
+
+/// A config subcommand
+#[derive(Debug, PartialEq)]
+pub enum Subcommand {
+    Show,
+    /// a `SETTING=VALUE` assignment
+    Set(String),
+    Unset(String),
+    Invalid,
+}
+
+impl Subcommand {
+    /// parses `config` command text, split on whitespace just as `is_config` is
+    pub fn parse(text: &str) -> Subcommand {
+        let mut words = text.split_whitespace().skip(1);
+        let subcommand = words.next();
+        let rest = words.collect::<Vec<_>>().join(" ");
+        match (subcommand, rest.is_empty()) {
+            (Some("show"), true) => Subcommand::Show,
+            (Some("set"), false) => Subcommand::Set(rest),
+            (Some("unset"), false) => Subcommand::Unset(rest),
+            _ => Subcommand::Invalid,
+        }
+    }
+
+    /// returns true if a user may run the subcommand. anyone may show a
+    /// channel's configuration, but only admins may change it
+    pub fn permitted(&self, admins: &[String], user_id: &str) -> bool {
+        match *self {
+            Subcommand::Set(_) | Subcommand::Unset(_) => {
+                admins.iter().any(|admin| admin == user_id)
+            }
+            Subcommand::Show | Subcommand::Invalid => true,
+        }
+    }
+}
+
+pub fn usage() -> String {
+    format!(
+        "Usage: `/standup config show`, `/standup config set SETTING=VALUE` or `/standup config unset SETTING`\nSettings: {}",
+        SETTINGS
+            .iter()
+            .map(|(setting, _)| format!("`{}`", setting))
+            .collect::<Vec<_>>()
+            .join(", ")
+    )
+}
+
+/// runs a config subcommand for a channel, returning the reply
+pub fn run(tenants: &Tenants, team_id: &str, channel_id: &str, subcommand: &Subcommand) -> String {
+    let result = match *subcommand {
+        Subcommand::Show => tenants
+            .overrides(team_id, channel_id)
+            .map(|overrides| show(&overrides)),
+        Subcommand::Set(ref assignment) => {
+            set(tenants, team_id, channel_id, assignment).map(|_| "👍 Saved".into())
+        }
+        Subcommand::Unset(ref setting) => {
+            unset(tenants, team_id, channel_id, setting).map(|_| "👍 Removed".into())
+        }
+        Subcommand::Invalid => Err(usage()),
+    };
+    result.unwrap_or_else(|err| err)
+}
+
+fn show(overrides: &Overrides) -> String {
+    let settings = SETTINGS
+        .iter()
+        .filter_map(|(setting, name)| {
+            overrides
+                .get(*name)
+                .map(|value| format!("• `{}` {}", setting, value))
+        })
+        .collect::<Vec<_>>();
+    if settings.is_empty() {
+        "This channel uses the default configuration".into()
+    } else {
+        settings.join("\n")
+    }
+}
+
+fn set(tenants: &Tenants, team_id: &str, channel_id: &str, assignment: &str) -> Result<(), String> {
+    let mut parts = assignment.splitn(2, '=');
+    let (setting, value) = match (parts.next(), parts.next()) {
+        (Some(setting), Some(value)) => (setting.trim(), value.trim()),
+        _ => return Err(usage()),
+    };
+    let name = env_name(setting)?;
+    if secrets::is_reference(value) {
+        return Err(format!("`{}` can't refer to a secret", setting));
+    }
+    let mut overrides = tenants.overrides(team_id, channel_id)?;
+    overrides.insert(name.into(), value.into());
+    // parse errors may quote other settings' values, so they aren't passed on
+    if Config::with_overrides(&overrides).is_err() {
+        return Err(format!("`{}` isn't a valid `{}`", value, setting));
+    }
+    tenants.save(team_id, channel_id, &overrides)
+}
+
+fn unset(tenants: &Tenants, team_id: &str, channel_id: &str, setting: &str) -> Result<(), String> {
+    let name = env_name(setting)?;
+    let mut overrides = tenants.overrides(team_id, channel_id)?;
+    overrides.remove(name);
+    tenants.save(team_id, channel_id, &overrides)
+}
+
+fn env_name(setting: &str) -> Result<&'static str, String> {
+    SETTINGS
+        .iter()
+        .find(|(known, _)| known.eq_ignore_ascii_case(setting))
+        .map(|(_, name)| *name)
+        .ok_or_else(|| format!("unknown setting `{}`\n{}", setting, usage()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_admins_change_configuration() {
+        let admins = vec!["U1".to_owned()];
+        let permitted =
+            |user_id: &str, text: &str| Subcommand::parse(text).permitted(&admins, user_id);
+        assert!(permitted("U2", "config show"));
+        assert!(permitted("U1", "config set timezone=UTC"));
+        assert!(!permitted("U2", "config set timezone=UTC"));
+        assert!(!permitted("U2", "config  unset timezone"));
+        assert!(!Subcommand::parse("config unset timezone").permitted(&[], "U1"));
+    }
+
+    #[test]
+    fn subcommands_split_on_any_whitespace() {
+        for text in &["config\tset sections=jira", "config\u{a0}set sections=jira"] {
+            let subcommand = Subcommand::parse(text);
+            assert_eq!(subcommand, Subcommand::Set("sections=jira".into()));
+            assert!(!subcommand.permitted(&["U1".to_owned()], "U2"));
+        }
+        assert_eq!(
+            Subcommand::parse("config\tx set sections=jira"),
+            Subcommand::Invalid
+        );
+        assert_eq!(
+            Subcommand::parse("config set statuses=In Progress, QA"),
+            Subcommand::Set("statuses=In Progress, QA".into())
+        );
+    }
+
+    #[test]
+    fn secrets_cant_be_set() {
+        let tenants = Tenants::new("channels".into());
+        assert_eq!(
+            set(&tenants, "T1", "C1", "timezone=ssm://x"),
+            Err("`timezone` can't refer to a secret".into())
+        );
+    }
+}
@@ -0,0 +1,163 @@
+//! Jira Agile api client
+//!
+//! `goji` only covers the core issue api. Boards and sprints live in the
+//! agile api, and the figures behind jira's own sprint report live in the
+//! older greenhopper api. Project versions, which `goji` doesn't cover
+//! either, are fetched here too.
+
+// Std lib
+use std::collections::HashMap;
+
+// Third party
+use chrono::{DateTime, FixedOffset, NaiveDate};
+use serde::de::DeserializeOwned;
+
+// Ours
+use error::FetchError;
+use jira_auth::Site;
+use retry::{self, Retry};
+use trace;
+
+#[derive(Deserialize, Debug)]
+struct Sprints {
+    values: Vec<Sprint>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Sprint {
+    pub id: u64,
+    pub name: String,
+    pub goal: Option<String>,
+    pub start_date: Option<DateTime<FixedOffset>>,
+    pub end_date: Option<DateTime<FixedOffset>>,
+}
+
+#[derive(Deserialize, Debug)]
+struct SprintReport {
+    contents: Contents,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct Contents {
+    completed_issues_estimate_sum: Estimate,
+    issues_not_completed_estimate_sum: Estimate,
+    punted_issues: Vec<ReportIssue>,
+    #[serde(default)]
+    issue_keys_added_during_sprint: HashMap<String, bool>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Estimate {
+    /// absent when no issues are estimated
+    value: Option<f64>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ReportIssue {
+    key: String,
+}
+
+/// Points and scope of a sprint
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Progress {
+    /// points of all issues currently in the sprint
+    pub committed: f64,
+    pub completed: f64,
+    /// keys of issues added after the sprint started
+    pub added: Vec<String>,
+    /// keys of issues removed after the sprint started
+    pub removed: Vec<String>,
+}
+
+/// A fix version of a project
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Version {
+    pub name: String,
+    pub release_date: Option<NaiveDate>,
+    #[serde(default)]
+    pub released: bool,
+}
+
+pub struct Agile {
+    site: Site,
+    retry: Retry,
+}
+
+impl Agile {
+    pub fn new(site: Site, retry: Retry) -> Self {
+        Agile { site, retry }
+    }
+
+    /// the active sprint of a board, if any
+    pub fn active_sprint(&self, board_id: u64) -> Result<Option<Sprint>, FetchError> {
+        self.get::<Sprints>(
+            &format!("/rest/agile/1.0/board/{}/sprint", board_id),
+            &[("state", "active".into())],
+        )
+        .map(|sprints| sprints.values.into_iter().next())
+    }
+
+    pub fn progress(&self, board_id: u64, sprint_id: u64) -> Result<Progress, FetchError> {
+        self.get::<SprintReport>(
+            "/rest/greenhopper/1.0/rapid/charts/sprintreport",
+            &[
+                ("rapidViewId", board_id.to_string()),
+                ("sprintId", sprint_id.to_string()),
+            ],
+        )
+        .map(|report| {
+            let contents = report.contents;
+            let completed = contents.completed_issues_estimate_sum.value.unwrap_or(0.0);
+            let mut added = contents
+                .issue_keys_added_during_sprint
+                .into_iter()
+                .filter(|(_, added)| *added)
+                .map(|(key, _)| key)
+                .collect::<Vec<_>>();
+            added.sort();
+            Progress {
+                committed: completed
+                    + contents
+                        .issues_not_completed_estimate_sum
+                        .value
+                        .unwrap_or(0.0),
+                completed,
+                added,
+                removed: contents
+                    .punted_issues
+                    .into_iter()
+                    .map(|issue| issue.key)
+                    .collect(),
+            }
+        })
+    }
+
+    /// the fix versions of a project
+    pub fn versions(&self, project: &str) -> Result<Vec<Version>, FetchError> {
+        self.get::<Vec<Version>>(&format!("/rest/api/2/project/{}/versions", project), &[])
+    }
+
+    fn get<T>(&self, path: &str, query: &[(&str, String)]) -> Result<T, FetchError>
+    where
+        T: DeserializeOwned,
+    {
+        self.retry
+            .run(
+                || {
+                    trace::request("Jira Agile", path, || {
+                        self.site
+                            .get(path)
+                            .query(query)
+                            .send()
+                            .and_then(|response| response.error_for_status())
+                            .and_then(|mut response| response.json::<T>())
+                    })
+                },
+                retry::http,
+            )
+            .map_err(FetchError::agile)
+    }
+}
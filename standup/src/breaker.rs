@@ -0,0 +1,278 @@
+//! Short circuiting upstreams which are down
+//!
+//! Retrying an upstream in the middle of an outage burns each standup's time
+//! budget. After `breaker_failures` failures in a row an upstream's circuit
+//! opens, and requests fail right away until `breaker_cooldown_secs` pass.
+//! Then the circuit is half open and one invocation's requests are let
+//! through, closing the circuit if they succeed or opening it for another
+//! cooldown if they don't. Other invocations' requests fail right away while
+//! the probe's in flight, unless it's been in flight so long it must have
+//! been lost. Circuits are kept in memory, so warm lambdas remember them
+//! between invocations.
+
+// Std lib
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Ours
+use config::Config;
+use log;
+
+/// how long a probe may be in flight before it's taken to be lost, as long
+/// as an invocation may run
+const PROBE_TIMEOUT_SECS: u64 = 30;
+
+lazy_static! {
+    /// circuits by upstream
+    static ref CIRCUITS: Mutex<HashMap<String, Circuit>> = Mutex::new(HashMap::new());
+}
+
+#[derive(Debug, Default)]
+struct Circuit {
+    /// failures in a row
+    failures: u32,
+    /// when the circuit last opened
+    opened: Option<Instant>,
+    /// requests being let through after cooling down
+    probe: Option<Probe>,
+}
+
+#[derive(Debug)]
+struct Probe {
+    started: Instant,
+    /// the invocation probing, by its request id
+    request_id: Option<String>,
+}
+
+/// clears an upstream's probe when a request panics, so it doesn't hold the
+/// circuit half open
+struct Probing<'a>(&'a str);
+
+impl<'a> Drop for Probing<'a> {
+    fn drop(&mut self) {
+        if thread::panicking() {
+            circuit(self.0, |circuit| circuit.probe = None);
+        }
+    }
+}
+
+/// Why a guarded request failed
+#[derive(Debug, PartialEq)]
+pub enum Tripped<E> {
+    /// the circuit is open, and closes after this many seconds
+    Open(u64),
+    Failed(E),
+}
+
+/// How many failures open a circuit, and for how long
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Breaker {
+    /// zero never opens circuits
+    pub failures: u32,
+    pub cooldown_secs: u64,
+}
+
+impl<'a> From<&'a Config> for Breaker {
+    fn from(config: &'a Config) -> Self {
+        Breaker {
+            failures: config.breaker_failures,
+            cooldown_secs: config.breaker_cooldown_secs,
+        }
+    }
+}
+
+impl Breaker {
+    /// calls `f` unless the upstream's circuit is open, counting errors which
+    /// are `failures`, i.e. outages rather than bad requests
+    pub fn run<T, E, F, R>(&self, upstream: &str, f: F, failure: R) -> Result<T, Tripped<E>>
+    where
+        F: FnOnce() -> Result<T, E>,
+        R: Fn(&E) -> bool,
+    {
+        if self.failures == 0 {
+            return f().map_err(Tripped::Failed);
+        }
+        let cooldown = Duration::from_secs(self.cooldown_secs);
+        let request_id = log::correlation()["request_id"].as_str().map(str::to_owned);
+        let open = circuit(upstream, |circuit| {
+            let opened = circuit.opened?;
+            let open_for = opened.elapsed();
+            if open_for < cooldown {
+                return Some(cooldown - open_for);
+            }
+            if let Some(ref probe) = circuit.probe {
+                if request_id.is_some() && probe.request_id == request_id {
+                    return None;
+                }
+                if probe.started.elapsed() < Duration::from_secs(PROBE_TIMEOUT_SECS) {
+                    return Some(cooldown);
+                }
+            }
+            circuit.probe = Some(Probe {
+                started: Instant::now(),
+                request_id: request_id.clone(),
+            });
+            None
+        });
+        if let Some(left) = open {
+            let partial = if left.subsec_nanos() > 0 { 1 } else { 0 };
+            return Err(Tripped::Open(left.as_secs() + partial));
+        }
+        let _probing = Probing(upstream);
+        let result = f();
+        let failed = match result {
+            Err(ref err) => failure(err),
+            Ok(_) => false,
+        };
+        let threshold = self.failures;
+        circuit(upstream, |circuit| {
+            if failed {
+                circuit.failures += 1;
+                if circuit.failures >= threshold {
+                    circuit.opened = Some(Instant::now());
+                }
+                circuit.probe = None;
+            } else {
+                *circuit = Circuit::default();
+            }
+        });
+        result.map_err(Tripped::Failed)
+    }
+}
+
+/// reads or changes an upstream's circuit
+fn circuit<T, F>(upstream: &str, f: F) -> T
+where
+    F: FnOnce(&mut Circuit) -> T,
+    T: Default,
+{
+    match CIRCUITS.lock() {
+        Ok(mut circuits) => f(circuits.entry(upstream.to_owned()).or_default()),
+        Err(_) => T::default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::panic;
+
+    #[test]
+    fn circuits_open_after_failures_in_a_row() {
+        let breaker = Breaker {
+            failures: 2,
+            cooldown_secs: 60,
+        };
+        let calls = Cell::new(0);
+        let failing = || {
+            calls.set(calls.get() + 1);
+            Err::<(), _>("503")
+        };
+        let outage = "https://down.example.com";
+        assert_eq!(
+            breaker.run(outage, failing, |_| true),
+            Err(Tripped::Failed("503"))
+        );
+        assert_eq!(
+            breaker.run(outage, failing, |_| true),
+            Err(Tripped::Failed("503"))
+        );
+        assert_eq!(
+            breaker.run(outage, failing, |_| true),
+            Err(Tripped::Open(60))
+        );
+        assert_eq!(calls.get(), 2);
+    }
+
+    #[test]
+    fn circuits_let_a_request_through_after_cooling_down() {
+        let breaker = Breaker {
+            failures: 1,
+            cooldown_secs: 0,
+        };
+        let flaky = "https://flaky.example.com";
+        let _ = breaker.run(flaky, || Err::<(), _>("503"), |_| true);
+        assert_eq!(
+            breaker.run(flaky, || Ok::<_, &str>("ok"), |_| true),
+            Ok("ok")
+        );
+        assert_eq!(circuit(flaky, |circuit| circuit.failures), 0);
+    }
+
+    #[test]
+    fn half_open_circuits_let_one_request_through() {
+        let breaker = Breaker {
+            failures: 1,
+            cooldown_secs: 0,
+        };
+        let recovering = "https://recovering.example.com";
+        let _ = breaker.run(recovering, || Err::<(), _>("503"), |_| true);
+        let concurrent = breaker.run(
+            recovering,
+            || Ok::<_, &str>(breaker.run(recovering, || Ok::<_, &str>("ok"), |_| true)),
+            |_| true,
+        );
+        assert_eq!(concurrent, Ok(Err(Tripped::Open(0))));
+        assert_eq!(
+            breaker.run(recovering, || Ok::<_, &str>("ok"), |_| true),
+            Ok("ok")
+        );
+    }
+
+    #[test]
+    fn half_open_circuits_let_the_probing_invocation_through() {
+        let breaker = Breaker {
+            failures: 1,
+            cooldown_secs: 0,
+        };
+        let recovered = "https://recovered.example.com";
+        let _ = breaker.run(recovered, || Err::<(), _>("503"), |_| true);
+        log::correlate(json!({ "request_id": "r1" }));
+        let concurrent = breaker.run(
+            recovered,
+            || Ok::<_, &str>(breaker.run(recovered, || Ok::<_, &str>("ok"), |_| true)),
+            |_| true,
+        );
+        log::reset();
+        assert_eq!(concurrent, Ok(Ok("ok")));
+    }
+
+    #[test]
+    fn panicking_probes_do_not_hold_circuits_half_open() {
+        let breaker = Breaker {
+            failures: 1,
+            cooldown_secs: 0,
+        };
+        let panicky = "https://panicky.example.com";
+        let _ = breaker.run(panicky, || Err::<(), _>("503"), |_| true);
+        let panicked = panic::catch_unwind(|| {
+            breaker.run(
+                panicky,
+                || -> Result<(), &'static str> { panic!("probe") },
+                |_| true,
+            )
+        });
+        assert!(panicked.is_err());
+        assert_eq!(
+            breaker.run(panicky, || Ok::<_, &str>("ok"), |_| true),
+            Ok("ok")
+        );
+    }
+
+    #[test]
+    fn bad_requests_do_not_count() {
+        let breaker = Breaker {
+            failures: 1,
+            cooldown_secs: 60,
+        };
+        let strict = "https://strict.example.com";
+        let _ = breaker.run(strict, || Err::<(), _>("400"), |_| false);
+        assert_eq!(
+            breaker.run(strict, || Err::<(), _>("400"), |_| false),
+            Err(Tripped::Failed("400"))
+        );
+    }
+}
@@ -0,0 +1,187 @@
+//! Short lived cache of upstream responses
+//!
+//! Several people running `/standup` within a few minutes ask jira and
+//! pagerduty the same questions. Responses are kept for `cache_ttl_secs` in
+//! memory, which warm lambdas keep between invocations, and in a DynamoDB
+//! table, when one's configured, which every lambda shares. Keys are hashes
+//! of requests and the credentials they're made with, so cached responses
+//! are only ever seen by those who could have requested them.
+
+// Std lib
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+// Third party
+use hex;
+use rusoto_core::Region;
+use rusoto_dynamodb::{DynamoDb, DynamoDbClient, GetItemInput, PutItemInput};
+use serde_json::{self, Value};
+use sha2::{Digest, Sha256};
+
+// Ours
+use config::Config;
+use dynamodb::{attributes, number};
+use error::FetchError;
+use log;
+
+lazy_static! {
+    /// responses and when they expire, by key
+    static ref RESPONSES: Mutex<HashMap<String, (Value, Instant)>> = Mutex::new(HashMap::new());
+}
+
+/// How long responses are kept for, and where besides memory
+#[derive(Debug, Clone, Default)]
+pub struct Cache {
+    /// zero turns caching off
+    ttl_secs: u64,
+    table: Option<String>,
+}
+
+impl<'a> From<&'a Config> for Cache {
+    fn from(config: &'a Config) -> Self {
+        Cache {
+            ttl_secs: config.cache_ttl_secs.unwrap_or_default(),
+            table: config.cache_table.clone(),
+        }
+    }
+}
+
+impl Cache {
+    /// the cached response to a request, or else the one `fetch` gets, which is kept
+    pub fn get<F>(&self, request: &str, fetch: F) -> Result<Value, FetchError>
+    where
+        F: FnOnce() -> Result<Value, FetchError>,
+    {
+        if self.ttl_secs == 0 {
+            return fetch();
+        }
+        let key = hex::encode(Sha256::digest(request.as_bytes()));
+        if let Some(response) = self.load(&key) {
+            return Ok(response);
+        }
+        let response = fetch()?;
+        self.keep(&key, &response);
+        Ok(response)
+    }
+
+    fn load(&self, key: &str) -> Option<Value> {
+        let remembered = RESPONSES
+            .lock()
+            .ok()
+            .and_then(|responses| responses.get(key).cloned())
+            .filter(|(_, expires)| *expires > Instant::now())
+            .map(|(response, _)| response);
+        if remembered.is_some() {
+            return remembered;
+        }
+        let table = self.table.as_ref()?;
+        let output = DynamoDbClient::new(Region::default())
+            .get_item(GetItemInput {
+                table_name: table.clone(),
+                key: attributes(vec![("key", key.to_owned())]),
+                ..Default::default()
+            })
+            .sync()
+            .map_err(|err| {
+                log::warn(
+                    "failed to load cached response",
+                    json!({ "error": err.to_string() }),
+                )
+            })
+            .ok()?;
+        let item = output.item?;
+        // the table's time to live removes expired items eventually, not immediately
+        let expires = item
+            .get("expires")
+            .and_then(|expires| expires.n.as_ref())
+            .and_then(|expires| expires.parse::<u64>().ok())?;
+        let left = expires.checked_sub(epoch_secs())?;
+        let response = item
+            .get("response")
+            .and_then(|response| response.s.as_ref())
+            .and_then(|response| serde_json::from_str::<Value>(response).ok())?;
+        self.remember(key, &response, left);
+        Some(response)
+    }
+
+    fn keep(&self, key: &str, response: &Value) {
+        self.remember(key, response, self.ttl_secs);
+        if let Some(ref table) = self.table {
+            let mut item = attributes(vec![
+                ("key", key.to_owned()),
+                ("response", response.to_string()),
+            ]);
+            item.insert("expires".to_owned(), number(epoch_secs() + self.ttl_secs));
+            let stored = DynamoDbClient::new(Region::default())
+                .put_item(PutItemInput {
+                    table_name: table.clone(),
+                    item,
+                    ..Default::default()
+                })
+                .sync();
+            // i.e. responses beyond dynamodb's item size limit, which stay in memory
+            if let Err(err) = stored {
+                log::warn(
+                    "failed to keep cached response",
+                    json!({ "error": err.to_string() }),
+                );
+            }
+        }
+    }
+
+    fn remember(&self, key: &str, response: &Value, secs: u64) {
+        if let Ok(mut responses) = RESPONSES.lock() {
+            let now = Instant::now();
+            responses.retain(|_, (_, expires)| *expires > now);
+            responses.insert(
+                key.to_owned(),
+                (response.clone(), now + Duration::from_secs(secs)),
+            );
+        }
+    }
+}
+
+fn epoch_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|since| since.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn responses_are_kept_until_they_expire() {
+        let cache = Cache {
+            ttl_secs: 60,
+            table: None,
+        };
+        let fetched = cache.get("GET /kept", || Ok(json!({ "fetched": 1 })));
+        assert_eq!(fetched.unwrap(), json!({ "fetched": 1 }));
+        let cached = cache.get("GET /kept", || Ok(json!({ "fetched": 2 })));
+        assert_eq!(cached.unwrap(), json!({ "fetched": 1 }));
+    }
+
+    #[test]
+    fn responses_are_not_kept_without_a_ttl() {
+        let cache = Cache::default();
+        let _ = cache.get("GET /uncached", || Ok(json!({ "fetched": 1 })));
+        let fetched = cache.get("GET /uncached", || Ok(json!({ "fetched": 2 })));
+        assert_eq!(fetched.unwrap(), json!({ "fetched": 2 }));
+    }
+
+    #[test]
+    fn failures_are_not_kept() {
+        let cache = Cache {
+            ttl_secs: 60,
+            table: None,
+        };
+        let failed = cache.get("GET /failing", || Err(FetchError::new("Jira", "503")));
+        assert!(failed.is_err());
+        let fetched = cache.get("GET /failing", || Ok(json!({ "fetched": 2 })));
+        assert_eq!(fetched.unwrap(), json!({ "fetched": 2 }));
+    }
+}
@@ -0,0 +1,167 @@
+//! Business day arithmetic
+
+// Std lib
+use std::collections::HashSet;
+
+// Third party
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Offset, TimeZone, Weekday};
+use chrono_tz::Tz;
+use reqwest::Client;
+
+// Ours
+use log;
+
+/// longest gap between business days we'll look back over
+const MAX_LOOKBACK_DAYS: i64 = 30;
+
+/// Holidays observed by the team
+pub struct Holidays {
+    dates: HashSet<NaiveDate>,
+}
+
+impl Holidays {
+    /// holidays from `YYYY-MM-DD` dates and, optionally, the all day events of an iCal feed
+    pub fn new(dates: &[String], calendar_url: Option<&str>, client: &Client) -> Self {
+        let mut holidays = dates
+            .iter()
+            .filter_map(|date| match NaiveDate::parse_from_str(date.trim(), "%F") {
+                Ok(date) => Some(date),
+                Err(err) => {
+                    log::warn(
+                        "skipping invalid holiday",
+                        json!({ "date": date, "error": err.to_string() }),
+                    );
+                    None
+                }
+            })
+            .collect::<HashSet<_>>();
+        if let Some(url) = calendar_url {
+            match client
+                .get(url)
+                .send()
+                .and_then(|response| response.error_for_status())
+                .and_then(|mut response| response.text())
+            {
+                Ok(ics) => holidays.extend(event_dates(&ics)),
+                Err(err) => log::error(
+                    "failed to fetch holiday calendar",
+                    json!({
+                        "error": err.to_string(),
+                        "upstream_status": err.status().map(|status| status.as_u16()),
+                    }),
+                ),
+            }
+        }
+        Holidays { dates: holidays }
+    }
+
+    pub fn is_business_day(&self, date: NaiveDate) -> bool {
+        match date.weekday() {
+            Weekday::Sat | Weekday::Sun => false,
+            _ => !self.dates.contains(&date),
+        }
+    }
+
+    /// number of days since the last business day before `today`
+    pub fn lookback_days(&self, today: NaiveDate) -> i64 {
+        let mut day = today.pred();
+        while !self.is_business_day(day) && (today - day).num_days() < MAX_LOOKBACK_DAYS {
+            day = day.pred();
+        }
+        (today - day).num_days()
+    }
+}
+
+/// the start of a day, which is midnight unless clocks went forward over it,
+/// when the day starts as they did. where midnight happened twice, it's the first
+pub fn midnight(day: DateTime<Tz>) -> DateTime<Tz> {
+    let timezone = day.timezone();
+    let midnight = day.date().naive_local().and_hms(0, 0, 0);
+    timezone
+        .from_local_datetime(&midnight)
+        .earliest()
+        .unwrap_or_else(|| {
+            // midnight by the offset before the clocks went forward
+            let before = timezone
+                .offset_from_utc_datetime(&(midnight - Duration::days(1)))
+                .fix();
+            timezone.from_utc_datetime(
+                &(midnight - Duration::seconds(i64::from(before.local_minus_utc()))),
+            )
+        })
+}
+
+/// start dates of the events in an iCal feed
+fn event_dates(ics: &str) -> Vec<NaiveDate> {
+    ics.lines()
+        .filter(|line| line.starts_with("DTSTART"))
+        .filter_map(|line| line.rsplit(':').next())
+        .filter_map(|value| value.get(..8))
+        .filter_map(|date| NaiveDate::parse_from_str(date, "%Y%m%d").ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(date: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(date, "%F").unwrap()
+    }
+
+    #[test]
+    fn looks_back_over_weekends() {
+        // a monday
+        assert_eq!(
+            Holidays::new(&[], None, &Client::new()).lookback_days(date("2019-01-07")),
+            3
+        );
+        // a tuesday
+        assert_eq!(
+            Holidays::new(&[], None, &Client::new()).lookback_days(date("2019-01-08")),
+            1
+        );
+    }
+
+    #[test]
+    fn looks_back_over_holidays() {
+        let holidays = Holidays::new(&["2019-01-21".into()], None, &Client::new());
+        // the tuesday after a monday holiday
+        assert_eq!(holidays.lookback_days(date("2019-01-22")), 4);
+    }
+
+    #[test]
+    fn days_start_at_midnight_or_as_clocks_skip_it() {
+        let havana = |date: &str, time: &str| {
+            midnight(
+                Tz::America__Havana
+                    .datetime_from_str(&format!("{} {}", date, time), "%F %T")
+                    .unwrap(),
+            )
+            .to_rfc3339()
+        };
+        assert_eq!(
+            havana("2019-01-07", "09:00:00"),
+            "2019-01-07T00:00:00-05:00"
+        );
+        // clocks went forward from midnight to 1am
+        assert_eq!(
+            havana("2019-03-10", "09:00:00"),
+            "2019-03-10T01:00:00-04:00"
+        );
+        // clocks went back from 1am to midnight
+        assert_eq!(
+            havana("2019-11-03", "09:00:00"),
+            "2019-11-03T00:00:00-04:00"
+        );
+    }
+
+    #[test]
+    fn parses_all_day_event_dates() {
+        let ics = "BEGIN:VEVENT\nDTSTART;VALUE=DATE:20190527\nSUMMARY:Memorial Day\nEND:VEVENT\nBEGIN:VEVENT\nDTSTART:20190704T000000Z\nEND:VEVENT";
+        assert_eq!(
+            event_dates(ics),
+            vec![date("2019-05-27"), date("2019-07-04")]
+        );
+    }
+}
@@ -0,0 +1,105 @@
+//! Trend charts
+//!
+//! Daily series are drawn as line charts into png images, which sinks that
+//! take files attach to what they've delivered. The bitmap backend only
+//! writes to files, so images are drawn in the temp dir and read back.
+//! Lambda has no system fonts, so text is drawn in a bundled one.
+
+// Std lib
+use std::env;
+use std::fs;
+use std::sync::Once;
+
+// Third party
+use plotters::prelude::*;
+use plotters::style::register_font;
+
+/// width and height of charts, in pixels
+const SIZE: (u32, u32) = (640, 320);
+
+/// DejaVu Sans, see `fonts/DejaVuSans-LICENSE`
+const FONT: &[u8] = include_bytes!("../fonts/DejaVuSans.ttf");
+
+/// name text is drawn in, which the bundled font is registered as
+const FONT_FAMILY: &str = "sans-serif";
+
+static FONT_REGISTERED: Once = Once::new();
+
+/// A value per day, i.e. issues closed each day of the week
+#[derive(Debug, PartialEq)]
+pub struct Series {
+    /// used as the chart caption and file name
+    pub title: String,
+    /// day labels and values, oldest first
+    pub points: Vec<(String, f64)>,
+}
+
+/// A rendered chart
+pub struct Chart {
+    pub title: String,
+    pub png: Vec<u8>,
+}
+
+/// draws a series as a line chart
+pub fn line(series: &Series) -> Result<Chart, String> {
+    FONT_REGISTERED.call_once(|| {
+        // the font's bundled, so it's known to parse
+        let _ = register_font(FONT_FAMILY, FontStyle::Normal, FONT);
+    });
+    let path = env::temp_dir().join(format!(
+        "{}.png",
+        series
+            .title
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+            .collect::<String>()
+    ));
+    {
+        let root = BitMapBackend::new(&path, SIZE).into_drawing_area();
+        root.fill(&WHITE).map_err(|err| err.to_string())?;
+        let last = series.points.len().saturating_sub(1).max(1) as i32;
+        let max = series
+            .points
+            .iter()
+            .map(|(_, value)| *value)
+            .fold(1.0, f64::max);
+        let mut chart = ChartBuilder::on(&root)
+            .caption(&series.title, (FONT_FAMILY, 24))
+            .margin(16)
+            .x_label_area_size(32)
+            .y_label_area_size(40)
+            .build_cartesian_2d(0..last, 0.0..max * 1.1)
+            .map_err(|err| err.to_string())?;
+        let label = |x: &i32| {
+            series
+                .points
+                .get(*x as usize)
+                .map(|(label, _)| label.clone())
+                .unwrap_or_default()
+        };
+        chart
+            .configure_mesh()
+            .x_labels(series.points.len())
+            .x_label_formatter(&label)
+            .label_style((FONT_FAMILY, 12))
+            .draw()
+            .map_err(|err| err.to_string())?;
+        chart
+            .draw_series(LineSeries::new(
+                series
+                    .points
+                    .iter()
+                    .enumerate()
+                    .map(|(x, (_, value))| (x as i32, *value)),
+                &BLUE,
+            ))
+            .map_err(|err| err.to_string())?;
+        root.present().map_err(|err| err.to_string())?;
+    }
+    let png = fs::read(&path).map_err(|err| format!("failed to read chart: {}", err))?;
+    let _ = fs::remove_file(&path);
+    Ok(Chart {
+        title: series.title.clone(),
+        png,
+    })
+}
@@ -0,0 +1,750 @@
+//! Asynchronous check-ins collected over direct message
+//!
+//! Instead of (or alongside) reporting on upstream services, the bot may ask
+//! each of `checkin_users` the classic standup questions in a DM. Answers
+//! arrive as `message.im` events, each prompting the next question, and are
+//! stored in DynamoDB keyed by the day and user they're for until they're
+//! summarized in the standup channel.
+//!
+//! Users out of office, by `/standup ooo` or an out of office event on the
+//! shared google calendar, aren't asked and are listed as out in the summary.
+//! Absences are stored in the same table under the `out` partition.
+//!
+//! The slack app needs the `chat:write`, `im:history` and `users:read` scopes
+//! and an event subscription to `message.im`.
+
+// Std lib
+use std::collections::{BTreeMap, HashMap};
+
+// Third party
+use chrono::{Duration, NaiveDate, Utc};
+use rusoto_core::Region;
+use rusoto_dynamodb::{
+    AttributeValue, DeleteItemInput, DynamoDb, DynamoDbClient, GetItemInput, PutItemError,
+    PutItemInput, QueryInput, UpdateItemError, UpdateItemInput,
+};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json;
+
+// Ours
+use calendar::midnight;
+use command::Ooo;
+use config::Config;
+use dynamodb::attributes;
+use escalation::{self, Blocker};
+use google_calendar::GoogleCalendar;
+use history::{Entry, History};
+use log;
+use render::{escape, Block, Message};
+use section::today::is_out;
+use sink::{Chat, Sink};
+use users::Directory;
+
+/// partition absences are stored under, alongside each day's check-ins
+const ABSENCES: &str = "out";
+
+/// times an answer is tried, when the check-in changes in between
+const ANSWER_ATTEMPTS: u32 = 3;
+
+/// what each user is asked, in order
+pub const QUESTIONS: [&str; 3] = [
+    "What did you get done yesterday?",
+    "What are you working on today?",
+    "Is anything blocking you?",
+];
+
+/// A user's answers for a day
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Checkin {
+    pub date: NaiveDate,
+    pub user: String,
+    /// answers to `QUESTIONS`, in the order they were answered
+    pub answers: Vec<String>,
+    /// how many times the user's been reminded to answer
+    #[serde(default)]
+    pub reminders: u32,
+}
+
+impl Checkin {
+    fn new(date: NaiveDate, user: String) -> Self {
+        Checkin {
+            date,
+            user,
+            answers: Vec::new(),
+            reminders: 0,
+        }
+    }
+
+    /// the question awaiting an answer, if any
+    pub fn next_question(&self) -> Option<&'static str> {
+        QUESTIONS.get(self.answers.len()).cloned()
+    }
+}
+
+/// A user's time out of office
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Absence {
+    pub user: String,
+    /// the last day they're out
+    pub until: NaiveDate,
+}
+
+/// Check-ins persisted to DynamoDB
+pub struct Checkins {
+    table: String,
+    client: DynamoDbClient,
+}
+
+impl Checkins {
+    pub fn new(table: String) -> Self {
+        Checkins {
+            table,
+            client: DynamoDbClient::new(Region::default()),
+        }
+    }
+
+    /// check-ins, when there are users to ask, a table and a bot token to DM them with
+    pub fn configured(config: &Config) -> Option<Checkins> {
+        if config.checkin_users.is_empty() || config.slack_bot_token.is_none() {
+            return None;
+        }
+        config.checkin_table.clone().map(Checkins::new)
+    }
+
+    pub fn get(&self, date: NaiveDate, user: &str) -> Result<Option<Checkin>, String> {
+        let output = self
+            .client
+            .get_item(GetItemInput {
+                table_name: self.table.clone(),
+                key: attributes(vec![("date", date.to_string()), ("user", user.to_owned())]),
+                ..Default::default()
+            })
+            .sync()
+            .map_err(|err| format!("failed to load check-in: {}", err))?;
+        Ok(output.item.as_ref().and_then(document))
+    }
+
+    /// saves a new check-in unless the user already has one for the day,
+    /// returning whether it was saved
+    pub fn start(&self, checkin: &Checkin) -> Result<bool, String> {
+        let document = serde_json::to_string(checkin).map_err(|err| err.to_string())?;
+        match self
+            .client
+            .put_item(PutItemInput {
+                table_name: self.table.clone(),
+                item: attributes(vec![
+                    ("date", checkin.date.to_string()),
+                    ("user", checkin.user.clone()),
+                    ("document", document),
+                ]),
+                // `user` is a reserved word
+                condition_expression: Some("attribute_not_exists(#user)".into()),
+                expression_attribute_names: Some(
+                    vec![("#user".to_owned(), "user".to_owned())]
+                        .into_iter()
+                        .collect(),
+                ),
+                ..Default::default()
+            })
+            .sync()
+        {
+            Ok(_) => Ok(true),
+            Err(PutItemError::ConditionalCheckFailed(_)) => Ok(false),
+            Err(err) => Err(format!("failed to start check-in: {}", err)),
+        }
+    }
+
+    /// removes a check-in, i.e. one whose first question never reached the user
+    pub fn forget(&self, date: NaiveDate, user: &str) -> Result<(), String> {
+        self.client
+            .delete_item(DeleteItemInput {
+                table_name: self.table.clone(),
+                key: attributes(vec![("date", date.to_string()), ("user", user.to_owned())]),
+                ..Default::default()
+            })
+            .sync()
+            .map(|_| ())
+            .map_err(|err| format!("failed to remove check-in: {}", err))
+    }
+
+    /// replaces a check-in as it was `read` with `updated`, unless it's
+    /// changed in between, i.e. with another answer or a reminder, returning
+    /// whether it was replaced
+    pub fn update(&self, read: &Checkin, updated: &Checkin) -> Result<bool, String> {
+        let document =
+            |checkin: &Checkin| serde_json::to_string(checkin).map_err(|err| err.to_string());
+        let (read_document, updated_document) = (document(read)?, document(updated)?);
+        match self
+            .client
+            .update_item(UpdateItemInput {
+                table_name: self.table.clone(),
+                key: attributes(vec![
+                    ("date", read.date.to_string()),
+                    ("user", read.user.clone()),
+                ]),
+                update_expression: Some("SET #document = :updated".into()),
+                condition_expression: Some("#document = :read".into()),
+                expression_attribute_names: Some(
+                    vec![("#document".to_owned(), "document".to_owned())]
+                        .into_iter()
+                        .collect(),
+                ),
+                expression_attribute_values: Some(attributes(vec![
+                    (":read", read_document),
+                    (":updated", updated_document),
+                ])),
+                ..Default::default()
+            })
+            .sync()
+        {
+            Ok(_) => Ok(true),
+            Err(UpdateItemError::ConditionalCheckFailed(_)) => Ok(false),
+            Err(err) => Err(format!("failed to update check-in: {}", err)),
+        }
+    }
+
+    /// everyone's check-ins for a day
+    pub fn on(&self, date: NaiveDate) -> Result<Vec<Checkin>, String> {
+        self.query(&date.to_string())
+            .map_err(|err| format!("failed to query check-ins: {}", err))
+    }
+
+    pub fn mark_out(&self, absence: &Absence) -> Result<(), String> {
+        self.put(ABSENCES, &absence.user, absence)
+            .map_err(|err| format!("failed to save absence: {}", err))
+    }
+
+    pub fn mark_back(&self, user: &str) -> Result<(), String> {
+        self.client
+            .delete_item(DeleteItemInput {
+                table_name: self.table.clone(),
+                key: attributes(vec![("date", ABSENCES.into()), ("user", user.to_owned())]),
+                ..Default::default()
+            })
+            .sync()
+            .map(|_| ())
+            .map_err(|err| format!("failed to remove absence: {}", err))
+    }
+
+    /// everyone's absences, including those which have ended
+    pub fn absences(&self) -> Result<Vec<Absence>, String> {
+        self.query(ABSENCES)
+            .map_err(|err| format!("failed to query absences: {}", err))
+    }
+
+    fn put<T>(&self, partition: &str, user: &str, document: &T) -> Result<(), String>
+    where
+        T: Serialize,
+    {
+        let document = serde_json::to_string(document).map_err(|err| err.to_string())?;
+        self.client
+            .put_item(PutItemInput {
+                table_name: self.table.clone(),
+                item: attributes(vec![
+                    ("date", partition.to_owned()),
+                    ("user", user.to_owned()),
+                    ("document", document),
+                ]),
+                ..Default::default()
+            })
+            .sync()
+            .map(|_| ())
+            .map_err(|err| err.to_string())
+    }
+
+    /// the documents stored in a partition
+    fn query<T>(&self, partition: &str) -> Result<Vec<T>, String>
+    where
+        T: DeserializeOwned,
+    {
+        let output = self
+            .client
+            .query(QueryInput {
+                table_name: self.table.clone(),
+                // `date` is a reserved word
+                key_condition_expression: Some("#date = :date".into()),
+                expression_attribute_names: Some(
+                    vec![("#date".to_owned(), "date".to_owned())]
+                        .into_iter()
+                        .collect(),
+                ),
+                expression_attribute_values: Some(attributes(vec![(
+                    ":date",
+                    partition.to_owned(),
+                )])),
+                ..Default::default()
+            })
+            .sync()
+            .map_err(|err| err.to_string())?;
+        Ok(output
+            .items
+            .unwrap_or_default()
+            .iter()
+            .filter_map(document)
+            .collect())
+    }
+}
+
+/// the day check-ins are for, in the team's timezone
+pub fn today(config: &Config) -> NaiveDate {
+    Utc::now()
+        .with_timezone(&config.timezone)
+        .date()
+        .naive_local()
+}
+
+/// marks a user out of office, or back, returning a reply for them
+pub fn mark(config: &Config, checkins: &Checkins, user: &str, ooo: Ooo) -> Result<String, String> {
+    match ooo {
+        Ooo::Until(until) if until < today(config) => {
+            Err(format!("{} has already passed", until.format("%a %b %-d")))
+        }
+        Ooo::Until(until) => checkins
+            .mark_out(&Absence {
+                user: user.to_owned(),
+                until,
+            })
+            .map(|_| {
+                format!(
+                    "🌴 Enjoy your time off! Check-ins are paused through {}",
+                    until.format("%a %b %-d")
+                )
+            }),
+        Ooo::Back => checkins
+            .mark_back(user)
+            .map(|_| "👋 Welcome back! You'll be asked to check in again".into()),
+    }
+}
+
+/// configured users who are out today, by their own say or the shared calendar
+///
+/// failures to find out are logged, leaving everyone in
+fn out_today(config: &Config, checkins: &Checkins) -> Vec<String> {
+    let date = today(config);
+    let mut out = checkins
+        .absences()
+        .unwrap_or_else(|err| {
+            log::error("failed to load absences", json!({ "error": err }));
+            Vec::new()
+        })
+        .into_iter()
+        .filter(|absence| absence.until >= date)
+        .map(|absence| absence.user)
+        .collect::<Vec<_>>();
+    out.extend(calendar_out(config));
+    out.retain(|user| config.checkin_users.contains(user));
+    out.sort_by_key(|user| {
+        config
+            .checkin_users
+            .iter()
+            .position(|configured| configured == user)
+    });
+    out.dedup();
+    out
+}
+
+/// slack ids of the creators of today's out of office events on the shared calendar
+fn calendar_out(config: &Config) -> Vec<String> {
+    let (calendar_id, calendar) = match (
+        config.google_calendar_id.clone(),
+        GoogleCalendar::configured(config),
+    ) {
+        (Some(calendar_id), Some(calendar)) => (calendar_id, calendar),
+        _ => return Vec::new(),
+    };
+    let start = midnight(Utc::now().with_timezone(&config.timezone));
+    let end = start + Duration::days(1);
+    let events = match calendar.events(&calendar_id, &start.to_rfc3339(), &end.to_rfc3339()) {
+        Ok(events) => events,
+        Err(err) => {
+            log::error(
+                "failed to load out of office events",
+                json!({ "error": err.to_string() }),
+            );
+            return Vec::new();
+        }
+    };
+    let users = Directory::new(
+        config.slack_bot_token.clone(),
+        &config.slack_user_overrides,
+        config.http_client(),
+    );
+    events
+        .iter()
+        .filter(|event| is_out(event, &config.calendar_ooo_keywords))
+        .filter_map(|event| event.creator.as_ref()?.email.as_ref())
+        .filter_map(|email| users.id(email))
+        .collect()
+}
+
+/// starts today's check-in with each user by asking the first question.
+/// users who've already been asked today are left to it, so a schedule
+/// which fires twice doesn't throw away their answers. a check-in whose
+/// question couldn't be sent is forgotten, so a retry asks again
+pub fn ask(config: &Config, checkins: &Checkins) -> Result<(), String> {
+    let date = today(config);
+    let out = out_today(config, checkins);
+    let mut failures = Vec::new();
+    for user in config
+        .checkin_users
+        .iter()
+        .filter(|user| !out.contains(user))
+    {
+        let asked = checkins
+            .start(&Checkin::new(date, user.clone()))
+            .and_then(|started| {
+                if !started {
+                    return Ok(());
+                }
+                dm(
+                    config,
+                    user,
+                    &format!("👋 Time for standup! {}", QUESTIONS[0]),
+                )
+                .map_err(|err| {
+                    if let Err(forget_err) = checkins.forget(date, user) {
+                        log::error(
+                            "failed to forget unasked check-in",
+                            json!({ "user_id": user, "error": forget_err }),
+                        );
+                    }
+                    err
+                })
+            });
+        if let Err(err) = asked {
+            log::error(
+                "failed to start check-in",
+                json!({ "user_id": user, "error": err }),
+            );
+            failures.push(user.clone());
+        }
+    }
+    if failures.is_empty() {
+        Ok(log::info(
+            "started check-ins",
+            json!({ "users": config.checkin_users.len() }),
+        ))
+    } else {
+        Err(format!(
+            "failed to start check-ins with {}",
+            failures.join(", ")
+        ))
+    }
+}
+
+/// nudges users who haven't finished checking in, more urgently each time
+pub fn remind(config: &Config, checkins: &Checkins) -> Result<(), String> {
+    let date = today(config);
+    let out = out_today(config, checkins);
+    let mut reminded = 0;
+    for checkin in checkins
+        .on(date)?
+        .into_iter()
+        .filter(|checkin| !out.contains(&checkin.user))
+    {
+        let question = match checkin.next_question() {
+            Some(question) => question,
+            None => continue,
+        };
+        // users who answer in the meantime aren't nagged about the wrong question
+        let reminded_checkin = Checkin {
+            reminders: checkin.reminders + 1,
+            ..checkin.clone()
+        };
+        let sent = checkins
+            .update(&checkin, &reminded_checkin)
+            .and_then(|counted| {
+                if !counted {
+                    return Ok(false);
+                }
+                dm(
+                    config,
+                    &checkin.user,
+                    &reminder(checkin.reminders, question),
+                )
+                .map(|_| true)
+            });
+        match sent {
+            Ok(true) => reminded += 1,
+            Ok(false) => (),
+            Err(err) => log::error(
+                "failed to remind",
+                json!({ "user_id": checkin.user, "error": err }),
+            ),
+        }
+    }
+    Ok(log::info(
+        "reminded check-ins",
+        json!({ "users": reminded }),
+    ))
+}
+
+/// whether an answer to the blocker question reports one, rather than "nope"
+fn is_blocker(answer: &str) -> bool {
+    let answer = answer
+        .trim()
+        .trim_end_matches(|c: char| c.is_ascii_punctuation())
+        .to_lowercase();
+    ![
+        "",
+        "no",
+        "nope",
+        "none",
+        "nothing",
+        "n/a",
+        "na",
+        "-",
+        "no blockers",
+        "nah",
+        "not really",
+        "all good",
+    ]
+    .contains(&answer.as_str())
+}
+
+/// where check-in participation is kept in the standup history, as check-ins aren't
+/// posted to a channel of their own
+pub const HISTORY_CHANNEL: &str = "checkins";
+
+/// Who checked in on a day, as kept in the standup history
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct Participation {
+    pub responded: Vec<String>,
+    /// those expected to check in who didn't, not counting anyone out
+    pub missing: Vec<String>,
+}
+
+/// a reminder which escalates with the number already sent
+fn reminder(sent: u32, question: &str) -> String {
+    match sent {
+        0 => format!(
+            "👋 Standup's coming up, when you get a chance: {}",
+            question
+        ),
+        1 => format!(
+            "⏰ Standup's posting soon, don't forget to check in! {}",
+            question
+        ),
+        _ => format!("🚨 Last call for standup! {}", question),
+    }
+}
+
+/// records a user's DM as the answer to their current question, then asks the next one
+///
+/// Messages from users who weren't asked today are ignored.
+pub fn answer(config: &Config, checkins: &Checkins, user: &str, text: &str) -> Result<(), String> {
+    // answers sent in quick succession, or reminders, may land in between
+    // reading the check-in and updating it, so it's read again until it holds
+    let mut attempts = 0;
+    let checkin = loop {
+        let read = match checkins.get(today(config), user)? {
+            Some(checkin) => checkin,
+            None => return Ok(()),
+        };
+        if read.next_question().is_none() {
+            return dm(config, user, "You're all checked in for today 👍");
+        }
+        let mut checkin = read.clone();
+        checkin.answers.push(text.trim().to_owned());
+        if checkins.update(&read, &checkin)? {
+            break checkin;
+        }
+        attempts += 1;
+        if attempts == ANSWER_ATTEMPTS {
+            return Err("check-in kept changing while answering".into());
+        }
+    };
+    if checkin.answers.len() == QUESTIONS.len() && is_blocker(text) {
+        escalation::escalate(
+            config,
+            "check-ins",
+            &[Blocker {
+                text: format!("<@{}>: {}", user, escape(text.trim())),
+                project: None,
+            }],
+        );
+    }
+    match checkin.next_question() {
+        Some(question) => dm(config, user, question),
+        None => dm(config, user, "Thanks! That's everything for today 🙌"),
+    }
+}
+
+/// posts everyone's answers for today
+pub fn summarize(config: &Config, checkins: &Checkins, sink: &dyn Sink) -> Result<(), String> {
+    let date = today(config);
+    let mut answered = checkins.on(date)?;
+    // in the order users are configured
+    answered.sort_by_key(|checkin| {
+        config
+            .checkin_users
+            .iter()
+            .position(|user| user == &checkin.user)
+            .unwrap_or_else(|| config.checkin_users.len())
+    });
+    let out = out_today(config, checkins);
+    let expected = config
+        .checkin_users
+        .iter()
+        .filter(|user| !out.contains(user))
+        .cloned()
+        .collect::<Vec<_>>();
+    // named rather than mentioned, so they're not bothered while they're out
+    let users = Directory::new(
+        config.slack_bot_token.clone(),
+        &config.slack_user_overrides,
+        config.http_client(),
+    );
+    let out = out.iter().map(|user| users.name(user)).collect::<Vec<_>>();
+    let message = summary(date, &answered, &expected, &out);
+    sink.send(&message)?;
+    if let Some(table) = config.history_table.clone() {
+        let (responded, missing): (Vec<_>, Vec<_>) = expected.into_iter().partition(|user| {
+            answered
+                .iter()
+                .any(|checkin| &checkin.user == user && !checkin.answers.is_empty())
+        });
+        let mut sections = BTreeMap::new();
+        sections.insert(
+            "checkins".to_owned(),
+            json!(Participation { responded, missing }),
+        );
+        let entry = Entry {
+            channel: HISTORY_CHANNEL.into(),
+            posted_at: Utc::now(),
+            sections,
+            message: json!(message),
+            ts: None,
+            permalink: None,
+            command: None,
+        };
+        if let Err(err) = History::new(table).save(&entry) {
+            log::error(
+                "failed to save check-in participation",
+                json!({ "error": err }),
+            );
+        }
+    }
+    Ok(log::info(
+        "summarized check-ins",
+        json!({ "checkins": answered.len() }),
+    ))
+}
+
+/// a section per user who answered at least one question, followed by those of `users` who
+/// didn't and the names of those `out`
+fn summary(date: NaiveDate, checkins: &[Checkin], users: &[String], out: &[String]) -> Message {
+    let text = format!("Check-ins for {}", date.format("%a %b %-d"));
+    let mut blocks = vec![Block::header(format!("📝 {}", text))];
+    for checkin in checkins
+        .iter()
+        .filter(|checkin| !checkin.answers.is_empty())
+    {
+        let answers = QUESTIONS
+            .iter()
+            .zip(&checkin.answers)
+            .map(|(question, answer)| format!("*{}*\n{}", question, escape(answer)))
+            .collect::<Vec<_>>()
+            .join("\n");
+        blocks.push(Block::section(format!("<@{}>\n{}", checkin.user, answers)));
+    }
+    if blocks.len() == 1 {
+        blocks.push(Block::section("Nobody has checked in yet"));
+    }
+    let missing = users
+        .iter()
+        .filter(|user| {
+            !checkins
+                .iter()
+                .any(|checkin| &checkin.user == *user && !checkin.answers.is_empty())
+        })
+        .map(|user| format!("<@{}>", user))
+        .collect::<Vec<_>>();
+    if !missing.is_empty() {
+        blocks.push(Block::context(format!(
+            "😶 No response: {}",
+            missing.join(", ")
+        )));
+    }
+    if !out.is_empty() {
+        blocks.push(Block::context(format!("🌴 Out: {}", out.join(", "))));
+    }
+    Message { text, blocks }
+}
+
+/// sends a user a direct message from the bot
+fn dm(config: &Config, user: &str, text: &str) -> Result<(), String> {
+    let token = config
+        .slack_bot_token
+        .clone()
+        .ok_or_else(|| "check-ins need a slack_bot_token".to_owned())?;
+    Chat::new(token, user.to_owned(), config.http_client()).send(&Message {
+        text: text.into(),
+        blocks: vec![Block::section(text)],
+    })
+}
+
+fn document<T>(item: &HashMap<String, AttributeValue>) -> Option<T>
+where
+    T: DeserializeOwned,
+{
+    item.get("document")
+        .and_then(|document| document.s.as_ref())
+        .and_then(|document| serde_json::from_str(document).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn questions_are_asked_in_order() {
+        let mut checkin = Checkin::new(NaiveDate::from_ymd(2019, 1, 7), "U1".into());
+        assert_eq!(checkin.next_question(), Some(QUESTIONS[0]));
+        checkin.answers.push("shipped it".into());
+        assert_eq!(checkin.next_question(), Some(QUESTIONS[1]));
+        checkin.answers.push("reviews".into());
+        checkin.answers.push("nope".into());
+        assert_eq!(checkin.next_question(), None);
+    }
+
+    #[test]
+    fn blockers_are_told_from_nope() {
+        assert!(is_blocker("Waiting on the API keys from ops"));
+        assert!(!is_blocker("Nope!"));
+        assert!(!is_blocker(" none "));
+    }
+
+    #[test]
+    fn reminders_escalate() {
+        assert!(reminder(0, QUESTIONS[0]).starts_with("👋"));
+        assert!(reminder(1, QUESTIONS[0]).starts_with("⏰"));
+        assert!(reminder(5, QUESTIONS[0]).starts_with("🚨"));
+    }
+
+    #[test]
+    fn summaries_list_answers_by_user() {
+        let date = NaiveDate::from_ymd(2019, 1, 7);
+        let mut answered = Checkin::new(date, "U1".into());
+        answered.answers.push("shipped it <!channel>".into());
+        let message = summary(
+            date,
+            &[answered, Checkin::new(date, "U2".into())],
+            &["U1".into(), "U2".into(), "U3".into()],
+            &["Dana".into(), "Lee".into()],
+        );
+        assert_eq!(message.text, "Check-ins for Mon Jan 7");
+        assert_eq!(
+            message.blocks,
+            vec![
+                Block::header("📝 Check-ins for Mon Jan 7"),
+                Block::section(
+                    "<@U1>\n*What did you get done yesterday?*\nshipped it &lt;!channel&gt;"
+                ),
+                Block::context("😶 No response: <@U2>, <@U3>"),
+                Block::context("🌴 Out: Dana, Lee"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,167 @@
+//! CircleCI api client
+
+// Third party
+use chrono::{DateTime, FixedOffset};
+use reqwest::header::ACCEPT;
+use reqwest::Client;
+use serde::de::DeserializeOwned;
+
+// Ours
+use error::FetchError;
+use retry::{self, Retry};
+use trace;
+
+const API: &str = "https://circleci.com/api/v2";
+
+/// workflow runs link here
+const APP: &str = "https://app.circleci.com";
+
+/// see https://circleci.com/docs/api/v2/#tag/Insights
+#[derive(Deserialize, Debug)]
+struct Items<T> {
+    items: Vec<T>,
+}
+
+#[derive(Deserialize, Debug)]
+struct WorkflowSummary {
+    name: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct WorkflowRun {
+    pub id: String,
+    /// `success`, `failed`, `error`, `canceled` etc.
+    pub status: String,
+    pub created_at: DateTime<FixedOffset>,
+}
+
+impl WorkflowRun {
+    pub fn url(&self) -> String {
+        format!("{}/pipelines/workflows/{}", APP, self.id)
+    }
+}
+
+pub struct CircleCi {
+    api: String,
+    token: String,
+    client: Client,
+    retry: Retry,
+}
+
+impl CircleCi {
+    pub fn new(token: String, client: Client, retry: Retry) -> Self {
+        CircleCi {
+            api: API.into(),
+            token,
+            client,
+            retry,
+        }
+    }
+
+    /// points requests at another api host, i.e. a mock server
+    pub fn with_api(self, api: String) -> Self {
+        CircleCi { api, ..self }
+    }
+
+    /// names of the workflows which have run on a branch of a project
+    pub fn workflows(&self, repo: &str, branch: &str) -> Result<Vec<String>, FetchError> {
+        self.get::<Items<WorkflowSummary>>(
+            &format!("/insights/{}/workflows", slug(repo)),
+            &[("branch", branch)],
+        )
+        .map(|workflows| {
+            workflows
+                .items
+                .into_iter()
+                .map(|workflow| workflow.name)
+                .collect()
+        })
+    }
+
+    /// the most recent runs of a workflow on a branch of a project, newest first
+    pub fn workflow_runs(
+        &self,
+        repo: &str,
+        workflow: &str,
+        branch: &str,
+    ) -> Result<Vec<WorkflowRun>, FetchError> {
+        self.get::<Items<WorkflowRun>>(
+            &format!("/insights/{}/workflows/{}", slug(repo), workflow),
+            &[("branch", branch)],
+        )
+        .map(|runs| runs.items)
+    }
+
+    fn get<T>(&self, path: &str, query: &[(&str, &str)]) -> Result<T, FetchError>
+    where
+        T: DeserializeOwned,
+    {
+        self.retry
+            .run(
+                || {
+                    trace::request("CircleCI", path, || {
+                        self.client
+                            .get(&format!("{}{}", self.api, path))
+                            .query(query)
+                            .header(ACCEPT, "application/json")
+                            .header("Circle-Token", self.token.as_str())
+                            .send()
+                            .and_then(|response| response.error_for_status())
+                            .and_then(|mut response| response.json::<T>())
+                    })
+                },
+                retry::http,
+            )
+            .map_err(FetchError::circleci)
+    }
+}
+
+/// circleci project slug, `owner/name` repositories are assumed to be on github
+fn slug(repo: &str) -> String {
+    if repo.matches('/').count() > 1 {
+        repo.to_owned()
+    } else {
+        format!("gh/{}", repo)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::{self, mock, Matcher};
+    use retry::Retry;
+
+    #[test]
+    fn repositories_default_to_github_projects() {
+        assert_eq!(slug("meetup/slack-standup"), "gh/meetup/slack-standup");
+        assert_eq!(slug("bb/meetup/slack-standup"), "bb/meetup/slack-standup");
+    }
+
+    #[test]
+    fn workflow_runs_are_listed_for_a_branch() {
+        let _runs = mock("GET", "/insights/gh/meetup/slack-standup/workflows/build")
+            .match_query(Matcher::UrlEncoded("branch".into(), "main".into()))
+            .match_header("circle-token", "secret")
+            .with_body(
+                r#"{"items":[{"id":"abc","status":"failed","created_at":"2019-01-07T15:00:00Z","stopped_at":"2019-01-07T15:05:00Z"}],"next_page_token":null}"#,
+            )
+            .create();
+        let runs = CircleCi::new(
+            "secret".into(),
+            Client::new(),
+            Retry {
+                attempts: 1,
+                backoff_ms: 0,
+            },
+        )
+        .with_api(mockito::server_url())
+        .workflow_runs("meetup/slack-standup", "build", "main")
+        .unwrap();
+        assert_eq!(runs.len(), 1);
+        assert_eq!(runs[0].status, "failed");
+        assert_eq!(
+            runs[0].url(),
+            "https://app.circleci.com/pipelines/workflows/abc"
+        );
+    }
+}
@@ -0,0 +1,441 @@
+//! Slash command argument parsing
+
+// Third party
+use chrono::NaiveDate;
+
+/// longest lookback window which may be requested
+const MAX_LOOKBACK_DAYS: i64 = 90;
+
+/// usage instructions listing the sections which may be requested
+pub fn usage(sections: &[String]) -> String {
+    format!(
+        "Usage: `/standup [all|{}] [since=DAYS] [by=status|assignee|epic] [show=closed|resolved] [brief] [preview] [fresh]`, `/standup weekly`, `/standup stats`, `/standup release-notes VERSION`, `/standup ooo until YYYY-MM-DD|back`, `/standup skip-me` or `/standup config`\n• `all` the full standup (default)\n{}\n• `since=DAYS` report on the last DAYS days\n• `by=assignee` group jira issues by who's working on them\n• `by=epic` group jira issues under their epics, with each epic's progress\n• `show=closed` or `show=resolved` list closed issues or resolved incidents\n• `brief` only the summary and open P1 incidents\n• `preview` show it only to you first, with a button to post it\n• `fresh` skip cached jira and pagerduty responses\n• `weekly` a digest of this week's standups\n• `stats` check-in and issue movement per person over the past month\n• `release-notes VERSION` release notes for a jira fix version\n• `ooo until YYYY-MM-DD` skip your check-ins until you're back, or `ooo back` to resume them\n• `skip-me` bow out of facilitating today",
+        sections.join("|"),
+        sections
+            .iter()
+            .map(|section| format!("• `{}` only the {} section", section, section))
+            .collect::<Vec<_>>()
+            .join("\n")
+    )
+}
+
+/// How jira issues are grouped
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Grouping {
+    Status,
+    Assignee,
+    Epic,
+}
+
+impl Default for Grouping {
+    fn default() -> Self {
+        Grouping::Status
+    }
+}
+
+/// Details interactive standups collapse until they're asked for
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Detail {
+    /// jira issues closed within the lookback window
+    Closed,
+    /// pagerduty incidents resolved within the lookback window
+    Resolved,
+}
+
+impl Detail {
+    pub fn all() -> Vec<Detail> {
+        vec![Detail::Closed, Detail::Resolved]
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Detail::Closed => "closed",
+            Detail::Resolved => "resolved",
+        }
+    }
+}
+
+/// A change to whether the invoking user is out of office
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Ooo {
+    /// out through a date
+    Until(NaiveDate),
+    Back,
+}
+
+/// A parsed `/standup` invocation
+#[derive(Debug, PartialEq, Clone)]
+pub struct Command {
+    /// names of the sections to include
+    pub sections: Vec<String>,
+    /// overrides the configured lookback window
+    pub lookback_days: Option<i64>,
+    /// requests a digest of the week's standups instead of a standup
+    pub weekly: bool,
+    /// requests participation stats instead of a standup
+    pub stats: bool,
+    /// shows the standup only to the invoking user, with a button to post it
+    pub preview: bool,
+    /// posts a previewed standup to the channel
+    pub post: bool,
+    /// only the summary and open P1 incidents, see `Config::slack_brief`
+    pub brief: bool,
+    pub grouping: Grouping,
+    /// details to show in interactive standups
+    pub details: Vec<Detail>,
+    /// requests release notes for a jira fix version instead of a standup
+    pub release_notes: Option<String>,
+    /// marks the invoking user out of office, or back, instead of a standup
+    pub ooo: Option<Ooo>,
+    /// bows the invoking user out of facilitating today instead of a standup
+    pub skip_me: bool,
+    /// fetches without reusing or keeping cached responses
+    pub fresh: bool,
+}
+
+impl Command {
+    /// the full standup
+    pub fn all(sections: &[String]) -> Command {
+        Command {
+            sections: sections.to_vec(),
+            lookback_days: None,
+            weekly: false,
+            stats: false,
+            preview: false,
+            post: false,
+            brief: false,
+            grouping: Grouping::default(),
+            details: Vec::new(),
+            release_notes: None,
+            ooo: None,
+            skip_me: false,
+            fresh: false,
+        }
+    }
+
+    /// parses the text following the slash command, given the available section names
+    pub fn parse(text: &str, sections: &[String]) -> Result<Command, String> {
+        let mut command = Command {
+            sections: Vec::new(),
+            lookback_days: None,
+            weekly: false,
+            stats: false,
+            preview: false,
+            post: false,
+            brief: false,
+            grouping: Grouping::default(),
+            details: Vec::new(),
+            release_notes: None,
+            ooo: None,
+            skip_me: false,
+            fresh: false,
+        };
+        // versions are case sensitive, so they're taken before lowercasing
+        let mut words = text.trim().splitn(2, char::is_whitespace);
+        if words
+            .next()
+            .map(|word| word.eq_ignore_ascii_case("release-notes"))
+            .unwrap_or(false)
+        {
+            let version = words.next().unwrap_or_default().trim();
+            if version.is_empty() {
+                return Err(
+                    "`release-notes` needs a fix version, i.e. `release-notes 2.4.0`".into(),
+                );
+            }
+            command.release_notes = Some(version.to_owned());
+            return Ok(command);
+        }
+        let args = text.to_lowercase();
+        let args = args.split_whitespace().collect::<Vec<_>>();
+        if args.first() == Some(&"ooo") {
+            command.ooo = Some(ooo(&args[1..])?);
+            return Ok(command);
+        }
+        for arg in args {
+            match arg {
+                "all" => command.sections.extend(sections.iter().cloned()),
+                "weekly" => command.weekly = true,
+                "stats" => command.stats = true,
+                "preview" => command.preview = true,
+                "post" => command.post = true,
+                "brief" => command.brief = true,
+                "skip-me" => command.skip_me = true,
+                "fresh" => command.fresh = true,
+                name if sections.iter().any(|section| section == name) => {
+                    command.sections.push(name.to_owned())
+                }
+                _ if arg.starts_with("since=") => {
+                    command.lookback_days = Some(lookback_days(&arg["since=".len()..])?)
+                }
+                _ if arg.starts_with("by=") => command.grouping = grouping(&arg["by=".len()..])?,
+                _ if arg.starts_with("show=") => {
+                    let detail = detail(&arg["show=".len()..])?;
+                    if !command.details.contains(&detail) {
+                        command.details.push(detail)
+                    }
+                }
+                other => return Err(format!("unknown argument `{}`", other)),
+            }
+        }
+        if command.sections.is_empty() {
+            command.sections = sections.to_vec();
+        }
+        Ok(command)
+    }
+
+    pub fn includes(&self, section: &str) -> bool {
+        self.sections.iter().any(|included| included == section)
+    }
+
+    pub fn shows(&self, detail: Detail) -> bool {
+        self.details.contains(&detail)
+    }
+
+    /// this command with a detail shown
+    pub fn showing(&self, detail: Detail) -> Command {
+        let mut command = self.clone();
+        if !command.shows(detail) {
+            command.details.push(detail);
+        }
+        command
+    }
+
+    /// the standup this command produces, regardless of how it's delivered
+    pub fn standup(&self) -> Command {
+        let mut command = self.clone();
+        command.preview = false;
+        command.post = false;
+        command.fresh = false;
+        command
+    }
+
+    /// this command posting a previewed standup
+    pub fn posting(&self) -> Command {
+        let mut command = self.clone();
+        command.preview = false;
+        command.post = true;
+        command
+    }
+
+    /// the arguments which parse as this command
+    pub fn text(&self) -> String {
+        if let Some(ref version) = self.release_notes {
+            return format!("release-notes {}", version);
+        }
+        match self.ooo {
+            Some(Ooo::Until(date)) => return format!("ooo until {}", date),
+            Some(Ooo::Back) => return "ooo back".into(),
+            None => (),
+        }
+        let mut args = if self.skip_me {
+            vec!["skip-me".to_owned()]
+        } else if self.weekly {
+            vec!["weekly".to_owned()]
+        } else if self.stats {
+            vec!["stats".to_owned()]
+        } else {
+            self.sections.clone()
+        };
+        if self.brief {
+            args.push("brief".into());
+        }
+        if self.preview {
+            args.push("preview".into());
+        }
+        if self.post {
+            args.push("post".into());
+        }
+        if self.fresh {
+            args.push("fresh".into());
+        }
+        if let Some(days) = self.lookback_days {
+            args.push(format!("since={}", days));
+        }
+        match self.grouping {
+            Grouping::Status => (),
+            Grouping::Assignee => args.push("by=assignee".into()),
+            Grouping::Epic => args.push("by=epic".into()),
+        }
+        args.extend(
+            self.details
+                .iter()
+                .map(|detail| format!("show={}", detail.name())),
+        );
+        args.join(" ")
+    }
+}
+
+fn lookback_days(value: &str) -> Result<i64, String> {
+    match value.parse::<i64>() {
+        Ok(days) if days > 0 && days <= MAX_LOOKBACK_DAYS => Ok(days),
+        _ => Err(format!(
+            "`since` must be a number of days between 1 and {}",
+            MAX_LOOKBACK_DAYS
+        )),
+    }
+}
+
+fn ooo(args: &[&str]) -> Result<Ooo, String> {
+    match (args.len(), args.first().cloned()) {
+        (1, Some("back")) => Ok(Ooo::Back),
+        (2, Some("until")) => args[1]
+            .parse()
+            .map(Ooo::Until)
+            .map_err(|_| format!("`{}` isn't a date like 2024-07-05", args[1])),
+        _ => Err("`ooo` must be followed by `until YYYY-MM-DD` or `back`".into()),
+    }
+}
+
+fn grouping(value: &str) -> Result<Grouping, String> {
+    match value {
+        "status" => Ok(Grouping::Status),
+        "assignee" => Ok(Grouping::Assignee),
+        "epic" => Ok(Grouping::Epic),
+        _ => Err("`by` must be one of `status`, `assignee` or `epic`".into()),
+    }
+}
+
+fn detail(value: &str) -> Result<Detail, String> {
+    Detail::all()
+        .into_iter()
+        .find(|detail| detail.name() == value)
+        .ok_or_else(|| "`show` must be one of `closed` or `resolved`".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sections() -> Vec<String> {
+        vec!["weather".into(), "jira".into()]
+    }
+
+    #[test]
+    fn standups_ignore_how_theyre_delivered() {
+        let command = Command::parse("jira since=7 preview fresh", &sections()).unwrap();
+        assert_eq!(command.standup().text(), "jira since=7");
+        assert_eq!(
+            command.posting().standup(),
+            Command::parse("jira since=7", &sections()).unwrap()
+        );
+    }
+
+    #[test]
+    fn defaults_to_all_sections() {
+        assert_eq!(
+            Command::parse("", &sections()),
+            Ok(Command::all(&sections()))
+        );
+        assert_eq!(
+            Command::parse("all", &sections()),
+            Ok(Command::all(&sections()))
+        );
+    }
+
+    #[test]
+    fn parses_sections_and_options() {
+        let command = Command::parse("Jira since=3 by=assignee", &sections()).unwrap();
+        assert_eq!(command.sections, vec!["jira".to_owned()]);
+        assert_eq!(command.lookback_days, Some(3));
+        assert_eq!(command.grouping, Grouping::Assignee);
+        assert!(command.includes("jira"));
+        assert!(!command.includes("weather"));
+    }
+
+    #[test]
+    fn parses_by_epic() {
+        let command = Command::parse("jira by=epic", &sections()).unwrap();
+        assert_eq!(command.grouping, Grouping::Epic);
+        assert_eq!(command.text(), "jira by=epic");
+    }
+
+    #[test]
+    fn fresh_standups_skip_the_cache() {
+        let command = Command::parse("jira fresh", &sections()).unwrap();
+        assert!(command.fresh);
+        assert_eq!(command.text(), "jira fresh");
+        assert!(!Command::parse("jira", &sections()).unwrap().fresh);
+    }
+
+    #[test]
+    fn previews_post_the_same_standup() {
+        let command = Command::parse("jira preview since=3", &sections()).unwrap();
+        assert!(command.preview);
+        assert_eq!(command.text(), "jira preview since=3");
+        let posting = Command::parse(&command.posting().text(), &sections()).unwrap();
+        assert!(!posting.preview);
+        assert!(posting.post);
+        assert_eq!(posting.sections, command.sections);
+    }
+
+    #[test]
+    fn parses_brief() {
+        let command = Command::parse("brief", &sections()).unwrap();
+        assert!(command.brief);
+        assert_eq!(command.sections, sections());
+        assert_eq!(command.text(), "weather jira brief");
+    }
+
+    #[test]
+    fn parses_stats() {
+        let command = Command::parse("stats", &sections()).unwrap();
+        assert!(command.stats);
+        assert_eq!(command.text(), "stats");
+    }
+
+    #[test]
+    fn parses_weekly() {
+        assert!(Command::parse("weekly", &sections()).unwrap().weekly);
+    }
+
+    #[test]
+    fn parses_release_notes_versions_verbatim() {
+        let command = Command::parse("Release-Notes  Mobile 2.4.0 ", &sections()).unwrap();
+        assert_eq!(command.release_notes, Some("Mobile 2.4.0".into()));
+        assert_eq!(command.text(), "release-notes Mobile 2.4.0");
+        assert_eq!(Command::parse(&command.text(), &sections()), Ok(command));
+        assert!(Command::parse("release-notes", &sections()).is_err());
+    }
+
+    #[test]
+    fn parses_skip_me() {
+        let command = Command::parse("skip-me", &sections()).unwrap();
+        assert!(command.skip_me);
+        assert_eq!(Command::parse(&command.text(), &sections()), Ok(command));
+    }
+
+    #[test]
+    fn parses_ooo() {
+        let command = Command::parse("ooo until 2024-07-05", &sections()).unwrap();
+        assert_eq!(
+            command.ooo,
+            Some(Ooo::Until(NaiveDate::from_ymd(2024, 7, 5)))
+        );
+        assert_eq!(command.text(), "ooo until 2024-07-05");
+        assert_eq!(
+            Command::parse("OOO back", &sections()).unwrap().ooo,
+            Some(Ooo::Back)
+        );
+        assert!(Command::parse("ooo until friday", &sections()).is_err());
+        assert!(Command::parse("ooo", &sections()).is_err());
+    }
+
+    #[test]
+    fn round_trips_as_text() {
+        let command = Command::parse("jira since=3 by=assignee", &sections())
+            .unwrap()
+            .showing(Detail::Closed);
+        assert_eq!(command.text(), "jira since=3 by=assignee show=closed");
+        assert_eq!(Command::parse(&command.text(), &sections()), Ok(command));
+    }
+
+    #[test]
+    fn rejects_invalid_arguments() {
+        assert!(Command::parse("since=0", &sections()).is_err());
+        assert!(Command::parse("since=91", &sections()).is_err());
+        assert!(Command::parse("by=priority", &sections()).is_err());
+        assert!(Command::parse("show=everything", &sections()).is_err());
+        assert!(Command::parse("github", &sections()).is_err());
+    }
+}
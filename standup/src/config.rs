@@ -0,0 +1,1057 @@
+//! App configuration
+
+// Std lib
+use std::collections::HashMap;
+use std::env;
+use std::fmt::Display;
+use std::str::FromStr;
+
+// Third party
+use chrono_tz::Tz;
+use envy;
+use reqwest::Client;
+use serde::de::Error;
+use serde::{Deserialize, Deserializer};
+
+// Ours
+use http;
+use secrets;
+use tenants::Overrides;
+
+/// Where scheduled standups are delivered
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Destination {
+    Slack,
+    Teams,
+    Discord,
+}
+
+impl Default for Destination {
+    fn default() -> Self {
+        Destination::Slack
+    }
+}
+
+impl FromStr for Destination {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Destination, String> {
+        match value.to_lowercase().as_str() {
+            "slack" => Ok(Destination::Slack),
+            "teams" => Ok(Destination::Teams),
+            "discord" => Ok(Destination::Discord),
+            other => Err(format!("unknown destination `{}`", other)),
+        }
+    }
+}
+
+/// Where the weather report's incidents and on calls come from
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum IncidentSource {
+    PagerDuty,
+    Opsgenie,
+}
+
+impl Default for IncidentSource {
+    fn default() -> Self {
+        IncidentSource::PagerDuty
+    }
+}
+
+impl FromStr for IncidentSource {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<IncidentSource, String> {
+        match value.to_lowercase().as_str() {
+            "pagerduty" => Ok(IncidentSource::PagerDuty),
+            "opsgenie" => Ok(IncidentSource::Opsgenie),
+            other => Err(format!("unknown incident source `{}`", other)),
+        }
+    }
+}
+
+/// How requests to jira are authorized, see `jira_auth`
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum JiraAuth {
+    /// basic auth with `jira_user` and `jira_password`
+    Password,
+    /// basic auth with `jira_user`'s email and `jira_api_token`
+    ApiToken,
+    /// 3LO OAuth through atlassian's api gateway
+    OAuth,
+}
+
+impl Default for JiraAuth {
+    fn default() -> Self {
+        JiraAuth::Password
+    }
+}
+
+impl FromStr for JiraAuth {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<JiraAuth, String> {
+        match value.to_lowercase().as_str() {
+            "password" => Ok(JiraAuth::Password),
+            "api_token" => Ok(JiraAuth::ApiToken),
+            "oauth" => Ok(JiraAuth::OAuth),
+            other => Err(format!("unknown jira auth `{}`", other)),
+        }
+    }
+}
+
+/// How issues are ordered within a jira group, see `jira_issue_order`
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum IssueOrder {
+    /// most recently resolved first when closed, longest in their status first when in flight
+    Relevance,
+    /// most recently updated first
+    Updated,
+    /// by project, then issue number
+    Key,
+}
+
+impl Default for IssueOrder {
+    fn default() -> Self {
+        IssueOrder::Relevance
+    }
+}
+
+impl FromStr for IssueOrder {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<IssueOrder, String> {
+        match value.to_lowercase().as_str() {
+            "relevance" => Ok(IssueOrder::Relevance),
+            "updated" => Ok(IssueOrder::Updated),
+            "key" => Ok(IssueOrder::Key),
+            other => Err(format!("unknown jira issue order `{}`", other)),
+        }
+    }
+}
+
+/// How subtasks listed along with their parent are shown, see `jira_subtasks`
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Subtasks {
+    /// listed under their parent
+    Nest,
+    /// counted in a note on their parent
+    Count,
+}
+
+impl Default for Subtasks {
+    fn default() -> Self {
+        Subtasks::Nest
+    }
+}
+
+impl FromStr for Subtasks {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Subtasks, String> {
+        match value.to_lowercase().as_str() {
+            "nest" => Ok(Subtasks::Nest),
+            "count" => Ok(Subtasks::Count),
+            other => Err(format!("unknown jira subtasks display `{}`", other)),
+        }
+    }
+}
+
+/// Where build health is reported from
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum CiProvider {
+    GitHubActions,
+    CircleCi,
+}
+
+impl Default for CiProvider {
+    fn default() -> Self {
+        CiProvider::GitHubActions
+    }
+}
+
+impl FromStr for CiProvider {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<CiProvider, String> {
+        match value.to_lowercase().as_str() {
+            "github" => Ok(CiProvider::GitHubActions),
+            "circleci" => Ok(CiProvider::CircleCi),
+            other => Err(format!("unknown ci provider `{}`", other)),
+        }
+    }
+}
+
+/// Where deploys are reported from
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum DeploySource {
+    GitHub,
+    DynamoDb,
+}
+
+impl Default for DeploySource {
+    fn default() -> Self {
+        DeploySource::GitHub
+    }
+}
+
+impl FromStr for DeploySource {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<DeploySource, String> {
+        match value.to_lowercase().as_str() {
+            "github" => Ok(DeploySource::GitHub),
+            "dynamodb" => Ok(DeploySource::DynamoDb),
+            other => Err(format!("unknown deploy source `{}`", other)),
+        }
+    }
+}
+
+/// How scheduled standups are published to Confluence
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ConfluencePublishing {
+    Off,
+    /// appended to the running page `confluence_page_id`
+    Append,
+    /// created as dated pages, under `confluence_parent_id` when set
+    Pages,
+}
+
+impl Default for ConfluencePublishing {
+    fn default() -> Self {
+        ConfluencePublishing::Off
+    }
+}
+
+impl FromStr for ConfluencePublishing {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<ConfluencePublishing, String> {
+        match value.to_lowercase().as_str() {
+            "off" => Ok(ConfluencePublishing::Off),
+            "append" => Ok(ConfluencePublishing::Append),
+            "pages" => Ok(ConfluencePublishing::Pages),
+            other => Err(format!("unknown confluence publishing `{}`", other)),
+        }
+    }
+}
+
+/// app configuration ( sourced from env variables )
+#[derive(Deserialize)]
+pub struct Config {
+    /// names of the sections to include, in display order
+    #[serde(default = "default_sections")]
+    pub standup_sections: Vec<String>,
+    /// IANA name of the timezone the team works in
+    #[serde(default = "default_timezone", deserialize_with = "timezone")]
+    pub timezone: Tz,
+    /// `YYYY-MM-DD` days off, skipped over when looking back to the last business day
+    #[serde(default, deserialize_with = "list")]
+    pub holidays: Vec<String>,
+    /// iCal feed of additional holidays
+    #[serde(default, deserialize_with = "optional")]
+    pub holiday_calendar_url: Option<String>,
+    /// fixed number of days to report on, instead of looking back to the last business day
+    #[serde(default, deserialize_with = "optional_parsed")]
+    pub lookback_days: Option<i64>,
+    /// `pagerduty` (default) or `opsgenie`
+    #[serde(default, deserialize_with = "parsed_or_default")]
+    pub incident_source: IncidentSource,
+    /// needed when `incident_source` is `pagerduty`
+    #[serde(default)]
+    pub pd_token: String,
+    #[serde(default, deserialize_with = "list")]
+    pub pd_team_ids: Vec<String>,
+    /// policies to report on call users for, defaults to those of `pd_team_ids`
+    #[serde(default, deserialize_with = "list")]
+    pub pd_escalation_policy_ids: Vec<String>,
+    /// services to report incidents on, defaults to all of `pd_team_ids`' services
+    #[serde(default, deserialize_with = "list")]
+    pub pd_service_ids: Vec<String>,
+    /// `high` and/or `low`, defaults to both
+    #[serde(default, deserialize_with = "list")]
+    pub pd_urgencies: Vec<String>,
+    /// report the past week's incident count, MTTA and MTTR against the week before
+    #[serde(default, deserialize_with = "flag")]
+    pub pd_analytics: bool,
+    /// needed when `incident_source` is `opsgenie`
+    #[serde(default, deserialize_with = "optional")]
+    pub opsgenie_api_key: Option<String>,
+    /// api host, i.e. `https://api.eu.opsgenie.com` for the eu region
+    #[serde(default, deserialize_with = "optional")]
+    pub opsgenie_api_url: Option<String>,
+    /// names of teams to report alerts for, defaults to all
+    #[serde(default, deserialize_with = "list")]
+    pub opsgenie_teams: Vec<String>,
+    /// names of schedules to report on call users for
+    #[serde(default, deserialize_with = "list")]
+    pub opsgenie_schedules: Vec<String>,
+    pub jira_host: String,
+    /// `password` (default), `api_token` or `oauth`
+    #[serde(default, deserialize_with = "parsed_or_default")]
+    pub jira_auth: JiraAuth,
+    /// needed unless `jira_auth` is `oauth`, an email address with `api_token`
+    #[serde(default)]
+    pub jira_user: String,
+    /// needed when `jira_auth` is `password`
+    #[serde(default)]
+    pub jira_password: String,
+    /// needed when `jira_auth` is `api_token`
+    #[serde(default, deserialize_with = "optional")]
+    pub jira_api_token: Option<String>,
+    /// needed when `jira_auth` is `oauth`, along with a client secret and refresh token
+    #[serde(default, deserialize_with = "optional")]
+    pub jira_oauth_client_id: Option<String>,
+    #[serde(default, deserialize_with = "optional")]
+    pub jira_oauth_client_secret: Option<String>,
+    /// the first refresh token, superseded by those kept in `jira_token_table`
+    #[serde(default, deserialize_with = "optional")]
+    pub jira_oauth_refresh_token: Option<String>,
+    /// the id of the site oauth requests are made to
+    #[serde(default, deserialize_with = "optional")]
+    pub jira_cloud_id: Option<String>,
+    /// DynamoDB table keeping rotated oauth refresh tokens
+    #[serde(default, deserialize_with = "optional")]
+    pub jira_token_table: Option<String>,
+    /// project keys, i.e. `CORE`, which workflow statuses are discovered by
+    pub jira_projects: Vec<String>,
+    /// labels shipped and in flight issues must have one of, scoping a shared project to a team
+    #[serde(default, deserialize_with = "list")]
+    pub jira_labels: Vec<String>,
+    /// components shipped and in flight issues must be in one of
+    #[serde(default, deserialize_with = "list")]
+    pub jira_components: Vec<String>,
+    /// statuses of work considered in flight, where empty counts every status
+    /// jira categorizes as in progress
+    #[serde(default, deserialize_with = "list")]
+    pub jira_in_flight_statuses: Vec<String>,
+    /// labels marking issues as blocked, in addition to jira's impediment flag
+    #[serde(default = "default_blocked_labels", deserialize_with = "list")]
+    pub jira_blocked_labels: Vec<String>,
+    /// resolutions of closed issues which count as shipped, where empty counts them all
+    #[serde(default = "default_shipped_resolutions", deserialize_with = "list")]
+    pub jira_shipped_resolutions: Vec<String>,
+    /// statuses of work which is blocked
+    #[serde(default = "default_blocked_statuses", deserialize_with = "list")]
+    pub jira_blocked_statuses: Vec<String>,
+    /// `status=emoji` pairs, statuses without one are marked by their workflow category
+    #[serde(default = "default_status_emoji", deserialize_with = "list")]
+    pub jira_status_emoji: Vec<String>,
+    /// `issue type=emoji` pairs issues are prefixed with
+    #[serde(default = "default_type_emoji", deserialize_with = "list")]
+    pub jira_type_emoji: Vec<String>,
+    /// `priority=emoji` pairs issues are prefixed with
+    #[serde(default = "default_priority_emoji", deserialize_with = "list")]
+    pub jira_priority_emoji: Vec<String>,
+    /// order statuses are displayed in, unlisted statuses follow in workflow
+    /// order and then alphabetically
+    #[serde(default = "default_status_order", deserialize_with = "list")]
+    pub jira_status_order: Vec<String>,
+    /// agile board the sprint section reports on
+    #[serde(default, deserialize_with = "optional_parsed")]
+    pub jira_board_id: Option<u64>,
+    /// days without an update after which in flight issues are flagged as stale
+    #[serde(default, deserialize_with = "optional_parsed")]
+    pub jira_stale_days: Option<i64>,
+    /// custom field issues link to their epic through, i.e. `customfield_10014`.
+    /// without it, epics are issues' `parent`s, as in team managed projects
+    #[serde(default, deserialize_with = "optional")]
+    pub jira_epic_link_field: Option<String>,
+    /// `field id=label` pairs of extra fields listed with issues, in order,
+    /// i.e. `customfield_10100=Squad`
+    #[serde(default, deserialize_with = "list")]
+    pub jira_display_fields: Vec<String>,
+    /// `status=limit` pairs, most issues that should be in a status at once
+    #[serde(default, deserialize_with = "list")]
+    pub jira_wip_limits: Vec<String>,
+    /// days in one status after which in flight issues are flagged
+    #[serde(default, deserialize_with = "optional_parsed")]
+    pub jira_status_warning_days: Option<i64>,
+    /// list in flight issues due within the week, and overdue, under their own heading
+    #[serde(default, deserialize_with = "flag")]
+    pub jira_due_this_week: bool,
+    /// most issues listed under a status, the rest are summarized with a link to jira
+    #[serde(default, deserialize_with = "optional_parsed")]
+    pub jira_group_limit: Option<usize>,
+    /// statuses of in flight issues annotated with the review state of their linked pull requests
+    #[serde(default = "default_review_statuses", deserialize_with = "list")]
+    pub jira_review_statuses: Vec<String>,
+    /// source control integration pull requests are linked through, i.e. `GitHub` or `bitbucket`
+    #[serde(default = "default_dev_status_application")]
+    pub jira_dev_status_application: String,
+    /// `nest` (default) or `count`
+    #[serde(default, deserialize_with = "parsed_or_default")]
+    pub jira_subtasks: Subtasks,
+    /// `relevance` (default), `updated` or `key`
+    #[serde(default, deserialize_with = "parsed_or_default")]
+    pub jira_issue_order: IssueOrder,
+    /// saved filter the link to issues beyond `jira_group_limit` opens,
+    /// instead of a search for the group's issues. ignored with
+    /// `jira_shipped_filter` or `jira_in_flight_filter`, as searches for
+    /// groups then go through those
+    #[serde(default, deserialize_with = "optional_parsed")]
+    pub jira_filter_id: Option<u64>,
+    /// saved filter finding shipped issues, instead of done issues in `jira_projects`.
+    /// only those it finds done and resolved since the last standup are reported
+    #[serde(default, deserialize_with = "optional_parsed")]
+    pub jira_shipped_filter: Option<u64>,
+    /// saved filter finding in flight issues, instead of issues in `jira_projects`
+    /// in `jira_in_flight_statuses`. done issues it finds aren't reported
+    #[serde(default, deserialize_with = "optional_parsed")]
+    pub jira_in_flight_filter: Option<u64>,
+    /// most issues each of the jira section's searches fetches, those beyond
+    /// it are counted but not listed. zero doesn't cap them
+    #[serde(default = "default_jira_max_results")]
+    pub jira_max_results: usize,
+    /// custom field story points are estimated in, i.e. `customfield_10002`
+    #[serde(default, deserialize_with = "optional")]
+    pub jira_story_points_field: Option<String>,
+    /// upcoming fix version the release section tracks, i.e. `2.4.0`
+    #[serde(default, deserialize_with = "optional")]
+    pub jira_fix_version: Option<String>,
+    /// used to search for pull requests
+    #[serde(default, deserialize_with = "optional")]
+    pub github_token: Option<String>,
+    /// `owner/name` repositories to report pull requests for
+    #[serde(default, deserialize_with = "list")]
+    pub github_repos: Vec<String>,
+    /// organization to report pull requests for
+    #[serde(default, deserialize_with = "optional")]
+    pub github_org: Option<String>,
+    /// where build health is reported from, `github` actions (default) or `circleci`
+    #[serde(default, deserialize_with = "parsed_or_default")]
+    pub ci_provider: CiProvider,
+    /// `owner/name` repositories to report build health for, defaults to `github_repos`
+    #[serde(default, deserialize_with = "list")]
+    pub ci_repos: Vec<String>,
+    /// branch builds are reported for
+    #[serde(default = "default_ci_branch")]
+    pub ci_branch: String,
+    /// hours after which failing workflows are called out
+    #[serde(default = "default_ci_red_hours")]
+    pub ci_red_hours: i64,
+    /// needed when `ci_provider` is `circleci`
+    #[serde(default, deserialize_with = "optional")]
+    pub circleci_token: Option<String>,
+    /// where deploys are reported from, `github` deployments (default) or a `dynamodb` deploy log
+    #[serde(default, deserialize_with = "parsed_or_default")]
+    pub deploy_source: DeploySource,
+    /// environment deploys are reported for
+    #[serde(default = "default_deploy_environment")]
+    pub deploy_environment: String,
+    /// `owner/name` repositories to report github deployments for, defaults to `github_repos`
+    #[serde(default, deserialize_with = "list")]
+    pub deploy_repos: Vec<String>,
+    /// DynamoDB table the pipeline logs deploys to, see `deploys`
+    #[serde(default, deserialize_with = "optional")]
+    pub deploy_table: Option<String>,
+    /// id of the shared google calendar the today section reports on
+    #[serde(default, deserialize_with = "optional")]
+    pub google_calendar_id: Option<String>,
+    /// oauth client the refresh token was issued to
+    #[serde(default, deserialize_with = "optional")]
+    pub google_client_id: Option<String>,
+    #[serde(default, deserialize_with = "optional")]
+    pub google_client_secret: Option<String>,
+    /// offline refresh token of an account with read access to the calendar
+    #[serde(default, deserialize_with = "optional")]
+    pub google_refresh_token: Option<String>,
+    /// words in the titles of all day events marking someone as out of office
+    #[serde(default = "default_ooo_keywords", deserialize_with = "list")]
+    pub calendar_ooo_keywords: Vec<String>,
+    /// seconds to wait on any one upstream request
+    #[serde(default = "default_http_timeout_secs")]
+    pub http_timeout_secs: u64,
+    /// proxy upstream requests are sent through, i.e. `http://proxy.internal:3128`
+    #[serde(default, deserialize_with = "optional")]
+    pub http_proxy: Option<String>,
+    /// seconds sections have to report before the standup is posted without them
+    #[serde(default = "default_time_budget_secs")]
+    pub time_budget_secs: u64,
+    /// attempts made at upstream requests which fail transiently
+    #[serde(default = "default_retry_attempts")]
+    pub http_retry_attempts: u32,
+    /// delay before retrying, doubled with each attempt
+    #[serde(default = "default_retry_backoff_ms")]
+    pub http_retry_backoff_ms: u64,
+    /// failures in a row after which jira or pagerduty are skipped, zero never skips them
+    #[serde(default = "default_breaker_failures")]
+    pub breaker_failures: u32,
+    /// seconds jira or pagerduty are skipped for before they're tried again
+    #[serde(default = "default_breaker_cooldown_secs")]
+    pub breaker_cooldown_secs: u64,
+    /// seconds jira and pagerduty responses are reused for, uncached without it
+    #[serde(default, deserialize_with = "optional_parsed")]
+    pub cache_ttl_secs: Option<u64>,
+    /// DynamoDB table cached responses are shared through, besides memory
+    #[serde(default, deserialize_with = "optional")]
+    pub cache_table: Option<String>,
+    pub slack_signing_secret: String,
+    /// bearer token `/health` requests must carry, which isn't served without it
+    #[serde(default, deserialize_with = "optional")]
+    pub health_token: Option<String>,
+    /// channel ids the command may be run in
+    #[serde(default, deserialize_with = "list")]
+    pub slack_allowed_channels: Vec<String>,
+    /// user ids which may run the command in any channel
+    #[serde(default, deserialize_with = "list")]
+    pub slack_allowed_users: Vec<String>,
+    /// user ids which may change a channel's configuration, where empty leaves it unchangeable
+    #[serde(default, deserialize_with = "list")]
+    pub slack_admin_users: Vec<String>,
+    /// DynamoDB table per channel configuration is stored in
+    #[serde(default, deserialize_with = "optional")]
+    pub channel_config_table: Option<String>,
+    /// DynamoDB table DM check-ins are collected in, see `checkin`
+    #[serde(default, deserialize_with = "optional")]
+    pub checkin_table: Option<String>,
+    /// slack user ids asked to check in over DM each day
+    #[serde(default, deserialize_with = "list")]
+    pub checkin_users: Vec<String>,
+    /// channel id blockers are also posted to, see `escalation`
+    #[serde(default, deserialize_with = "optional")]
+    pub escalation_channel: Option<String>,
+    /// slack user ids mentioned on escalated blockers, or `PROJECT=user id` for a jira project's
+    #[serde(default, deserialize_with = "list")]
+    pub escalation_leads: Vec<String>,
+    /// slack user ids who take turns facilitating standup
+    #[serde(default, deserialize_with = "list")]
+    pub facilitator_roster: Vec<String>,
+    /// DynamoDB table each channel's facilitator rotation is kept in, see `facilitator`
+    #[serde(default, deserialize_with = "optional")]
+    pub rotation_table: Option<String>,
+    /// SQS queue commands are run from by the worker, rather than an async re-invocation
+    #[serde(default, deserialize_with = "optional")]
+    pub job_queue_url: Option<String>,
+    /// seconds after a standup's posted that runs link to it rather than posting another
+    #[serde(default, deserialize_with = "optional_parsed")]
+    pub reuse_within_secs: Option<u64>,
+    /// DynamoDB table runs are counted in, so channels aren't flooded with standups
+    #[serde(default, deserialize_with = "optional")]
+    pub rate_limit_table: Option<String>,
+    /// runs a channel may have per window, zero doesn't limit them
+    #[serde(default = "default_rate_limit_max")]
+    pub rate_limit_max: u32,
+    #[serde(default = "default_rate_limit_window_secs")]
+    pub rate_limit_window_secs: u64,
+    /// DynamoDB table deliveries are claimed in, so redelivered requests are skipped
+    #[serde(default, deserialize_with = "optional")]
+    pub delivery_table: Option<String>,
+    /// DynamoDB table standups are recorded in
+    #[serde(default, deserialize_with = "optional")]
+    pub history_table: Option<String>,
+    /// incoming webhook scheduled standups are posted to
+    #[serde(default, deserialize_with = "optional")]
+    pub slack_webhook_url: Option<String>,
+    /// channel id scheduled standups are posted to with `slack_bot_token`, in favor of the webhook
+    #[serde(default, deserialize_with = "optional")]
+    pub slack_channel: Option<String>,
+    /// where scheduled standups are delivered, `slack` (default), `teams` or `discord`
+    #[serde(default, deserialize_with = "parsed_or_default")]
+    pub standup_destination: Destination,
+    /// Microsoft Teams incoming webhook scheduled standups are posted to
+    #[serde(default, deserialize_with = "optional")]
+    pub teams_webhook_url: Option<String>,
+    /// Discord webhook scheduled standups are posted to
+    #[serde(default, deserialize_with = "optional")]
+    pub discord_webhook_url: Option<String>,
+    /// verified SES address scheduled standups are emailed from
+    #[serde(default, deserialize_with = "optional")]
+    pub email_sender: Option<String>,
+    /// addresses scheduled standups are also emailed to
+    #[serde(default, deserialize_with = "list")]
+    pub email_recipients: Vec<String>,
+    /// Confluence site pages are created in, i.e. `https://example.atlassian.net/wiki`
+    #[serde(default, deserialize_with = "optional")]
+    pub confluence_url: Option<String>,
+    #[serde(default, deserialize_with = "optional")]
+    pub confluence_user: Option<String>,
+    /// api token of `confluence_user`
+    #[serde(default, deserialize_with = "optional")]
+    pub confluence_token: Option<String>,
+    /// key of the space pages are created in
+    #[serde(default, deserialize_with = "optional")]
+    pub confluence_space: Option<String>,
+    /// page new pages are created under, otherwise they're top level
+    #[serde(default, deserialize_with = "optional")]
+    pub confluence_parent_id: Option<String>,
+    /// whether scheduled standups are published to Confluence, `off` (default), `append` or `pages`
+    #[serde(default, deserialize_with = "parsed_or_default")]
+    pub confluence_standups: ConfluencePublishing,
+    /// running page standups are appended to
+    #[serde(default, deserialize_with = "optional")]
+    pub confluence_page_id: Option<String>,
+    /// also publish release notes to Confluence
+    #[serde(default, deserialize_with = "flag")]
+    pub confluence_release_notes: bool,
+    /// webhook each standup is also posted to as a json document, see `export`
+    #[serde(default, deserialize_with = "optional")]
+    pub data_webhook_url: Option<String>,
+    /// integration token each standup is also created as a notion page with, see `notion`
+    #[serde(default, deserialize_with = "optional")]
+    pub notion_token: Option<String>,
+    /// database standup pages are created in, shared with the `notion_token` integration
+    #[serde(default, deserialize_with = "optional")]
+    pub notion_database_id: Option<String>,
+    /// api host, i.e. a proxy
+    #[serde(default, deserialize_with = "optional")]
+    pub notion_api_url: Option<String>,
+    /// channel ids every standup is also posted to with `slack_bot_token`,
+    /// optionally followed by `=` and the `+` separated sections to post there
+    #[serde(default, deserialize_with = "list")]
+    pub slack_cross_posts: Vec<String>,
+    /// used to look up slack users by email
+    #[serde(default, deserialize_with = "optional")]
+    pub slack_bot_token: Option<String>,
+    /// post a summary with details threaded under it, which needs `slack_bot_token`
+    #[serde(default, deserialize_with = "flag")]
+    pub slack_threaded: bool,
+    /// post only the summary and open P1 incidents, without issue lists, i.e. for leadership channels
+    #[serde(default, deserialize_with = "flag")]
+    pub slack_brief: bool,
+    /// add buttons to refresh and expand standups, which needs slack interactivity pointed at the gateway
+    #[serde(default, deserialize_with = "flag")]
+    pub slack_interactive: bool,
+    /// `jira name or email=slack user id` pairs
+    #[serde(default, deserialize_with = "list")]
+    pub slack_user_overrides: Vec<String>,
+    /// json object of template names to handlebars templates, see `templates`
+    #[serde(default, deserialize_with = "optional")]
+    pub template_overrides: Option<String>,
+    /// `s3://bucket/key` or https url of a json object of templates
+    #[serde(default, deserialize_with = "optional")]
+    pub template_url: Option<String>,
+}
+
+// unset optional values are deployed as empty strings
+
+fn optional<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Option::<String>::deserialize(deserializer).map(|value| value.filter(|value| !value.is_empty()))
+}
+
+fn optional_parsed<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: Display,
+{
+    match optional(deserializer)? {
+        Some(value) => value.parse::<T>().map(Some).map_err(D::Error::custom),
+        None => Ok(None),
+    }
+}
+
+fn parsed_or_default<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr + Default,
+    T::Err: Display,
+{
+    optional_parsed(deserializer).map(Option::unwrap_or_default)
+}
+
+fn flag<'de, D>(deserializer: D) -> Result<bool, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    optional_parsed(deserializer).map(|value| value.unwrap_or(false))
+}
+
+fn list<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Vec::<String>::deserialize(deserializer).map(|values| {
+        values
+            .into_iter()
+            .filter(|value| !value.is_empty())
+            .collect()
+    })
+}
+
+fn timezone<'de, D>(deserializer: D) -> Result<Tz, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    String::deserialize(deserializer)?
+        .parse::<Tz>()
+        .map_err(D::Error::custom)
+}
+
+fn default_timezone() -> Tz {
+    Tz::UTC
+}
+
+fn default_sections() -> Vec<String> {
+    vec!["weather".into(), "blocked".into(), "jira".into()]
+}
+
+fn default_ci_branch() -> String {
+    "main".into()
+}
+
+fn default_ci_red_hours() -> i64 {
+    24
+}
+
+fn default_deploy_environment() -> String {
+    "production".into()
+}
+
+fn default_ooo_keywords() -> Vec<String> {
+    vec![
+        "OOO".into(),
+        "PTO".into(),
+        "Out".into(),
+        "Vacation".into(),
+        "Sick".into(),
+        "Leave".into(),
+    ]
+}
+
+fn default_jira_max_results() -> usize {
+    500
+}
+
+fn default_http_timeout_secs() -> u64 {
+    10
+}
+
+fn default_time_budget_secs() -> u64 {
+    25
+}
+
+fn default_retry_attempts() -> u32 {
+    3
+}
+
+fn default_retry_backoff_ms() -> u64 {
+    200
+}
+
+fn default_rate_limit_max() -> u32 {
+    3
+}
+
+fn default_rate_limit_window_secs() -> u64 {
+    10 * 60
+}
+
+fn default_breaker_failures() -> u32 {
+    3
+}
+
+fn default_breaker_cooldown_secs() -> u64 {
+    60
+}
+
+fn default_blocked_labels() -> Vec<String> {
+    vec!["blocked".into()]
+}
+
+fn default_blocked_statuses() -> Vec<String> {
+    vec!["Blocked".into()]
+}
+
+fn default_shipped_resolutions() -> Vec<String> {
+    vec!["Done".into()]
+}
+
+fn default_status_emoji() -> Vec<String> {
+    vec![
+        "In Progress=👩🏻‍💻".into(),
+        "In Review=👩🏼‍🔬".into(),
+        "Closed=🎉".into(),
+    ]
+}
+
+fn default_review_statuses() -> Vec<String> {
+    vec!["In Review".into()]
+}
+
+fn default_dev_status_application() -> String {
+    "GitHub".into()
+}
+
+fn default_type_emoji() -> Vec<String> {
+    vec!["Bug=🐞".into(), "Story=✨".into(), "Task=🔧".into()]
+}
+
+fn default_priority_emoji() -> Vec<String> {
+    vec![
+        "Highest=🔴".into(),
+        "High=🔴".into(),
+        "Medium=🟡".into(),
+        "Low=⚪".into(),
+        "Lowest=⚪".into(),
+    ]
+}
+
+fn default_status_order() -> Vec<String> {
+    vec!["Closed".into(), "In Progress".into(), "In Review".into()]
+}
+
+impl Config {
+    /// configuration sourced from env variables
+    pub fn from_env() -> Result<Config, String> {
+        Config::with_overrides(&Overrides::new())
+    }
+
+    /// env variables, overridden by a channel's configuration
+    ///
+    /// env values referring to a secret store are resolved before parsing.
+    /// overrides come from slack users, so they're taken literally and never
+    /// resolved with the lambda's access to secrets
+    pub fn with_overrides(overrides: &Overrides) -> Result<Config, String> {
+        let mut vars = env::vars()
+            .map(|(name, value)| secrets::resolve(&value).map(|value| (name, value)))
+            .collect::<Result<HashMap<_, _>, _>>()?;
+        vars.extend(
+            overrides
+                .iter()
+                .map(|(name, value)| (name.to_uppercase(), value.clone())),
+        );
+        envy::from_iter(vars).map_err(|err| err.to_string())
+    }
+
+    /// http client which gives up on slow upstream requests
+    pub fn http_client(&self) -> Client {
+        http::client(
+            self.http_timeout_secs,
+            self.http_proxy.as_ref().map(String::as_str),
+        )
+    }
+
+    pub fn status_emoji(&self) -> HashMap<String, String> {
+        pairs(&self.jira_status_emoji)
+    }
+
+    /// work in progress limits by status, skipping limits which aren't numbers
+    pub fn wip_limits(&self) -> HashMap<String, usize> {
+        pairs(&self.jira_wip_limits)
+            .into_iter()
+            .filter_map(|(status, limit)| limit.parse().ok().map(|limit| (status, limit)))
+            .collect()
+    }
+
+    /// extra fields listed with issues and their labels, in configured order
+    pub fn display_fields(&self) -> Vec<(String, String)> {
+        let labels = pairs(&self.jira_display_fields);
+        self.jira_display_fields
+            .iter()
+            .filter_map(|pair| {
+                let field = pair.splitn(2, '=').next()?.trim();
+                labels
+                    .get(field)
+                    .map(|label| (field.to_owned(), label.clone()))
+            })
+            .collect()
+    }
+
+    pub fn type_emoji(&self) -> HashMap<String, String> {
+        pairs(&self.jira_type_emoji)
+    }
+
+    pub fn priority_emoji(&self) -> HashMap<String, String> {
+        pairs(&self.jira_priority_emoji)
+    }
+
+    /// display position of a status
+    pub fn status_rank(&self, status: &str) -> usize {
+        self.jira_status_order
+            .iter()
+            .position(|ordered| ordered.eq_ignore_ascii_case(status))
+            .unwrap_or_else(|| self.jira_status_order.len())
+    }
+
+    /// cross post channels and the sections posted to each, where no sections means all of them
+    pub fn cross_posts(&self) -> Vec<(String, Vec<String>)> {
+        self.slack_cross_posts
+            .iter()
+            .map(|target| {
+                let mut parts = target.splitn(2, '=');
+                let channel = parts.next().unwrap_or_default().trim().to_owned();
+                let sections = parts
+                    .next()
+                    .map(|sections| {
+                        sections
+                            .split('+')
+                            .map(|section| section.trim().to_owned())
+                            .filter(|section| !section.is_empty())
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                (channel, sections)
+            })
+            .collect()
+    }
+}
+
+/// parses a list of `key=value` pairs, skipping malformed entries
+pub fn pairs(values: &[String]) -> HashMap<String, String> {
+    values
+        .iter()
+        .filter_map(|pair| {
+            let mut parts = pair.splitn(2, '=');
+            match (parts.next(), parts.next()) {
+                (Some(key), Some(value)) => Some((key.trim().to_owned(), value.trim().to_owned())),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    /// a minimal valid config with `vars` set
+    pub fn config(vars: &[(&str, &str)]) -> Config {
+        let required = [
+            ("JIRA_HOST", "https://jira"),
+            ("JIRA_USER", "ada"),
+            ("JIRA_PASSWORD", "secret"),
+            ("JIRA_PROJECTS", "CORE"),
+            ("SLACK_SIGNING_SECRET", "secret"),
+        ];
+        envy::from_iter(
+            required
+                .iter()
+                .chain(vars)
+                .map(|&(key, value)| (key.to_owned(), value.to_owned())),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn cross_posts_filter_sections() {
+        assert_eq!(
+            config(&[("SLACK_CROSS_POSTS", "C1=weather+blocked,C2")]).cross_posts(),
+            vec![
+                (
+                    "C1".to_owned(),
+                    vec!["weather".to_owned(), "blocked".to_owned()]
+                ),
+                ("C2".to_owned(), Vec::new()),
+            ]
+        );
+    }
+
+    #[test]
+    fn destinations_default_to_slack() {
+        assert_eq!(config(&[]).standup_destination, Destination::Slack);
+        assert_eq!(
+            config(&[("STANDUP_DESTINATION", "Teams")]).standup_destination,
+            Destination::Teams
+        );
+        assert_eq!(
+            config(&[("STANDUP_DESTINATION", "")]).standup_destination,
+            Destination::Slack
+        );
+    }
+
+    #[test]
+    fn incidents_default_to_pagerduty() {
+        assert_eq!(config(&[]).incident_source, IncidentSource::PagerDuty);
+        assert_eq!(
+            config(&[("INCIDENT_SOURCE", "Opsgenie")]).incident_source,
+            IncidentSource::Opsgenie
+        );
+    }
+
+    #[test]
+    fn confluence_publishing_is_off_by_default() {
+        assert_eq!(config(&[]).confluence_standups, ConfluencePublishing::Off);
+        assert_eq!(
+            config(&[("CONFLUENCE_STANDUPS", "pages")]).confluence_standups,
+            ConfluencePublishing::Pages
+        );
+    }
+
+    #[test]
+    fn jira_subtasks_default_to_nesting() {
+        assert_eq!(config(&[]).jira_subtasks, Subtasks::Nest);
+        assert_eq!(
+            config(&[("JIRA_SUBTASKS", "count")]).jira_subtasks,
+            Subtasks::Count
+        );
+    }
+
+    #[test]
+    fn jira_issue_order_defaults_to_relevance() {
+        assert_eq!(config(&[]).jira_issue_order, IssueOrder::Relevance);
+        assert_eq!(
+            config(&[("JIRA_ISSUE_ORDER", "Key")]).jira_issue_order,
+            IssueOrder::Key
+        );
+    }
+
+    #[test]
+    fn jira_auth_defaults_to_password() {
+        assert_eq!(config(&[]).jira_auth, JiraAuth::Password);
+        assert_eq!(
+            config(&[("JIRA_AUTH", "api_token")]).jira_auth,
+            JiraAuth::ApiToken
+        );
+        assert_eq!(config(&[("JIRA_AUTH", "OAuth")]).jira_auth, JiraAuth::OAuth);
+    }
+
+    #[test]
+    fn wip_limits_are_numbers() {
+        let limits = config(&[("JIRA_WIP_LIMITS", "In Review=4,In Progress=lots")]).wip_limits();
+        assert_eq!(limits.len(), 1);
+        assert_eq!(limits.get("In Review"), Some(&4));
+    }
+
+    #[test]
+    fn only_done_issues_ship_by_default() {
+        assert_eq!(
+            config(&[]).jira_shipped_resolutions,
+            vec!["Done".to_owned()]
+        );
+        assert!(config(&[("JIRA_SHIPPED_RESOLUTIONS", "")])
+            .jira_shipped_resolutions
+            .is_empty());
+    }
+
+    #[test]
+    fn empty_status_lists_are_empty() {
+        let config = config(&[
+            ("JIRA_IN_FLIGHT_STATUSES", ""),
+            ("JIRA_STATUS_EMOJI", ""),
+            ("JIRA_STATUS_ORDER", ""),
+            ("JIRA_TYPE_EMOJI", ""),
+            ("JIRA_PRIORITY_EMOJI", ""),
+        ]);
+        assert!(config.jira_in_flight_statuses.is_empty());
+        assert!(config.jira_status_emoji.is_empty());
+        assert!(config.jira_status_order.is_empty());
+        assert!(config.jira_type_emoji.is_empty());
+        assert!(config.jira_priority_emoji.is_empty());
+    }
+
+    #[test]
+    fn display_fields_keep_their_order() {
+        assert_eq!(
+            config(&[(
+                "JIRA_DISPLAY_FIELDS",
+                "customfield_10200=Squad,environment,customfield_10100=Environment"
+            )])
+            .display_fields(),
+            vec![
+                ("customfield_10200".to_owned(), "Squad".to_owned()),
+                ("customfield_10100".to_owned(), "Environment".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn pairs_skip_malformed_entries() {
+        let pairs = pairs(&["In Progress = 👩🏻‍💻".into(), "Closed".into()]);
+        assert_eq!(pairs.len(), 1);
+        assert_eq!(pairs.get("In Progress"), Some(&"👩🏻‍💻".to_owned()));
+    }
+}
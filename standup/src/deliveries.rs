@@ -0,0 +1,94 @@
+//! Redelivered Slack requests
+//!
+//! Slack redelivers requests it doesn't hear back about in time, which would
+//! post the same standup twice. Redeliveries are signed afresh, so they're
+//! told apart by what they share with the original: an interaction's
+//! `trigger_id` or an event's `event_id`. That key is claimed in DynamoDB
+//! with a conditional write, so only the first delivery of a request claims
+//! it. Claims expire through the table's time to live.
+
+// Std lib
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Third party
+use rusoto_core::Region;
+use rusoto_dynamodb::{DynamoDb, DynamoDbClient, PutItemError, PutItemInput};
+
+// Ours
+use config::Config;
+use dynamodb::{number, string};
+
+/// how long claims are kept, well beyond slack's retries
+const CLAIM_TTL_SECS: u64 = 60 * 60;
+
+pub struct Deliveries {
+    table: String,
+    client: DynamoDbClient,
+}
+
+impl Deliveries {
+    pub fn new(table: String) -> Self {
+        Deliveries {
+            table,
+            client: DynamoDbClient::new(Region::default()),
+        }
+    }
+
+    /// deduplication, when there's a table to claim deliveries in
+    pub fn configured(config: &Config) -> Option<Deliveries> {
+        config.delivery_table.clone().map(Deliveries::new)
+    }
+
+    /// claims a delivery, returning false if an earlier delivery already has
+    pub fn claim(&self, key: &str) -> Result<bool, String> {
+        let expires = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|since| since.as_secs())
+            .unwrap_or_default()
+            + CLAIM_TTL_SECS;
+        let mut item = HashMap::new();
+        item.insert("key".to_owned(), string(key.to_owned()));
+        item.insert("expires".to_owned(), number(expires));
+        match self
+            .client
+            .put_item(PutItemInput {
+                table_name: self.table.clone(),
+                item,
+                condition_expression: Some("attribute_not_exists(#key)".into()),
+                expression_attribute_names: Some(
+                    vec![("#key".to_owned(), "key".to_owned())]
+                        .into_iter()
+                        .collect(),
+                ),
+                ..Default::default()
+            })
+            .sync()
+        {
+            Ok(_) => Ok(true),
+            Err(PutItemError::ConditionalCheckFailed(_)) => Ok(false),
+            Err(err) => Err(format!("failed to claim delivery: {}", err)),
+        }
+    }
+}
+
+/// identifies a request and its redeliveries by the workspace and the
+/// interaction's `trigger_id` or the event's `event_id`
+pub fn key(team_id: &str, id: &str) -> String {
+    format!("{}:{}", team_id, id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_are_shared_by_redeliveries() {
+        assert_eq!(
+            key("T1", "13345224609.738474920.8088930838d88f008e0"),
+            "T1:13345224609.738474920.8088930838d88f008e0"
+        );
+        assert_eq!(key("T1", "Ev0PV52K21"), key("T1", "Ev0PV52K21"));
+        assert_ne!(key("T1", "Ev0PV52K21"), key("T2", "Ev0PV52K21"));
+    }
+}
@@ -0,0 +1,129 @@
+//! Deploy log written to DynamoDB by the deployment pipeline
+//!
+//! Each deploy is an item keyed by the `environment` deployed to and when it
+//! was `deployed_at`, an rfc3339 timestamp, with the `service` and `version`
+//! deployed and, optionally, a `url` and the `deployer`.
+
+// Std lib
+use std::collections::HashMap;
+
+// Third party
+use chrono::{DateTime, FixedOffset, Utc};
+use rusoto_core::Region;
+use rusoto_dynamodb::{AttributeValue, DynamoDb, DynamoDbClient, QueryInput};
+
+// Ours
+use dynamodb::attributes;
+use error::FetchError;
+
+/// A deploy to an environment
+#[derive(Serialize, Debug)]
+pub struct Deploy {
+    pub service: String,
+    pub version: String,
+    pub deployed_at: DateTime<FixedOffset>,
+    /// i.e. release notes or the pipeline run
+    pub url: Option<String>,
+    pub deployer: Option<String>,
+}
+
+pub struct DeployLog {
+    table: String,
+    client: DynamoDbClient,
+}
+
+impl DeployLog {
+    pub fn new(table: String) -> Self {
+        DeployLog {
+            table,
+            client: DynamoDbClient::new(Region::default()),
+        }
+    }
+
+    /// deploys to an environment since a given time, newest first
+    pub fn since(
+        &self,
+        environment: &str,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<Deploy>, FetchError> {
+        let values = attributes(vec![
+            (":environment", environment.to_owned()),
+            (":since", since.to_rfc3339()),
+        ]);
+        let output = self
+            .client
+            .query(QueryInput {
+                table_name: self.table.clone(),
+                key_condition_expression: Some(
+                    "environment = :environment AND deployed_at >= :since".into(),
+                ),
+                expression_attribute_values: Some(values),
+                scan_index_forward: Some(false),
+                ..Default::default()
+            })
+            .sync()
+            .map_err(|err| FetchError::new("the deploy log", err.to_string()))?;
+        Ok(output
+            .items
+            .unwrap_or_default()
+            .iter()
+            .filter_map(deploy)
+            .collect())
+    }
+}
+
+/// items missing a service, version or valid timestamp are skipped
+fn deploy(item: &HashMap<String, AttributeValue>) -> Option<Deploy> {
+    let string = |name: &str| {
+        item.get(name)
+            .and_then(|value| value.s.clone())
+            .filter(|value| !value.is_empty())
+    };
+    Some(Deploy {
+        service: string("service")?,
+        version: string("version")?,
+        deployed_at: DateTime::parse_from_rfc3339(&string("deployed_at")?).ok()?,
+        url: string("url"),
+        deployer: string("deployer"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(values: &[(&str, &str)]) -> HashMap<String, AttributeValue> {
+        attributes(
+            values
+                .iter()
+                .map(|&(name, value)| (name, value.to_owned()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn deploys_are_read_from_items() {
+        let deploy = deploy(&item(&[
+            ("environment", "production"),
+            ("deployed_at", "2019-01-07T15:00:00Z"),
+            ("service", "api"),
+            ("version", "v1.2.3"),
+            ("url", ""),
+            ("deployer", "dana"),
+        ]))
+        .unwrap();
+        assert_eq!(deploy.service, "api");
+        assert_eq!(deploy.version, "v1.2.3");
+        assert_eq!(deploy.url, None);
+        assert_eq!(deploy.deployer, Some("dana".into()));
+    }
+
+    #[test]
+    fn incomplete_items_are_skipped() {
+        assert!(deploy(&item(&[
+            ("deployed_at", "2019-01-07T15:00:00Z"),
+            ("service", "api"),
+        ]))
+        .is_none());
+    }
+}
@@ -0,0 +1,138 @@
+//! Jira development information
+//!
+//! Jira's dev-status api reports the branches, commits and pull requests
+//! linked to an issue by its source control integrations. It's the api
+//! behind the development panel of an issue, undocumented but stable, and
+//! keyed by the issue's numeric id rather than its key.
+
+// Third party
+use serde_json;
+
+// Ours
+use error::FetchError;
+use jira_auth::Site;
+use retry::Retry;
+
+#[derive(Deserialize, Debug)]
+struct Details {
+    #[serde(default)]
+    detail: Vec<Detail>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct Detail {
+    #[serde(default)]
+    pull_requests: Vec<LinkedPullRequest>,
+}
+
+/// A pull request linked to an issue
+#[derive(Deserialize, Debug, Clone)]
+pub struct LinkedPullRequest {
+    pub name: String,
+    pub url: String,
+    /// `OPEN`, `MERGED` or `DECLINED`
+    pub status: String,
+    #[serde(default)]
+    pub reviewers: Vec<Reviewer>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Reviewer {
+    pub name: String,
+    #[serde(default)]
+    pub approved: bool,
+}
+
+impl LinkedPullRequest {
+    pub fn is_open(&self) -> bool {
+        self.status.eq_ignore_ascii_case("open")
+    }
+
+    pub fn is_approved(&self) -> bool {
+        self.reviewers.iter().any(|reviewer| reviewer.approved)
+    }
+}
+
+/// pull requests linked to an issue through an integration, i.e. `GitHub`
+pub fn pull_requests(
+    site: &Site,
+    retry: Retry,
+    issue_id: &str,
+    application: &str,
+) -> Result<Vec<LinkedPullRequest>, FetchError> {
+    let details = site
+        .get_json(
+            "/rest/dev-status/latest/issue/detail",
+            &[
+                ("issueId", issue_id),
+                ("applicationType", application),
+                ("dataType", "pullrequest"),
+            ],
+            retry,
+        )
+        .and_then(|details| {
+            serde_json::from_value::<Details>(details)
+                .map_err(|err| FetchError::new("Jira", err.to_string()))
+        })?;
+    Ok(details
+        .detail
+        .into_iter()
+        .flat_map(|detail| detail.pull_requests)
+        .collect())
+}
+
+/// how open pull requests are doing in review, i.e. `2 PRs, 1 approved`
+pub fn review_state(pull_requests: &[LinkedPullRequest]) -> Option<String> {
+    let open = pull_requests
+        .iter()
+        .filter(|pull| pull.is_open())
+        .collect::<Vec<_>>();
+    let approved = open.iter().filter(|pull| pull.is_approved()).count();
+    match open.len() {
+        0 => None,
+        1 => Some(format!(
+            "<{}|1 PR>, {}",
+            open[0].url,
+            if approved > 0 {
+                "approved"
+            } else {
+                "awaiting approval"
+            }
+        )),
+        count => Some(format!("{} PRs, {} approved", count, approved)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jira_auth::tests::{site, RETRY};
+    use mockito::{mock, Matcher};
+
+    #[test]
+    fn open_pull_requests_are_summarized() {
+        let _mock = mock("GET", "/rest/dev-status/latest/issue/detail")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("issueId".into(), "10001".into()),
+                Matcher::UrlEncoded("applicationType".into(), "GitHub".into()),
+                Matcher::UrlEncoded("dataType".into(), "pullrequest".into()),
+            ]))
+            .with_body(
+                r##"{"errors":[],"detail":[{"branches":[],"pullRequests":[
+                    {"id":"#12","name":"Add search","url":"https://github.com/org/api/pull/12","status":"OPEN","reviewers":[{"name":"dana","approved":true}]},
+                    {"id":"#13","name":"Fix search","url":"https://github.com/org/api/pull/13","status":"OPEN","reviewers":[{"name":"dana","approved":false}]},
+                    {"id":"#9","name":"Spike","url":"https://github.com/org/api/pull/9","status":"DECLINED","reviewers":[]}
+                ]}]}"##,
+            )
+            .create();
+        let pulls = pull_requests(&site(), RETRY, "10001", "GitHub").unwrap();
+        assert_eq!(pulls.len(), 3);
+        assert_eq!(review_state(&pulls), Some("2 PRs, 1 approved".into()));
+        assert_eq!(
+            review_state(&pulls[1..]),
+            Some("<https://github.com/org/api/pull/13|1 PR>, awaiting approval".into())
+        );
+        assert_eq!(review_state(&pulls[2..]), None);
+    }
+}
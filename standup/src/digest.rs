@@ -0,0 +1,270 @@
+//! Weekly digest
+//!
+//! Summarizes a week of standup history: what shipped, how many incidents
+//! came and went, who shipped the most and where each daily standup was posted.
+//! Day by day trends of the same are charted alongside it.
+
+// Std lib
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+// Third party
+use chrono::NaiveDate;
+use chrono_tz::Tz;
+use serde::de::DeserializeOwned;
+use serde_json::{self, Value};
+
+// Ours
+use chart::Series;
+use history::Entry;
+use pagerduty::Incident;
+use render::{Block, Message};
+use section::IssueRecord;
+use users::{Directory, JiraUser};
+
+/// number of contributors called out
+const TOP_CONTRIBUTORS: usize = 3;
+
+/// renders a digest of standups, oldest first. issues count as shipped with
+/// any of the `shipped_resolutions`
+pub fn digest(
+    entries: &[Entry],
+    timezone: Tz,
+    shipped_resolutions: &[String],
+    users: &Directory,
+) -> Message {
+    let mut blocks = vec![Block::header("🗓 Week in review")];
+    if entries.is_empty() {
+        blocks.push(Block::section("No standups were recorded this week"));
+        return Message {
+            text: "Weekly digest".into(),
+            blocks,
+        };
+    }
+
+    // issues are reported closed for as long as they're in the lookback window
+    let shipped = entries
+        .iter()
+        .flat_map(|entry| records::<IssueRecord>(entry, "jira", None))
+        .filter(|issue| issue.shipped(shipped_resolutions))
+        .map(|issue| (issue.key.clone(), issue))
+        .collect::<BTreeMap<_, _>>();
+    let opened = entries
+        .iter()
+        .flat_map(|entry| {
+            let mut incidents = records::<Incident>(entry, "weather", Some("open"));
+            incidents.extend(records::<Incident>(entry, "weather", Some("resolved")));
+            incidents
+        })
+        .map(|incident| incident.incident_number)
+        .collect::<BTreeSet<_>>();
+    let resolved = entries
+        .iter()
+        .flat_map(|entry| records::<Incident>(entry, "weather", Some("resolved")))
+        .map(|incident| incident.incident_number)
+        .collect::<BTreeSet<_>>();
+
+    blocks.push(Block::fields(vec![
+        format!("*Shipped*\n{} tickets", shipped.len()),
+        format!(
+            "*Incidents*\n{} opened, {} resolved",
+            opened.len(),
+            resolved.len()
+        ),
+    ]));
+
+    let mut contributors = shipped
+        .values()
+        .filter_map(|issue| issue.assignee.clone())
+        .fold(HashMap::new(), |mut acc, name| {
+            *acc.entry(name).or_insert(0) += 1;
+            acc
+        })
+        .into_iter()
+        .collect::<Vec<_>>();
+    contributors.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.cmp(b)));
+    if !contributors.is_empty() {
+        blocks.push(Block::section(format!(
+            "🏆 *Top contributors* {}",
+            contributors
+                .into_iter()
+                .take(TOP_CONTRIBUTORS)
+                .map(|(name, count)| format!(
+                    "{} ({})",
+                    users.mention(&JiraUser {
+                        name,
+                        email_address: None,
+                    }),
+                    count
+                ))
+                .collect::<Vec<_>>()
+                .join(", ")
+        )));
+    }
+
+    blocks.push(Block::section(format!(
+        "📅 *Daily standups*\n{}",
+        entries
+            .iter()
+            .map(|entry| {
+                let day = entry
+                    .posted_at
+                    .with_timezone(&timezone)
+                    .format("%a %b %-d")
+                    .to_string();
+                match entry.permalink {
+                    Some(ref permalink) => format!("• <{}|{}>", permalink, day),
+                    None => format!("• {}", day),
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    )));
+
+    Message {
+        text: "Weekly digest".into(),
+        blocks,
+    }
+}
+
+/// issues shipped and incidents open per day, for charting
+///
+/// Issues count on the first day they were reported shipped, and incidents on
+/// each day the last standup of the day reported them open.
+pub fn trends(entries: &[Entry], timezone: Tz, shipped_resolutions: &[String]) -> Vec<Series> {
+    let day = |entry: &Entry| {
+        entry
+            .posted_at
+            .with_timezone(&timezone)
+            .date()
+            .naive_local()
+    };
+    let mut closed = BTreeMap::new();
+    let mut open = BTreeMap::new();
+    let mut seen = BTreeSet::new();
+    for entry in entries {
+        let count = records::<IssueRecord>(entry, "jira", None)
+            .into_iter()
+            .filter(|issue| issue.shipped(shipped_resolutions))
+            .filter(|issue| seen.insert(issue.key.clone()))
+            .count();
+        *closed.entry(day(entry)).or_insert(0) += count;
+        // entries are oldest first, so later standups of a day replace earlier ones
+        open.insert(
+            day(entry),
+            records::<Incident>(entry, "weather", Some("open")).len(),
+        );
+    }
+    vec![
+        series("Issues closed per day", closed),
+        series("Open incidents per day", open),
+    ]
+}
+
+fn series(title: &str, counts: BTreeMap<NaiveDate, usize>) -> Series {
+    Series {
+        title: title.into(),
+        points: counts
+            .into_iter()
+            .map(|(day, count)| (day.format("%a").to_string(), count as f64))
+            .collect(),
+    }
+}
+
+/// a list a section recorded, optionally nested under a field of its record
+pub fn records<T>(entry: &Entry, section: &str, field: Option<&str>) -> Vec<T>
+where
+    T: DeserializeOwned,
+{
+    entry
+        .sections
+        .get(section)
+        .and_then(|record| match field {
+            Some(field) => record.get(field),
+            None => Some(record),
+        })
+        .cloned()
+        .and_then(|value: Value| serde_json::from_value(value).ok())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+    use serde_json;
+
+    fn entry(posted_at: &str, sections: Value) -> Entry {
+        Entry {
+            channel: "C1".into(),
+            posted_at: Utc.datetime_from_str(posted_at, "%F %T").unwrap(),
+            sections: serde_json::from_value(sections).unwrap(),
+            message: json!({}),
+            ts: None,
+            permalink: None,
+            command: None,
+        }
+    }
+
+    #[test]
+    fn trends_count_issues_the_day_they_shipped() {
+        let closed = json!({
+            "key": "API-1",
+            "summary": null,
+            "status": "Closed",
+            "assignee": null,
+            "resolution": "Done"
+        });
+        let wont_do = json!({
+            "key": "API-2",
+            "summary": null,
+            "status": "Closed",
+            "assignee": null,
+            "resolution": "Won't Do"
+        });
+        let incident = json!({
+            "incident_number": 7,
+            "title": "Search is down",
+            "status": "triggered",
+            "html_url": "https://meetup.pagerduty.com/incidents/7"
+        });
+        let entries = vec![
+            entry(
+                "2019-01-07 15:00:00",
+                json!({ "jira": [], "weather": { "open": [incident] } }),
+            ),
+            entry(
+                "2019-01-08 15:00:00",
+                json!({ "jira": [closed, wont_do], "weather": { "open": [incident] } }),
+            ),
+            entry(
+                "2019-01-08 21:00:00",
+                json!({ "jira": [closed], "weather": { "open": [] } }),
+            ),
+            entry(
+                "2019-01-09 15:00:00",
+                json!({ "jira": [closed], "weather": { "open": [] } }),
+            ),
+        ];
+        assert_eq!(
+            trends(&entries, Tz::UTC, &["Done".to_owned()]),
+            vec![
+                Series {
+                    title: "Issues closed per day".into(),
+                    points: vec![
+                        ("Mon".into(), 0.0),
+                        ("Tue".into(), 1.0),
+                        ("Wed".into(), 0.0)
+                    ],
+                },
+                Series {
+                    title: "Open incidents per day".into(),
+                    points: vec![
+                        ("Mon".into(), 1.0),
+                        ("Tue".into(), 0.0),
+                        ("Wed".into(), 0.0)
+                    ],
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,34 @@
+//! DynamoDB attribute values
+//!
+//! Everything kept in DynamoDB is keyed and stored by string attributes,
+//! with numbers for counts and expiry times.
+
+// Std lib
+use std::collections::HashMap;
+
+// Third party
+use rusoto_dynamodb::AttributeValue;
+
+/// string attributes keyed by name, for items, keys and expression values
+pub fn attributes(values: Vec<(&str, String)>) -> HashMap<String, AttributeValue> {
+    values
+        .into_iter()
+        .map(|(name, value)| (name.to_owned(), string(value)))
+        .collect()
+}
+
+/// a string attribute
+pub fn string(value: String) -> AttributeValue {
+    AttributeValue {
+        s: Some(value),
+        ..Default::default()
+    }
+}
+
+/// a number attribute
+pub fn number(value: u64) -> AttributeValue {
+    AttributeValue {
+        n: Some(value.to_string()),
+        ..Default::default()
+    }
+}
@@ -0,0 +1,70 @@
+//! Upstream failures
+
+// Third party
+use reqwest;
+
+/// A failure to load data from an upstream service, rendered in place
+/// of the section it would have populated
+#[derive(Debug, Fail)]
+#[fail(display = "couldn't load {} ({})", upstream, reason)]
+pub struct FetchError {
+    pub upstream: &'static str,
+    pub reason: String,
+}
+
+impl FetchError {
+    pub fn new<R>(upstream: &'static str, reason: R) -> Self
+    where
+        R: Into<String>,
+    {
+        FetchError {
+            upstream,
+            reason: reason.into(),
+        }
+    }
+
+    pub fn pagerduty(err: reqwest::Error) -> Self {
+        FetchError::new("PagerDuty", http_reason(&err))
+    }
+
+    pub fn opsgenie(err: reqwest::Error) -> Self {
+        FetchError::new("Opsgenie", http_reason(&err))
+    }
+
+    pub fn google_calendar(err: reqwest::Error) -> Self {
+        FetchError::new("Google Calendar", http_reason(&err))
+    }
+
+    pub fn github(err: reqwest::Error) -> Self {
+        FetchError::new("GitHub", http_reason(&err))
+    }
+
+    pub fn circleci(err: reqwest::Error) -> Self {
+        FetchError::new("CircleCI", http_reason(&err))
+    }
+
+    pub fn agile(err: reqwest::Error) -> Self {
+        FetchError::new("Jira Agile", http_reason(&err))
+    }
+
+    pub fn jira(err: reqwest::Error) -> Self {
+        FetchError::new("Jira", http_reason(&err))
+    }
+
+    /// an upstream skipped while its circuit is open, see `breaker`
+    pub fn unavailable(upstream: &'static str, retry_in_secs: u64) -> Self {
+        FetchError::new(
+            upstream,
+            format!("unavailable, trying again in {}s", retry_in_secs),
+        )
+    }
+}
+
+/// prefer terse status codes over full error descriptions
+fn http_reason(err: &reqwest::Error) -> String {
+    match err.status() {
+        Some(status) => status.as_u16().to_string(),
+        None if err.is_timeout() => "timed out".into(),
+        None => err.to_string(),
+    }
+}
@@ -0,0 +1,223 @@
+//! Blockers escalated to leads
+//!
+//! Blockers reported in check-ins or flagged in Jira are also posted, on their
+//! own, to `escalation_channel` with the relevant `escalation_leads` mentioned,
+//! so they get seen beyond the standup thread.
+//!
+//! Jira blockers are escalated once, when they're first reported. Standups
+//! without the `blocked` section don't report any, so the blockers each
+//! standup reports are kept in history under a channel of their own, which
+//! the next report is compared against.
+
+// Std lib
+use std::collections::BTreeMap;
+
+// Third party
+use chrono::Utc;
+use serde::Deserialize;
+use serde_json::Value;
+
+// Ours
+use config::Config;
+use history::{Entry, History};
+use log;
+use render::{escape, Block, Message};
+use section::jira::browse_url;
+use section::IssueRecord;
+use sink::{Chat, Sink};
+
+/// Something holding someone up
+#[derive(Debug, PartialEq)]
+pub struct Blocker {
+    /// mrkdwn describing the blocker
+    pub text: String,
+    /// the jira project it's in, whose leads are mentioned alongside everyone's
+    pub project: Option<String>,
+}
+
+/// posts blockers to the escalation channel, if one's configured
+pub fn escalate(config: &Config, source: &str, blockers: &[Blocker]) {
+    if blockers.is_empty() {
+        return;
+    }
+    let (token, channel) = match (
+        config.slack_bot_token.clone(),
+        config.escalation_channel.clone(),
+    ) {
+        (Some(token), Some(channel)) => (token, channel),
+        _ => return,
+    };
+    let message = message(source, blockers, &leads(&config.escalation_leads, blockers));
+    match Chat::new(token, channel, config.http_client()).send(&message) {
+        Ok(_) => log::info(
+            "escalated blockers",
+            json!({ "source": source, "blockers": blockers.len() }),
+        ),
+        Err(err) => log::error("failed to escalate blockers", json!({ "error": err })),
+    }
+}
+
+/// escalates issues in a standup's `blocked` record which weren't blocked
+/// when blockers were last reported in its channel
+pub fn escalate_blocked(config: &Config, channel: &str, blocked: &Value) {
+    let history = config.history_table.clone().map(History::new);
+    let channel = format!("{}:blocked", channel);
+    let previous = history.as_ref().and_then(|history| {
+        history.latest(&channel).unwrap_or_else(|err| {
+            log::error("failed to load previous blockers", json!({ "error": err }));
+            None
+        })
+    });
+    escalate(
+        config,
+        "Jira",
+        &newly_blocked(
+            blocked,
+            previous
+                .as_ref()
+                .and_then(|previous| previous.sections.get("blocked")),
+            &config.jira_host,
+        ),
+    );
+    if let Some(history) = history {
+        let mut sections = BTreeMap::new();
+        sections.insert("blocked".to_owned(), blocked.clone());
+        let entry = Entry {
+            channel,
+            posted_at: Utc::now(),
+            sections,
+            message: Value::Null,
+            ts: None,
+            permalink: None,
+            command: None,
+        };
+        if let Err(err) = history.save(&entry) {
+            log::error("failed to save blockers", json!({ "error": err }));
+        }
+    }
+}
+
+/// issues in a standup's `blocked` record which weren't in the previous report's
+fn newly_blocked(blocked: &Value, previous: Option<&Value>, jira_host: &str) -> Vec<Blocker> {
+    let previous = previous
+        .and_then(|previous| Vec::<IssueRecord>::deserialize(previous).ok())
+        .unwrap_or_default();
+    Vec::<IssueRecord>::deserialize(blocked)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|issue| !previous.iter().any(|previous| previous.key == issue.key))
+        .map(|issue| Blocker {
+            text: format!(
+                "<{}|{}> {}{}",
+                browse_url(jira_host, &issue.key),
+                issue.key,
+                escape(&issue.summary.unwrap_or_default()),
+                issue
+                    .assignee
+                    .map(|assignee| format!(" ({})", escape(&assignee)))
+                    .unwrap_or_default()
+            ),
+            project: issue.key.split('-').next().map(String::from),
+        })
+        .collect()
+}
+
+/// leads listed as `user id`, for everything, or `PROJECT=user id`, for a jira project's blockers
+fn leads(configured: &[String], blockers: &[Blocker]) -> Vec<String> {
+    let mut leads = Vec::new();
+    for lead in configured {
+        let mut parts = lead.splitn(2, '=');
+        let (project, user) = match (parts.next(), parts.next()) {
+            (Some(project), Some(user)) => (Some(project.trim()), user.trim()),
+            (Some(user), None) => (None, user.trim()),
+            _ => continue,
+        };
+        let relevant = project.map_or(true, |project| {
+            blockers
+                .iter()
+                .any(|blocker| blocker.project.as_ref().map(String::as_str) == Some(project))
+        });
+        if relevant && !user.is_empty() && !leads.iter().any(|lead| lead == user) {
+            leads.push(user.to_owned());
+        }
+    }
+    leads
+}
+
+fn message(source: &str, blockers: &[Blocker], leads: &[String]) -> Message {
+    let text = match blockers.len() {
+        1 => format!("🚨 1 blocker from {}", source),
+        count => format!("🚨 {} blockers from {}", count, source),
+    };
+    let heading = if leads.is_empty() {
+        text.clone()
+    } else {
+        format!(
+            "{} {}",
+            leads
+                .iter()
+                .map(|lead| format!("<@{}>", lead))
+                .collect::<Vec<_>>()
+                .join(" "),
+            text
+        )
+    };
+    let mut blocks = vec![Block::section(heading)];
+    blocks.extend(
+        blockers
+            .iter()
+            .map(|blocker| Block::section(blocker.text.as_str())),
+    );
+    Message { text, blocks }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn blocker(project: Option<&str>) -> Blocker {
+        Blocker {
+            text: "stuck".into(),
+            project: project.map(String::from),
+        }
+    }
+
+    #[test]
+    fn leads_are_mentioned_for_their_projects() {
+        let configured = vec!["U1".into(), "CORE=U2".into(), "WEB=U3".into()];
+        assert_eq!(
+            leads(&configured, &[blocker(Some("CORE")), blocker(None)]),
+            vec!["U1".to_owned(), "U2".to_owned()]
+        );
+        assert_eq!(leads(&configured, &[blocker(None)]), vec!["U1".to_owned()]);
+    }
+
+    #[test]
+    fn only_newly_blocked_issues_are_escalated() {
+        let blocked = json!([
+            { "key": "CORE-1", "summary": "stuck", "status": "Blocked", "assignee": "dana" },
+            { "key": "CORE-2", "summary": "still stuck", "status": "Blocked", "assignee": null },
+        ]);
+        let previous = json!([{ "key": "CORE-2", "summary": "still stuck", "status": "Blocked", "assignee": null }]);
+        assert_eq!(
+            newly_blocked(&blocked, Some(&previous), "https://jira"),
+            vec![Blocker {
+                text: "<https://jira/browse/CORE-1|CORE-1> stuck (dana)".into(),
+                project: Some("CORE".into()),
+            }]
+        );
+    }
+
+    #[test]
+    fn messages_mention_leads() {
+        let message = message("check-ins", &[blocker(None)], &["U1".into()]);
+        assert_eq!(message.text, "🚨 1 blocker from check-ins");
+        assert_eq!(
+            message.blocks,
+            vec![
+                Block::section("<@U1> 🚨 1 blocker from check-ins"),
+                Block::section("stuck"),
+            ]
+        );
+    }
+}
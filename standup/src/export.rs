@@ -0,0 +1,115 @@
+//! Machine readable standups
+//!
+//! Alongside the slack message, each standup may be posted as a json document
+//! to a webhook for dashboards and data warehouse loaders to consume.
+
+// Std lib
+use std::collections::BTreeMap;
+
+// Third party
+use chrono::Utc;
+use serde::Deserialize;
+use serde_json::Value;
+
+// Ours
+use log;
+use retry::{self, Retry};
+use section::{Context, IssueRecord};
+
+/// bumped whenever the shape of the document changes incompatibly
+const VERSION: u32 = 1;
+
+/// What a standup reported on
+pub struct Report<'a> {
+    pub channel: &'a str,
+    /// raw section data, keyed by section name
+    pub records: &'a BTreeMap<String, Value>,
+    /// why sections failed to report, keyed by section name
+    pub errors: &'a BTreeMap<String, String>,
+    /// sections' measurements, keyed by metric name
+    pub metrics: &'a BTreeMap<&'static str, f64>,
+    pub summary: Vec<String>,
+}
+
+/// the json document describing a standup
+pub fn document(report: &Report, context: &Context) -> Value {
+    json!({
+        "version": VERSION,
+        "channel": report.channel,
+        "generated_at": Utc::now().to_rfc3339(),
+        "lookback_days": context.lookback_days,
+        "since": context.since(),
+        "summary": report.summary,
+        "incidents": report.records.get("weather"),
+        "issues_by_status": report.records.get("jira").map(by_status),
+        "blocked": report.records.get("blocked"),
+        "sections": report.records,
+        "errors": report.errors,
+    })
+}
+
+/// posts a standup's document to the configured webhook, if any
+pub fn publish(report: &Report, context: &Context) {
+    let url = match context.config.data_webhook_url {
+        Some(ref url) => url,
+        None => return,
+    };
+    let document = document(report, context);
+    let client = context.config.http_client();
+    let published = Retry::from(&context.config).run(
+        || {
+            client
+                .post(url.as_str())
+                .json(&document)
+                .send()
+                .and_then(|response| response.error_for_status())
+        },
+        retry::http,
+    );
+    if let Err(err) = published {
+        log::error(
+            "failed to publish standup data",
+            json!({
+                "error": err.to_string(),
+                "upstream_status": err.status().map(|status| status.as_u16()),
+            }),
+        );
+    }
+}
+
+/// issue records grouped by status name
+fn by_status(issues: &Value) -> BTreeMap<String, Vec<IssueRecord>> {
+    let issues = Vec::<IssueRecord>::deserialize(issues).unwrap_or_default();
+    issues.into_iter().fold(BTreeMap::new(), |mut acc, issue| {
+        acc.entry(
+            issue
+                .status
+                .clone()
+                .unwrap_or_else(|| "Unknown Status".into()),
+        )
+        .or_insert_with(Vec::new)
+        .push(issue);
+        acc
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn groups_issues_by_status() {
+        let grouped = by_status(&json!([
+            { "key": "A-1", "summary": "one", "status": "Closed", "assignee": "ada" },
+            { "key": "A-2", "summary": "two", "status": "In Progress", "assignee": null },
+            { "key": "A-3", "summary": "three", "status": "Closed", "assignee": "grace" },
+        ]));
+        assert_eq!(
+            grouped
+                .iter()
+                .map(|(status, issues)| (status.as_str(), issues.len()))
+                .collect::<Vec<_>>(),
+            vec![("Closed", 2), ("In Progress", 1)]
+        );
+    }
+}
@@ -0,0 +1,233 @@
+//! Standup facilitators, rotated through a roster
+//!
+//! Each channel's rotation is stored in DynamoDB, so it carries on across
+//! cold starts and standups run more than once a day keep the same
+//! facilitator. Only scheduled standups pick one, so ad-hoc standups don't
+//! move the rotation along. Anyone who bows out with `/standup skip-me` on their turn
+//! is owed one, and facilitates the next standup they're around for.
+
+// Third party
+use chrono::NaiveDate;
+use rusoto_core::Region;
+use rusoto_dynamodb::{DynamoDb, DynamoDbClient, GetItemInput, PutItemInput};
+use serde_json;
+
+// Ours
+use config::Config;
+use dynamodb::attributes;
+
+/// Where a channel's rotation is up to
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+pub struct Rotation {
+    /// the facilitator last picked in roster order
+    #[serde(default)]
+    pub last: Option<String>,
+    /// who's facilitating on `date`
+    #[serde(default)]
+    pub facilitator: Option<String>,
+    #[serde(default)]
+    pub date: Option<NaiveDate>,
+    /// those who skipped their turn, first in line for the next one
+    #[serde(default)]
+    pub owed: Vec<String>,
+    /// those skipping on `date`
+    #[serde(default)]
+    pub skipping: Vec<String>,
+}
+
+impl Rotation {
+    /// who's facilitating on a date, picking the next in line if nobody has been yet
+    pub fn facilitator(&mut self, roster: &[String], date: NaiveDate) -> Option<String> {
+        if self.date != Some(date) {
+            self.date = Some(date);
+            self.facilitator = None;
+            self.skipping.clear();
+        }
+        if self.facilitator.is_none() {
+            self.facilitator = self.next(roster);
+        }
+        self.facilitator.clone()
+    }
+
+    /// bows a user out for a date, returning false if they're not on the roster
+    pub fn skip(&mut self, roster: &[String], user: &str, date: NaiveDate) -> bool {
+        if !roster.iter().any(|member| member == user) {
+            return false;
+        }
+        if self.date != Some(date) {
+            self.date = Some(date);
+            self.facilitator = None;
+            self.skipping.clear();
+        }
+        if !self.skipping.iter().any(|skipping| skipping == user) {
+            self.skipping.push(user.to_owned());
+        }
+        if self.facilitator.as_ref().map(String::as_str) == Some(user) {
+            self.facilitator = None;
+            self.owed.push(user.to_owned());
+        }
+        true
+    }
+
+    /// whoever's owed a turn, otherwise the next in the roster after the last picked
+    fn next(&mut self, roster: &[String]) -> Option<String> {
+        let skipping = &self.skipping;
+        let available = |user: &String| {
+            roster.contains(user) && !skipping.iter().any(|skipping| skipping == user)
+        };
+        if let Some(index) = self.owed.iter().position(&available) {
+            return Some(self.owed.remove(index));
+        }
+        let start = self
+            .last
+            .as_ref()
+            .and_then(|last| roster.iter().position(|member| member == last))
+            .map(|index| index + 1)
+            .unwrap_or(0);
+        let next = roster
+            .iter()
+            .cycle()
+            .skip(start)
+            .take(roster.len())
+            .find(|user| available(user))
+            .cloned();
+        if next.is_some() {
+            self.last = next.clone();
+        }
+        next
+    }
+}
+
+/// Rotations persisted to DynamoDB, keyed by channel
+pub struct Rotations {
+    table: String,
+    client: DynamoDbClient,
+}
+
+impl Rotations {
+    pub fn new(table: String) -> Self {
+        Rotations {
+            table,
+            client: DynamoDbClient::new(Region::default()),
+        }
+    }
+
+    /// rotations, when there's a roster and a table to keep track of it in
+    pub fn configured(config: &Config) -> Option<Rotations> {
+        if config.facilitator_roster.is_empty() {
+            return None;
+        }
+        config.rotation_table.clone().map(Rotations::new)
+    }
+
+    /// a channel's rotation, from the top of the roster if it hasn't started
+    pub fn get(&self, channel: &str) -> Result<Rotation, String> {
+        let output = self
+            .client
+            .get_item(GetItemInput {
+                table_name: self.table.clone(),
+                key: attributes(vec![("channel", channel.to_owned())]),
+                ..Default::default()
+            })
+            .sync()
+            .map_err(|err| format!("failed to load rotation: {}", err))?;
+        Ok(output
+            .item
+            .as_ref()
+            .and_then(|item| item.get("document"))
+            .and_then(|document| document.s.as_ref())
+            .and_then(|document| serde_json::from_str(document).ok())
+            .unwrap_or_default())
+    }
+
+    pub fn save(&self, channel: &str, rotation: &Rotation) -> Result<(), String> {
+        let document = serde_json::to_string(rotation).map_err(|err| err.to_string())?;
+        self.client
+            .put_item(PutItemInput {
+                table_name: self.table.clone(),
+                item: attributes(vec![
+                    ("channel", channel.to_owned()),
+                    ("document", document),
+                ]),
+                ..Default::default()
+            })
+            .sync()
+            .map(|_| ())
+            .map_err(|err| format!("failed to save rotation: {}", err))
+    }
+}
+
+/// today's facilitator for a channel
+pub fn today(
+    config: &Config,
+    rotations: &Rotations,
+    channel: &str,
+    date: NaiveDate,
+) -> Result<Option<String>, String> {
+    let mut rotation = rotations.get(channel)?;
+    let before = rotation.clone();
+    let facilitator = rotation.facilitator(&config.facilitator_roster, date);
+    if rotation != before {
+        rotations.save(channel, &rotation)?;
+    }
+    Ok(facilitator)
+}
+
+/// bows a user out of facilitating in a channel for a day, returning a reply for them
+pub fn skip(
+    config: &Config,
+    rotations: &Rotations,
+    channel: &str,
+    user: &str,
+    date: NaiveDate,
+) -> Result<String, String> {
+    let mut rotation = rotations.get(channel)?;
+    if !rotation.skip(&config.facilitator_roster, user, date) {
+        return Ok("You're not in the facilitator rotation".into());
+    }
+    rotations.save(channel, &rotation)?;
+    Ok("👍 You're off the hook for facilitating today".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roster() -> Vec<String> {
+        vec!["sam".into(), "dana".into(), "lee".into()]
+    }
+
+    fn day(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd(2019, 1, day)
+    }
+
+    #[test]
+    fn facilitators_rotate_daily() {
+        let mut rotation = Rotation::default();
+        assert_eq!(rotation.facilitator(&roster(), day(7)), Some("sam".into()));
+        assert_eq!(rotation.facilitator(&roster(), day(7)), Some("sam".into()));
+        assert_eq!(rotation.facilitator(&roster(), day(8)), Some("dana".into()));
+        assert_eq!(rotation.facilitator(&roster(), day(9)), Some("lee".into()));
+        assert_eq!(rotation.facilitator(&roster(), day(10)), Some("sam".into()));
+    }
+
+    #[test]
+    fn skippers_are_owed_a_turn() {
+        let mut rotation = Rotation::default();
+        assert_eq!(rotation.facilitator(&roster(), day(7)), Some("sam".into()));
+        assert!(rotation.skip(&roster(), "sam", day(7)));
+        assert_eq!(rotation.facilitator(&roster(), day(7)), Some("dana".into()));
+        assert_eq!(rotation.facilitator(&roster(), day(8)), Some("sam".into()));
+        assert_eq!(rotation.facilitator(&roster(), day(9)), Some("lee".into()));
+        assert!(!rotation.skip(&roster(), "pat", day(9)));
+    }
+
+    #[test]
+    fn nobody_facilitates_when_everyone_skips() {
+        let mut rotation = Rotation::default();
+        for user in roster() {
+            rotation.skip(&roster(), &user, day(7));
+        }
+        assert_eq!(rotation.facilitator(&roster(), day(7)), None);
+    }
+}
@@ -0,0 +1,265 @@
+//! GitHub api client
+
+// Third party
+use chrono::{DateTime, FixedOffset};
+use reqwest::header::{ACCEPT, AUTHORIZATION, USER_AGENT};
+use reqwest::Client;
+use serde::de::DeserializeOwned;
+
+// Ours
+use error::FetchError;
+use retry::{self, Retry};
+use trace;
+
+const API: &str = "https://api.github.com";
+
+#[derive(Deserialize, Debug)]
+struct SearchResults {
+    items: Vec<PullRequest>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PullRequest {
+    pub number: usize,
+    pub title: String,
+    pub html_url: String,
+    pub user: User,
+    /// api url of the repository, ending in `owner/name`
+    repository_url: String,
+    // absent from standups recorded before these were reported
+    #[serde(default)]
+    pub created_at: Option<DateTime<FixedOffset>>,
+    /// when it was merged, for merged pull requests
+    #[serde(default)]
+    pub closed_at: Option<DateTime<FixedOffset>>,
+}
+
+impl PullRequest {
+    /// `owner/name` of the repository this pull request was opened against
+    pub fn repository(&self) -> &str {
+        let mut slashes = self.repository_url.rmatch_indices('/').map(|(i, _)| i);
+        match (slashes.next(), slashes.next()) {
+            (Some(_), Some(start)) => &self.repository_url[start + 1..],
+            _ => &self.repository_url,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct User {
+    pub login: String,
+}
+
+/// see https://docs.github.com/en/rest/deployments/deployments#list-deployments
+#[derive(Deserialize, Debug)]
+pub struct Deployment {
+    pub id: u64,
+    pub sha: String,
+    /// the branch, tag or sha deployed
+    #[serde(rename = "ref")]
+    pub reference: String,
+    pub created_at: DateTime<FixedOffset>,
+    pub creator: Option<User>,
+}
+
+/// see https://docs.github.com/en/rest/deployments/statuses#list-deployment-statuses
+#[derive(Deserialize, Debug)]
+pub struct DeploymentStatus {
+    /// `success`, `failure`, `in_progress` etc.
+    pub state: String,
+    #[serde(default)]
+    pub environment_url: Option<String>,
+    #[serde(default)]
+    pub log_url: Option<String>,
+}
+
+/// see https://docs.github.com/en/rest/actions/workflow-runs#list-workflow-runs-for-a-repository
+#[derive(Deserialize, Debug)]
+struct WorkflowRuns {
+    workflow_runs: Vec<WorkflowRun>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct WorkflowRun {
+    pub name: String,
+    pub html_url: String,
+    /// `success`, `failure`, `cancelled`, `timed_out` etc. once completed
+    pub conclusion: Option<String>,
+    pub created_at: DateTime<FixedOffset>,
+}
+
+pub struct GitHub {
+    api: String,
+    token: String,
+    client: Client,
+    retry: Retry,
+}
+
+impl GitHub {
+    pub fn new(token: String, client: Client, retry: Retry) -> Self {
+        GitHub {
+            api: API.into(),
+            token,
+            client,
+            retry,
+        }
+    }
+
+    /// points requests at another api host, i.e. a mock server
+    pub fn with_api(self, api: String) -> Self {
+        GitHub { api, ..self }
+    }
+
+    /// open pull requests which haven't been approved yet
+    pub fn awaiting_review(&self, scope: &str) -> Result<Vec<PullRequest>, FetchError> {
+        self.search(&format!(
+            "is:pr is:open draft:false -review:approved {}",
+            scope
+        ))
+    }
+
+    /// pull requests merged since a given `YYYY-MM-DD` date
+    pub fn merged(&self, scope: &str, since: &str) -> Result<Vec<PullRequest>, FetchError> {
+        self.search(&format!("is:pr is:merged merged:>={} {}", since, scope))
+    }
+
+    /// the most recent completed github actions runs on a branch of an `owner/name` repository, newest first
+    pub fn workflow_runs(&self, repo: &str, branch: &str) -> Result<Vec<WorkflowRun>, FetchError> {
+        self.get::<WorkflowRuns>(
+            &format!("/repos/{}/actions/runs", repo),
+            &[
+                ("branch", branch),
+                ("status", "completed"),
+                ("per_page", "100"),
+            ],
+        )
+        .map(|runs| runs.workflow_runs)
+    }
+
+    /// the most recent deployments of an `owner/name` repository to an environment, newest first
+    pub fn deployments(
+        &self,
+        repo: &str,
+        environment: &str,
+    ) -> Result<Vec<Deployment>, FetchError> {
+        self.get(
+            &format!("/repos/{}/deployments", repo),
+            &[("environment", environment), ("per_page", "100")],
+        )
+    }
+
+    /// statuses of a deployment, newest first
+    pub fn deployment_statuses(
+        &self,
+        repo: &str,
+        deployment_id: u64,
+    ) -> Result<Vec<DeploymentStatus>, FetchError> {
+        self.get(
+            &format!("/repos/{}/deployments/{}/statuses", repo, deployment_id),
+            &[("per_page", "100")],
+        )
+    }
+
+    fn search(&self, query: &str) -> Result<Vec<PullRequest>, FetchError> {
+        self.get::<SearchResults>(
+            "/search/issues",
+            &[("q", query), ("sort", "updated"), ("per_page", "100")],
+        )
+        .map(|results| results.items)
+    }
+
+    fn get<T>(&self, path: &str, query: &[(&str, &str)]) -> Result<T, FetchError>
+    where
+        T: DeserializeOwned,
+    {
+        self.retry
+            .run(
+                || {
+                    trace::request("GitHub", path, || {
+                        self.client
+                            .get(&format!("{}{}", self.api, path))
+                            .query(query)
+                            .header(ACCEPT, "application/vnd.github.v3+json")
+                            .header(AUTHORIZATION, format!("token {}", self.token))
+                            .header(USER_AGENT, "slack-standup")
+                            .send()
+                            .and_then(|response| response.error_for_status())
+                            .and_then(|mut response| response.json::<T>())
+                    })
+                },
+                retry::http,
+            )
+            .map_err(FetchError::github)
+    }
+}
+
+/// search qualifiers limiting results to the given repositories and/or organization
+pub fn scope(repos: &[String], org: Option<&str>) -> String {
+    repos
+        .iter()
+        .map(|repo| format!("repo:{}", repo))
+        .chain(org.map(|org| format!("org:{}", org)))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::{self, mock, Matcher};
+    use retry::Retry;
+
+    #[test]
+    fn awaiting_review_searches_unapproved_pull_requests() {
+        let _search = mock("GET", "/search/issues")
+            .match_query(Matcher::UrlEncoded(
+                "q".into(),
+                "is:pr is:open draft:false -review:approved repo:meetup/slack-standup".into(),
+            ))
+            .match_header("authorization", "token secret")
+            .with_body(
+                r#"{"items":[{"number":7,"title":"Add tests","html_url":"https://github.com/meetup/slack-standup/pull/7","user":{"login":"octocat"},"repository_url":"https://api.github.com/repos/meetup/slack-standup"}]}"#,
+            )
+            .create();
+        let pulls = GitHub::new(
+            "secret".into(),
+            Client::new(),
+            Retry {
+                attempts: 1,
+                backoff_ms: 0,
+            },
+        )
+        .with_api(mockito::server_url())
+        .awaiting_review("repo:meetup/slack-standup")
+        .unwrap();
+        assert_eq!(pulls.len(), 1);
+        assert_eq!(pulls[0].user.login, "octocat");
+        assert_eq!(pulls[0].repository(), "meetup/slack-standup");
+    }
+
+    #[test]
+    fn workflow_runs_are_listed_for_a_branch() {
+        let _runs = mock("GET", "/repos/meetup/slack-standup/actions/runs")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("branch".into(), "main".into()),
+                Matcher::UrlEncoded("status".into(), "completed".into()),
+            ]))
+            .with_body(
+                r#"{"total_count":1,"workflow_runs":[{"id":1,"name":"ci","html_url":"https://github.com/meetup/slack-standup/actions/runs/1","conclusion":"failure","created_at":"2019-01-07T15:00:00Z"}]}"#,
+            )
+            .create();
+        let runs = GitHub::new(
+            "secret".into(),
+            Client::new(),
+            Retry {
+                attempts: 1,
+                backoff_ms: 0,
+            },
+        )
+        .with_api(mockito::server_url())
+        .workflow_runs("meetup/slack-standup", "main")
+        .unwrap();
+        assert_eq!(runs.len(), 1);
+        assert_eq!(runs[0].conclusion, Some("failure".into()));
+    }
+}
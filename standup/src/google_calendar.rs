@@ -0,0 +1,224 @@
+//! Google Calendar api client
+//!
+//! Authorized with the refresh token of an account the shared calendar is
+//! shared with, see https://developers.google.com/identity/protocols/oauth2/web-server#offline
+
+// Third party
+use chrono::{DateTime, FixedOffset, NaiveDate};
+use reqwest::header::AUTHORIZATION;
+use reqwest::Client;
+
+// Ours
+use config::Config;
+use error::FetchError;
+use retry::{self, Retry};
+use trace;
+
+const API: &str = "https://www.googleapis.com/calendar/v3";
+
+const TOKEN_URL: &str = "https://oauth2.googleapis.com/token";
+
+#[derive(Deserialize, Debug)]
+struct AccessToken {
+    access_token: String,
+}
+
+/// see https://developers.google.com/calendar/api/v3/reference/events/list
+#[derive(Deserialize, Debug)]
+struct Events {
+    items: Vec<Event>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Event {
+    /// absent for private events
+    pub summary: Option<String>,
+    pub html_link: String,
+    pub start: EventTime,
+    /// `default` or `outOfOffice`
+    pub event_type: Option<String>,
+    pub creator: Option<Creator>,
+}
+
+impl Event {
+    pub fn is_all_day(&self) -> bool {
+        self.start.date.is_some()
+    }
+}
+
+/// all day events start on a date, others at a time
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct EventTime {
+    pub date: Option<NaiveDate>,
+    pub date_time: Option<DateTime<FixedOffset>>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Creator {
+    pub email: Option<String>,
+    pub display_name: Option<String>,
+}
+
+pub struct GoogleCalendar {
+    api: String,
+    token_url: String,
+    client_id: String,
+    client_secret: String,
+    refresh_token: String,
+    client: Client,
+    retry: Retry,
+}
+
+impl GoogleCalendar {
+    pub fn new(
+        client_id: String,
+        client_secret: String,
+        refresh_token: String,
+        client: Client,
+        retry: Retry,
+    ) -> Self {
+        GoogleCalendar {
+            api: API.into(),
+            token_url: TOKEN_URL.into(),
+            client_id,
+            client_secret,
+            refresh_token,
+            client,
+            retry,
+        }
+    }
+
+    /// the calendar, when an oauth client and refresh token are configured
+    pub fn configured(config: &Config) -> Option<GoogleCalendar> {
+        match (
+            config.google_client_id.clone(),
+            config.google_client_secret.clone(),
+            config.google_refresh_token.clone(),
+        ) {
+            (Some(client_id), Some(client_secret), Some(refresh_token)) => {
+                Some(GoogleCalendar::new(
+                    client_id,
+                    client_secret,
+                    refresh_token,
+                    config.http_client(),
+                    Retry::from(config),
+                ))
+            }
+            _ => None,
+        }
+    }
+
+    /// points requests at another host, i.e. a mock server
+    pub fn with_api(self, api: String) -> Self {
+        GoogleCalendar {
+            token_url: format!("{}/token", api),
+            api,
+            ..self
+        }
+    }
+
+    /// events on a calendar between two timestamps, in the order they start
+    ///
+    /// recurring events are expanded into their occurrences
+    pub fn events(
+        &self,
+        calendar_id: &str,
+        start: &str,
+        end: &str,
+    ) -> Result<Vec<Event>, FetchError> {
+        let token = self.access_token()?;
+        self.retry
+            .run(
+                || {
+                    trace::request("Google Calendar", "/calendars/events", || {
+                        self.client
+                            .get(&format!("{}/calendars/{}/events", self.api, calendar_id))
+                            .query(&[
+                                ("timeMin", start),
+                                ("timeMax", end),
+                                ("singleEvents", "true"),
+                                ("orderBy", "startTime"),
+                                ("maxResults", "250"),
+                            ])
+                            .header(AUTHORIZATION, format!("Bearer {}", token))
+                            .send()
+                            .and_then(|response| response.error_for_status())
+                            .and_then(|mut response| response.json::<Events>())
+                    })
+                },
+                retry::http,
+            )
+            .map(|events| events.items)
+            .map_err(FetchError::google_calendar)
+    }
+
+    fn access_token(&self) -> Result<String, FetchError> {
+        self.retry
+            .run(
+                || {
+                    self.client
+                        .post(self.token_url.as_str())
+                        .form(&[
+                            ("client_id", self.client_id.as_str()),
+                            ("client_secret", self.client_secret.as_str()),
+                            ("refresh_token", self.refresh_token.as_str()),
+                            ("grant_type", "refresh_token"),
+                        ])
+                        .send()
+                        .and_then(|response| response.error_for_status())
+                        .and_then(|mut response| response.json::<AccessToken>())
+                },
+                retry::http,
+            )
+            .map(|token| token.access_token)
+            .map_err(FetchError::google_calendar)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::{self, mock, Matcher};
+    use retry::Retry;
+
+    #[test]
+    fn events_are_listed_with_a_refreshed_access_token() {
+        let _token = mock("POST", "/token")
+            .match_body(Matcher::Regex("refresh_token=refresh".into()))
+            .with_body(r#"{"access_token":"access","expires_in":3599,"token_type":"Bearer"}"#)
+            .create();
+        let _events = mock("GET", "/calendars/team@group.calendar.google.com/events")
+            .match_query(Matcher::UrlEncoded("singleEvents".into(), "true".into()))
+            .match_header("authorization", "Bearer access")
+            .with_body(
+                r#"{"items":[
+                    {"summary":"Dana OOO","htmlLink":"https://calendar/1","start":{"date":"2019-01-07"},"eventType":"default"},
+                    {"summary":"Planning","htmlLink":"https://calendar/2","start":{"dateTime":"2019-01-07T10:00:00-05:00"}}
+                ]}"#,
+            )
+            .create();
+        let events = GoogleCalendar::new(
+            "id".into(),
+            "secret".into(),
+            "refresh".into(),
+            Client::new(),
+            Retry {
+                attempts: 1,
+                backoff_ms: 0,
+            },
+        )
+        .with_api(mockito::server_url())
+        .events(
+            "team@group.calendar.google.com",
+            "2019-01-07T00:00:00-05:00",
+            "2019-01-08T00:00:00-05:00",
+        )
+        .unwrap();
+        assert_eq!(events.len(), 2);
+        assert!(events[0].is_all_day());
+        assert!(!events[1].is_all_day());
+    }
+}
@@ -0,0 +1,147 @@
+//! Health checks
+//!
+//! Verifies we can reach, and are authorized by, each configured upstream,
+//! so a broken integration shows up in monitoring rather than at standup.
+//! Checks skip the cache, and run in parallel to fit api gateway's timeout.
+//! They make live, authorized requests, refreshing OAuth tokens included, so
+//! they're only run for requests carrying the `health_token`.
+
+// Third party
+use reqwest::header::AUTHORIZATION;
+use reqwest::Client;
+use sha2::{Digest, Sha256};
+
+// Ours
+use breaker::Breaker;
+use cache::Cache;
+use config::Config;
+use jira_auth::Site;
+use pagerduty::PagerDuty;
+use retry::Retry;
+use trace;
+
+const API: &str = "https://slack.com/api";
+
+/// Whether an upstream is healthy
+#[derive(Debug)]
+pub struct Check {
+    pub upstream: &'static str,
+    /// why the upstream is unhealthy
+    pub result: Result<(), String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct AuthTest {
+    ok: bool,
+    error: Option<String>,
+}
+
+/// returns true if an `Authorization` header carries the configured token
+pub fn authorized(token: Option<&str>, authorization: Option<&str>) -> bool {
+    match (token, authorization) {
+        // digests are compared, so the comparison's timing doesn't give the token away
+        (Some(token), Some(authorization)) if authorization.starts_with("Bearer ") => {
+            Sha256::digest(authorization["Bearer ".len()..].as_bytes())
+                == Sha256::digest(token.as_bytes())
+        }
+        _ => false,
+    }
+}
+
+/// checks slack, jira and pagerduty, skipping those which aren't configured
+pub fn check(config: &Config) -> Vec<Check> {
+    let retry = Retry::from(config);
+    let mut pending = Vec::new();
+    if let Some(token) = config.slack_bot_token.clone() {
+        let client = config.http_client();
+        pending.push(("Slack", trace::spawn(move || slack(API, &token, &client))));
+    }
+    if !config.jira_host.is_empty() {
+        match Site::configured(config) {
+            Ok(site) => pending.push((
+                "Jira",
+                trace::spawn(move || {
+                    site.with_cache(Cache::default())
+                        .get_json("/rest/api/2/myself", &[], retry)
+                        .map(|_| ())
+                        .map_err(|err| err.to_string())
+                }),
+            )),
+            Err(err) => pending.push(("Jira", trace::spawn(move || Err(err.to_string())))),
+        }
+    }
+    if !config.pd_token.is_empty() {
+        let pagerduty = PagerDuty::new(config.pd_token.clone(), config.http_client(), retry)
+            .with_breaker(Breaker::from(config));
+        pending.push((
+            "PagerDuty",
+            trace::spawn(move || {
+                pagerduty
+                    .abilities()
+                    .map(|_| ())
+                    .map_err(|err| err.to_string())
+            }),
+        ));
+    }
+    pending
+        .into_iter()
+        .map(|(upstream, handle)| Check {
+            upstream,
+            result: handle
+                .join()
+                .unwrap_or_else(|_| Err("check panicked".into())),
+        })
+        .collect()
+}
+
+/// verifies the bot token
+fn slack(api: &str, token: &str, client: &Client) -> Result<(), String> {
+    let tested = client
+        .post(&format!("{}/auth.test", api))
+        .header(AUTHORIZATION, format!("Bearer {}", token))
+        .send()
+        .and_then(|response| response.error_for_status())
+        .and_then(|mut response| response.json::<AuthTest>())
+        .map_err(|err| format!("couldn't reach Slack ({})", err))?;
+    if tested.ok {
+        Ok(())
+    } else {
+        Err(format!(
+            "Slack rejected the bot token ({})",
+            tested.error.unwrap_or_default()
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::{self, mock};
+
+    #[test]
+    fn checks_need_the_token() {
+        assert!(authorized(Some("s3cret"), Some("Bearer s3cret")));
+        assert!(!authorized(Some("s3cret"), Some("Bearer guess")));
+        assert!(!authorized(Some("s3cret"), Some("s3cret")));
+        assert!(!authorized(Some("s3cret"), None));
+        assert!(!authorized(None, Some("Bearer ")));
+    }
+
+    #[test]
+    fn slack_checks_the_bot_token() {
+        let _ok = mock("POST", "/auth.test")
+            .match_header("authorization", "Bearer xoxb-valid")
+            .with_body(r#"{"ok":true}"#)
+            .create();
+        let _revoked = mock("POST", "/auth.test")
+            .match_header("authorization", "Bearer xoxb-revoked")
+            .with_body(r#"{"ok":false,"error":"token_revoked"}"#)
+            .create();
+        let client = Client::new();
+        assert_eq!(slack(&mockito::server_url(), "xoxb-valid", &client), Ok(()));
+        assert_eq!(
+            slack(&mockito::server_url(), "xoxb-revoked", &client),
+            Err("Slack rejected the bot token (token_revoked)".into())
+        );
+    }
+}
@@ -0,0 +1,116 @@
+//! Standup history persisted to DynamoDB
+//!
+//! Each posted standup is stored as a json document keyed by the channel it
+//! was posted to and when it was posted.
+
+// Std lib
+use std::collections::BTreeMap;
+
+// Third party
+use chrono::{DateTime, Utc};
+use rusoto_core::Region;
+use rusoto_dynamodb::{DynamoDb, DynamoDbClient, PutItemInput, QueryInput};
+use serde_json::{self, Value};
+
+/// A posted standup
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Entry {
+    pub channel: String,
+    pub posted_at: DateTime<Utc>,
+    /// raw data each section reported on, keyed by section name
+    pub sections: BTreeMap<String, Value>,
+    /// the rendered slack message
+    pub message: Value,
+    /// `ts` of the posted message, when slack tells us where it was posted
+    #[serde(default)]
+    pub ts: Option<String>,
+    /// link to the posted message
+    #[serde(default)]
+    pub permalink: Option<String>,
+    /// arguments of the command which produced it, see `Command::standup`
+    #[serde(default)]
+    pub command: Option<String>,
+}
+
+pub struct History {
+    table: String,
+    client: DynamoDbClient,
+}
+
+impl History {
+    pub fn new(table: String) -> Self {
+        History {
+            table,
+            client: DynamoDbClient::new(Region::default()),
+        }
+    }
+
+    pub fn save(&self, entry: &Entry) -> Result<(), String> {
+        let document = serde_json::to_string(entry).map_err(|err| err.to_string())?;
+        let item = attributes(vec![
+            ("channel", entry.channel.clone()),
+            ("posted_at", entry.posted_at.to_rfc3339()),
+            ("document", document),
+        ]);
+        self.client
+            .put_item(PutItemInput {
+                table_name: self.table.clone(),
+                item,
+                ..Default::default()
+            })
+            .sync()
+            .map(|_| ())
+            .map_err(|err| format!("failed to save standup history: {}", err))
+    }
+
+    /// the most recent standup posted to a channel
+    pub fn latest(&self, channel: &str) -> Result<Option<Entry>, String> {
+        self.query(channel, None, Some(1))
+            .map(|mut entries| entries.pop())
+    }
+
+    /// standups posted to a channel since a given time, oldest first
+    pub fn since(&self, channel: &str, since: DateTime<Utc>) -> Result<Vec<Entry>, String> {
+        self.query(channel, Some(since), None).map(|mut entries| {
+            entries.reverse();
+            entries
+        })
+    }
+
+    /// entries newest first
+    fn query(
+        &self,
+        channel: &str,
+        since: Option<DateTime<Utc>>,
+        limit: Option<i64>,
+    ) -> Result<Vec<Entry>, String> {
+        let mut values = vec![(":channel", channel.to_owned())];
+        let mut condition = String::from("channel = :channel");
+        if let Some(since) = since {
+            condition.push_str(" AND posted_at >= :since");
+            values.push((":since", since.to_rfc3339()));
+        }
+        let output = self
+            .client
+            .query(QueryInput {
+                table_name: self.table.clone(),
+                key_condition_expression: Some(condition),
+                expression_attribute_values: Some(attributes(values)),
+                scan_index_forward: Some(false),
+                limit,
+                ..Default::default()
+            })
+            .sync()
+            .map_err(|err| format!("failed to query standup history: {}", err))?;
+        Ok(output
+            .items
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|item| {
+                item.get("document")
+                    .and_then(|document| document.s.as_ref())
+                    .and_then(|document| serde_json::from_str(document).ok())
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,90 @@
+//! Shared http clients
+//!
+//! Clients pool connections and reuse TLS sessions, which only helps when
+//! they're reused. Clients are built once per timeout and proxy and kept in
+//! memory, so every upstream, and warm lambdas, share them.
+
+// Std lib
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+// Third party
+use reqwest::header::{HeaderMap, HeaderValue, USER_AGENT};
+use reqwest::{Client, Proxy};
+
+// Ours
+use log;
+
+/// timeout of clients made without a config, matching `http_timeout_secs`'s default
+pub const DEFAULT_TIMEOUT_SECS: u64 = 10;
+
+lazy_static! {
+    /// clients by timeout and proxy
+    static ref CLIENTS: Mutex<HashMap<(u64, Option<String>), Client>> = Mutex::new(HashMap::new());
+}
+
+/// the shared client which gives up after `timeout_secs`, sending requests through `proxy`
+pub fn client(timeout_secs: u64, proxy: Option<&str>) -> Client {
+    let key = (timeout_secs, proxy.map(str::to_owned));
+    if let Some(client) = CLIENTS
+        .lock()
+        .ok()
+        .and_then(|clients| clients.get(&key).cloned())
+    {
+        return client;
+    }
+    let client = build(timeout_secs, proxy);
+    if let Ok(mut clients) = CLIENTS.lock() {
+        clients.insert(key, client.clone());
+    }
+    client
+}
+
+/// the shared client for requests made without a config
+pub fn default_client() -> Client {
+    client(DEFAULT_TIMEOUT_SECS, None)
+}
+
+fn build(timeout_secs: u64, proxy: Option<&str>) -> Client {
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        USER_AGENT,
+        HeaderValue::from_static(concat!("slack-standup/", env!("CARGO_PKG_VERSION"))),
+    );
+    let builder = Client::builder()
+        .timeout(Duration::from_secs(timeout_secs))
+        .default_headers(headers);
+    let builder = match proxy.map(Proxy::all) {
+        Some(Ok(proxy)) => builder.proxy(proxy),
+        Some(Err(err)) => {
+            log::warn(
+                "skipping invalid http proxy",
+                json!({ "error": err.to_string() }),
+            );
+            builder
+        }
+        None => builder,
+    };
+    builder.build().unwrap_or_else(|err| {
+        log::warn(
+            "failed to build http client",
+            json!({ "error": err.to_string() }),
+        );
+        Client::new()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clients_are_shared() {
+        let _ = client(7, None);
+        let _ = client(7, None);
+        let _ = client(7, Some("http://proxy.example.com:3128"));
+        let clients = CLIENTS.lock().unwrap();
+        assert_eq!(clients.keys().filter(|(secs, _)| *secs == 7).count(), 2);
+    }
+}
@@ -0,0 +1,360 @@
+//! Jira authentication
+//!
+//! Jira is reached with basic auth, using a password or, as Jira Cloud
+//! requires, an api token, or with 3LO OAuth. OAuth requests go through
+//! Atlassian's api gateway with an access token. Atlassian rotates refresh
+//! tokens on every refresh, so the latest is kept in `jira_token_table`, a
+//! refresh fails if it can't be, and access tokens are cached until they're
+//! about to expire.
+//!
+//! see https://developer.atlassian.com/cloud/jira/platform/oauth-2-3lo-apps/
+
+// Std lib
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+// Third party
+use chrono::{DateTime, Duration, Utc};
+use reqwest::header::AUTHORIZATION;
+use reqwest::{Client, RequestBuilder};
+use rusoto_core::Region;
+use rusoto_dynamodb::{DynamoDb, DynamoDbClient, GetItemInput, PutItemInput};
+use serde_json::Value;
+
+// Ours
+use breaker::{Breaker, Tripped};
+use cache::Cache;
+use config::{Config, JiraAuth};
+use dynamodb::attributes;
+use error::FetchError;
+use log;
+use retry::{self, Retry};
+use trace;
+
+const TOKEN_URL: &str = "https://auth.atlassian.com/oauth/token";
+
+/// sites of OAuth apps, by cloud id
+const API_GATEWAY: &str = "https://api.atlassian.com/ex/jira";
+
+/// how long before they expire access tokens are refreshed
+const EXPIRY_MARGIN_SECS: i64 = 60;
+
+lazy_static! {
+    /// access tokens and when they expire, by client id
+    static ref ACCESS_TOKENS: Mutex<HashMap<String, (String, DateTime<Utc>)>> =
+        Mutex::new(HashMap::new());
+}
+
+/// How requests are authorized
+#[derive(Debug, Clone, PartialEq)]
+pub enum Authorization {
+    Basic { user: String, password: String },
+    Bearer(String),
+}
+
+/// A jira site's api
+#[derive(Debug, Clone)]
+pub struct Site {
+    url: String,
+    authorization: Authorization,
+    client: Client,
+    cache: Cache,
+    breaker: Breaker,
+}
+
+#[derive(Deserialize, Debug)]
+struct Grant {
+    access_token: String,
+    /// replaces the refresh token used to get this grant
+    refresh_token: Option<String>,
+    expires_in: i64,
+}
+
+impl Site {
+    pub fn new(url: String, authorization: Authorization, client: Client) -> Self {
+        Site {
+            url,
+            authorization,
+            client,
+            cache: Cache::default(),
+            breaker: Breaker::default(),
+        }
+    }
+
+    /// reuses responses to cached requests
+    pub fn with_cache(self, cache: Cache) -> Self {
+        Site { cache, ..self }
+    }
+
+    /// skips requests while the site keeps failing
+    pub fn with_breaker(self, breaker: Breaker) -> Self {
+        Site { breaker, ..self }
+    }
+
+    /// the configured site, authorized as `jira_auth` says
+    pub fn configured(config: &Config) -> Result<Site, FetchError> {
+        let client = config.http_client();
+        let site = match config.jira_auth {
+            JiraAuth::Password => Site::new(
+                config.jira_host.clone(),
+                Authorization::Basic {
+                    user: config.jira_user.clone(),
+                    password: config.jira_password.clone(),
+                },
+                client,
+            ),
+            JiraAuth::ApiToken => {
+                let token = config
+                    .jira_api_token
+                    .clone()
+                    .ok_or_else(|| FetchError::new("Jira", "no jira_api_token configured"))?;
+                Site::new(
+                    config.jira_host.clone(),
+                    Authorization::Basic {
+                        user: config.jira_user.clone(),
+                        password: token,
+                    },
+                    client,
+                )
+            }
+            JiraAuth::OAuth => {
+                let cloud_id = config
+                    .jira_cloud_id
+                    .clone()
+                    .ok_or_else(|| FetchError::new("Jira", "no jira_cloud_id configured"))?;
+                let token = access_token(config)?;
+                Site::new(
+                    format!("{}/{}", API_GATEWAY, cloud_id),
+                    Authorization::Bearer(token),
+                    client,
+                )
+            }
+        };
+        Ok(site
+            .with_cache(Cache::from(config))
+            .with_breaker(Breaker::from(config)))
+    }
+
+    /// an authorized get request for a path of the api, i.e. `/rest/api/2/search`
+    pub fn get(&self, path: &str) -> RequestBuilder {
+        let request = self
+            .client
+            .get(&format!("{}{}", self.url.trim_end_matches('/'), path));
+        match self.authorization {
+            Authorization::Basic {
+                ref user,
+                ref password,
+            } => request.basic_auth(user.clone(), Some(password.clone())),
+            Authorization::Bearer(ref token) => {
+                request.header(AUTHORIZATION, format!("Bearer {}", token))
+            }
+        }
+    }
+
+    /// the json response to a get request for a path of the api, reused
+    /// while it's cached
+    pub fn get_json(
+        &self,
+        path: &str,
+        query: &[(&str, &str)],
+        retry: Retry,
+    ) -> Result<Value, FetchError> {
+        let request = format!(
+            "GET {}{} {:?} {:?}",
+            self.url, path, query, self.authorization
+        );
+        self.cache.get(&request, || {
+            self.breaker
+                .run(
+                    &self.url,
+                    || {
+                        retry.run(
+                            || {
+                                trace::request("Jira", path, || {
+                                    self.get(path)
+                                        .query(query)
+                                        .send()
+                                        .and_then(|response| response.error_for_status())
+                                        .and_then(|mut response| response.json::<Value>())
+                                })
+                            },
+                            retry::http,
+                        )
+                    },
+                    retry::http,
+                )
+                .map_err(|tripped| match tripped {
+                    Tripped::Open(secs) => FetchError::unavailable("Jira", secs),
+                    Tripped::Failed(err) => FetchError::jira(err),
+                })
+        })
+    }
+}
+
+/// an unexpired access token, refreshing it if need be
+fn access_token(config: &Config) -> Result<String, FetchError> {
+    let (client_id, client_secret) = match (
+        config.jira_oauth_client_id.clone(),
+        config.jira_oauth_client_secret.clone(),
+    ) {
+        (Some(client_id), Some(client_secret)) => (client_id, client_secret),
+        _ => {
+            return Err(FetchError::new(
+                "Jira",
+                "no jira_oauth_client_id or jira_oauth_client_secret configured",
+            ))
+        }
+    };
+    let cached = ACCESS_TOKENS
+        .lock()
+        .ok()
+        .and_then(|tokens| tokens.get(&client_id).cloned());
+    if let Some((token, expires)) = cached {
+        if Utc::now() < expires {
+            return Ok(token);
+        }
+    }
+
+    let tokens = config.jira_token_table.clone().map(RefreshTokens::new);
+    let stored = match tokens {
+        Some(ref tokens) => tokens.get(&client_id).unwrap_or_else(|err| {
+            log::error("failed to load jira refresh token", json!({ "error": err }));
+            None
+        }),
+        None => None,
+    };
+    let refresh_token = stored
+        .or_else(|| config.jira_oauth_refresh_token.clone())
+        .ok_or_else(|| FetchError::new("Jira", "no jira_oauth_refresh_token configured"))?;
+    let client = config.http_client();
+    let retry = Retry::from(config);
+    // refresh tokens are spent once jira responds, so only requests which
+    // never got a response are tried again
+    let grant = retry
+        .run(
+            || {
+                client
+                    .post(TOKEN_URL)
+                    .json(&json!({
+                        "grant_type": "refresh_token",
+                        "client_id": client_id,
+                        "client_secret": client_secret,
+                        "refresh_token": refresh_token,
+                    }))
+                    .send()
+                    .and_then(|response| response.error_for_status())
+                    .and_then(|mut response| response.json::<Grant>())
+            },
+            |err| err.status().is_none() && retry::http(err),
+        )
+        .map_err(FetchError::jira)?;
+
+    // the refresh token we had is spent, so its replacement must be kept
+    // before the access token is used
+    if let (Some(tokens), Some(rotated)) = (tokens, grant.refresh_token.as_ref()) {
+        retry
+            .run(|| tokens.save(&client_id, rotated), |_| true)
+            .map_err(|err| {
+                log::error("failed to save jira refresh token", json!({ "error": err }));
+                FetchError::new("Jira", "failed to save the rotated refresh token")
+            })?;
+    }
+    let expires = Utc::now() + Duration::seconds(grant.expires_in - EXPIRY_MARGIN_SECS);
+    if let Ok(mut cached) = ACCESS_TOKENS.lock() {
+        cached.insert(client_id, (grant.access_token.clone(), expires));
+    }
+    Ok(grant.access_token)
+}
+
+/// The latest refresh token of each OAuth app, persisted to DynamoDB
+struct RefreshTokens {
+    table: String,
+    client: DynamoDbClient,
+}
+
+impl RefreshTokens {
+    fn new(table: String) -> Self {
+        RefreshTokens {
+            table,
+            client: DynamoDbClient::new(Region::default()),
+        }
+    }
+
+    fn get(&self, client_id: &str) -> Result<Option<String>, String> {
+        let output = self
+            .client
+            .get_item(GetItemInput {
+                table_name: self.table.clone(),
+                key: attributes(vec![("client_id", client_id.to_owned())]),
+                ..Default::default()
+            })
+            .sync()
+            .map_err(|err| format!("failed to load refresh token: {}", err))?;
+        Ok(output
+            .item
+            .as_ref()
+            .and_then(|item| item.get("refresh_token"))
+            .and_then(|token| token.s.clone()))
+    }
+
+    fn save(&self, client_id: &str, refresh_token: &str) -> Result<(), String> {
+        self.client
+            .put_item(PutItemInput {
+                table_name: self.table.clone(),
+                item: attributes(vec![
+                    ("client_id", client_id.to_owned()),
+                    ("refresh_token", refresh_token.to_owned()),
+                ]),
+                ..Default::default()
+            })
+            .sync()
+            .map(|_| ())
+            .map_err(|err| format!("failed to save refresh token: {}", err))
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use mockito::{self, mock};
+
+    /// a single attempt, so failures fail tests right away
+    pub const RETRY: Retry = Retry {
+        attempts: 1,
+        backoff_ms: 0,
+    };
+
+    /// the mock server's jira, with an OAuth access token
+    pub fn site() -> Site {
+        Site::new(
+            mockito::server_url(),
+            Authorization::Bearer("access".into()),
+            Client::new(),
+        )
+    }
+
+    #[test]
+    fn requests_are_authorized() {
+        let basic = mock("GET", "/rest/api/2/myself")
+            .match_header("authorization", "Basic ZGFuYTp0b2tlbg==")
+            .create();
+        Site::new(
+            mockito::server_url(),
+            Authorization::Basic {
+                user: "dana".into(),
+                password: "token".into(),
+            },
+            Client::new(),
+        )
+        .get("/rest/api/2/myself")
+        .send()
+        .unwrap();
+        basic.assert();
+
+        let bearer = mock("GET", "/rest/api/2/myself")
+            .match_header("authorization", "Bearer access")
+            .create();
+        site().get("/rest/api/2/myself").send().unwrap();
+        bearer.assert();
+    }
+}
@@ -0,0 +1,145 @@
+//! JQL query building
+//!
+//! Projects, statuses, labels and versions come from config and commands, so
+//! they're always quoted and escaped rather than interpolated, and a name
+//! with quotes or JQL keywords in it can't break or change a query. Field
+//! names are ours and left as is.
+//!
+//! see https://support.atlassian.com/jira-software-cloud/docs/use-advanced-search-with-jira-query-language-jql/
+
+// Std lib
+use std::fmt;
+
+/// Clauses which must all hold, optionally ordered
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Query {
+    clauses: Vec<String>,
+    order_by: Vec<&'static str>,
+}
+
+impl Query {
+    pub fn new() -> Self {
+        Query::default()
+    }
+
+    /// `field in ("a", "b")`
+    pub fn is_in(mut self, field: &str, values: &[String]) -> Self {
+        self.clauses
+            .push(format!("{} in ({})", field, list(values)));
+        self
+    }
+
+    /// `field = "value"`
+    pub fn eq(mut self, field: &str, value: &str) -> Self {
+        self.clauses.push(format!("{} = {}", field, quote(value)));
+        self
+    }
+
+    /// `field != "value"`
+    pub fn not_eq(mut self, field: &str, value: &str) -> Self {
+        self.clauses.push(format!("{} != {}", field, quote(value)));
+        self
+    }
+
+    /// `field >= "value"`
+    pub fn at_least(mut self, field: &str, value: &str) -> Self {
+        self.clauses.push(format!("{} >= {}", field, quote(value)));
+        self
+    }
+
+    /// `filter = 10423`, the issues a saved filter finds
+    pub fn filter(mut self, id: u64) -> Self {
+        self.clauses.push(format!("filter = {}", id));
+        self
+    }
+
+    /// `field is EMPTY`
+    pub fn is_empty(mut self, field: &str) -> Self {
+        self.clauses.push(format!("{} is EMPTY", field));
+        self
+    }
+
+    /// a clause holding when any of the alternatives' clauses all do
+    pub fn any(mut self, alternatives: Vec<Query>) -> Self {
+        let alternatives = alternatives
+            .into_iter()
+            .filter(|alternative| !alternative.clauses.is_empty())
+            .map(|alternative| format!("({})", alternative.clauses.join(" AND ")))
+            .collect::<Vec<_>>();
+        if !alternatives.is_empty() {
+            self.clauses
+                .push(format!("({})", alternatives.join(" OR ")));
+        }
+        self
+    }
+
+    pub fn order_by(mut self, field: &'static str) -> Self {
+        self.order_by.push(field);
+        self
+    }
+}
+
+impl fmt::Display for Query {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.clauses.join(" AND "))?;
+        if !self.order_by.is_empty() {
+            write!(f, " order by {}", self.order_by.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// a jql string literal
+pub fn quote(value: &str) -> String {
+    format!(r#""{}""#, value.replace('\\', r"\\").replace('"', r#"\""#))
+}
+
+/// a comma separated list of jql string literals
+pub fn list(values: &[String]) -> String {
+    values
+        .iter()
+        .map(|value| quote(value))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_are_quoted_and_escaped() {
+        assert_eq!(quote("Core Services"), r#""Core Services""#);
+        assert_eq!(quote(r#"The "A" Team"#), r#""The \"A\" Team""#);
+        assert_eq!(quote(r"back\slash"), r#""back\\slash""#);
+        assert_eq!(
+            quote(r#"x") OR project is not EMPTY OR (status = "x"#),
+            r#""x\") OR project is not EMPTY OR (status = \"x""#
+        );
+    }
+
+    #[test]
+    fn queries_join_clauses() {
+        let query = Query::new()
+            .is_in(
+                "project",
+                &["CORE".into(), "Dana's \"side\" project".into()],
+            )
+            .is_empty("resolution")
+            .not_eq("status", "Closed")
+            .filter(10423)
+            .any(vec![
+                Query::new().eq("Flagged", "Impediment"),
+                Query::new()
+                    .is_in("labels", &["blocked".into()])
+                    .is_in("status", &["Blocked".into()]),
+                Query::new(),
+            ])
+            .order_by("status")
+            .order_by("key");
+        assert_eq!(
+            query.to_string(),
+            r#"project in ("CORE", "Dana's \"side\" project") AND resolution is EMPTY AND status != "Closed" AND filter = 10423 AND ((Flagged = "Impediment") OR (labels in ("blocked") AND status in ("Blocked"))) order by status, key"#
+        );
+    }
+}
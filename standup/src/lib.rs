@@ -0,0 +1,683 @@
+//! Standup facilitation
+//!
+//! Gathers what shipped, what's in flight and how production is doing from
+//! the configured sections, and renders it as a slack message for a `Sink`.
+//! Runtimes, i.e. the lambda handler, parse requests and hand off to `run`.
+
+extern crate chrono;
+extern crate chrono_tz;
+extern crate envy;
+#[macro_use]
+extern crate failure;
+extern crate goji;
+extern crate handlebars;
+extern crate hex;
+extern crate hmac;
+#[cfg(test)]
+extern crate mockito;
+#[macro_use]
+extern crate lazy_static;
+extern crate plotters;
+extern crate reqwest;
+extern crate rusoto_core;
+extern crate rusoto_dynamodb;
+extern crate rusoto_s3;
+extern crate rusoto_secretsmanager;
+extern crate rusoto_ses;
+extern crate rusoto_ssm;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate serde_json;
+extern crate sha2;
+
+pub mod admin;
+mod agile;
+mod breaker;
+mod cache;
+mod calendar;
+mod chart;
+pub mod checkin;
+mod circleci;
+pub mod command;
+pub mod config;
+pub mod deliveries;
+mod deploys;
+mod dev_status;
+mod digest;
+mod dynamodb;
+mod error;
+mod escalation;
+mod export;
+pub mod facilitator;
+mod github;
+mod google_calendar;
+pub mod health;
+mod history;
+mod http;
+mod jira_auth;
+mod jql;
+pub mod limits;
+pub mod log;
+pub mod metrics;
+mod notion;
+mod opsgenie;
+mod pagerduty;
+mod release_notes;
+pub mod render;
+mod retry;
+mod secrets;
+mod section;
+pub mod sink;
+pub mod slack;
+mod stats;
+mod templates;
+pub mod tenants;
+pub mod trace;
+mod users;
+mod workflow;
+
+// Std lib
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{mpsc, Arc};
+use std::time::{Duration as StdDuration, Instant};
+
+// Third party
+use chrono::{Duration, Utc};
+use serde_json::Value;
+
+// Ours
+pub use command::Command;
+use command::Detail;
+pub use config::Config;
+use error::FetchError;
+use facilitator::Rotations;
+use history::{Entry, History};
+use metrics::Metric;
+use pagerduty::Incident;
+use release_notes::ReleaseNotes;
+use render::{escape, Block, Element, Message};
+use section::{Context, Output};
+pub use sink::Sink;
+use sink::{Chat, Confluence};
+use users::Directory;
+
+/// How a standup was asked for, which decides what's done with it beyond
+/// delivering it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Origin {
+    /// the daily schedule, whose standups pick the day's facilitator and are
+    /// published, i.e. to notion
+    Scheduled,
+    /// a slash command or button click
+    Requested,
+    /// tried out locally, which changes nothing beyond delivering it, i.e.
+    /// history
+    DryRun,
+}
+
+/// fetches, renders and delivers a standup to a sink, recording it in the
+/// history of `channel` when history is configured, unless it's a dry run
+pub fn run(
+    mut config: Config,
+    command: Command,
+    channel: String,
+    origin: Origin,
+    sink: &dyn Sink,
+) -> Result<(), String> {
+    log::info("fetching debrief info", json!({}));
+    if command.fresh {
+        config.cache_ttl_secs = None;
+    }
+    let sections = section::registry(&config)
+        .into_iter()
+        .filter(|(name, _)| command.includes(name))
+        .collect::<Vec<_>>();
+    let history = config.history_table.clone().map(History::new);
+    let mut context = Context::new(config, command.lookback_days);
+    context.grouping = command.grouping;
+    if context.config.slack_interactive {
+        context.details = command.details.clone();
+    }
+    if let Some(ref history) = history {
+        context.previous = history.latest(&channel).unwrap_or_else(|err| {
+            log::error("failed to load previous standup", json!({ "error": err }));
+            None
+        });
+    }
+    let deadline = Instant::now() + StdDuration::from_secs(context.config.time_budget_secs);
+    let context = Arc::new(context);
+
+    let (sender, receiver) = mpsc::channel();
+    let reports = sections
+        .into_iter()
+        .enumerate()
+        .map(|(index, (name, section))| {
+            let context = context.clone();
+            let sender = sender.clone();
+            let title = context.templates.title(&name, section.title());
+            let section_name = name.clone();
+            trace::spawn(move || {
+                let started = Instant::now();
+                let output = trace::span(
+                    &section_name,
+                    || section.report(&context),
+                    |output, segment| {
+                        if output.error.is_some() {
+                            segment.insert("error".into(), json!(true));
+                        }
+                    },
+                );
+                let elapsed = started.elapsed();
+                let duration_ms = elapsed.as_secs() * 1000 + u64::from(elapsed.subsec_millis());
+                match output.error {
+                    Some(ref err) => log::warn(
+                        "section failed",
+                        json!({
+                            "section": section_name,
+                            "duration_ms": duration_ms,
+                            "upstream": err.upstream,
+                            "upstream_status": err.reason,
+                        }),
+                    ),
+                    None => log::info(
+                        "section reported",
+                        json!({ "section": section_name, "duration_ms": duration_ms }),
+                    ),
+                }
+                let mut measured = vec![Metric::milliseconds("Latency", duration_ms)];
+                measured.extend(output.metrics.iter().cloned());
+                metrics::emit(&[("Section", section_name.as_str())], &measured);
+                if let Some(ref err) = output.error {
+                    metrics::emit(
+                        &[("Upstream", err.upstream)],
+                        &[Metric::count("Failures", 1)],
+                    );
+                }
+                sender.send((index, output))
+            });
+            (name, title)
+        })
+        .collect::<Vec<_>>();
+    drop(sender);
+
+    // collect what we can within the time budget
+    let mut outputs = HashMap::new();
+    while outputs.len() < reports.len() {
+        let now = Instant::now();
+        if now >= deadline {
+            break;
+        }
+        match receiver.recv_timeout(deadline - now) {
+            Ok((index, output)) => {
+                outputs.insert(index, output);
+            }
+            // either out of time or the remaining sections panicked
+            Err(_) => break,
+        }
+    }
+    let out_of_time = Instant::now() >= deadline;
+
+    let mut rendered = Vec::new();
+    let mut records = BTreeMap::new();
+    let mut errors = BTreeMap::new();
+    let mut measured = BTreeMap::new();
+    let mut timed_out = Vec::new();
+    for (index, (name, title)) in reports.into_iter().enumerate() {
+        let output = outputs.remove(&index).unwrap_or_else(|| {
+            let reason = if out_of_time {
+                timed_out.push(title.clone());
+                "timed out"
+            } else {
+                "panicked"
+            };
+            log::error(
+                "section didn't report",
+                json!({ "section": name, "reason": reason }),
+            );
+            let err = FetchError::new("section", reason);
+            Output {
+                blocks: vec![section::warning(&err)],
+                record: Value::Null,
+                summary: Vec::new(),
+                metrics: Vec::new(),
+                error: Some(err),
+            }
+        });
+        let mut blocks = vec![Block::header(title.as_str())];
+        blocks.extend(output.blocks);
+        records.insert(name.clone(), output.record);
+        if let Some(ref err) = output.error {
+            errors.insert(name.clone(), err.to_string());
+        }
+        for metric in &output.metrics {
+            measured.insert(metric.name, metric.value);
+        }
+        rendered.push(Rendered {
+            name,
+            blocks,
+            summary: output.summary,
+        });
+    }
+    let brief = command.brief || context.config.slack_brief;
+    let mut message = if brief {
+        compose_brief(&rendered, records.get("weather"))
+    } else {
+        compose(&rendered, |_| true)
+    };
+    let rotations =
+        Rotations::configured(&context.config).filter(|_| !brief && origin == Origin::Scheduled);
+    if let Some(rotations) = rotations {
+        match facilitator::today(
+            &context.config,
+            &rotations,
+            &channel,
+            context.now.date().naive_local(),
+        ) {
+            Ok(Some(facilitator)) => {
+                // under the summary, when there is one
+                let at = if rendered.iter().any(|section| !section.summary.is_empty()) {
+                    1
+                } else {
+                    0
+                };
+                message.blocks.insert(
+                    at,
+                    Block::section(format!("🎤 Today's facilitator: <@{}>", facilitator)),
+                );
+            }
+            Ok(None) => (),
+            Err(err) => log::error("failed to pick a facilitator", json!({ "error": err })),
+        }
+    }
+    if !timed_out.is_empty() {
+        message.blocks.push(Block::context(format!(
+            "⏱ Posted without {} which took longer than {}s",
+            timed_out.join(", "),
+            context.config.time_budget_secs
+        )));
+    }
+    if command.preview {
+        message.blocks.push(Block::Actions {
+            elements: vec![Element::button(
+                "📣 Post to channel",
+                "post",
+                command.posting().text(),
+            )],
+        });
+    }
+    if context.config.slack_interactive && !brief {
+        message.blocks.push(actions(&command));
+    }
+
+    // send it, whole where replies wouldn't land in a thread, i.e. an
+    // interaction's `response_url`
+    let sent = if context.config.slack_threaded && !brief && sink.threads() {
+        deliver_threaded(&message, sink)
+    } else {
+        sink.send(&message)
+    };
+    let delivered = match sent {
+        Ok(()) => true,
+        Err(err) => {
+            log::error("failed to deliver standup", json!({ "error": err }));
+            false
+        }
+    };
+    // only the day's standup, once it's out, is published beyond its sink
+    let published = origin == Origin::Scheduled && !brief && delivered;
+    // previews aren't posted until they're approved
+    if command.preview {
+        return Ok(log::info("previewed debrief", json!({})));
+    }
+    if published {
+        cross_post(&context.config, &rendered);
+    }
+    // escalations go to their own channel, so brief standups escalate too
+    if let Some(blocked) = records.get("blocked") {
+        if origin == Origin::Scheduled && delivered {
+            escalation::escalate_blocked(&context.config, &channel, blocked);
+        }
+    }
+    if published {
+        let report = export::Report {
+            channel: &channel,
+            records: &records,
+            errors: &errors,
+            metrics: &measured,
+            summary: rendered
+                .iter()
+                .flat_map(|section| section.summary.iter().cloned())
+                .collect(),
+        };
+        export::publish(&report, &context);
+        notion::publish(&report, &message, &context);
+    }
+
+    // remember it
+    if let Some(history) = history.filter(|_| origin != Origin::DryRun) {
+        let link = sink.posted();
+        let entry = Entry {
+            channel,
+            posted_at: Utc::now(),
+            sections: records,
+            message: json!(message),
+            ts: link.as_ref().map(|link| link.ts.clone()),
+            permalink: link.map(|link| link.permalink),
+            command: Some(command.standup().text()),
+        };
+        if let Err(err) = history.save(&entry) {
+            log::error("failed to save standup", json!({ "error": err }));
+        }
+    }
+
+    Ok(log::info("debriefed", json!({})))
+}
+
+/// delivers a digest of the past week of standups posted to a channel
+pub fn weekly(config: Config, channel: String, sink: &dyn Sink) -> Result<(), String> {
+    let history = match config.history_table.clone() {
+        Some(table) => History::new(table),
+        None => {
+            sink.send(&Message {
+                text: "Weekly digests need standup history, which isn't configured".into(),
+                ..Default::default()
+            })?;
+            return Err("no history_table configured".into());
+        }
+    };
+    let entries = history.since(&channel, Utc::now() - Duration::days(7))?;
+    let users = Directory::new(
+        config.slack_bot_token.clone(),
+        &config.slack_user_overrides,
+        config.http_client(),
+    );
+    sink.send(&digest::digest(
+        &entries,
+        config.timezone,
+        &config.jira_shipped_resolutions,
+        &users,
+    ))?;
+    // charts are extras, so failing to draw or attach them doesn't fail the
+    // digest. only channels posted to with a bot token take files, webhooks skip them
+    for series in digest::trends(&entries, config.timezone, &config.jira_shipped_resolutions) {
+        if let Err(err) = chart::line(&series).and_then(|chart| sink.upload(&chart)) {
+            log::warn(
+                "failed to chart digest",
+                json!({ "chart": series.title, "error": err }),
+            );
+        }
+    }
+    Ok(log::info(
+        "digested standups",
+        json!({ "standups": entries.len() }),
+    ))
+}
+
+/// delivers per-person participation over the past month of standups posted to a channel
+/// and of check-ins
+pub fn stats(config: Config, channel: String, sink: &dyn Sink) -> Result<(), String> {
+    let history = match config.history_table.clone() {
+        Some(table) => History::new(table),
+        None => {
+            sink.send(&Message {
+                text: "Participation stats need standup history, which isn't configured".into(),
+                ..Default::default()
+            })?;
+            return Err("no history_table configured".into());
+        }
+    };
+    let since = Utc::now() - Duration::days(stats::DAYS);
+    let checkins = history.since(checkin::HISTORY_CHANNEL, since)?;
+    let standups = history.since(&channel, since)?;
+    let users = Directory::new(
+        config.slack_bot_token.clone(),
+        &config.slack_user_overrides,
+        config.http_client(),
+    );
+    sink.send(&stats::stats(
+        &checkins,
+        &standups,
+        config.timezone,
+        &config.jira_shipped_resolutions,
+        &users,
+    ))?;
+    Ok(log::info(
+        "summarized participation",
+        json!({ "checkins": checkins.len(), "standups": standups.len() }),
+    ))
+}
+
+/// posts release notes for a jira fix version, followed by a markdown copy in thread
+///
+/// The notes are also published to Confluence when `confluence_release_notes` is set.
+pub fn release_notes(config: Config, version: &str, sink: &dyn Sink) -> Result<(), String> {
+    let notes = match ReleaseNotes::fetch(&config, version) {
+        Ok(notes) => notes,
+        Err(err) => {
+            sink.send(&Message {
+                text: format!("Release notes for {} aren't available", version),
+                blocks: vec![section::warning(&err)],
+            })?;
+            return Err(err.to_string());
+        }
+    };
+    sink.thread(&notes.message(), &[notes.markdown_message()])?;
+    if config.confluence_release_notes {
+        Confluence::configured(&config)
+            .ok_or_else(|| "release notes need confluence, which isn't configured".to_owned())?
+            .send(&notes.message())?;
+    }
+    Ok(log::info(
+        "released notes",
+        json!({
+            "version": version,
+            "issues": notes.notes.values().map(Vec::len).sum::<usize>(),
+        }),
+    ))
+}
+
+/// A section's contribution to the standup message
+struct Rendered {
+    name: String,
+    /// the section's heading and content
+    blocks: Vec<Block>,
+    summary: Vec<String>,
+}
+
+/// a standup message of the sections `includes` accepts, led by their summary
+fn compose<F>(sections: &[Rendered], includes: F) -> Message
+where
+    F: Fn(&str) -> bool,
+{
+    let mut blocks = Vec::new();
+    let mut summary = Vec::new();
+    for section in sections.iter().filter(|section| includes(&section.name)) {
+        if !blocks.is_empty() {
+            blocks.push(Block::Divider);
+        }
+        blocks.extend(section.blocks.iter().cloned());
+        summary.extend(section.summary.iter().cloned());
+    }
+    let text = if summary.is_empty() {
+        "Standup debrief".to_owned()
+    } else {
+        let text = summary.join(" · ");
+        blocks.insert(0, Block::section(text.as_str()));
+        text
+    };
+    Message { text, blocks }
+}
+
+/// a standup's summary and open P1 incidents, from the weather section's record
+fn compose_brief(sections: &[Rendered], weather: Option<&Value>) -> Message {
+    let summary = sections
+        .iter()
+        .flat_map(|section| section.summary.iter().cloned())
+        .collect::<Vec<_>>();
+    let text = if summary.is_empty() {
+        "Standup debrief".to_owned()
+    } else {
+        summary.join(" · ")
+    };
+    let mut blocks = vec![Block::section(if summary.is_empty() {
+        "✅ Nothing to report"
+    } else {
+        text.as_str()
+    })];
+    let incidents = weather
+        .and_then(|weather| weather.get("open"))
+        .cloned()
+        .and_then(|open| serde_json::from_value::<Vec<Incident>>(open).ok())
+        .unwrap_or_default();
+    blocks.extend(
+        incidents
+            .iter()
+            .filter(|incident| incident.is_p1())
+            .map(|incident| {
+                Block::section(format!(
+                    "🔥 <{}|#{}> {}",
+                    incident.html_url,
+                    incident.incident_number,
+                    escape(&incident.title)
+                ))
+            }),
+    );
+    Message { text, blocks }
+}
+
+/// posts the configured sections of a standup to each cross post channel
+fn cross_post(config: &Config, sections: &[Rendered]) {
+    let targets = config.cross_posts();
+    if targets.is_empty() {
+        return;
+    }
+    let token = match config.slack_bot_token.clone() {
+        Some(token) => token,
+        None => {
+            return log::warn(
+                "skipping cross posts: no slack_bot_token configured",
+                json!({}),
+            )
+        }
+    };
+    for (channel, filter) in targets {
+        let message = compose(sections, |name| {
+            filter.is_empty() || filter.iter().any(|section| section == name)
+        });
+        if message.blocks.is_empty() {
+            continue;
+        }
+        let sink = Chat::new(token.clone(), channel.clone(), config.http_client());
+        if let Err(err) = sink.send(&message) {
+            log::error(
+                "failed to cross post standup",
+                json!({ "cross_post_channel": channel, "error": err }),
+            );
+        }
+    }
+}
+
+/// buttons which re-run a command, replacing the standup they're attached to
+fn actions(command: &Command) -> Block {
+    let mut buttons = vec![Element::button("🔄 Refresh", "refresh", command.text())];
+    if command.includes("jira") && !command.shows(Detail::Closed) {
+        buttons.push(Element::button(
+            "Show closed issues",
+            "expand_closed",
+            command.showing(Detail::Closed).text(),
+        ));
+    }
+    if command.includes("weather") && !command.shows(Detail::Resolved) {
+        buttons.push(Element::button(
+            "Show resolved incidents",
+            "expand_resolved",
+            command.showing(Detail::Resolved).text(),
+        ));
+    }
+    Block::Actions { elements: buttons }
+}
+
+/// sends the summary of a message, with each of its sections as a reply in its thread
+fn deliver_threaded(message: &Message, sink: &dyn Sink) -> Result<(), String> {
+    let (parent, replies) = threaded(message);
+    sink.thread(&parent, &replies)
+}
+
+/// a message split into a parent and a reply per section. what leads the
+/// first section's header, i.e. the summary and today's facilitator, stays
+/// in the parent
+fn threaded(message: &Message) -> (Message, Vec<Message>) {
+    let is_header = |block: &&Block| match **block {
+        Block::Header { .. } => true,
+        _ => false,
+    };
+    let mut lead = message
+        .blocks
+        .iter()
+        .take_while(|block| !is_header(block))
+        .cloned()
+        .collect::<Vec<_>>();
+    if lead.is_empty() {
+        lead.push(Block::section(message.text.as_str()));
+    }
+    lead.push(Block::context("🧵 Details in the thread"));
+    let parent = Message {
+        text: message.text.clone(),
+        blocks: lead,
+    };
+    let details = message
+        .blocks
+        .iter()
+        .skip_while(|block| !is_header(block))
+        .cloned()
+        .collect::<Vec<_>>();
+    let replies = details
+        .split(|block| *block == Block::Divider)
+        .map(|blocks| Message {
+            text: message.text.clone(),
+            blocks: blocks.to_vec(),
+        })
+        .collect::<Vec<_>>();
+    (parent, replies)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn threaded_parents_keep_what_leads_the_sections() {
+        let message = Message {
+            text: "3 shipped".into(),
+            blocks: vec![
+                Block::section("3 shipped"),
+                Block::section("🎤 Today's facilitator: <@U1>"),
+                Block::header("📋 Jira"),
+                Block::section("CORE-1"),
+                Block::Divider,
+                Block::header("⛅ Weather"),
+            ],
+        };
+        let (parent, replies) = threaded(&message);
+        assert_eq!(
+            parent.blocks,
+            vec![
+                Block::section("3 shipped"),
+                Block::section("🎤 Today's facilitator: <@U1>"),
+                Block::context("🧵 Details in the thread"),
+            ]
+        );
+        assert_eq!(
+            replies
+                .iter()
+                .map(|reply| reply.blocks.clone())
+                .collect::<Vec<_>>(),
+            vec![
+                vec![Block::header("📋 Jira"), Block::section("CORE-1")],
+                vec![Block::header("⛅ Weather")],
+            ]
+        );
+    }
+}
@@ -0,0 +1,211 @@
+//! Rate limiting standups
+//!
+//! A few people running `/standup` at once would each post a standup. Runs
+//! are counted per channel in fixed windows of `rate_limit_window_secs`, in
+//! DynamoDB so every lambda shares the count, and runs beyond
+//! `rate_limit_max` are refused. Counts expire through the table's time to
+//! live.
+//!
+//! Runs within `reuse_within_secs` of the same standup being posted link to
+//! it instead, unless they ask for a `fresh` one.
+
+// Third party
+use chrono::{DateTime, Utc};
+use rusoto_core::Region;
+use rusoto_dynamodb::{DynamoDb, DynamoDbClient, UpdateItemError, UpdateItemInput};
+
+// Ours
+use command::Command;
+use config::Config;
+use dynamodb::{attributes, number};
+use history::{Entry, History};
+use log;
+
+pub struct Limits {
+    table: String,
+    client: DynamoDbClient,
+    max: u32,
+    window_secs: u64,
+}
+
+impl Limits {
+    pub fn new(table: String, max: u32, window_secs: u64) -> Self {
+        Limits {
+            table,
+            client: DynamoDbClient::new(Region::default()),
+            max,
+            window_secs,
+        }
+    }
+
+    /// rate limiting, when there's a table to count runs in
+    pub fn configured(config: &Config) -> Option<Limits> {
+        config
+            .rate_limit_table
+            .clone()
+            .filter(|_| config.rate_limit_max > 0 && config.rate_limit_window_secs > 0)
+            .map(|table| Limits::new(table, config.rate_limit_max, config.rate_limit_window_secs))
+    }
+
+    /// counts a run in a channel, returning false if the channel's already had its fill
+    pub fn acquire(
+        &self,
+        team_id: &str,
+        channel_id: &str,
+        now: DateTime<Utc>,
+    ) -> Result<bool, String> {
+        let now = now.timestamp() as u64;
+        let window = now - now % self.window_secs;
+        let key = attributes(vec![(
+            "key",
+            format!("{}:{}:{}", team_id, channel_id, window),
+        )]);
+        let updated = self
+            .client
+            .update_item(UpdateItemInput {
+                table_name: self.table.clone(),
+                key,
+                update_expression: Some("ADD #runs :one SET #expires = :expires".into()),
+                condition_expression: Some("attribute_not_exists(#runs) OR #runs < :max".into()),
+                expression_attribute_names: Some(
+                    vec![
+                        ("#runs".to_owned(), "runs".to_owned()),
+                        ("#expires".to_owned(), "expires".to_owned()),
+                    ]
+                    .into_iter()
+                    .collect(),
+                ),
+                expression_attribute_values: Some(
+                    vec![
+                        (":one".to_owned(), number(1)),
+                        (":max".to_owned(), number(u64::from(self.max))),
+                        (":expires".to_owned(), number(window + self.window_secs)),
+                    ]
+                    .into_iter()
+                    .collect(),
+                ),
+                ..Default::default()
+            })
+            .sync();
+        match updated {
+            Ok(_) => Ok(true),
+            Err(UpdateItemError::ConditionalCheckFailed(_)) => Ok(false),
+            Err(err) => Err(format!("failed to count standup: {}", err)),
+        }
+    }
+}
+
+/// a link to the same standup as `command`'s, posted to the channel within
+/// `reuse_within_secs`, in place of another
+pub fn reusable(
+    config: &Config,
+    channel: &str,
+    command: &Command,
+    now: DateTime<Utc>,
+) -> Option<String> {
+    let within = config.reuse_within_secs.filter(|secs| *secs > 0)?;
+    let entry = latest(config, channel)?;
+    if entry.command != Some(command.standup().text()) {
+        return None;
+    }
+    let permalink = entry.permalink?;
+    if (now - entry.posted_at).num_seconds() > within as i64 {
+        return None;
+    }
+    Some(reused(entry.posted_at, &permalink, now))
+}
+
+/// why a run was refused, linking to the channel's latest standup when it's recorded
+pub fn refusal(config: &Config, channel: &str, now: DateTime<Utc>) -> String {
+    match latest(config, channel) {
+        Some(entry) => refused(
+            Some((
+                entry.posted_at,
+                entry.permalink.as_ref().map(String::as_str),
+            )),
+            now,
+        ),
+        None => refused(None, now),
+    }
+}
+
+fn latest(config: &Config, channel: &str) -> Option<Entry> {
+    let table = config.history_table.clone()?;
+    History::new(table)
+        .latest(channel)
+        .map_err(|err| log::error("failed to load previous standup", json!({ "error": err })))
+        .ok()
+        .and_then(|latest| latest)
+}
+
+fn reused(posted_at: DateTime<Utc>, permalink: &str, now: DateTime<Utc>) -> String {
+    format!(
+        "👀 The standup was posted here {} — <{}|here it is>. Run `/standup fresh` for a new one",
+        ago(posted_at, now),
+        permalink
+    )
+}
+
+fn refused(latest: Option<(DateTime<Utc>, Option<&str>)>, now: DateTime<Utc>) -> String {
+    match latest {
+        Some((posted_at, permalink)) => {
+            let ago = ago(posted_at, now);
+            match permalink {
+                Some(permalink) => format!(
+                    "🛑 A standup was posted here {} — <{}|here's the link>",
+                    ago, permalink
+                ),
+                None => format!("🛑 A standup was posted here {}, scroll up for it", ago),
+            }
+        }
+        None => {
+            "🛑 Plenty of standups have been run here lately, try again in a few minutes".into()
+        }
+    }
+}
+
+fn ago(then: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    match (now - then).num_minutes() {
+        minutes if minutes < 1 => "just now".to_owned(),
+        1 => "a minute ago".to_owned(),
+        minutes => format!("{} minutes ago", minutes),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn refusals_link_to_the_latest_standup() {
+        let now = Utc::now();
+        assert_eq!(
+            refused(
+                Some((
+                    now - Duration::minutes(2),
+                    Some("https://acme.slack.com/archives/C1/p1")
+                )),
+                now
+            ),
+            "🛑 A standup was posted here 2 minutes ago — <https://acme.slack.com/archives/C1/p1|here's the link>"
+        );
+        assert_eq!(
+            refused(Some((now, None)), now),
+            "🛑 A standup was posted here just now, scroll up for it"
+        );
+    }
+
+    #[test]
+    fn reused_standups_are_linked() {
+        let now = Utc::now();
+        assert_eq!(
+            reused(
+                now - Duration::seconds(90),
+                "https://acme.slack.com/archives/C1/p1",
+                now
+            ),
+            "👀 The standup was posted here a minute ago — <https://acme.slack.com/archives/C1/p1|here it is>. Run `/standup fresh` for a new one"
+        );
+    }
+}
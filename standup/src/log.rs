@@ -0,0 +1,61 @@
+//! Structured json logging, to stderr
+//!
+//! Each line is a json object carrying its level and message, fields
+//! describing the event, and correlation fields identifying the invocation
+//! and slack channel it happened in, for querying with CloudWatch Logs Insights.
+//!
+//! Correlation fields are kept per thread, so requests handled concurrently
+//! each log with their own. Threads spawned for an invocation carry them on
+//! through `trace::spawn`.
+
+// Std lib
+use std::cell::RefCell;
+
+// Third party
+use chrono::Utc;
+use serde_json::{Map, Value};
+
+thread_local! {
+    /// fields of the invocation this thread is working on
+    static CORRELATION: RefCell<Map<String, Value>> = RefCell::new(Map::new());
+}
+
+/// adds fields to every subsequent line logged by this invocation
+pub fn correlate(fields: Value) {
+    if let Value::Object(fields) = fields {
+        CORRELATION.with(|correlation| correlation.borrow_mut().extend(fields));
+    }
+}
+
+/// this invocation's correlation fields, for threads it spawns to `correlate` with
+pub fn correlation() -> Value {
+    CORRELATION.with(|correlation| Value::Object(correlation.borrow().clone()))
+}
+
+/// forgets correlation fields of a previous invocation
+pub fn reset() {
+    CORRELATION.with(|correlation| correlation.borrow_mut().clear());
+}
+
+pub fn info(message: &str, fields: Value) {
+    log("info", message, fields)
+}
+
+pub fn warn(message: &str, fields: Value) {
+    log("warn", message, fields)
+}
+
+pub fn error(message: &str, fields: Value) {
+    log("error", message, fields)
+}
+
+fn log(level: &str, message: &str, fields: Value) {
+    let mut line = CORRELATION.with(|correlation| correlation.borrow().clone());
+    if let Value::Object(fields) = fields {
+        line.extend(fields);
+    }
+    line.insert("timestamp".into(), json!(Utc::now().to_rfc3339()));
+    line.insert("level".into(), json!(level));
+    line.insert("message".into(), json!(message));
+    eprintln!("{}", Value::Object(line));
+}
@@ -0,0 +1,79 @@
+//! Runs a standup locally, printing the rendered slack message
+//!
+//! Reads the same env configuration as the lambda handler. Arguments are
+//! those of the slash command, i.e. `standup jira since=3`. Printed standups
+//! are dry runs, which aren't recorded, rotated or published anywhere.
+
+extern crate standup;
+
+// Std lib
+use std::env;
+use std::process;
+
+// Ours
+use standup::sink::{Chat, Discord, Stdout, Teams, Webhook};
+use standup::{command, metrics, Command, Config, Origin, Sink};
+
+/// history channel of standups run from the command line
+const CLI_CHANNEL: &str = "cli";
+
+fn main() {
+    let mut webhook = None;
+    let mut channel = None;
+    let mut teams = None;
+    let mut discord = None;
+    let mut args = Vec::new();
+    let mut argv = env::args().skip(1);
+    while let Some(arg) = argv.next() {
+        match arg.as_str() {
+            "--webhook" => webhook = argv.next(),
+            "--channel" => channel = argv.next(),
+            "--teams" => teams = argv.next(),
+            "--discord" => discord = argv.next(),
+            "-h" | "--help" => {
+                println!("Usage: standup [--webhook URL | --channel ID | --teams URL | --discord URL] [ARGS...]\nPrints the standup, or posts it to a slack webhook, a channel with SLACK_BOT_TOKEN, or a teams or discord webhook");
+                return;
+            }
+            _ => args.push(arg),
+        }
+    }
+
+    let config = Config::from_env().unwrap_or_else(|err| exit(&format!("invalid config: {}", err)));
+    let command = Command::parse(&args.join(" "), &config.standup_sections).unwrap_or_else(|err| {
+        exit(&format!(
+            "{}\n{}",
+            err,
+            command::usage(&config.standup_sections)
+        ))
+    });
+    metrics::disable();
+    let mut origin = Origin::Requested;
+    let sink: Box<dyn Sink> = if let Some(url) = webhook {
+        Box::new(Webhook::new(url))
+    } else if let Some(channel) = channel {
+        match config.slack_bot_token.clone() {
+            Some(token) => Box::new(Chat::new(token, channel, config.http_client())),
+            None => exit("posting to a channel requires SLACK_BOT_TOKEN"),
+        }
+    } else if let Some(url) = teams {
+        Box::new(Teams::new(url, config.http_client()))
+    } else if let Some(url) = discord {
+        Box::new(Discord::new(url, config.http_client()))
+    } else {
+        origin = Origin::DryRun;
+        Box::new(Stdout)
+    };
+    let result = if command.weekly {
+        standup::weekly(config, CLI_CHANNEL.into(), &*sink)
+    } else {
+        standup::run(config, command, CLI_CHANNEL.into(), origin, &*sink)
+    };
+    if let Err(err) = result {
+        exit(&err)
+    }
+}
+
+fn exit(message: &str) -> ! {
+    eprintln!("{}", message);
+    process::exit(1)
+}
@@ -0,0 +1,77 @@
+//! CloudWatch metrics
+//!
+//! Metrics are logged in the CloudWatch embedded metric format, which
+//! CloudWatch Logs extracts into custom metrics without any api calls.
+//!
+//! see https://docs.aws.amazon.com/AmazonCloudWatch/latest/monitoring/CloudWatch_Embedded_Metric_Format_Specification.html
+
+// Std lib
+use std::sync::atomic::{AtomicBool, Ordering, ATOMIC_BOOL_INIT};
+
+// Third party
+use chrono::Utc;
+use serde_json::{Map, Value};
+
+const NAMESPACE: &str = "SlackStandup";
+
+static DISABLED: AtomicBool = ATOMIC_BOOL_INIT;
+
+/// stops emitting metrics, i.e. outside of lambda where nothing collects them
+pub fn disable() {
+    DISABLED.store(true, Ordering::Relaxed);
+}
+
+/// A single measurement
+#[derive(Debug, Clone)]
+pub struct Metric {
+    pub name: &'static str,
+    pub value: f64,
+    pub unit: &'static str,
+}
+
+impl Metric {
+    pub fn count(name: &'static str, value: usize) -> Self {
+        Metric {
+            name,
+            value: value as f64,
+            unit: "Count",
+        }
+    }
+
+    pub fn milliseconds(name: &'static str, value: u64) -> Self {
+        Metric {
+            name,
+            value: value as f64,
+            unit: "Milliseconds",
+        }
+    }
+}
+
+/// logs metrics, all sharing the given dimensions
+pub fn emit(dimensions: &[(&str, &str)], metrics: &[Metric]) {
+    if metrics.is_empty() || DISABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    let mut line = Map::new();
+    line.insert(
+        "_aws".into(),
+        json!({
+            "Timestamp": Utc::now().timestamp_millis(),
+            "CloudWatchMetrics": [{
+                "Namespace": NAMESPACE,
+                "Dimensions": [dimensions.iter().map(|(name, _)| name).collect::<Vec<_>>()],
+                "Metrics": metrics
+                    .iter()
+                    .map(|metric| json!({ "Name": metric.name, "Unit": metric.unit }))
+                    .collect::<Vec<_>>(),
+            }],
+        }),
+    );
+    for (name, value) in dimensions {
+        line.insert((*name).to_owned(), json!(value));
+    }
+    for metric in metrics {
+        line.insert(metric.name.to_owned(), json!(metric.value));
+    }
+    println!("{}", Value::Object(line));
+}
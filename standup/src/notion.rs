@@ -0,0 +1,174 @@
+//! Notion database publishing
+//!
+//! Each standup may also be created as a page in a Notion database, for teams
+//! whose wiki lives there. Pages are titled with the date and carry the
+//! standup's headline counts as properties, so the database needs a `Name`
+//! title, a `Date` date and `Closed` and `Incidents` number properties.
+//!
+//! see https://developers.notion.com/reference/post-page
+
+// Std lib
+use std::collections::BTreeMap;
+
+// Third party
+use chrono::NaiveDate;
+use reqwest::header::AUTHORIZATION;
+use serde_json::Value;
+
+// Ours
+use export::Report;
+use log;
+use render::Message;
+use retry::{self, Retry};
+use section::Context;
+use sink::markup::{sections, truncated, Markup};
+
+const API: &str = "https://api.notion.com/v1";
+
+/// api version requests are made against
+const NOTION_VERSION: &str = "2022-06-28";
+
+/// most blocks notion accepts when creating a page
+const MAX_CHILDREN: usize = 100;
+
+/// longest rich text notion accepts
+const MAX_TEXT: usize = 2000;
+
+/// creates a page for a standup in the configured database, if any
+pub fn publish(report: &Report, message: &Message, context: &Context) {
+    let config = &context.config;
+    let (token, database_id) = match (
+        config.notion_token.clone(),
+        config.notion_database_id.clone(),
+    ) {
+        (Some(token), Some(database_id)) => (token, database_id),
+        _ => return,
+    };
+    let page = page(
+        &database_id,
+        context.now.date().naive_local(),
+        report.metrics,
+        message,
+    );
+    let url = format!(
+        "{}/pages",
+        config
+            .notion_api_url
+            .as_ref()
+            .map(String::as_str)
+            .unwrap_or(API)
+    );
+    let client = config.http_client();
+    let created = Retry::from(config).run(
+        || {
+            client
+                .post(url.as_str())
+                .header(AUTHORIZATION, format!("Bearer {}", token))
+                .header("Notion-Version", NOTION_VERSION)
+                .json(&page)
+                .send()
+                .and_then(|response| response.error_for_status())
+        },
+        retry::http,
+    );
+    if let Err(err) = created {
+        log::error(
+            "failed to publish standup to notion",
+            json!({
+                "error": err.to_string(),
+                "upstream_status": err.status().map(|status| status.as_u16()),
+            }),
+        );
+    }
+}
+
+/// a database page for a standup, with counts for the sections which reported them
+fn page(
+    database_id: &str,
+    date: NaiveDate,
+    metrics: &BTreeMap<&'static str, f64>,
+    message: &Message,
+) -> Value {
+    let mut properties = json!({
+        "Name": { "title": [text(&format!("Standup {}", date))] },
+        "Date": { "date": { "start": date.to_string() } },
+    });
+    for &(property, metric) in &[("Closed", "ShippedIssues"), ("Incidents", "OpenIncidents")] {
+        if let Some(value) = metrics.get(metric) {
+            properties[property] = json!({ "number": value });
+        }
+    }
+    json!({
+        "parent": { "database_id": database_id },
+        "properties": properties,
+        "children": children(message),
+    })
+}
+
+/// a message's sections as headings and paragraphs
+fn children(message: &Message) -> Vec<Value> {
+    let mut children = Vec::new();
+    for section in sections(message, Markup::Markdown) {
+        if let Some(title) = section.title {
+            children.push(json!({
+                "type": "heading_3",
+                "heading_3": { "rich_text": [text(&title)] },
+            }));
+        }
+        children.extend(section.lines.iter().map(|line| {
+            json!({
+                "type": "paragraph",
+                "paragraph": { "rich_text": [text(line)] },
+            })
+        }));
+    }
+    children.truncate(MAX_CHILDREN);
+    children
+}
+
+fn text(content: &str) -> Value {
+    json!({
+        "type": "text",
+        "text": { "content": truncated(content, MAX_TEXT) },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use render::Block;
+
+    #[test]
+    fn pages_have_counts_of_reported_sections() {
+        let mut metrics = BTreeMap::new();
+        metrics.insert("ShippedIssues", 3.0);
+        let page = page(
+            "db",
+            NaiveDate::from_ymd(2019, 1, 7),
+            &metrics,
+            &Message {
+                text: "🚫 1 blocked".into(),
+                blocks: vec![Block::header("🚫 Blocked"), Block::section("*stuck*")],
+            },
+        );
+        assert_eq!(
+            page["properties"]["Name"]["title"][0]["text"]["content"],
+            json!("Standup 2019-01-07")
+        );
+        assert_eq!(page["properties"]["Closed"], json!({ "number": 3.0 }));
+        assert_eq!(page["properties"]["Incidents"], Value::Null);
+        assert_eq!(
+            page["children"],
+            json!([
+                {
+                    "type": "heading_3",
+                    "heading_3": { "rich_text": [{ "type": "text", "text": { "content": "🚫 Blocked" } }] },
+                },
+                {
+                    "type": "paragraph",
+                    "paragraph": { "rich_text": [{ "type": "text", "text": { "content": "**stuck**" } }] },
+                },
+            ])
+        );
+    }
+}
@@ -0,0 +1,304 @@
+//! Opsgenie api client
+//!
+//! Alerts are reported in the same shape as PagerDuty incidents so either
+//! may back the weather report
+
+// Third party
+use chrono::{DateTime, FixedOffset};
+use reqwest::header::AUTHORIZATION;
+use reqwest::Client;
+use serde::de::DeserializeOwned;
+
+// Ours
+use error::FetchError;
+use log;
+use pagerduty::{Assignment, Incident, OnCall, Reference};
+use retry::{self, Retry};
+use trace;
+
+const API: &str = "https://api.opsgenie.com";
+
+/// alerts link here, opsgenie redirects to the signed in account
+const APP: &str = "https://app.opsgenie.com";
+
+/// most results opsgenie returns per page
+const PAGE_LIMIT: usize = 100;
+
+/// stops runaway paging should opsgenie keep reporting more results
+const MAX_PAGES: usize = 10;
+
+/// see https://docs.opsgenie.com/docs/alert-api#list-alerts
+#[derive(Deserialize, Debug)]
+struct Alerts {
+    data: Vec<Alert>,
+    #[serde(default)]
+    paging: Paging,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct Paging {
+    next: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct Alert {
+    id: String,
+    tiny_id: String,
+    message: String,
+    /// `open` or `closed`
+    status: String,
+    #[serde(default)]
+    acknowledged: bool,
+    created_at: Option<DateTime<FixedOffset>>,
+    /// `P1` through `P5`
+    priority: Option<String>,
+    /// empty when unowned
+    #[serde(default)]
+    owner: String,
+}
+
+impl From<Alert> for Incident {
+    fn from(alert: Alert) -> Incident {
+        let status = match (alert.status.as_str(), alert.acknowledged) {
+            ("open", true) => "acknowledged".to_owned(),
+            _ => alert.status,
+        };
+        Incident {
+            incident_number: alert.tiny_id.parse().unwrap_or_default(),
+            title: alert.message,
+            status,
+            html_url: format!("{}/alert/detail/{}/details", APP, alert.id),
+            created_at: alert.created_at,
+            urgency: alert.priority,
+            assignments: Some(alert.owner)
+                .filter(|owner| !owner.is_empty())
+                .map(|owner| Assignment {
+                    assignee: Reference {
+                        id: owner.clone(),
+                        summary: owner,
+                    },
+                })
+                .into_iter()
+                .collect(),
+        }
+    }
+}
+
+/// see https://docs.opsgenie.com/docs/who-is-on-call-api#get-on-calls
+#[derive(Deserialize, Debug)]
+struct OnCalls {
+    data: OnCallRecipients,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct OnCallRecipients {
+    #[serde(default)]
+    on_call_recipients: Vec<String>,
+}
+
+pub struct Opsgenie {
+    api: String,
+    api_key: String,
+    client: Client,
+    retry: Retry,
+}
+
+impl Opsgenie {
+    pub fn new(api_key: String, client: Client, retry: Retry) -> Self {
+        Opsgenie {
+            api: API.into(),
+            api_key,
+            client,
+            retry,
+        }
+    }
+
+    /// points requests at another api host, i.e. a mock server or opsgenie's eu region
+    pub fn with_api(self, api: String) -> Self {
+        Opsgenie { api, ..self }
+    }
+
+    /// open and acknowledged alerts for teams created since a given unix time in milliseconds
+    pub fn open_alerts(&self, teams: &[String], since: i64) -> Result<Vec<Incident>, FetchError> {
+        self.alerts("open", teams, since)
+    }
+
+    /// closed alerts for teams created since a given unix time in milliseconds
+    pub fn closed_alerts(&self, teams: &[String], since: i64) -> Result<Vec<Incident>, FetchError> {
+        self.alerts("closed", teams, since)
+    }
+
+    /// users currently on call for schedules, by name
+    pub fn oncalls(&self, schedules: &[String]) -> Result<Vec<OnCall>, FetchError> {
+        let mut oncalls = Vec::new();
+        for schedule in schedules {
+            let recipients = self.get::<OnCalls>(
+                &format!("/v2/schedules/{}/on-calls", schedule),
+                &[
+                    ("scheduleIdentifierType", "name".into()),
+                    ("flat", "true".into()),
+                ],
+            )?;
+            oncalls.extend(
+                recipients
+                    .data
+                    .on_call_recipients
+                    .into_iter()
+                    .map(|recipient| OnCall {
+                        user: Reference {
+                            id: recipient.clone(),
+                            summary: recipient,
+                        },
+                        escalation_level: 1,
+                        end: None,
+                    }),
+            );
+        }
+        Ok(oncalls)
+    }
+
+    fn alerts(
+        &self,
+        status: &str,
+        teams: &[String],
+        since: i64,
+    ) -> Result<Vec<Incident>, FetchError> {
+        let query = vec![
+            ("query", search(status, teams, since)),
+            ("sort", "createdAt".to_owned()),
+            ("order", "asc".to_owned()),
+            ("limit", PAGE_LIMIT.to_string()),
+        ];
+        let mut incidents = Vec::new();
+        for _ in 0..MAX_PAGES {
+            let mut page_query = query.clone();
+            page_query.push(("offset", incidents.len().to_string()));
+            let page = self.get::<Alerts>("/v2/alerts", &page_query)?;
+            let more = page.paging.next.is_some() && !page.data.is_empty();
+            incidents.extend(page.data.into_iter().map(Incident::from));
+            if !more {
+                return Ok(incidents);
+            }
+        }
+        log::warn(
+            "truncated opsgenie alerts",
+            json!({ "alerts": incidents.len(), "pages": MAX_PAGES }),
+        );
+        Ok(incidents)
+    }
+
+    fn get<T>(&self, path: &str, query: &[(&str, String)]) -> Result<T, FetchError>
+    where
+        T: DeserializeOwned,
+    {
+        self.retry
+            .run(
+                || {
+                    trace::request("Opsgenie", path, || {
+                        self.client
+                            .get(&format!("{}{}", self.api, path))
+                            .query(query)
+                            .header(AUTHORIZATION, format!("GenieKey {}", self.api_key))
+                            .send()
+                            .and_then(|response| response.error_for_status())
+                            .and_then(|mut response| response.json::<T>())
+                    })
+                },
+                retry::http,
+            )
+            .map_err(FetchError::opsgenie)
+    }
+}
+
+/// see https://docs.opsgenie.com/docs/alerts-search-query-help
+fn search(status: &str, teams: &[String], since: i64) -> String {
+    let mut query = format!("status: {} AND createdAt >= {}", status, since);
+    if !teams.is_empty() {
+        query.push_str(&format!(
+            " AND ({})",
+            teams
+                .iter()
+                .map(|team| format!("teams: \"{}\"", team))
+                .collect::<Vec<_>>()
+                .join(" OR ")
+        ));
+    }
+    query
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::{self, mock, Matcher};
+    use retry::Retry;
+
+    fn opsgenie() -> Opsgenie {
+        Opsgenie::new(
+            "secret".into(),
+            Client::new(),
+            Retry {
+                attempts: 1,
+                backoff_ms: 0,
+            },
+        )
+        .with_api(mockito::server_url())
+    }
+
+    #[test]
+    fn searches_by_status_team_and_creation() {
+        assert_eq!(
+            search("open", &["sre".into(), "web".into()], 1_546_819_200_000),
+            "status: open AND createdAt >= 1546819200000 AND (teams: \"sre\" OR teams: \"web\")"
+        );
+        assert_eq!(
+            search("closed", &[], 1_546_819_200_000),
+            "status: closed AND createdAt >= 1546819200000"
+        );
+    }
+
+    #[test]
+    fn alerts_are_reported_as_incidents() {
+        let _alerts = mock("GET", "/v2/alerts")
+            .match_query(Matcher::UrlEncoded(
+                "query".into(),
+                "status: open AND createdAt >= 1546819200000 AND (teams: \"sre\")".into(),
+            ))
+            .match_header("authorization", "GenieKey secret")
+            .with_body(
+                r#"{"data":[{"id":"abc-123","tinyId":"42","message":"api is down","status":"open","acknowledged":true,"createdAt":"2019-01-07T15:00:00Z","priority":"P1","owner":"dana@example.com"}],"paging":{"first":"..."}}"#,
+            )
+            .create();
+        let incidents = opsgenie()
+            .open_alerts(&["sre".into()], 1_546_819_200_000)
+            .unwrap();
+        assert_eq!(incidents.len(), 1);
+        assert_eq!(incidents[0].incident_number, 42);
+        assert_eq!(incidents[0].title, "api is down");
+        assert_eq!(incidents[0].status, "acknowledged");
+        assert_eq!(
+            incidents[0].html_url,
+            "https://app.opsgenie.com/alert/detail/abc-123/details"
+        );
+        assert_eq!(incidents[0].urgency, Some("P1".into()));
+        assert_eq!(
+            incidents[0].assignments[0].assignee.summary,
+            "dana@example.com"
+        );
+    }
+
+    #[test]
+    fn oncalls_are_listed_per_schedule() {
+        let _oncalls = mock("GET", "/v2/schedules/primary/on-calls")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("scheduleIdentifierType".into(), "name".into()),
+                Matcher::UrlEncoded("flat".into(), "true".into()),
+            ]))
+            .with_body(r#"{"data":{"onCallRecipients":["ada@example.com"]}}"#)
+            .create();
+        let oncalls = opsgenie().oncalls(&["primary".into()]).unwrap();
+        assert_eq!(oncalls.len(), 1);
+        assert_eq!(oncalls[0].user.summary, "ada@example.com");
+    }
+}
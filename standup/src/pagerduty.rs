@@ -0,0 +1,549 @@
+//! PagerDuty api client
+
+// Third party
+use chrono::{DateTime, FixedOffset};
+use reqwest::header::{ACCEPT, AUTHORIZATION};
+use reqwest::{Client, RequestBuilder};
+use serde::de::DeserializeOwned;
+use serde_json::{self, Value};
+
+// Ours
+use breaker::{Breaker, Tripped};
+use cache::Cache;
+use error::FetchError;
+use log;
+use retry::{self, Retry};
+use trace;
+
+const API: &str = "https://api.pagerduty.com";
+
+/// most results pagerduty returns per page
+const PAGE_LIMIT: usize = 100;
+
+/// stops runaway paging should pagerduty keep reporting more results
+const MAX_PAGES: usize = 10;
+
+/// see https://developer.pagerduty.com/docs/rest-api-v2/pagination/
+#[derive(Deserialize, Debug)]
+struct Incidents {
+    incidents: Vec<Incident>,
+    #[serde(default)]
+    more: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Incident {
+    pub incident_number: usize,
+    pub title: String,
+    pub status: String,
+    pub html_url: String,
+    // absent from standups recorded before these were reported
+    #[serde(default)]
+    pub created_at: Option<DateTime<FixedOffset>>,
+    /// `high` or `low`, or an opsgenie priority i.e. `P1`
+    #[serde(default)]
+    pub urgency: Option<String>,
+    #[serde(default)]
+    pub assignments: Vec<Assignment>,
+}
+
+impl Incident {
+    /// whether this is a P1, or high urgency for pagerduty
+    pub fn is_p1(&self) -> bool {
+        self.urgency
+            .as_ref()
+            .map(|urgency| {
+                urgency.eq_ignore_ascii_case("high") || urgency.eq_ignore_ascii_case("p1")
+            })
+            .unwrap_or(false)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Assignment {
+    pub assignee: Reference,
+}
+
+/// see https://developer.pagerduty.com/api-reference/reference/REST/openapiv3.json/paths/~1analytics~1metrics~1incidents~1all/post
+#[derive(Deserialize, Debug)]
+struct AggregatedMetrics {
+    data: Vec<IncidentMetrics>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct IncidentMetrics {
+    pub total_incident_count: usize,
+    /// absent when no incidents were acknowledged
+    pub mean_seconds_to_first_ack: Option<f64>,
+    /// absent when no incidents were resolved
+    pub mean_seconds_to_resolve: Option<f64>,
+}
+
+#[derive(Deserialize, Debug)]
+struct EscalationPolicies {
+    escalation_policies: Vec<Reference>,
+}
+
+#[derive(Deserialize, Debug)]
+struct OnCalls {
+    oncalls: Vec<OnCall>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct OnCall {
+    pub user: Reference,
+    pub escalation_level: usize,
+    /// absent for permanent on call assignments
+    pub end: Option<DateTime<FixedOffset>>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Reference {
+    pub id: String,
+    pub summary: String,
+}
+
+/// Which incidents to report on
+///
+/// empty lists don't filter
+#[derive(Debug, Default)]
+pub struct Scope<'a> {
+    pub team_ids: &'a [String],
+    pub service_ids: &'a [String],
+    /// `high` and/or `low`
+    pub urgencies: &'a [String],
+}
+
+#[derive(Deserialize, Debug)]
+struct Abilities {
+    abilities: Vec<String>,
+}
+
+pub struct PagerDuty {
+    api: String,
+    token: String,
+    client: Client,
+    retry: Retry,
+    cache: Cache,
+    breaker: Breaker,
+}
+
+impl PagerDuty {
+    pub fn new(token: String, client: Client, retry: Retry) -> Self {
+        PagerDuty {
+            api: API.into(),
+            token,
+            client,
+            retry,
+            cache: Cache::default(),
+            breaker: Breaker::default(),
+        }
+    }
+
+    /// reuses responses to cached requests
+    pub fn with_cache(self, cache: Cache) -> Self {
+        PagerDuty { cache, ..self }
+    }
+
+    /// skips requests while the api keeps failing
+    pub fn with_breaker(self, breaker: Breaker) -> Self {
+        PagerDuty { breaker, ..self }
+    }
+
+    /// points requests at another api host, i.e. a mock server
+    pub fn with_api(self, api: String) -> Self {
+        PagerDuty { api, ..self }
+    }
+
+    /// features the account has, which any valid token may list
+    pub fn abilities(&self) -> Result<Vec<String>, FetchError> {
+        self.get::<Abilities>("/abilities", &[])
+            .map(|abilities| abilities.abilities)
+    }
+
+    /// triggered and acknowledged incidents in scope since a given date
+    pub fn open_incidents(&self, scope: &Scope, since: &str) -> Result<Vec<Incident>, FetchError> {
+        self.incidents(&["triggered", "acknowledged"], scope, since)
+    }
+
+    /// incidents in scope since a given date which have been resolved
+    pub fn resolved_incidents(
+        &self,
+        scope: &Scope,
+        since: &str,
+    ) -> Result<Vec<Incident>, FetchError> {
+        self.incidents(&["resolved"], scope, since)
+    }
+
+    /// incident count, mean time to acknowledge and mean time to resolve for
+    /// incidents in scope created between two timestamps
+    pub fn incident_metrics(
+        &self,
+        scope: &Scope,
+        start: &str,
+        end: &str,
+    ) -> Result<IncidentMetrics, FetchError> {
+        let mut filters = json!({
+            "created_at_start": start,
+            "created_at_end": end,
+        });
+        if !scope.team_ids.is_empty() {
+            filters["team_ids"] = json!(scope.team_ids);
+        }
+        if !scope.service_ids.is_empty() {
+            filters["service_ids"] = json!(scope.service_ids);
+        }
+        // analytics only filters on a single urgency
+        if scope.urgencies.len() == 1 {
+            filters["urgency"] = json!(scope.urgencies[0]);
+        }
+        self.post::<AggregatedMetrics>(
+            "/analytics/metrics/incidents/all",
+            &json!({ "filters": filters }),
+        )
+        .map(|metrics| metrics.data.into_iter().next().unwrap_or_default())
+    }
+
+    /// first level on calls for escalation policies
+    ///
+    /// when no policies are provided, those of the given teams are used
+    pub fn oncalls(
+        &self,
+        escalation_policy_ids: &[String],
+        team_ids: &[String],
+    ) -> Result<Vec<OnCall>, FetchError> {
+        let policy_ids = if escalation_policy_ids.is_empty() {
+            self.escalation_policies(team_ids)?
+        } else {
+            escalation_policy_ids.to_vec()
+        };
+        if policy_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let query = policy_ids
+            .into_iter()
+            .map(|id| ("escalation_policy_ids[]", id))
+            .collect::<Vec<_>>();
+        self.get::<OnCalls>("/oncalls", &query).map(|oncalls| {
+            oncalls
+                .oncalls
+                .into_iter()
+                .filter(|oncall| oncall.escalation_level == 1)
+                .collect()
+        })
+    }
+
+    fn incidents(
+        &self,
+        statuses: &[&str],
+        scope: &Scope,
+        since: &str,
+    ) -> Result<Vec<Incident>, FetchError> {
+        let mut query = vec![("since", since.to_owned())];
+        query.extend(
+            statuses
+                .iter()
+                .map(|status| ("statuses[]", (*status).to_owned())),
+        );
+        query.extend(scope.team_ids.iter().map(|id| ("team_ids[]", id.clone())));
+        query.extend(
+            scope
+                .service_ids
+                .iter()
+                .map(|id| ("service_ids[]", id.clone())),
+        );
+        query.extend(
+            scope
+                .urgencies
+                .iter()
+                .map(|urgency| ("urgencies[]", urgency.clone())),
+        );
+        query.push(("limit", PAGE_LIMIT.to_string()));
+        let mut incidents = Vec::new();
+        for _ in 0..MAX_PAGES {
+            let mut page_query = query.clone();
+            page_query.push(("offset", incidents.len().to_string()));
+            let page = self.get::<Incidents>("/incidents", &page_query)?;
+            let more = page.more && !page.incidents.is_empty();
+            incidents.extend(page.incidents);
+            if !more {
+                return Ok(incidents);
+            }
+        }
+        log::warn(
+            "truncated pagerduty incidents",
+            json!({ "incidents": incidents.len(), "pages": MAX_PAGES }),
+        );
+        Ok(incidents)
+    }
+
+    fn escalation_policies(&self, team_ids: &[String]) -> Result<Vec<String>, FetchError> {
+        if team_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let query = team_ids
+            .iter()
+            .map(|id| ("team_ids[]", id.clone()))
+            .collect::<Vec<_>>();
+        self.get::<EscalationPolicies>("/escalation_policies", &query)
+            .map(|policies| {
+                policies
+                    .escalation_policies
+                    .into_iter()
+                    .map(|policy| policy.id)
+                    .collect()
+            })
+    }
+
+    fn get<T>(&self, path: &str, query: &[(&str, String)]) -> Result<T, FetchError>
+    where
+        T: DeserializeOwned,
+    {
+        self.send(format!("GET {} {:?}", path, query), || {
+            self.client
+                .get(&format!("{}{}", self.api, path))
+                .query(query)
+        })
+    }
+
+    fn post<T>(&self, path: &str, body: &Value) -> Result<T, FetchError>
+    where
+        T: DeserializeOwned,
+    {
+        self.send(format!("POST {} {}", path, body), || {
+            self.client
+                .post(&format!("{}{}", self.api, path))
+                .header("X-EARLY-ACCESS", "analytics-v2")
+                .json(body)
+        })
+    }
+
+    /// `described` identifies the request in the cache, along with the api and token
+    fn send<T, R>(&self, described: String, request: R) -> Result<T, FetchError>
+    where
+        T: DeserializeOwned,
+        R: Fn() -> RequestBuilder,
+    {
+        let key = format!("{} {} {}", self.api, described, self.token);
+        let response = self.cache.get(&key, || {
+            self.breaker
+                .run(
+                    &self.api,
+                    || {
+                        self.retry.run(
+                            || {
+                                trace::request("PagerDuty", &described, || {
+                                    request()
+                                        .header(ACCEPT, "application/vnd.pagerduty+json;version=2")
+                                        .header(
+                                            AUTHORIZATION,
+                                            format!("Token token={}", self.token),
+                                        )
+                                        .send()
+                                        .and_then(|response| response.error_for_status())
+                                        .and_then(|mut response| response.json::<Value>())
+                                })
+                            },
+                            retry::http,
+                        )
+                    },
+                    retry::http,
+                )
+                .map_err(|tripped| match tripped {
+                    Tripped::Open(secs) => FetchError::unavailable("PagerDuty", secs),
+                    Tripped::Failed(err) => FetchError::pagerduty(err),
+                })
+        })?;
+        serde_json::from_value(response)
+            .map_err(|err| FetchError::new("PagerDuty", err.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::{self, mock, Matcher};
+    use retry::Retry;
+
+    fn pagerduty(attempts: u32) -> PagerDuty {
+        PagerDuty::new(
+            "secret".into(),
+            Client::new(),
+            Retry {
+                attempts,
+                backoff_ms: 0,
+            },
+        )
+        .with_api(mockito::server_url())
+    }
+
+    #[test]
+    fn open_incidents_are_filtered_by_status_and_team() {
+        let _incidents = mock("GET", "/incidents")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("since".into(), "2019-01-07T00:00:00+00:00".into()),
+                Matcher::UrlEncoded("statuses[]".into(), "triggered".into()),
+                Matcher::UrlEncoded("statuses[]".into(), "acknowledged".into()),
+                Matcher::UrlEncoded("team_ids[]".into(), "PTEAM".into()),
+            ]))
+            .match_header("authorization", "Token token=secret")
+            .with_body(
+                r#"{"incidents":[{"incident_number":42,"title":"api is down","status":"triggered","html_url":"https://pd/42","urgency":"high","created_at":"2019-01-07T10:00:00-05:00","assignments":[{"assignee":{"id":"U1","summary":"Dana"}}]}]}"#,
+            )
+            .create();
+        let team_ids = vec!["PTEAM".to_owned()];
+        let incidents = pagerduty(1)
+            .open_incidents(
+                &Scope {
+                    team_ids: &team_ids,
+                    ..Default::default()
+                },
+                "2019-01-07T00:00:00+00:00",
+            )
+            .unwrap();
+        assert_eq!(incidents.len(), 1);
+        assert_eq!(incidents[0].incident_number, 42);
+        assert_eq!(incidents[0].title, "api is down");
+        assert_eq!(incidents[0].urgency, Some("high".into()));
+        assert!(incidents[0].is_p1());
+        assert_eq!(incidents[0].assignments[0].assignee.summary, "Dana");
+    }
+
+    #[test]
+    fn incidents_are_filtered_by_service_and_urgency() {
+        let incidents = mock("GET", "/incidents")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("service_ids[]".into(), "PSERVICE".into()),
+                Matcher::UrlEncoded("urgencies[]".into(), "high".into()),
+            ]))
+            .with_body(r#"{"incidents":[]}"#)
+            .create();
+        let service_ids = vec!["PSERVICE".to_owned()];
+        let urgencies = vec!["high".to_owned()];
+        pagerduty(1)
+            .open_incidents(
+                &Scope {
+                    service_ids: &service_ids,
+                    urgencies: &urgencies,
+                    ..Default::default()
+                },
+                "2019-01-07T00:00:00+00:00",
+            )
+            .unwrap();
+        incidents.assert();
+    }
+
+    #[test]
+    fn incidents_are_paged_through() {
+        let first = mock("GET", "/incidents")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("team_ids[]".into(), "PPAGED".into()),
+                Matcher::UrlEncoded("offset".into(), "0".into()),
+            ]))
+            .with_body(
+                r#"{"incidents":[{"incident_number":1,"title":"one","status":"triggered","html_url":"https://pd/1"}],"more":true}"#,
+            )
+            .create();
+        let second = mock("GET", "/incidents")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("team_ids[]".into(), "PPAGED".into()),
+                Matcher::UrlEncoded("offset".into(), "1".into()),
+            ]))
+            .with_body(
+                r#"{"incidents":[{"incident_number":2,"title":"two","status":"triggered","html_url":"https://pd/2"}],"more":false}"#,
+            )
+            .create();
+        let team_ids = vec!["PPAGED".to_owned()];
+        let incidents = pagerduty(1)
+            .open_incidents(
+                &Scope {
+                    team_ids: &team_ids,
+                    ..Default::default()
+                },
+                "2019-01-07T00:00:00+00:00",
+            )
+            .unwrap();
+        first.assert();
+        second.assert();
+        assert_eq!(
+            incidents
+                .iter()
+                .map(|incident| incident.incident_number)
+                .collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+    }
+
+    #[test]
+    fn incident_metrics_are_filtered_by_scope() {
+        let _metrics = mock("POST", "/analytics/metrics/incidents/all")
+            .match_body(Matcher::Json(json!({
+                "filters": {
+                    "created_at_start": "2019-01-01T00:00:00+00:00",
+                    "created_at_end": "2019-01-08T00:00:00+00:00",
+                    "team_ids": ["PTEAM"],
+                    "urgency": "high",
+                }
+            })))
+            .with_body(
+                r#"{"data":[{"total_incident_count":3,"mean_seconds_to_first_ack":120.5,"mean_seconds_to_resolve":null}]}"#,
+            )
+            .create();
+        let team_ids = vec!["PTEAM".to_owned()];
+        let urgencies = vec!["high".to_owned()];
+        let metrics = pagerduty(1)
+            .incident_metrics(
+                &Scope {
+                    team_ids: &team_ids,
+                    urgencies: &urgencies,
+                    ..Default::default()
+                },
+                "2019-01-01T00:00:00+00:00",
+                "2019-01-08T00:00:00+00:00",
+            )
+            .unwrap();
+        assert_eq!(metrics.total_incident_count, 3);
+        assert_eq!(metrics.mean_seconds_to_first_ack, Some(120.5));
+        assert_eq!(metrics.mean_seconds_to_resolve, None);
+    }
+
+    #[test]
+    fn oncalls_are_first_level_only() {
+        let _oncalls = mock("GET", "/oncalls")
+            .match_query(Matcher::UrlEncoded(
+                "escalation_policy_ids[]".into(),
+                "PPOLICY".into(),
+            ))
+            .with_body(
+                r#"{"oncalls":[
+                    {"user":{"id":"U1","summary":"Ada"},"escalation_level":1,"end":"2019-01-08T14:00:00-05:00"},
+                    {"user":{"id":"U2","summary":"Grace"},"escalation_level":2,"end":null}
+                ]}"#,
+            )
+            .create();
+        let oncalls = pagerduty(1).oncalls(&["PPOLICY".into()], &[]).unwrap();
+        assert_eq!(oncalls.len(), 1);
+        assert_eq!(oncalls[0].user.summary, "Ada");
+    }
+
+    #[test]
+    fn server_errors_are_retried_and_reported_by_status() {
+        let unavailable = mock("GET", "/incidents")
+            .match_query(Matcher::UrlEncoded("statuses[]".into(), "resolved".into()))
+            .with_status(503)
+            .expect(2)
+            .create();
+        let team_ids = vec!["PTEAM".to_owned()];
+        let err = pagerduty(2)
+            .resolved_incidents(
+                &Scope {
+                    team_ids: &team_ids,
+                    ..Default::default()
+                },
+                "2019-01-07",
+            )
+            .unwrap_err();
+        unavailable.assert();
+        assert_eq!(err.to_string(), "couldn't load PagerDuty (503)");
+    }
+}
@@ -0,0 +1,202 @@
+//! Release notes for a Jira fix version
+//!
+//! Issues are grouped by type and rendered for slack, with a markdown copy
+//! for changelogs and the like.
+
+// Std lib
+use std::collections::BTreeMap;
+
+// Third party
+use goji::Issue;
+use serde_json::Value;
+
+// Ours
+use config::Config;
+use error::FetchError;
+use jql::Query;
+use render::{chunks, escape, Block, Message};
+use retry::Retry;
+use section::jira;
+
+/// longest section text slack accepts, less room for a code fence
+const MAX_TEXT: usize = 2900;
+
+/// What kind of change an issue was, in the order they're listed
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub enum Category {
+    Feature,
+    Bug,
+    Task,
+}
+
+impl Category {
+    fn of(issue_type: &str) -> Category {
+        match issue_type.to_lowercase().as_str() {
+            "bug" | "defect" => Category::Bug,
+            "task" | "sub-task" | "subtask" | "chore" => Category::Task,
+            // stories, features, improvements, epics etc.
+            _ => Category::Feature,
+        }
+    }
+
+    fn heading(self) -> &'static str {
+        match self {
+            Category::Feature => "✨ Features",
+            Category::Bug => "🐛 Bug fixes",
+            Category::Task => "🔧 Tasks",
+        }
+    }
+}
+
+/// An issue's line in the release notes
+#[derive(Debug, PartialEq)]
+pub struct Note {
+    pub key: String,
+    pub summary: String,
+    pub url: String,
+}
+
+pub struct ReleaseNotes {
+    pub version: String,
+    pub notes: BTreeMap<Category, Vec<Note>>,
+}
+
+impl ReleaseNotes {
+    /// notes for all issues of the configured projects with a fix version
+    pub fn fetch(config: &Config, version: &str) -> Result<ReleaseNotes, FetchError> {
+        let issues = jira::search(
+            &jira::client(config)?,
+            &Query::new()
+                .is_in("project", &config.jira_projects)
+                .eq("fixVersion", version)
+                .order_by("key"),
+            Retry::from(config),
+        )?;
+        Ok(ReleaseNotes::new(version, &issues, &config.jira_host))
+    }
+
+    fn new(version: &str, issues: &[Issue], jira_host: &str) -> ReleaseNotes {
+        let mut notes = BTreeMap::new();
+        for issue in issues {
+            let issue_type = issue
+                .field::<Value>("issuetype")
+                .and_then(|issue_type| issue_type.ok())
+                .and_then(|issue_type| issue_type["name"].as_str().map(str::to_owned))
+                .unwrap_or_default();
+            notes
+                .entry(Category::of(&issue_type))
+                .or_insert_with(Vec::new)
+                .push(Note {
+                    key: issue.key.clone(),
+                    summary: issue.summary().unwrap_or_default(),
+                    url: jira::browse_url(jira_host, &issue.key),
+                });
+        }
+        ReleaseNotes {
+            version: version.to_owned(),
+            notes,
+        }
+    }
+
+    pub fn title(&self) -> String {
+        format!("Release notes for {}", self.version)
+    }
+
+    /// the notes as a slack message
+    pub fn message(&self) -> Message {
+        let mut blocks = vec![Block::header(format!("📝 {}", self.title()))];
+        if self.notes.is_empty() {
+            blocks.push(Block::section(format!(
+                "No issues have the fix version `{}`",
+                self.version
+            )));
+        }
+        for (category, notes) in &self.notes {
+            blocks.push(Block::header(category.heading()));
+            blocks.extend(
+                chunks(
+                    notes.iter().map(|note| {
+                        format!("• <{}|{}> {}", note.url, note.key, escape(&note.summary))
+                    }),
+                    MAX_TEXT,
+                )
+                .into_iter()
+                .map(Block::section),
+            );
+        }
+        Message {
+            text: self.title(),
+            blocks,
+        }
+    }
+
+    /// the notes as markdown
+    pub fn markdown(&self) -> String {
+        let mut markdown = format!("# {}\n", self.title());
+        for (category, notes) in &self.notes {
+            markdown.push_str(&format!("\n## {}\n\n", category.heading()));
+            for note in notes {
+                markdown.push_str(&format!(
+                    "- [{}]({}) {}\n",
+                    note.key, note.url, note.summary
+                ));
+            }
+        }
+        markdown
+    }
+
+    /// the markdown notes in code blocks, ready to copy out of slack
+    pub fn markdown_message(&self) -> Message {
+        Message {
+            text: format!("{} (markdown)", self.title()),
+            blocks: chunks(self.markdown().lines().map(str::to_owned), MAX_TEXT)
+                .into_iter()
+                .map(|chunk| Block::section(format!("```{}```", chunk)))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+
+    fn issue(key: &str, issue_type: &str) -> Issue {
+        serde_json::from_value(json!({
+            "self": "https://jira/rest/api/2/issue/1",
+            "id": "1",
+            "key": key,
+            "fields": {
+                "summary": format!("{} summary", key),
+                "issuetype": { "name": issue_type }
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn notes_are_grouped_by_issue_type() {
+        let notes = ReleaseNotes::new(
+            "2.4.0",
+            &[
+                issue("CORE-3", "Task"),
+                issue("CORE-2", "Bug"),
+                issue("CORE-1", "Story"),
+            ],
+            "https://jira",
+        );
+        assert_eq!(
+            notes.markdown(),
+            "# Release notes for 2.4.0\n\n## ✨ Features\n\n- [CORE-1](https://jira/browse/CORE-1) CORE-1 summary\n\n## 🐛 Bug fixes\n\n- [CORE-2](https://jira/browse/CORE-2) CORE-2 summary\n\n## 🔧 Tasks\n\n- [CORE-3](https://jira/browse/CORE-3) CORE-3 summary\n"
+        );
+    }
+
+    #[test]
+    fn long_notes_are_chunked() {
+        let line = "x".repeat(1000);
+        let chunks = chunks(vec![line.clone(), line.clone(), line.clone()], MAX_TEXT);
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0], format!("{}\n{}", line, line));
+    }
+}
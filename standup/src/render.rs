@@ -0,0 +1,348 @@
+//! Slack Block Kit message layout
+//!
+//! see https://api.slack.com/reference/block-kit/blocks
+
+/// escapes text from jira, pagerduty and the like for mrkdwn, so it can't
+/// be mistaken for links, mentions or entities
+///
+/// `|` only separates a link from its label at its first occurrence, so it's
+/// left as is.
+///
+/// see https://api.slack.com/reference/surfaces/formatting#escaping
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Block Kit text object
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type")]
+pub enum Text {
+    #[serde(rename = "plain_text")]
+    Plain { text: String, emoji: bool },
+    #[serde(rename = "mrkdwn")]
+    Markdown { text: String },
+}
+
+impl Text {
+    pub fn plain<T>(text: T) -> Self
+    where
+        T: Into<String>,
+    {
+        Text::Plain {
+            text: text.into(),
+            emoji: true,
+        }
+    }
+
+    pub fn markdown<T>(text: T) -> Self
+    where
+        T: Into<String>,
+    {
+        Text::Markdown { text: text.into() }
+    }
+}
+
+/// Block Kit interactive elements
+/// only the elements we're using are represented
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Element {
+    Button {
+        text: Text,
+        action_id: String,
+        value: String,
+    },
+}
+
+impl Element {
+    pub fn button<T, A, V>(text: T, action_id: A, value: V) -> Self
+    where
+        T: Into<String>,
+        A: Into<String>,
+        V: Into<String>,
+    {
+        Element::Button {
+            text: Text::plain(text),
+            action_id: action_id.into(),
+            value: value.into(),
+        }
+    }
+}
+
+/// Block Kit layout blocks
+/// only the blocks we're using are represented
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Block {
+    Header {
+        text: Text,
+    },
+    Section {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        text: Option<Text>,
+        #[serde(skip_serializing_if = "Vec::is_empty")]
+        fields: Vec<Text>,
+    },
+    Divider,
+    Context {
+        elements: Vec<Text>,
+    },
+    Actions {
+        elements: Vec<Element>,
+    },
+}
+
+impl Block {
+    pub fn header<T>(text: T) -> Self
+    where
+        T: Into<String>,
+    {
+        Block::Header {
+            text: Text::plain(text),
+        }
+    }
+
+    pub fn section<T>(text: T) -> Self
+    where
+        T: Into<String>,
+    {
+        Block::Section {
+            text: Some(Text::markdown(text)),
+            fields: Vec::new(),
+        }
+    }
+
+    pub fn fields<T>(fields: Vec<T>) -> Self
+    where
+        T: Into<String>,
+    {
+        Block::Section {
+            text: None,
+            fields: fields.into_iter().map(Text::markdown).collect(),
+        }
+    }
+
+    pub fn context<T>(text: T) -> Self
+    where
+        T: Into<String>,
+    {
+        Block::Context {
+            elements: vec![Text::markdown(text)],
+        }
+    }
+}
+
+/// most blocks slack accepts in one message
+const MAX_BLOCKS: usize = 50;
+
+/// longest text slack accepts in a section block
+pub const MAX_SECTION_TEXT: usize = 3000;
+
+/// longest text slack accepts in a section field
+const MAX_FIELD_TEXT: usize = 2000;
+
+/// A renderable Slack message
+#[derive(Serialize, Debug, Default)]
+pub struct Message {
+    /// fallback text used in notifications
+    pub text: String,
+    pub blocks: Vec<Block>,
+}
+
+impl Message {
+    /// Splits a message slack would reject into messages it won't
+    ///
+    /// Groups of blocks between dividers are kept together unless a group
+    /// alone is too long for one message, and overlong text is truncated.
+    pub fn split(&self) -> Vec<Message> {
+        let groups = self
+            .blocks
+            .split(|block| *block == Block::Divider)
+            .flat_map(|group| group.chunks(MAX_BLOCKS))
+            .collect::<Vec<_>>();
+        let mut parts: Vec<Vec<Block>> = Vec::new();
+        for group in groups {
+            let fits = parts
+                .last()
+                .map(|part| part.len() + 1 + group.len() <= MAX_BLOCKS)
+                .unwrap_or(false);
+            if fits {
+                let part = parts.last_mut().unwrap();
+                part.push(Block::Divider);
+                part.extend(group.iter().map(truncated));
+            } else {
+                parts.push(group.iter().map(truncated).collect());
+            }
+        }
+        let count = parts.len();
+        if count <= 1 {
+            return vec![Message {
+                text: self.text.clone(),
+                blocks: parts.pop().unwrap_or_default(),
+            }];
+        }
+        parts
+            .into_iter()
+            .enumerate()
+            .map(|(index, blocks)| Message {
+                text: format!("{} ({}/{})", self.text, index + 1, count),
+                blocks,
+            })
+            .collect()
+    }
+}
+
+/// a block with its text cut to fit slack's limits
+fn truncated(block: &Block) -> Block {
+    match *block {
+        Block::Section {
+            ref text,
+            ref fields,
+        } => Block::Section {
+            text: text
+                .as_ref()
+                .map(|text| truncated_text(text, MAX_SECTION_TEXT)),
+            fields: fields
+                .iter()
+                .map(|field| truncated_text(field, MAX_FIELD_TEXT))
+                .collect(),
+        },
+        ref block => block.clone(),
+    }
+}
+
+fn truncated_text(text: &Text, max: usize) -> Text {
+    let truncate = |text: &str| -> String {
+        if text.chars().count() <= max {
+            text.to_owned()
+        } else {
+            text.chars().take(max - 1).chain(Some('…')).collect()
+        }
+    };
+    match *text {
+        Text::Plain { ref text, emoji } => Text::Plain {
+            text: truncate(text),
+            emoji,
+        },
+        Text::Markdown { ref text } => Text::Markdown {
+            text: truncate(text),
+        },
+    }
+}
+
+/// lines joined into texts of at most `max` bytes, e.g. for section blocks,
+/// so long lists are spread over several blocks rather than cut short
+pub fn chunks<I>(lines: I, max: usize) -> Vec<String>
+where
+    I: IntoIterator<Item = String>,
+{
+    let mut chunks = Vec::new();
+    let mut chunk = String::new();
+    for line in lines {
+        if !chunk.is_empty() && chunk.len() + line.len() + 1 > max {
+            chunks.push(chunk);
+            chunk = String::new();
+        }
+        if !chunk.is_empty() {
+            chunk.push('\n');
+        }
+        chunk.push_str(&line);
+    }
+    if !chunk.is_empty() {
+        chunks.push(chunk);
+    }
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+
+    #[test]
+    fn escapes_mrkdwn_control_characters() {
+        assert_eq!(escape("<b> R&D | <@U1>"), "&lt;b&gt; R&amp;D | &lt;@U1&gt;");
+    }
+
+    #[test]
+    fn serializes_block_kit_json() {
+        let message = Message {
+            text: "Standup debrief".into(),
+            blocks: vec![
+                Block::header("📋 Jira"),
+                Block::section("*Closed*"),
+                Block::Divider,
+            ],
+        };
+        assert_eq!(
+            serde_json::to_value(&message).unwrap(),
+            json!({
+                "text": "Standup debrief",
+                "blocks": [
+                    { "type": "header", "text": { "type": "plain_text", "text": "📋 Jira", "emoji": true } },
+                    { "type": "section", "text": { "type": "mrkdwn", "text": "*Closed*" } },
+                    { "type": "divider" }
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn small_messages_are_not_split() {
+        let message = Message {
+            text: "Standup debrief".into(),
+            blocks: vec![Block::header("📋 Jira"), Block::section("*Closed*")],
+        };
+        let parts = message.split();
+        assert_eq!(parts.len(), 1);
+        assert_eq!(parts[0].text, "Standup debrief");
+        assert_eq!(parts[0].blocks, message.blocks);
+    }
+
+    #[test]
+    fn large_messages_are_split_between_sections() {
+        let mut blocks = Vec::new();
+        for section in 0..3 {
+            if section > 0 {
+                blocks.push(Block::Divider);
+            }
+            blocks.push(Block::header(format!("section {}", section)));
+            blocks.extend((0..20).map(|line| Block::section(format!("line {}", line))));
+        }
+        let parts = Message {
+            text: "Standup debrief".into(),
+            blocks,
+        }
+        .split();
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0].text, "Standup debrief (1/2)");
+        assert_eq!(parts[0].blocks.len(), 43);
+        assert_eq!(parts[1].blocks[0], Block::header("section 2"));
+        assert_eq!(parts[1].blocks.len(), 21);
+    }
+
+    #[test]
+    fn oversized_sections_are_split_and_truncated() {
+        let mut blocks = vec![Block::header("📋 Jira")];
+        blocks.extend((0..60).map(|_| Block::section("x".repeat(4000))));
+        let parts = Message {
+            text: "Standup debrief".into(),
+            blocks,
+        }
+        .split();
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0].blocks.len(), MAX_BLOCKS);
+        match parts[1].blocks[0] {
+            Block::Section {
+                text: Some(Text::Markdown { ref text }),
+                ..
+            } => {
+                assert_eq!(text.chars().count(), MAX_SECTION_TEXT);
+                assert!(text.ends_with('…'));
+            }
+            ref block => panic!("unexpected block {:?}", block),
+        }
+    }
+}
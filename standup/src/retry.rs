@@ -0,0 +1,124 @@
+//! Retrying transient upstream failures
+//!
+//! Failed attempts are retried with exponential backoff and full jitter,
+//! i.e. a random delay of up to `backoff * 2^attempt`.
+
+// Std lib
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// Third party
+use reqwest;
+
+// Ours
+use config::Config;
+
+/// longest delay between attempts
+const MAX_BACKOFF_MS: u64 = 5_000;
+
+/// How many times to try and how long to wait in between
+#[derive(Debug, Clone, Copy)]
+pub struct Retry {
+    pub attempts: u32,
+    /// base delay, doubled with each attempt
+    pub backoff_ms: u64,
+}
+
+impl<'a> From<&'a Config> for Retry {
+    fn from(config: &'a Config) -> Self {
+        Retry {
+            attempts: config.http_retry_attempts,
+            backoff_ms: config.http_retry_backoff_ms,
+        }
+    }
+}
+
+impl Retry {
+    /// calls `f` until it succeeds, fails with an error which isn't `retryable`,
+    /// or we run out of attempts
+    pub fn run<T, E, F, R>(&self, mut f: F, retryable: R) -> Result<T, E>
+    where
+        F: FnMut() -> Result<T, E>,
+        R: Fn(&E) -> bool,
+    {
+        let mut attempt = 1;
+        loop {
+            match f() {
+                Err(ref err) if attempt < self.attempts && retryable(err) => {
+                    thread::sleep(self.delay(attempt));
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    fn delay(&self, attempt: u32) -> Duration {
+        let ceiling = self
+            .backoff_ms
+            .saturating_mul(1 << (attempt - 1).min(16))
+            .min(MAX_BACKOFF_MS);
+        Duration::from_millis(jitter(ceiling))
+    }
+}
+
+/// a pseudo random number of millis up to `ceiling`. good enough to spread out retries
+fn jitter(ceiling: u64) -> u64 {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|now| u64::from(now.subsec_nanos()))
+        .unwrap_or(0);
+    if ceiling == 0 {
+        0
+    } else {
+        nanos % (ceiling + 1)
+    }
+}
+
+/// server errors, timeouts and connection failures are worth retrying
+pub fn http(err: &reqwest::Error) -> bool {
+    err.is_server_error() || err.is_timeout() || err.is_http()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    const RETRY: Retry = Retry {
+        attempts: 3,
+        backoff_ms: 0,
+    };
+
+    #[test]
+    fn retries_until_success() {
+        let calls = Cell::new(0);
+        let result = RETRY.run(
+            || {
+                calls.set(calls.get() + 1);
+                if calls.get() < 3 {
+                    Err("unavailable")
+                } else {
+                    Ok("ok")
+                }
+            },
+            |_| true,
+        );
+        assert_eq!(result, Ok("ok"));
+        assert_eq!(calls.get(), 3);
+    }
+
+    #[test]
+    fn gives_up_on_errors_which_are_not_retryable() {
+        let calls = Cell::new(0);
+        let result: Result<(), _> = RETRY.run(
+            || {
+                calls.set(calls.get() + 1);
+                Err("unauthorized")
+            },
+            |_| false,
+        );
+        assert_eq!(result, Err("unauthorized"));
+        assert_eq!(calls.get(), 1);
+    }
+}
@@ -0,0 +1,97 @@
+//! Config values resolved from AWS secret stores
+//!
+//! Values of the form `ssm://name` are read from SSM Parameter Store and
+//! `secretsmanager://id` from Secrets Manager. Parameter names in a
+//! hierarchy, i.e. `ssm://slack-standup/name`, are read from its root,
+//! `/slack-standup/name`, as SSM requires. Resolved values are cached
+//! for the life of the process, so warm invocations skip the lookup.
+
+// Std lib
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+// Third party
+use rusoto_core::Region;
+use rusoto_secretsmanager::{GetSecretValueRequest, SecretsManager, SecretsManagerClient};
+use rusoto_ssm::{GetParameterRequest, Ssm, SsmClient};
+
+const SSM_PREFIX: &str = "ssm://";
+const SECRETS_MANAGER_PREFIX: &str = "secretsmanager://";
+
+lazy_static! {
+    static ref RESOLVED: Mutex<HashMap<String, String>> = Mutex::new(HashMap::new());
+}
+
+/// returns true if a value refers to a secret store
+pub fn is_reference(value: &str) -> bool {
+    value.starts_with(SSM_PREFIX) || value.starts_with(SECRETS_MANAGER_PREFIX)
+}
+
+/// resolves a secret store reference, leaving other values untouched
+pub fn resolve(value: &str) -> Result<String, String> {
+    if !is_reference(value) {
+        return Ok(value.to_owned());
+    }
+    let cached = RESOLVED
+        .lock()
+        .ok()
+        .and_then(|resolved| resolved.get(value).cloned());
+    if let Some(cached) = cached {
+        return Ok(cached);
+    }
+    let resolved = if value.starts_with(SSM_PREFIX) {
+        parameter(&parameter_name(&value[SSM_PREFIX.len()..]))?
+    } else {
+        secret(&value[SECRETS_MANAGER_PREFIX.len()..])?
+    };
+    if let Ok(mut cache) = RESOLVED.lock() {
+        cache.insert(value.to_owned(), resolved.clone());
+    }
+    Ok(resolved)
+}
+
+/// a referenced parameter's name, rooted if it's in a hierarchy
+fn parameter_name(reference: &str) -> String {
+    if reference.contains('/') && !reference.starts_with('/') {
+        format!("/{}", reference)
+    } else {
+        reference.to_owned()
+    }
+}
+
+fn parameter(name: &str) -> Result<String, String> {
+    SsmClient::new(Region::default())
+        .get_parameter(GetParameterRequest {
+            name: name.to_owned(),
+            with_decryption: Some(true),
+        })
+        .sync()
+        .map_err(|err| format!("failed to read parameter {}: {}", name, err))?
+        .parameter
+        .and_then(|parameter| parameter.value)
+        .ok_or_else(|| format!("parameter {} has no value", name))
+}
+
+fn secret(id: &str) -> Result<String, String> {
+    SecretsManagerClient::new(Region::default())
+        .get_secret_value(GetSecretValueRequest {
+            secret_id: id.to_owned(),
+            ..Default::default()
+        })
+        .sync()
+        .map_err(|err| format!("failed to read secret {}: {}", id, err))?
+        .secret_string
+        .ok_or_else(|| format!("secret {} has no string value", id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hierarchical_parameters_are_rooted() {
+        assert_eq!(parameter_name("slack-standup/name"), "/slack-standup/name");
+        assert_eq!(parameter_name("/slack-standup/name"), "/slack-standup/name");
+        assert_eq!(parameter_name("name"), "name");
+    }
+}
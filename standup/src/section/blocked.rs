@@ -0,0 +1,96 @@
+//! Jira issues which can't move forward
+
+// Third party
+use goji::Issue;
+use serde_json::Value;
+
+// Ours
+use super::jira::{self, IssueRecord};
+use super::{Context, Section};
+use error::FetchError;
+use jql::Query;
+use metrics::Metric;
+use render::{escape, Block};
+use retry::Retry;
+use users::JiraUser;
+
+pub struct BlockedSection;
+
+impl Section for BlockedSection {
+    type Data = Vec<Issue>;
+
+    fn title(&self) -> String {
+        "🚫 Blocked".into()
+    }
+
+    fn fetch(&self, context: &Context) -> Result<Vec<Issue>, FetchError> {
+        let config = &context.config;
+        let mut blocked = vec![Query::new().eq("Flagged", "Impediment")];
+        if !config.jira_blocked_labels.is_empty() {
+            blocked.push(Query::new().is_in("labels", &config.jira_blocked_labels));
+        }
+        if !config.jira_blocked_statuses.is_empty() {
+            blocked.push(Query::new().is_in("status", &config.jira_blocked_statuses));
+        }
+        jira::search(
+            &jira::client(config)?,
+            &Query::new()
+                .is_in("project", &config.jira_projects)
+                .is_empty("resolution")
+                .any(blocked)
+                .order_by("updated"),
+            Retry::from(config),
+        )
+    }
+
+    fn record(&self, issues: &Vec<Issue>) -> Value {
+        json!(issues.iter().map(IssueRecord::from).collect::<Vec<_>>())
+    }
+
+    fn metrics(&self, issues: &Vec<Issue>) -> Vec<Metric> {
+        vec![Metric::count("BlockedIssues", issues.len())]
+    }
+
+    fn summary(&self, issues: &Vec<Issue>, _: &Context) -> Vec<String> {
+        match issues.len() {
+            0 => Vec::new(),
+            blocked => vec![format!("🚫 {} blocked", blocked)],
+        }
+    }
+
+    fn render(&self, issues: Vec<Issue>, context: &Context) -> Vec<Block> {
+        if issues.is_empty() {
+            return vec![Block::section(
+                context.templates.render("blocked.empty", &json!({})),
+            )];
+        }
+        issues
+            .into_iter()
+            .flat_map(|issue| {
+                let assignee = issue
+                    .field::<JiraUser>("assignee")
+                    .and_then(|user| user.ok())
+                    .map(|user| context.users.mention(&user))
+                    .unwrap_or_else(|| "@nobody".into());
+                let status = issue
+                    .status()
+                    .map(|status| status.name)
+                    .unwrap_or_else(|| "Unknown Status".into());
+                vec![
+                    Block::section(context.templates.render(
+                        "blocked.issue",
+                        &json!({
+                            "url": jira::browse_url(&context.config.jira_host, &issue.key),
+                            "key": issue.key,
+                            "summary": escape(&issue.summary().unwrap_or_else(|| "no summary".into())),
+                        }),
+                    )),
+                    Block::context(context.templates.render(
+                        "blocked.owner",
+                        &json!({ "assignee": assignee, "status": status }),
+                    )),
+                ]
+            })
+            .collect()
+    }
+}
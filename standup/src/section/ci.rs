@@ -0,0 +1,299 @@
+//! Main branch build health from GitHub Actions or CircleCI
+
+// Std lib
+use std::collections::BTreeMap;
+
+// Third party
+use chrono::{DateTime, FixedOffset};
+use serde_json::Value;
+
+// Ours
+use super::{warning, Context, Section};
+use circleci::CircleCi;
+use config::CiProvider;
+use error::FetchError;
+use github::GitHub;
+use metrics::Metric;
+use render::Block;
+use retry::Retry;
+
+pub struct CiSection;
+
+pub struct BuildHealth {
+    branch: String,
+    /// workflows of each configured `owner/name` repository
+    repos: Vec<(String, Result<Vec<Workflow>, FetchError>)>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Workflow {
+    name: String,
+    /// the latest completed run
+    url: String,
+    passing: bool,
+    /// when the failures the workflow is currently on began
+    red_since: Option<DateTime<FixedOffset>>,
+}
+
+/// a run which either passed or failed
+struct Run {
+    passed: bool,
+    url: String,
+    created_at: DateTime<FixedOffset>,
+}
+
+impl Section for CiSection {
+    type Data = BuildHealth;
+
+    fn title(&self) -> String {
+        "🏗 Build Health".into()
+    }
+
+    fn fetch(&self, context: &Context) -> Result<BuildHealth, FetchError> {
+        let config = &context.config;
+        let repos = if config.ci_repos.is_empty() {
+            &config.github_repos
+        } else {
+            &config.ci_repos
+        };
+        if repos.is_empty() {
+            return Err(FetchError::new(
+                "CI",
+                "no ci_repos or github_repos configured",
+            ));
+        }
+        let branch = &config.ci_branch;
+        let repos = match config.ci_provider {
+            CiProvider::GitHubActions => {
+                let token = config
+                    .github_token
+                    .clone()
+                    .ok_or_else(|| FetchError::new("GitHub", "no github_token configured"))?;
+                let github = GitHub::new(token, config.http_client(), Retry::from(config));
+                repos
+                    .iter()
+                    .map(|repo| (repo.clone(), github_workflows(&github, repo, branch)))
+                    .collect()
+            }
+            CiProvider::CircleCi => {
+                let token = config
+                    .circleci_token
+                    .clone()
+                    .ok_or_else(|| FetchError::new("CircleCI", "no circleci_token configured"))?;
+                let circleci = CircleCi::new(token, config.http_client(), Retry::from(config));
+                repos
+                    .iter()
+                    .map(|repo| (repo.clone(), circleci_workflows(&circleci, repo, branch)))
+                    .collect()
+            }
+        };
+        Ok(BuildHealth {
+            branch: branch.clone(),
+            repos,
+        })
+    }
+
+    fn record(&self, health: &BuildHealth) -> Value {
+        json!({
+            "branch": health.branch,
+            "repos": health
+                .repos
+                .iter()
+                .filter_map(|(repo, workflows)| {
+                    workflows.as_ref().ok().map(|workflows| (repo, workflows))
+                })
+                .collect::<BTreeMap<_, _>>(),
+        })
+    }
+
+    fn metrics(&self, health: &BuildHealth) -> Vec<Metric> {
+        vec![Metric::count("FailingWorkflows", failing(health))]
+    }
+
+    fn summary(&self, health: &BuildHealth, _: &Context) -> Vec<String> {
+        match failing(health) {
+            0 => Vec::new(),
+            1 => vec!["🔴 1 failing build".into()],
+            failing => vec![format!("🔴 {} failing builds", failing)],
+        }
+    }
+
+    fn render(&self, health: BuildHealth, context: &Context) -> Vec<Block> {
+        let templates = &context.templates;
+        let red_hours = context.config.ci_red_hours;
+        let mut blocks = Vec::new();
+        let mut long_red = Vec::new();
+        for (repository, workflows) in health.repos {
+            let workflows = match workflows {
+                Ok(workflows) => workflows,
+                Err(err) => {
+                    blocks.push(warning(&err));
+                    continue;
+                }
+            };
+            let failing = workflows
+                .iter()
+                .filter(|workflow| !workflow.passing)
+                .map(|workflow| format!("<{}|{}>", workflow.url, workflow.name))
+                .collect::<Vec<_>>();
+            blocks.push(Block::section(templates.render(
+                "ci.repo",
+                &json!({
+                    "repository": repository,
+                    "branch": health.branch,
+                    "passing": failing.is_empty(),
+                    "failing": failing.join(", "),
+                }),
+            )));
+            for workflow in workflows {
+                let hours = match workflow.red_since {
+                    Some(since) => context.now.signed_duration_since(since).num_hours(),
+                    None => continue,
+                };
+                if hours >= red_hours {
+                    long_red.push(templates.render(
+                        "ci.red",
+                        &json!({
+                            "url": workflow.url,
+                            "repository": repository,
+                            "workflow": workflow.name,
+                            "hours": hours,
+                        }),
+                    ));
+                }
+            }
+        }
+        if !long_red.is_empty() {
+            blocks.push(Block::section(
+                templates.render("ci.red.heading", &json!({ "hours": red_hours })),
+            ));
+            blocks.extend(long_red.into_iter().map(Block::section));
+        }
+        blocks
+    }
+}
+
+/// number of workflows currently failing
+fn failing(health: &BuildHealth) -> usize {
+    health
+        .repos
+        .iter()
+        .filter_map(|(_, workflows)| workflows.as_ref().ok())
+        .flat_map(|workflows| workflows.iter())
+        .filter(|workflow| !workflow.passing)
+        .count()
+}
+
+fn github_workflows(
+    github: &GitHub,
+    repo: &str,
+    branch: &str,
+) -> Result<Vec<Workflow>, FetchError> {
+    let mut runs = BTreeMap::<String, Vec<Run>>::new();
+    for run in github.workflow_runs(repo, branch)? {
+        let passed = match run.conclusion.as_ref().map(String::as_str) {
+            Some("success") => true,
+            Some("failure") | Some("timed_out") | Some("startup_failure") => false,
+            // cancelled and skipped runs say nothing about the build
+            _ => continue,
+        };
+        runs.entry(run.name).or_insert_with(Vec::new).push(Run {
+            passed,
+            url: run.html_url,
+            created_at: run.created_at,
+        });
+    }
+    Ok(runs
+        .into_iter()
+        .filter_map(|(name, runs)| workflow(name, &runs))
+        .collect())
+}
+
+fn circleci_workflows(
+    circleci: &CircleCi,
+    repo: &str,
+    branch: &str,
+) -> Result<Vec<Workflow>, FetchError> {
+    let mut workflows = Vec::new();
+    for name in circleci.workflows(repo, branch)? {
+        let runs = circleci
+            .workflow_runs(repo, &name, branch)?
+            .into_iter()
+            .filter_map(|run| {
+                let passed = match run.status.as_str() {
+                    "success" => true,
+                    "failed" | "error" | "infrastructure_fail" | "timedout" => false,
+                    _ => return None,
+                };
+                Some(Run {
+                    passed,
+                    url: run.url(),
+                    created_at: run.created_at,
+                })
+            })
+            .collect::<Vec<_>>();
+        workflows.extend(workflow(name, &runs));
+    }
+    Ok(workflows)
+}
+
+/// a workflow's health from its runs, newest first
+fn workflow(name: String, runs: &[Run]) -> Option<Workflow> {
+    let latest = runs.first()?;
+    Some(Workflow {
+        name,
+        url: latest.url.clone(),
+        passing: latest.passed,
+        red_since: runs
+            .iter()
+            .take_while(|run| !run.passed)
+            .last()
+            .map(|run| run.created_at),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn run(passed: bool, created_at: &str) -> Run {
+        Run {
+            passed,
+            url: format!("https://ci/{}", created_at),
+            created_at: DateTime::parse_from_rfc3339(created_at).unwrap(),
+        }
+    }
+
+    #[test]
+    fn workflows_are_red_since_their_first_consecutive_failure() {
+        let runs = vec![
+            run(false, "2019-01-07T15:00:00Z"),
+            run(false, "2019-01-07T09:00:00Z"),
+            run(true, "2019-01-06T09:00:00Z"),
+            run(false, "2019-01-05T09:00:00Z"),
+        ];
+        let workflow = workflow("ci".into(), &runs).unwrap();
+        assert!(!workflow.passing);
+        assert_eq!(workflow.url, "https://ci/2019-01-07T15:00:00Z");
+        assert_eq!(
+            workflow.red_since,
+            Some(DateTime::parse_from_rfc3339("2019-01-07T09:00:00Z").unwrap())
+        );
+        assert_eq!(
+            workflow.red_since.map(|since| runs[0].created_at - since),
+            Some(Duration::hours(6))
+        );
+    }
+
+    #[test]
+    fn passing_workflows_are_not_red() {
+        let runs = vec![
+            run(true, "2019-01-07T15:00:00Z"),
+            run(false, "2019-01-07T09:00:00Z"),
+        ];
+        let workflow = workflow("ci".into(), &runs).unwrap();
+        assert!(workflow.passing);
+        assert_eq!(workflow.red_since, None);
+    }
+}
@@ -0,0 +1,239 @@
+//! What was deployed since the last standup, from GitHub deployments or the
+//! pipeline's deploy log
+
+// Third party
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+
+// Ours
+use super::{warning, Context, Section};
+use config::{Config, DeploySource};
+use deploys::{Deploy, DeployLog};
+use error::FetchError;
+use github::GitHub;
+use metrics::Metric;
+use render::{escape, Block};
+use retry::Retry;
+
+pub struct DeploysSection;
+
+pub struct Deploys {
+    /// newest first
+    pub deploys: Vec<Deploy>,
+    /// repositories whose deployments couldn't be loaded
+    pub errors: Vec<FetchError>,
+}
+
+impl Section for DeploysSection {
+    type Data = Deploys;
+
+    fn title(&self) -> String {
+        "🚀 Deploys".into()
+    }
+
+    fn fetch(&self, context: &Context) -> Result<Deploys, FetchError> {
+        let config = &context.config;
+        // the lookback window stands in for the first standup
+        let since = context
+            .previous
+            .as_ref()
+            .map(|previous| previous.posted_at)
+            .unwrap_or_else(|| context.since_midnight().with_timezone(&Utc));
+        deploys_since(config, since)
+    }
+
+    fn record(&self, deploys: &Deploys) -> Value {
+        json!({ "deploys": deploys.deploys })
+    }
+
+    fn metrics(&self, deploys: &Deploys) -> Vec<Metric> {
+        vec![Metric::count("Deploys", deploys.deploys.len())]
+    }
+
+    fn summary(&self, deploys: &Deploys, _: &Context) -> Vec<String> {
+        match deploys.deploys.len() {
+            0 => Vec::new(),
+            1 => vec!["🚀 1 deploy".into()],
+            count => vec![format!("🚀 {} deploys", count)],
+        }
+    }
+
+    fn render(&self, deploys: Deploys, context: &Context) -> Vec<Block> {
+        let templates = &context.templates;
+        let mut blocks = deploys.errors.iter().map(warning).collect::<Vec<_>>();
+        if deploys.deploys.is_empty() {
+            blocks.push(Block::section(
+                templates.render("deploys.empty", &json!({})),
+            ));
+        }
+        blocks.extend(deploys.deploys.into_iter().map(|deploy| {
+            Block::fields(vec![
+                templates.render(
+                    "deploys.deploy",
+                    &json!({
+                        "service": escape(&deploy.service),
+                        "version": escape(&deploy.version),
+                        "url": deploy.url,
+                        "deployer": deploy.deployer.map(|deployer| escape(&deployer)),
+                    }),
+                ),
+                templates.render(
+                    "deploys.deployed_at",
+                    &json!({
+                        "time": deploy
+                            .deployed_at
+                            .with_timezone(&context.config.timezone)
+                            .format("%a %-I:%M%P")
+                            .to_string(),
+                    }),
+                ),
+            ])
+        }));
+        blocks
+    }
+}
+
+/// deploys to the configured environment since a given time, newest first
+pub fn deploys_since(config: &Config, since: DateTime<Utc>) -> Result<Deploys, FetchError> {
+    let environment = &config.deploy_environment;
+    match config.deploy_source {
+        DeploySource::GitHub => {
+            let token = config
+                .github_token
+                .clone()
+                .ok_or_else(|| FetchError::new("GitHub", "no github_token configured"))?;
+            let repos = if config.deploy_repos.is_empty() {
+                &config.github_repos
+            } else {
+                &config.deploy_repos
+            };
+            if repos.is_empty() {
+                return Err(FetchError::new(
+                    "GitHub",
+                    "no deploy_repos or github_repos configured",
+                ));
+            }
+            let github = GitHub::new(token, config.http_client(), Retry::from(config));
+            let mut deploys = Vec::new();
+            let mut errors = Vec::new();
+            for repo in repos {
+                match github_deploys(&github, repo, environment, since) {
+                    Ok(repo_deploys) => deploys.extend(repo_deploys),
+                    Err(err) => errors.push(err),
+                }
+            }
+            deploys.sort_by(|a, b| b.deployed_at.cmp(&a.deployed_at));
+            Ok(Deploys { deploys, errors })
+        }
+        DeploySource::DynamoDb => {
+            let table = config
+                .deploy_table
+                .clone()
+                .ok_or_else(|| FetchError::new("the deploy log", "no deploy_table configured"))?;
+            Ok(Deploys {
+                deploys: DeployLog::new(table).since(environment, since)?,
+                errors: Vec::new(),
+            })
+        }
+    }
+}
+
+/// successful deployments of an `owner/name` repository since a given time, newest first
+fn github_deploys(
+    github: &GitHub,
+    repo: &str,
+    environment: &str,
+    since: DateTime<Utc>,
+) -> Result<Vec<Deploy>, FetchError> {
+    let mut deploys = Vec::new();
+    for deployment in github.deployments(repo, environment)? {
+        if deployment.created_at.with_timezone(&Utc) < since {
+            break;
+        }
+        let status = match github
+            .deployment_statuses(repo, deployment.id)?
+            .into_iter()
+            .next()
+        {
+            Some(status) => status,
+            None => continue,
+        };
+        if status.state != "success" {
+            continue;
+        }
+        let sha = deployment.sha.get(..7).unwrap_or(&deployment.sha);
+        let version = if deployment.reference == deployment.sha {
+            sha.to_owned()
+        } else {
+            format!("{} ({})", deployment.reference, sha)
+        };
+        deploys.push(Deploy {
+            service: repo.rsplit('/').next().unwrap_or(repo).to_owned(),
+            version,
+            deployed_at: deployment.created_at,
+            url: status
+                .environment_url
+                .filter(|url| !url.is_empty())
+                .or(status.log_url)
+                .filter(|url| !url.is_empty())
+                .or_else(|| {
+                    Some(format!(
+                        "https://github.com/{}/commit/{}",
+                        repo, deployment.sha
+                    ))
+                }),
+            deployer: deployment.creator.map(|creator| creator.login),
+        });
+    }
+    Ok(deploys)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::{self, mock, Matcher};
+    use reqwest::Client;
+
+    #[test]
+    fn successful_deployments_since_are_listed() {
+        let _deployments = mock("GET", "/repos/meetup/api/deployments")
+            .match_query(Matcher::UrlEncoded(
+                "environment".into(),
+                "production".into(),
+            ))
+            .with_body(
+                r#"[
+                    {"id":3,"sha":"ccccccccc","ref":"v1.3.0","created_at":"2019-01-07T16:00:00Z","creator":{"login":"dana"}},
+                    {"id":2,"sha":"bbbbbbbbb","ref":"bbbbbbbbb","created_at":"2019-01-07T15:00:00Z","creator":null},
+                    {"id":1,"sha":"aaaaaaaaa","ref":"v1.2.0","created_at":"2019-01-04T15:00:00Z","creator":null}
+                ]"#,
+            )
+            .create();
+        let _succeeded = mock("GET", "/repos/meetup/api/deployments/3/statuses")
+            .match_query(Matcher::Any)
+            .with_body(r#"[{"state":"success","environment_url":"https://api.meetup.com"}]"#)
+            .create();
+        let _failed = mock("GET", "/repos/meetup/api/deployments/2/statuses")
+            .match_query(Matcher::Any)
+            .with_body(r#"[{"state":"failure"},{"state":"in_progress"}]"#)
+            .create();
+        let github = GitHub::new(
+            "secret".into(),
+            Client::new(),
+            Retry {
+                attempts: 1,
+                backoff_ms: 0,
+            },
+        )
+        .with_api(mockito::server_url());
+        let since = DateTime::parse_from_rfc3339("2019-01-07T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let deploys = github_deploys(&github, "meetup/api", "production", since).unwrap();
+        assert_eq!(deploys.len(), 1);
+        assert_eq!(deploys[0].service, "api");
+        assert_eq!(deploys[0].version, "v1.3.0 (ccccccc)");
+        assert_eq!(deploys[0].url, Some("https://api.meetup.com".into()));
+        assert_eq!(deploys[0].deployer, Some("dana".into()));
+    }
+}
@@ -0,0 +1,318 @@
+//! A weekly snapshot of the four DORA metrics
+//!
+//! - deployment frequency, from the configured deploy source
+//! - lead time for changes, from when pull requests merged this week were
+//!   opened to the first deploy of their repository after they merged
+//! - change failure rate, the share of deploys followed by an incident
+//!   within `FAILURE_WINDOW_HOURS`
+//! - time to restore, pagerduty's mean time to resolve
+//!
+//! see https://dora.dev/guides/dora-metrics-four-keys/
+
+// Third party
+use chrono::{DateTime, Duration, FixedOffset, Utc};
+use serde_json::Value;
+
+// Ours
+use super::deploys::deploys_since;
+use super::flow::median;
+use super::weather::duration;
+use super::{warning, Context, Section};
+use breaker::Breaker;
+use cache::Cache;
+use config::{Config, IncidentSource};
+use deploys::Deploy;
+use error::FetchError;
+use github::{self, GitHub, PullRequest};
+use metrics::Metric;
+use opsgenie::Opsgenie;
+use pagerduty::{PagerDuty, Scope};
+use render::Block;
+use retry::Retry;
+
+/// days the snapshot covers
+const DAYS: i64 = 7;
+
+/// hours after a deploy an incident counts against it
+const FAILURE_WINDOW_HOURS: i64 = 1;
+
+pub struct DoraSection;
+
+pub struct Dora {
+    deploys: Vec<Deploy>,
+    /// seconds from opening changes to deploying them
+    lead_times: Result<Vec<f64>, FetchError>,
+    /// when incidents were created
+    incidents: Result<Vec<DateTime<FixedOffset>>, FetchError>,
+    /// mean seconds to resolve incidents, where known
+    time_to_restore: Result<Option<f64>, FetchError>,
+}
+
+impl Section for DoraSection {
+    type Data = Dora;
+
+    fn title(&self) -> String {
+        "📈 DORA".into()
+    }
+
+    fn fetch(&self, context: &Context) -> Result<Dora, FetchError> {
+        let config = &context.config;
+        let since = context.now.with_timezone(&Utc) - Duration::days(DAYS);
+        let deploys = deploys_since(config, since)?.deploys;
+        let lead_times = merged(config, since).map(|merged| lead_times(&merged, &deploys));
+        let (incidents, time_to_restore) = incidents(context, since);
+        Ok(Dora {
+            deploys,
+            lead_times,
+            incidents,
+            time_to_restore,
+        })
+    }
+
+    fn record(&self, dora: &Dora) -> Value {
+        json!({
+            "deploys": dora.deploys.len(),
+            "lead_time": dora.lead_times.as_ref().ok().and_then(|lead_times| median(lead_times)),
+            "change_failure_rate": dora
+                .incidents
+                .as_ref()
+                .ok()
+                .and_then(|incidents| failure_rate(&dora.deploys, incidents)),
+            "time_to_restore": dora.time_to_restore.as_ref().ok(),
+        })
+    }
+
+    fn metrics(&self, dora: &Dora) -> Vec<Metric> {
+        let mut metrics = Vec::new();
+        if let Some(lead_time) = dora
+            .lead_times
+            .as_ref()
+            .ok()
+            .and_then(|lead_times| median(lead_times))
+        {
+            metrics.push(Metric::milliseconds(
+                "LeadTime",
+                (lead_time * 1000.0) as u64,
+            ));
+        }
+        metrics
+    }
+
+    fn render(&self, dora: Dora, _: &Context) -> Vec<Block> {
+        let mut blocks = Vec::new();
+        blocks.push(Block::fields(vec![
+            format!(
+                "*Deployment frequency*\n{:.1} a day",
+                dora.deploys.len() as f64 / DAYS as f64
+            ),
+            format!(
+                "*Lead time for changes*\n{}",
+                dora.lead_times
+                    .as_ref()
+                    .ok()
+                    .and_then(|lead_times| median(lead_times))
+                    .map(duration)
+                    .unwrap_or_else(|| "n/a".into())
+            ),
+            format!(
+                "*Change failure rate*\n{}",
+                dora.incidents
+                    .as_ref()
+                    .ok()
+                    .and_then(|incidents| failure_rate(&dora.deploys, incidents))
+                    .map(|rate| format!("{:.0}%", rate * 100.0))
+                    .unwrap_or_else(|| "n/a".into())
+            ),
+            format!(
+                "*Time to restore*\n{}",
+                dora.time_to_restore
+                    .as_ref()
+                    .ok()
+                    .and_then(|seconds| *seconds)
+                    .map(duration)
+                    .unwrap_or_else(|| "n/a".into())
+            ),
+        ]));
+        blocks.extend(
+            dora.lead_times
+                .err()
+                .into_iter()
+                .chain(dora.incidents.err())
+                .chain(dora.time_to_restore.err())
+                .map(|err| warning(&err)),
+        );
+        blocks
+    }
+}
+
+/// pull requests merged since a time
+fn merged(config: &Config, since: DateTime<Utc>) -> Result<Vec<PullRequest>, FetchError> {
+    let token = config
+        .github_token
+        .clone()
+        .ok_or_else(|| FetchError::new("GitHub", "no github_token configured"))?;
+    let repos = if config.deploy_repos.is_empty() {
+        &config.github_repos
+    } else {
+        &config.deploy_repos
+    };
+    let scope = github::scope(repos, config.github_org.as_ref().map(String::as_str));
+    GitHub::new(token, config.http_client(), Retry::from(config))
+        .merged(&scope, &since.format("%F").to_string())
+}
+
+/// seconds from opening each pull request to the first deploy of its
+/// repository after it merged, for those deployed
+fn lead_times(merged: &[PullRequest], deploys: &[Deploy]) -> Vec<f64> {
+    merged
+        .iter()
+        .filter_map(|pull| {
+            let (opened, merged) = (pull.created_at?, pull.closed_at?);
+            let repo = pull.repository();
+            let service = repo.rsplit('/').next().unwrap_or(repo);
+            deploys
+                .iter()
+                .filter(|deploy| deploy.service == service && deploy.deployed_at >= merged)
+                .map(|deploy| deploy.deployed_at)
+                .min()
+                .map(|deployed| deployed.signed_duration_since(opened).num_seconds() as f64)
+        })
+        .collect()
+}
+
+/// when incidents since a time were created, and how long they took to resolve
+fn incidents(
+    context: &Context,
+    since: DateTime<Utc>,
+) -> (
+    Result<Vec<DateTime<FixedOffset>>, FetchError>,
+    Result<Option<f64>, FetchError>,
+) {
+    let config = &context.config;
+    if config.incident_source == IncidentSource::Opsgenie {
+        let api_key = match config.opsgenie_api_key.clone() {
+            Some(api_key) => api_key,
+            None => {
+                return (
+                    Err(FetchError::new(
+                        "Opsgenie",
+                        "no opsgenie_api_key configured",
+                    )),
+                    Ok(None),
+                )
+            }
+        };
+        let mut opsgenie = Opsgenie::new(api_key, config.http_client(), Retry::from(config));
+        if let Some(ref api) = config.opsgenie_api_url {
+            opsgenie = opsgenie.with_api(api.clone());
+        }
+        let since = since.timestamp_millis();
+        let created = opsgenie
+            .open_alerts(&config.opsgenie_teams, since)
+            .and_then(|mut alerts| {
+                alerts.extend(opsgenie.closed_alerts(&config.opsgenie_teams, since)?);
+                Ok(alerts)
+            })
+            .map(|alerts| {
+                alerts
+                    .into_iter()
+                    .filter_map(|alert| alert.created_at)
+                    .collect()
+            });
+        // opsgenie's analytics aren't available through its api
+        return (created, Ok(None));
+    }
+    let pagerduty = PagerDuty::new(
+        config.pd_token.clone(),
+        config.http_client(),
+        Retry::from(config),
+    )
+    .with_cache(Cache::from(config))
+    .with_breaker(Breaker::from(config));
+    let scope = Scope {
+        team_ids: &config.pd_team_ids,
+        service_ids: &config.pd_service_ids,
+        urgencies: &config.pd_urgencies,
+    };
+    let start = since.to_rfc3339();
+    let created = pagerduty
+        .open_incidents(&scope, &start)
+        .and_then(|mut incidents| {
+            incidents.extend(pagerduty.resolved_incidents(&scope, &start)?);
+            Ok(incidents)
+        })
+        .map(|incidents| {
+            incidents
+                .into_iter()
+                .filter_map(|incident| incident.created_at)
+                .collect()
+        });
+    let time_to_restore = pagerduty
+        .incident_metrics(&scope, &start, &context.now.to_rfc3339())
+        .map(|metrics| metrics.mean_seconds_to_resolve);
+    (created, time_to_restore)
+}
+
+/// the share of deploys followed by an incident, if there were any deploys
+fn failure_rate(deploys: &[Deploy], incidents: &[DateTime<FixedOffset>]) -> Option<f64> {
+    if deploys.is_empty() {
+        return None;
+    }
+    let window = Duration::hours(FAILURE_WINDOW_HOURS);
+    let failed = deploys
+        .iter()
+        .filter(|deploy| {
+            incidents.iter().any(|created| {
+                *created >= deploy.deployed_at && *created < deploy.deployed_at + window
+            })
+        })
+        .count();
+    Some(failed as f64 / deploys.len() as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+
+    fn deploy(service: &str, deployed_at: &str) -> Deploy {
+        Deploy {
+            service: service.into(),
+            version: "v1".into(),
+            deployed_at: DateTime::parse_from_rfc3339(deployed_at).unwrap(),
+            url: None,
+            deployer: None,
+        }
+    }
+
+    #[test]
+    fn deploys_followed_by_incidents_failed() {
+        let deploys = vec![
+            deploy("api", "2019-01-07T15:00:00Z"),
+            deploy("api", "2019-01-08T15:00:00Z"),
+        ];
+        let incidents = vec![DateTime::parse_from_rfc3339("2019-01-07T15:30:00Z").unwrap()];
+        assert_eq!(failure_rate(&deploys, &incidents), Some(0.5));
+        assert_eq!(failure_rate(&[], &incidents), None);
+    }
+
+    #[test]
+    fn changes_lead_to_the_next_deploy_of_their_repository() {
+        let merged = serde_json::from_value::<Vec<PullRequest>>(json!([{
+            "number": 1,
+            "title": "Add search",
+            "html_url": "https://github.com/meetup/api/pull/1",
+            "user": { "login": "dana" },
+            "repository_url": "https://api.github.com/repos/meetup/api",
+            "created_at": "2019-01-07T10:00:00Z",
+            "closed_at": "2019-01-07T14:00:00Z"
+        }]))
+        .unwrap();
+        let deploys = vec![
+            deploy("api", "2019-01-08T12:00:00Z"),
+            deploy("api", "2019-01-07T16:00:00Z"),
+            deploy("web", "2019-01-07T15:00:00Z"),
+        ];
+        assert_eq!(lead_times(&merged, &deploys), vec![6.0 * 3600.0]);
+    }
+}
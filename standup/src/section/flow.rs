@@ -0,0 +1,239 @@
+//! Throughput and cycle time of done Jira issues, this week against last
+//!
+//! An issue's cycle runs from when it first moved into an in flight status,
+//! from its changelog, to when it was resolved. Without configured in flight
+//! statuses, those jira categorizes as in progress are. Issues which skipped
+//! straight to done count from when they were created.
+
+// Std lib
+use std::cmp::Ordering;
+
+// Third party
+use chrono::{DateTime, Duration, FixedOffset};
+use chrono_tz::Tz;
+use serde_json::Value;
+
+// Ours
+use super::jira::{self, Expanded, JIRA_TIMESTAMP};
+use super::{Context, Section};
+use error::FetchError;
+use jql::Query;
+use log;
+use metrics::Metric;
+use render::Block;
+use retry::Retry;
+use workflow::{self, Category};
+
+/// days in each period compared
+const WEEK_DAYS: i64 = 7;
+
+pub struct FlowSection;
+
+/// How a week's work flowed
+#[derive(Serialize, Debug, Default, PartialEq)]
+pub struct Week {
+    /// issues closed
+    pub throughput: usize,
+    /// median days from starting to closing issues
+    pub cycle_time: Option<f64>,
+}
+
+pub struct Flow {
+    this_week: Week,
+    last_week: Week,
+}
+
+impl Section for FlowSection {
+    type Data = Flow;
+
+    fn title(&self) -> String {
+        "⏳ Flow".into()
+    }
+
+    fn fetch(&self, context: &Context) -> Result<Flow, FetchError> {
+        let config = &context.config;
+        let week_ago = context.now - Duration::days(WEEK_DAYS);
+        let two_weeks_ago = context.now - Duration::days(2 * WEEK_DAYS);
+        let jira = jira::client(config)?;
+        let retry = Retry::from(config);
+        let closed = jira::search_with_changelogs(
+            &jira,
+            &Query::new()
+                .is_in("project", &config.jira_projects)
+                .eq("statusCategory", "Done")
+                .at_least("resolutiondate", &two_weeks_ago.format("%F").to_string()),
+            retry,
+        )?;
+        let in_flight = if config.jira_in_flight_statuses.is_empty() {
+            workflow::discover(&jira, retry, &config.jira_projects)
+                .map(|workflow| workflow.statuses(Category::InProgress))
+                .unwrap_or_else(|err| {
+                    log::warn(
+                        "failed to discover workflow statuses",
+                        json!({ "error": err.to_string() }),
+                    );
+                    Vec::new()
+                })
+        } else {
+            config.jira_in_flight_statuses.clone()
+        };
+        let cycles = closed
+            .iter()
+            .filter_map(|expanded| cycle(expanded, &in_flight))
+            .collect::<Vec<_>>();
+        let week = |from: DateTime<Tz>, until: DateTime<Tz>| {
+            summarize(
+                cycles
+                    .iter()
+                    .filter(|(resolved, _)| *resolved >= from && *resolved < until)
+                    .map(|(_, days)| *days)
+                    .collect(),
+            )
+        };
+        Ok(Flow {
+            this_week: week(week_ago, context.now),
+            last_week: week(two_weeks_ago, week_ago),
+        })
+    }
+
+    fn record(&self, flow: &Flow) -> Value {
+        json!({
+            "this_week": flow.this_week,
+            "last_week": flow.last_week,
+        })
+    }
+
+    fn metrics(&self, flow: &Flow) -> Vec<Metric> {
+        let mut metrics = vec![Metric::count("Throughput", flow.this_week.throughput)];
+        if let Some(days) = flow.this_week.cycle_time {
+            metrics.push(Metric::milliseconds(
+                "CycleTime",
+                (days * 86_400_000.0) as u64,
+            ));
+        }
+        metrics
+    }
+
+    fn summary(&self, flow: &Flow, _: &Context) -> Vec<String> {
+        flow.this_week
+            .cycle_time
+            .map(|days| {
+                format!(
+                    "⏳ cycle time {:.1}d{}",
+                    days,
+                    flow.last_week
+                        .cycle_time
+                        .map(|last| format!(", {} vs last week", trend(days - last, "d")))
+                        .unwrap_or_default()
+                )
+            })
+            .into_iter()
+            .collect()
+    }
+
+    fn render(&self, flow: Flow, _: &Context) -> Vec<Block> {
+        let (this, last) = (flow.this_week, flow.last_week);
+        vec![Block::fields(vec![
+            format!(
+                "*Throughput*\n{} closed · {} vs last week",
+                this.throughput,
+                trend(this.throughput as f64 - last.throughput as f64, "")
+            ),
+            format!(
+                "*Cycle time*\n{}",
+                match (this.cycle_time, last.cycle_time) {
+                    (Some(days), Some(last)) => {
+                        format!("{:.1}d · {} vs last week", days, trend(days - last, "d"))
+                    }
+                    (Some(days), None) => format!("{:.1}d", days),
+                    (None, _) => "Nothing closed this week".to_owned(),
+                }
+            ),
+        ])]
+    }
+}
+
+/// when an issue was resolved, and the days it took since it was started
+fn cycle(expanded: &Expanded, in_flight: &[String]) -> Option<(DateTime<FixedOffset>, f64)> {
+    let resolved = expanded
+        .issue
+        .field::<String>("resolutiondate")
+        .and_then(|resolved| resolved.ok())
+        .and_then(|resolved| DateTime::parse_from_str(&resolved, JIRA_TIMESTAMP).ok())?;
+    let started = expanded
+        .status_changes()
+        .into_iter()
+        .find(|(_, to)| {
+            to.as_ref()
+                .map(|to| {
+                    in_flight
+                        .iter()
+                        .any(|status| status.eq_ignore_ascii_case(to))
+                })
+                .unwrap_or(false)
+        })
+        .map(|(changed, _)| changed)
+        .or_else(|| expanded.created())?;
+    let days = resolved.signed_duration_since(started).num_seconds() as f64 / 86_400.0;
+    Some((resolved, days.max(0.0)))
+}
+
+fn summarize(days: Vec<f64>) -> Week {
+    Week {
+        throughput: days.len(),
+        cycle_time: median(&days),
+    }
+}
+
+/// the middle value, or the mean of the middle two
+pub fn median(values: &[f64]) -> Option<f64> {
+    let mut values = values.to_vec();
+    values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+    let middle = values.len() / 2;
+    match values.len() {
+        0 => None,
+        len if len % 2 == 0 => Some((values[middle - 1] + values[middle]) / 2.0),
+        _ => Some(values[middle]),
+    }
+}
+
+/// a change with its direction, i.e. `▼0.8d`
+fn trend(delta: f64, unit: &str) -> String {
+    if delta.abs() < 0.05 {
+        "no change".into()
+    } else if delta > 0.0 {
+        format!("▲{}{}", round(delta), unit)
+    } else {
+        format!("▼{}{}", round(-delta), unit)
+    }
+}
+
+/// rounded to a tenth
+fn round(value: f64) -> f64 {
+    (value * 10.0).round() / 10.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn weeks_have_median_cycle_times() {
+        assert_eq!(summarize(vec![]), Week::default());
+        assert_eq!(
+            summarize(vec![4.0, 1.0, 2.5]),
+            Week {
+                throughput: 3,
+                cycle_time: Some(2.5),
+            }
+        );
+        assert_eq!(summarize(vec![4.0, 1.0]).cycle_time, Some(2.5));
+    }
+
+    #[test]
+    fn trends_point_up_or_down() {
+        assert_eq!(trend(-0.8, "d"), "▼0.8d");
+        assert_eq!(trend(3.0, ""), "▲3");
+        assert_eq!(trend(0.01, "d"), "no change");
+    }
+}
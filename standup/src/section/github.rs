@@ -0,0 +1,118 @@
+//! Pull requests awaiting review and recently merged in GitHub
+
+// Third party
+use serde_json::Value;
+
+// Ours
+use super::{warning, Context, Section};
+use error::FetchError;
+use github::{self, GitHub, PullRequest};
+use metrics::Metric;
+use render::{escape, Block};
+use retry::Retry;
+use templates::Templates;
+
+pub struct GitHubSection;
+
+pub struct CodeReview {
+    awaiting_review: Vec<PullRequest>,
+    merged: Result<Vec<PullRequest>, FetchError>,
+}
+
+impl Section for GitHubSection {
+    type Data = CodeReview;
+
+    fn title(&self) -> String {
+        "🔀 Code Review".into()
+    }
+
+    fn fetch(&self, context: &Context) -> Result<CodeReview, FetchError> {
+        let config = &context.config;
+        let token = config
+            .github_token
+            .clone()
+            .ok_or_else(|| FetchError::new("GitHub", "no github_token configured"))?;
+        let scope = github::scope(
+            &config.github_repos,
+            config.github_org.as_ref().map(String::as_str),
+        );
+        if scope.is_empty() {
+            return Err(FetchError::new(
+                "GitHub",
+                "no github_repos or github_org configured",
+            ));
+        }
+        let github = GitHub::new(token, config.http_client(), Retry::from(config));
+        Ok(CodeReview {
+            awaiting_review: github.awaiting_review(&scope)?,
+            merged: github.merged(&scope, &context.since()),
+        })
+    }
+
+    fn record(&self, review: &CodeReview) -> Value {
+        json!({
+            "awaiting_review": review.awaiting_review,
+            "merged": review.merged.as_ref().ok(),
+        })
+    }
+
+    fn metrics(&self, review: &CodeReview) -> Vec<Metric> {
+        let mut metrics = vec![Metric::count(
+            "AwaitingReview",
+            review.awaiting_review.len(),
+        )];
+        if let Ok(ref merged) = review.merged {
+            metrics.push(Metric::count("MergedPullRequests", merged.len()));
+        }
+        metrics
+    }
+
+    fn render(&self, review: CodeReview, context: &Context) -> Vec<Block> {
+        let templates = &context.templates;
+        let mut blocks = Vec::new();
+        if !review.awaiting_review.is_empty() {
+            blocks.push(Block::section(
+                templates.render("github.awaiting_review.heading", &json!({})),
+            ));
+            blocks.extend(
+                review
+                    .awaiting_review
+                    .iter()
+                    .map(|pull| pull_request_block(pull, templates)),
+            );
+        }
+        match review.merged {
+            Ok(ref merged) if merged.is_empty() => (),
+            Ok(merged) => {
+                blocks.push(Block::section(
+                    templates.render("github.merged.heading", &json!({})),
+                ));
+                blocks.extend(
+                    merged
+                        .iter()
+                        .map(|pull| pull_request_block(pull, templates)),
+                );
+            }
+            Err(err) => blocks.push(warning(&err)),
+        }
+        if blocks.is_empty() {
+            blocks.push(Block::section(templates.render("github.empty", &json!({}))));
+        }
+        blocks
+    }
+}
+
+fn pull_request_block(pull: &PullRequest, templates: &Templates) -> Block {
+    Block::fields(vec![
+        templates.render(
+            "github.pull",
+            &json!({
+                "url": pull.html_url,
+                "repository": pull.repository(),
+                "number": pull.number,
+                "title": escape(&pull.title),
+            }),
+        ),
+        templates.render("github.author", &json!({ "login": pull.user.login })),
+    ])
+}
@@ -0,0 +1,1907 @@
+//! What shipped and what's in flight in Jira
+
+// Std lib
+use std::cmp::{self, Ordering};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+// Third party
+use chrono::{DateTime, Duration, FixedOffset, NaiveDate};
+use goji::Issue;
+use reqwest::Url;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+// Ours
+use super::release::is_done;
+use super::{Context, Section};
+use command::{Detail, Grouping};
+use config::{Config, IssueOrder, Subtasks};
+use dev_status::{self, LinkedPullRequest};
+use error::FetchError;
+use jira_auth::Site;
+use jql::Query;
+use log;
+use metrics::Metric;
+use render::{chunks, escape, Block, MAX_SECTION_TEXT};
+use retry::Retry;
+use trace;
+use users::{Directory, JiraUser};
+use workflow::{self, Workflow};
+
+/// format of timestamp fields, i.e. `2019-01-30T14:10:02.000+0000`
+pub const JIRA_TIMESTAMP: &str = "%Y-%m-%dT%H:%M:%S%.f%z";
+
+/// most issues jira returns per search request
+const SEARCH_PAGE_SIZE: usize = 100;
+
+/// most issue by issue lookups made at once
+const LOOKUP_BATCH_SIZE: usize = 8;
+
+pub struct JiraSection;
+
+/// Shipped and in flight issues, and what's known about them beyond their fields
+pub struct Tracked {
+    issues: Vec<Issue>,
+    /// searches which matched more issues than `jira_max_results`
+    truncated: Vec<Truncated>,
+    /// open and closed pull requests linked to issues in review, by issue key
+    pull_requests: HashMap<String, Vec<LinkedPullRequest>>,
+    /// when in flight issues entered their current status, by issue key
+    entered: HashMap<String, DateTime<FixedOffset>>,
+    /// epics of shipped and in flight issues, by key, when grouping by epic
+    epics: HashMap<String, Epic>,
+    /// statuses of the projects' workflows, empty if they couldn't be discovered
+    workflow: Workflow,
+}
+
+/// An epic and how many of its issues are done
+struct Epic {
+    name: String,
+    done: usize,
+    total: usize,
+}
+
+/// What issues are annotated and grouped by
+struct Signals {
+    previous: Option<HashMap<String, IssueRecord>>,
+    pull_requests: HashMap<String, Vec<LinkedPullRequest>>,
+    entered: HashMap<String, DateTime<FixedOffset>>,
+    epics: HashMap<String, Epic>,
+    /// subtasks listed along with their parent, by parent key
+    subtasks: HashMap<String, Vec<Issue>>,
+    workflow: Workflow,
+}
+
+/// Issues found by a search, and how many matched, including those beyond
+/// the most it fetched
+pub struct Found<T> {
+    pub issues: Vec<T>,
+    pub total: usize,
+}
+
+/// A search which matched more issues than it fetched
+struct Truncated {
+    /// `shipped` or `in flight`
+    kind: &'static str,
+    query: Query,
+    fetched: usize,
+    matched: usize,
+}
+
+impl Truncated {
+    fn of(kind: &'static str, query: Query, fetched: usize, matched: usize) -> Option<Self> {
+        if fetched >= matched {
+            return None;
+        }
+        log::warn(
+            "jira search matched more issues than it fetched",
+            json!({ "search": kind, "fetched": fetched, "matched": matched }),
+        );
+        Some(Truncated {
+            kind,
+            query,
+            fetched,
+            matched,
+        })
+    }
+}
+
+/// A page of search results
+#[derive(Deserialize, Debug)]
+struct SearchResults<T> {
+    total: usize,
+    issues: Vec<T>,
+}
+
+/// An issue searched for with its changelog
+#[derive(Deserialize, Debug)]
+pub struct Expanded {
+    #[serde(flatten)]
+    pub issue: Issue,
+    changelog: Option<Changelog>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Changelog {
+    #[serde(default)]
+    histories: Vec<History>,
+}
+
+#[derive(Deserialize, Debug)]
+struct History {
+    created: String,
+    #[serde(default)]
+    items: Vec<ChangeItem>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ChangeItem {
+    field: String,
+    #[serde(rename = "toString")]
+    to: Option<String>,
+}
+
+impl Expanded {
+    /// when the issue changed status and what to, oldest first
+    pub fn status_changes(&self) -> Vec<(DateTime<FixedOffset>, Option<String>)> {
+        let mut changes = self
+            .changelog
+            .iter()
+            .flat_map(|changelog| &changelog.histories)
+            .filter_map(|history| {
+                let created = DateTime::parse_from_str(&history.created, JIRA_TIMESTAMP).ok()?;
+                history
+                    .items
+                    .iter()
+                    .find(|item| item.field == "status")
+                    .map(|item| (created, item.to.clone()))
+            })
+            .collect::<Vec<_>>();
+        changes.sort_by_key(|(created, _)| *created);
+        changes
+    }
+
+    /// when the issue was created
+    pub fn created(&self) -> Option<DateTime<FixedOffset>> {
+        self.issue
+            .field::<String>("created")
+            .and_then(|created| created.ok())
+            .and_then(|created| DateTime::parse_from_str(&created, JIRA_TIMESTAMP).ok())
+    }
+}
+
+/// What the standup history keeps of an issue
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct IssueRecord {
+    pub key: String,
+    pub summary: Option<String>,
+    pub status: Option<String>,
+    pub assignee: Option<String>,
+    #[serde(default)]
+    pub resolution: Option<String>,
+    /// the key of the status's category, i.e. `done`
+    #[serde(default)]
+    pub status_category: Option<String>,
+}
+
+impl IssueRecord {
+    /// whether the issue's status is done. records from before status
+    /// categories were kept go by the workflow
+    pub fn done(&self, workflow: &Workflow) -> bool {
+        match self.status_category {
+            Some(ref key) => key == "done",
+            None => self
+                .status
+                .as_ref()
+                .map_or(false, |status| workflow.is_done(status)),
+        }
+    }
+
+    /// whether the issue shipped, as `shipped_query` searches for them.
+    /// records from before resolutions were kept go by their status alone
+    pub fn shipped(&self, resolutions: &[String]) -> bool {
+        self.done(&Workflow::default())
+            && (resolutions.is_empty()
+                || self.resolution.as_ref().map_or(true, |resolution| {
+                    resolutions
+                        .iter()
+                        .any(|shipped| shipped.eq_ignore_ascii_case(resolution))
+                }))
+    }
+}
+
+impl<'a> From<&'a Issue> for IssueRecord {
+    fn from(issue: &'a Issue) -> Self {
+        IssueRecord {
+            key: issue.key.clone(),
+            summary: issue.summary(),
+            status: issue.status().map(|status| status.name),
+            assignee: issue
+                .field::<JiraUser>("assignee")
+                .and_then(|user| user.ok())
+                .map(|user| user.name),
+            resolution: issue
+                .field::<Value>("resolution")
+                .and_then(|resolution| resolution.ok())
+                .and_then(|resolution| resolution["name"].as_str().map(str::to_owned)),
+            status_category: issue
+                .field::<Value>("status")
+                .and_then(|status| status.ok())
+                .and_then(|status| status["statusCategory"]["key"].as_str().map(str::to_owned)),
+        }
+    }
+}
+
+impl Section for JiraSection {
+    type Data = Tracked;
+
+    fn title(&self) -> String {
+        "📋 Jira".into()
+    }
+
+    fn fetch(&self, context: &Context) -> Result<Tracked, FetchError> {
+        let config = &context.config;
+        let jira = client(config)?;
+        let retry = Retry::from(config);
+
+        // which statuses are there, and in what order?
+        let workflow = {
+            let jira = jira.clone();
+            let projects = config.jira_projects.clone();
+            trace::spawn(move || workflow::discover(&jira, retry, &projects))
+        };
+
+        let max_results = Some(config.jira_max_results).filter(|max| *max > 0);
+
+        // what shipped?
+        let shipped_query = shipped_query(context);
+        let shipped = {
+            let jira = jira.clone();
+            let query = shipped_query.clone();
+            trace::spawn(move || found(&jira, &query, retry, max_results))
+        };
+
+        // what's in flight, and since when. changelogs are dropped a page at
+        // a time, as they're by far the bulk of a search's results
+        let in_flight_query = in_flight_query(config)
+            .order_by("status")
+            .order_by("assignee");
+        let mut in_flight = Vec::new();
+        let mut entered = HashMap::new();
+        let in_flight_matched = pages(
+            &jira,
+            &in_flight_query,
+            Some("changelog"),
+            retry,
+            max_results,
+            |page: Vec<Expanded>| {
+                for expanded in page {
+                    if let Some(since) = entered_status(&expanded) {
+                        entered.insert(expanded.issue.key.clone(), since);
+                    }
+                    in_flight.push(expanded.issue);
+                }
+            },
+        )?;
+        let pull_requests = pull_requests(&jira, &in_flight, config, retry);
+        let truncated = Truncated::of(
+            "in flight",
+            in_flight_query,
+            in_flight.len(),
+            in_flight_matched,
+        );
+        let Found {
+            issues: mut issues,
+            total: shipped_matched,
+        } = shipped
+            .join()
+            .unwrap_or_else(|_| Err(FetchError::new("Jira", "panicked")))?;
+        let truncated = Truncated::of("shipped", shipped_query, issues.len(), shipped_matched)
+            .into_iter()
+            .chain(truncated)
+            .collect();
+        issues.extend(in_flight);
+        let epics = if context.grouping == Grouping::Epic {
+            epics(&jira, &issues, config, retry)
+        } else {
+            HashMap::new()
+        };
+        // statuses are ordered and marked by configuration alone without it
+        let workflow = workflow
+            .join()
+            .unwrap_or_else(|_| Err(FetchError::new("Jira", "panicked")))
+            .unwrap_or_else(|err| {
+                log::warn(
+                    "failed to discover workflow statuses",
+                    json!({ "error": err.to_string() }),
+                );
+                Workflow::default()
+            });
+        Ok(Tracked {
+            issues,
+            truncated,
+            pull_requests,
+            entered,
+            epics,
+            workflow,
+        })
+    }
+
+    fn record(&self, tracked: &Tracked) -> Value {
+        json!(tracked
+            .issues
+            .iter()
+            .map(IssueRecord::from)
+            .collect::<Vec<_>>())
+    }
+
+    fn metrics(&self, tracked: &Tracked) -> Vec<Metric> {
+        let issues = &tracked.issues;
+        let shipped = issues
+            .iter()
+            .filter(|issue| tracked.workflow.is_done(&status(issue)))
+            .count();
+        vec![
+            Metric::count("ShippedIssues", shipped),
+            Metric::count("InFlightIssues", issues.len() - shipped),
+        ]
+    }
+
+    fn summary(&self, tracked: &Tracked, context: &Context) -> Vec<String> {
+        let config = &context.config;
+        let totals = tracked
+            .issues
+            .iter()
+            .fold(BTreeMap::new(), |mut acc, issue| {
+                let status = status(issue);
+                let points = config
+                    .jira_story_points_field
+                    .as_ref()
+                    .and_then(|field| issue.field::<f64>(field))
+                    .and_then(|points| points.ok());
+                {
+                    let total = acc
+                        .entry((rank(config, &tracked.workflow, &status), status))
+                        .or_insert((0, None));
+                    total.0 += 1;
+                    if let Some(points) = points {
+                        total.1 = Some(total.1.unwrap_or(0.0) + points);
+                    }
+                }
+                acc
+            });
+        let status_emoji = config.status_emoji();
+        totals
+            .into_iter()
+            .map(|((_, status), (count, points))| {
+                format!(
+                    "{} {} {}{}",
+                    emoji(&status_emoji, &tracked.workflow, &status),
+                    count,
+                    status.to_lowercase(),
+                    points
+                        .map(|points| format!(" ({} pts)", points))
+                        .unwrap_or_default()
+                )
+            })
+            .collect()
+    }
+
+    fn render(&self, tracked: Tracked, context: &Context) -> Vec<Block> {
+        let config = &context.config;
+        let Tracked {
+            issues,
+            truncated,
+            pull_requests,
+            entered,
+            epics,
+            workflow,
+        } = tracked;
+        let mut signals = Signals {
+            previous: previous_issues(context),
+            pull_requests,
+            entered,
+            epics,
+            subtasks: HashMap::new(),
+            workflow,
+        };
+        let current = issues
+            .iter()
+            .map(|issue| issue.key.clone())
+            .collect::<HashSet<_>>();
+        let (closed, mut issues): (Vec<_>, Vec<_>) = issues
+            .into_iter()
+            .partition(|issue| signals.workflow.is_done(&status(issue)));
+        let (in_flight, shipped) = (issues.len(), closed.len());
+        let banners = wip_exceeded(&issues, context, &signals.workflow);
+        let due_soon = due_this_week(&issues, context, &signals.workflow);
+        let collapsed = if context.shows(Detail::Closed) {
+            issues.extend(closed);
+            0
+        } else {
+            shipped
+        };
+        order(
+            &mut issues,
+            config.jira_issue_order,
+            &signals.entered,
+            &signals.workflow,
+        );
+        let (subtasks, issues) = nest(issues);
+        signals.subtasks = subtasks;
+
+        // empty states, so the section never renders as a bare header
+        let mut blocks = banners;
+        if in_flight == 0 {
+            blocks.push(Block::section(
+                context.templates.render("jira.empty", &json!({})),
+            ));
+        }
+        blocks.extend(match context.grouping {
+            Grouping::Status => by_status(issues, context, &signals),
+            Grouping::Assignee => by_assignee(issues, context, &signals),
+            Grouping::Epic => by_epic(issues, context, &signals),
+        });
+        blocks.extend(truncated.iter().map(|truncated| {
+            Block::context(context.templates.render(
+                "jira.truncated",
+                &json!({
+                    "count": truncated.fetched,
+                    "total": truncated.matched,
+                    "kind": truncated.kind,
+                    "url": filter_url(&config.jira_host, link_filter(config), &truncated.query),
+                }),
+            ))
+        }));
+        blocks.extend(due_soon);
+        if collapsed > 0 {
+            blocks.push(Block::context(
+                context
+                    .templates
+                    .render("jira.closed.collapsed", &json!({ "count": collapsed })),
+            ));
+        }
+        if shipped == 0 {
+            blocks.push(Block::context(
+                context.templates.render("jira.closed.empty", &json!({})),
+            ));
+        }
+
+        // what fell out of flight without shipping? issues beyond the cap
+        // weren't fetched, so they can't be told apart from dropped ones
+        if !truncated.is_empty() {
+            return blocks;
+        }
+        let workflow = signals.workflow;
+        let mut dropped = signals
+            .previous
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(_, record)| record)
+            .filter(|record| !current.contains(&record.key) && !record.done(&workflow))
+            .collect::<Vec<_>>();
+        if !dropped.is_empty() {
+            dropped.sort_by(|a, b| a.key.cmp(&b.key));
+            blocks.push(Block::section(
+                context.templates.render("jira.dropped.heading", &json!({})),
+            ));
+            blocks.extend(dropped.into_iter().map(|record| {
+                Block::section(context.templates.render(
+                    "jira.dropped",
+                    &json!({
+                        "url": browse_url(&config.jira_host, &record.key),
+                        "key": record.key,
+                        "summary": escape(&record.summary.unwrap_or_else(|| "no summary".into())),
+                        "status": record.status.unwrap_or_else(|| "Unknown Status".into()),
+                    }),
+                ))
+            }));
+        }
+        blocks
+    }
+}
+
+/// warnings for statuses with more in flight issues than their wip limit
+fn wip_exceeded(in_flight: &[Issue], context: &Context, workflow: &Workflow) -> Vec<Block> {
+    let config = &context.config;
+    let limits = config.wip_limits();
+    if limits.is_empty() {
+        return Vec::new();
+    }
+    let counts = in_flight.iter().fold(BTreeMap::new(), |mut acc, issue| {
+        let status = status(issue);
+        *acc.entry((rank(config, workflow, &status), status))
+            .or_insert(0) += 1;
+        acc
+    });
+    let status_emoji = config.status_emoji();
+    counts
+        .into_iter()
+        .filter_map(|((_, status), count)| {
+            let limit = *limits.get(&status)?;
+            if count <= limit {
+                return None;
+            }
+            Some(Block::section(context.templates.render(
+                "jira.wip",
+                &json!({
+                    "emoji": emoji(&status_emoji, workflow, &status),
+                    "status": status,
+                    "count": count,
+                    "limit": limit,
+                }),
+            )))
+        })
+        .collect()
+}
+
+/// issues grouped by ordered status
+fn by_status(issues: Vec<Issue>, context: &Context, signals: &Signals) -> Vec<Block> {
+    let config = &context.config;
+    let grouped = issues.into_iter().fold(BTreeMap::new(), |mut acc, issue| {
+        let status = status(&issue);
+        let notes = notes(&issue, &status, context, signals);
+        acc.entry((rank(config, &signals.workflow, &status), status.clone()))
+            .or_insert(Vec::new())
+            .push(issue_blocks(issue, &status, notes, context, signals));
+        acc
+    });
+
+    let limit = config.jira_group_limit.unwrap_or(usize::max_value());
+    let status_emoji = config.status_emoji();
+    let mut blocks = Vec::new();
+    for ((_, status), issues) in grouped {
+        let hidden = issues.len().saturating_sub(limit);
+        blocks.push(Block::section(context.templates.render(
+            "jira.status",
+            &json!({
+                "emoji": emoji(&status_emoji, &signals.workflow, &status),
+                "status": status,
+            }),
+        )));
+        blocks.extend(issues.into_iter().take(limit).flat_map(|blocks| blocks));
+        if hidden > 0 {
+            blocks.push(Block::context(more(
+                context,
+                hidden,
+                &group_query(context, &signals.workflow, &status),
+            )));
+        }
+    }
+    blocks
+}
+
+/// issues grouped by assignee, then by ordered status. unassigned issues come last
+fn by_assignee(issues: Vec<Issue>, context: &Context, signals: &Signals) -> Vec<Block> {
+    let config = &context.config;
+    let grouped = issues.into_iter().fold(BTreeMap::new(), |mut acc, issue| {
+        let assignee = issue
+            .field::<JiraUser>("assignee")
+            .and_then(|user| user.ok());
+        let status = status(&issue);
+        let notes = notes(&issue, &status, context, signals);
+        let mut line = context.templates.render(
+            "jira.issue",
+            &json!({
+                "url": browse_url(&config.jira_host, &issue.key),
+                "key": issue.key,
+                "summary": escape(&issue.summary().unwrap_or_else(|| "no summary".into())),
+                "icons": icons(&issue, config),
+            }),
+        );
+        if !notes.is_empty() {
+            line.push_str(&format!(" _{}_", notes.join(" · ")));
+        }
+        line.extend(nested(&issue, context, signals));
+        let name = assignee.as_ref().map(|user| user.name.clone());
+        acc.entry((name.is_none(), name))
+            .or_insert_with(|| (assignee, BTreeMap::new()))
+            .1
+            .entry((rank(config, &signals.workflow, &status), status))
+            .or_insert(Vec::new())
+            .push(line);
+        acc
+    });
+
+    let limit = config.jira_group_limit.unwrap_or(usize::max_value());
+    let status_emoji = config.status_emoji();
+    let mut blocks = Vec::new();
+    for (_, (assignee, statuses)) in grouped {
+        let name = assignee.as_ref().map(|user| user.name.clone());
+        blocks.push(Block::section(context.templates.render(
+            "jira.assignee",
+            &json!({
+                "assignee": assignee
+                    .map(|user| context.users.mention(&user))
+                    .unwrap_or_else(|| "Unassigned".into()),
+            }),
+        )));
+        for ((_, status), lines) in statuses {
+            let hidden = lines.len().saturating_sub(limit);
+            let mut lines = lines
+                .into_iter()
+                .take(limit)
+                .map(|line| format!("• {}", line))
+                .collect::<Vec<_>>();
+            if hidden > 0 {
+                let query = group_query(context, &signals.workflow, &status);
+                let query = match name {
+                    Some(ref name) => query.eq("assignee", name),
+                    None => query.is_empty("assignee"),
+                };
+                lines.push(more(context, hidden, &query));
+            }
+            let heading = context.templates.render(
+                "jira.assignee.status",
+                &json!({
+                    "emoji": emoji(&status_emoji, &signals.workflow, &status),
+                    "status": status,
+                }),
+            );
+            blocks.extend(sections(heading, lines));
+        }
+    }
+    blocks
+}
+
+/// issues grouped by epic, each with its progress. issues without an epic come last
+fn by_epic(issues: Vec<Issue>, context: &Context, signals: &Signals) -> Vec<Block> {
+    let config = &context.config;
+    let status_emoji = config.status_emoji();
+    let grouped = issues.into_iter().fold(BTreeMap::new(), |mut acc, issue| {
+        let status = status(&issue);
+        let notes = notes(&issue, &status, context, signals);
+        let mut line = format!(
+            "{} {}",
+            emoji(&status_emoji, &signals.workflow, &status),
+            context.templates.render(
+                "jira.issue",
+                &json!({
+                    "url": browse_url(&config.jira_host, &issue.key),
+                    "key": issue.key,
+                    "summary": escape(&issue.summary().unwrap_or_else(|| "no summary".into())),
+                    "icons": icons(&issue, config),
+                }),
+            )
+        );
+        if !notes.is_empty() {
+            line.push_str(&format!(" _{}_", notes.join(" · ")));
+        }
+        line.extend(nested(&issue, context, signals));
+        let epic = epic(&issue, config);
+        let group = acc
+            .entry((epic.is_none(), epic))
+            .or_insert((Vec::new(), false));
+        group.0.push(line);
+        group.1 |= signals.workflow.is_done(&status);
+        acc
+    });
+
+    let limit = config.jira_group_limit.unwrap_or(usize::max_value());
+    let mut blocks = Vec::new();
+    for ((_, key), (lines, shipped)) in grouped {
+        let heading = match key {
+            Some(ref key) => {
+                let epic = signals.epics.get(key);
+                context.templates.render(
+                    "jira.epic",
+                    &json!({
+                        "url": browse_url(&config.jira_host, key),
+                        "key": key,
+                        "name": escape(epic.map(|epic| epic.name.as_str()).unwrap_or(key)),
+                        "done": epic.map(|epic| epic.done),
+                        "total": epic.map(|epic| epic.total),
+                    }),
+                )
+            }
+            None => context.templates.render("jira.epic.none", &json!({})),
+        };
+        let hidden = lines.len().saturating_sub(limit);
+        let mut lines = lines.into_iter().take(limit).collect::<Vec<_>>();
+        if hidden > 0 {
+            // closed issues are listed alongside in flight ones when shown
+            let query = if shipped {
+                Query::new().any(vec![in_flight_query(config), shipped_query(context)])
+            } else {
+                in_flight_query(config)
+            };
+            let field = epic_field(config);
+            let query = match key {
+                Some(ref key) => query.eq(&field, key),
+                None => query.is_empty(&field),
+            };
+            lines.push(more(context, hidden, &query));
+        }
+        blocks.extend(sections(heading, lines));
+    }
+    blocks
+}
+
+/// annotations on how an issue is moving, or isn't
+fn notes(issue: &Issue, status: &str, context: &Context, signals: &Signals) -> Vec<String> {
+    signals
+        .previous
+        .as_ref()
+        .and_then(|previous| {
+            change(
+                &context.config,
+                &signals.workflow,
+                previous,
+                &issue.key,
+                status,
+            )
+        })
+        .into_iter()
+        .chain(time_in_status(issue, status, context, signals))
+        .chain(resolution(
+            issue,
+            status,
+            &context.config,
+            &signals.workflow,
+        ))
+        .chain(stale(issue, status, context, &signals.workflow))
+        .chain(
+            due_date(issue, status, &signals.workflow)
+                .map(|due_date| due(due_date, today(context))),
+        )
+        .chain(subtask_count(issue, context, signals))
+        .chain(display_fields(issue, &context.config))
+        .chain(
+            signals
+                .pull_requests
+                .get(&issue.key)
+                .and_then(|pulls| dev_status::review_state(pulls))
+                .map(|state| format!("🔀 {}", state)),
+        )
+        .collect()
+}
+
+/// the configured extra fields an issue has values for, i.e. `Squad: Payments`
+fn display_fields(issue: &Issue, config: &Config) -> Vec<String> {
+    config
+        .display_fields()
+        .into_iter()
+        .filter_map(|(field, label)| {
+            let value = issue.field::<Value>(&field)?.ok()?;
+            Some(format!("{}: {}", label, escape(&field_text(&value)?)))
+        })
+        .collect()
+}
+
+/// text of a field value, whether plain, an option, a user or a list of them
+fn field_text(value: &Value) -> Option<String> {
+    match *value {
+        Value::String(ref text) => Some(text.clone()),
+        Value::Number(ref number) => Some(number.to_string()),
+        Value::Array(ref values) => {
+            let texts = values.iter().filter_map(field_text).collect::<Vec<_>>();
+            if texts.is_empty() {
+                None
+            } else {
+                Some(texts.join(", "))
+            }
+        }
+        Value::Object(_) => ["value", "displayName", "name"]
+            .iter()
+            .filter_map(|key| value[*key].as_str())
+            .next()
+            .map(str::to_owned),
+        _ => None,
+    }
+    .filter(|text| !text.is_empty())
+}
+
+/// sets aside subtasks listed along with their parent, keyed by parent, from
+/// the rest of the issues
+fn nest(issues: Vec<Issue>) -> (HashMap<String, Vec<Issue>>, Vec<Issue>) {
+    let keys = issues
+        .iter()
+        .map(|issue| issue.key.clone())
+        .collect::<HashSet<_>>();
+    issues.into_iter().fold(
+        (HashMap::new(), Vec::new()),
+        |(mut subtasks, mut rest), issue| {
+            match parent(&issue).filter(|parent| keys.contains(parent)) {
+                Some(parent) => subtasks.entry(parent).or_insert(Vec::new()).push(issue),
+                None => rest.push(issue),
+            }
+            (subtasks, rest)
+        },
+    )
+}
+
+/// key of the parent of a subtask
+fn parent(issue: &Issue) -> Option<String> {
+    issue
+        .field::<Value>("issuetype")
+        .and_then(|issue_type| issue_type.ok())
+        .filter(|issue_type| issue_type["subtask"] == true)?;
+    issue
+        .field::<Value>("parent")
+        .and_then(|parent| parent.ok())
+        .and_then(|parent| parent["key"].as_str().map(str::to_owned))
+}
+
+/// lines listing an issue's subtasks under it, when nesting them
+fn nested(issue: &Issue, context: &Context, signals: &Signals) -> Vec<String> {
+    let config = &context.config;
+    if config.jira_subtasks != Subtasks::Nest {
+        return Vec::new();
+    }
+    let status_emoji = config.status_emoji();
+    signals
+        .subtasks
+        .get(&issue.key)
+        .into_iter()
+        .flat_map(|subtasks| subtasks)
+        .map(|subtask| {
+            let status = status(subtask);
+            format!(
+                "\n{}",
+                context.templates.render(
+                    "jira.subtask",
+                    &json!({
+                        "url": browse_url(&config.jira_host, &subtask.key),
+                        "key": subtask.key,
+                        "summary": escape(&subtask.summary().unwrap_or_else(|| "no summary".into())),
+                        "emoji": emoji(&status_emoji, &signals.workflow, &status),
+                    }),
+                )
+            )
+        })
+        .collect()
+}
+
+/// how many of an issue's subtasks are done, when counting them
+fn subtask_count(issue: &Issue, context: &Context, signals: &Signals) -> Option<String> {
+    if context.config.jira_subtasks != Subtasks::Count {
+        return None;
+    }
+    let subtasks = signals.subtasks.get(&issue.key)?;
+    Some(format!(
+        "🧩 {}/{} subtasks done",
+        subtasks.iter().filter(|subtask| is_done(subtask)).count(),
+        subtasks.len()
+    ))
+}
+
+/// the epics of issues, with how many of each epic's issues are done. they
+/// only head groups, so if they can't be fetched groups go by epic key alone
+fn epics(jira: &Site, issues: &[Issue], config: &Config, retry: Retry) -> HashMap<String, Epic> {
+    let keys = issues
+        .iter()
+        .filter_map(|issue| epic(issue, config))
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect::<Vec<_>>();
+    if keys.is_empty() {
+        return HashMap::new();
+    }
+    let epics = search(jira, &Query::new().is_in("key", &keys), retry);
+    let children = search(jira, &Query::new().is_in(&epic_field(config), &keys), retry);
+    match (epics, children) {
+        (Ok(epics), Ok(children)) => epics
+            .into_iter()
+            .map(|found| {
+                let (done, total) = children
+                    .iter()
+                    .filter(|child| epic(child, config).as_ref() == Some(&found.key))
+                    .fold((0, 0), |(done, total), child| {
+                        (done + is_done(child) as usize, total + 1)
+                    });
+                let name = found.summary().unwrap_or_else(|| found.key.clone());
+                (found.key, Epic { name, done, total })
+            })
+            .collect(),
+        (Err(err), _) | (_, Err(err)) => {
+            log::warn(
+                "failed to fetch epics",
+                json!({ "epics": keys, "error": err.to_string() }),
+            );
+            HashMap::new()
+        }
+    }
+}
+
+/// key of the epic an issue belongs to
+fn epic(issue: &Issue, config: &Config) -> Option<String> {
+    match config.jira_epic_link_field {
+        Some(ref field) => issue.field::<String>(field).and_then(|key| key.ok()),
+        None => issue
+            .field::<Value>("parent")
+            .and_then(|parent| parent.ok())
+            .filter(|parent| parent["fields"]["issuetype"]["name"] == "Epic")
+            .and_then(|parent| parent["key"].as_str().map(str::to_owned)),
+    }
+}
+
+/// the field epics are searched by, i.e. `cf[10014]` for `customfield_10014`
+fn epic_field(config: &Config) -> String {
+    match config.jira_epic_link_field {
+        Some(ref field) if field.starts_with("customfield_") => {
+            format!("cf[{}]", &field["customfield_".len()..])
+        }
+        Some(ref field) => field.clone(),
+        None => "parent".into(),
+    }
+}
+
+/// pull requests linked to in flight issues in a review status, by issue key.
+/// they only annotate issues, so issues whose pull requests can't be fetched
+/// go without
+fn pull_requests(
+    jira: &Site,
+    issues: &[Issue],
+    config: &Config,
+    retry: Retry,
+) -> HashMap<String, Vec<LinkedPullRequest>> {
+    let lookups = issues
+        .iter()
+        .filter(|issue| {
+            let status = status(issue);
+            config
+                .jira_review_statuses
+                .iter()
+                .any(|review| review.eq_ignore_ascii_case(&status))
+        })
+        .map(|issue| (issue.key.clone(), issue.id.clone()))
+        .collect::<Vec<_>>();
+    let (jira, application) = (jira.clone(), config.jira_dev_status_application.clone());
+    batched(lookups, move |(key, id)| {
+        let pulls = dev_status::pull_requests(&jira, retry, &id, &application);
+        (key, pulls)
+    })
+    .into_iter()
+    .flatten()
+    .filter_map(|(key, pulls)| match pulls {
+        Ok(pulls) => Some((key, pulls)),
+        Err(err) => {
+            log::warn(
+                "failed to fetch linked pull requests",
+                json!({ "issue": key, "error": err.to_string() }),
+            );
+            None
+        }
+    })
+    .collect()
+}
+
+/// how long an in flight issue has been in its status, with a warning past
+/// `jira_status_warning_days`
+fn time_in_status(
+    issue: &Issue,
+    status: &str,
+    context: &Context,
+    signals: &Signals,
+) -> Option<String> {
+    let entered = signals.entered.get(&issue.key)?;
+    let days = context.now.signed_duration_since(*entered).num_days();
+    let warning = context
+        .config
+        .jira_status_warning_days
+        .map(|warning_days| days >= warning_days)
+        .unwrap_or(false);
+    Some(format!(
+        "{}{} — {}d",
+        if warning { "⚠️ " } else { "" },
+        status,
+        days
+    ))
+}
+
+/// when an issue entered its status: its last status change, or its creation
+/// if it never changed
+fn entered_status(expanded: &Expanded) -> Option<DateTime<FixedOffset>> {
+    expanded
+        .status_changes()
+        .pop()
+        .map(|(changed, _)| changed)
+        .or_else(|| expanded.created())
+}
+
+/// flags in flight issues which haven't been touched in a while
+fn stale(issue: &Issue, status: &str, context: &Context, workflow: &Workflow) -> Option<String> {
+    let stale_days = context.config.jira_stale_days?;
+    if workflow.is_done(status) {
+        return None;
+    }
+    let updated = issue
+        .field::<String>("updated")
+        .and_then(|updated| updated.ok())
+        .and_then(|updated| DateTime::parse_from_str(&updated, JIRA_TIMESTAMP).ok())?;
+    let idle = context.now.signed_duration_since(updated).num_days();
+    if idle >= stale_days {
+        Some(format!("🕸 stale {}d", idle))
+    } else {
+        None
+    }
+}
+
+/// how a closed issue was resolved, i.e. `Won't Do`, unless it's one of the
+/// `jira_shipped_resolutions` which go without saying
+fn resolution(issue: &Issue, status: &str, config: &Config, workflow: &Workflow) -> Option<String> {
+    if !workflow.is_done(status) {
+        return None;
+    }
+    issue
+        .field::<Value>("resolution")
+        .and_then(|resolution| resolution.ok())
+        .and_then(|resolution| resolution["name"].as_str().map(str::to_owned))
+        .filter(|name| {
+            !config
+                .jira_shipped_resolutions
+                .iter()
+                .any(|shipped| shipped.eq_ignore_ascii_case(name))
+        })
+        .map(|name| format!("✔️ {}", name))
+}
+
+/// when an issue is due, unless it's closed
+fn due_date(issue: &Issue, status: &str, workflow: &Workflow) -> Option<NaiveDate> {
+    if workflow.is_done(status) {
+        return None;
+    }
+    issue
+        .field::<String>("duedate")
+        .and_then(|due_date| due_date.ok())
+        .and_then(|due_date| NaiveDate::parse_from_str(&due_date, "%F").ok())
+}
+
+/// how soon something is due, i.e. `📅 due Fri` or `🔥 overdue 2d`
+fn due(due_date: NaiveDate, today: NaiveDate) -> String {
+    match due_date.signed_duration_since(today).num_days() {
+        days if days < 0 => format!("🔥 overdue {}d", -days),
+        0 => "📅 due today".into(),
+        days if days < 7 => format!("📅 due {}", due_date.format("%a")),
+        _ => format!("📅 due {}", due_date.format("%b %-d")),
+    }
+}
+
+fn today(context: &Context) -> NaiveDate {
+    context.now.date().naive_local()
+}
+
+/// in flight issues due within the week or overdue, soonest first, when configured
+fn due_this_week(in_flight: &[Issue], context: &Context, workflow: &Workflow) -> Vec<Block> {
+    let config = &context.config;
+    if !config.jira_due_this_week {
+        return Vec::new();
+    }
+    let today = today(context);
+    let mut due_soon = in_flight
+        .iter()
+        .filter_map(|issue| {
+            due_date(issue, &status(issue), workflow)
+                .filter(|due_date| *due_date < today + Duration::days(7))
+                .map(|due_date| (due_date, issue))
+        })
+        .collect::<Vec<_>>();
+    if due_soon.is_empty() {
+        return Vec::new();
+    }
+    due_soon.sort_by(|(a_due, a), (b_due, b)| a_due.cmp(b_due).then(key(a).cmp(&key(b))));
+    sections(
+        context.templates.render("jira.due.heading", &json!({})),
+        due_soon
+            .into_iter()
+            .map(|(due_date, issue)| {
+                format!(
+                    "• {} _{}_",
+                    context.templates.render(
+                        "jira.issue",
+                        &json!({
+                            "url": browse_url(&config.jira_host, &issue.key),
+                            "key": issue.key,
+                            "summary": escape(&issue.summary().unwrap_or_else(|| "no summary".into())),
+                            "icons": icons(issue, config),
+                        }),
+                    ),
+                    due(due_date, today)
+                )
+            })
+            .collect(),
+    )
+}
+
+/// a group's heading and lines, spread over as many sections as it takes to
+/// keep every line, rather than have slack's limit cut the group short
+fn sections(heading: String, lines: Vec<String>) -> Vec<Block> {
+    chunks(Some(heading).into_iter().chain(lines), MAX_SECTION_TEXT)
+        .into_iter()
+        .map(Block::section)
+        .collect()
+}
+
+fn status(issue: &Issue) -> String {
+    issue
+        .status()
+        .map(|status| status.name)
+        .unwrap_or_else(|| "Unknown Status".into())
+}
+
+/// issues reported in the previous standup, keyed by issue key
+fn previous_issues(context: &Context) -> Option<HashMap<String, IssueRecord>> {
+    context
+        .previous
+        .as_ref()
+        .and_then(|entry| entry.sections.get("jira"))
+        .and_then(|record| ::serde_json::from_value::<Vec<IssueRecord>>(record.clone()).ok())
+        .map(|records| {
+            records
+                .into_iter()
+                .map(|record| (record.key.clone(), record))
+                .collect()
+        })
+}
+
+/// position of a status in the workflow, if it's one we track. without
+/// configured in flight statuses, the discovered workflow tracks them all
+fn progress(config: &Config, workflow: &Workflow, status: &str) -> Option<usize> {
+    if config.jira_in_flight_statuses.is_empty() {
+        return workflow.position(status);
+    }
+    if workflow.is_done(status) {
+        return Some(config.jira_in_flight_statuses.len());
+    }
+    config
+        .jira_in_flight_statuses
+        .iter()
+        .position(|in_flight| in_flight == status)
+}
+
+/// describes how an issue moved since the previous standup
+fn change(
+    config: &Config,
+    workflow: &Workflow,
+    previous: &HashMap<String, IssueRecord>,
+    key: &str,
+    status: &str,
+) -> Option<String> {
+    let before = match previous.get(key) {
+        None => return Some("🆕 new".into()),
+        Some(record) => record.status.as_ref()?,
+    };
+    if before == status {
+        return None;
+    }
+    let direction = match (
+        progress(config, workflow, before),
+        progress(config, workflow, status),
+    ) {
+        (Some(before), Some(after)) => match after.cmp(&before) {
+            Ordering::Greater => " ⬆️",
+            Ordering::Less => " ⬇️",
+            Ordering::Equal => "",
+        },
+        _ => "",
+    };
+    Some(format!("{} → {}{}", before, status, direction))
+}
+
+pub fn client(config: &Config) -> Result<Site, FetchError> {
+    Site::configured(config)
+}
+
+/// looks up each of `items` on its own thread, `LOOKUP_BATCH_SIZE` at a time
+/// so long lists of issues don't flood jira. Results are in the order of
+/// `items`, with `None` for lookups which panicked
+pub fn batched<T, R, F>(items: Vec<T>, lookup: F) -> Vec<Option<R>>
+where
+    T: Send + 'static,
+    R: Send + 'static,
+    F: Fn(T) -> R + Clone + Send + 'static,
+{
+    let mut results = Vec::with_capacity(items.len());
+    let mut items = items.into_iter().peekable();
+    while items.peek().is_some() {
+        let batch = items
+            .by_ref()
+            .take(LOOKUP_BATCH_SIZE)
+            .map(|item| {
+                let lookup = lookup.clone();
+                trace::spawn(move || lookup(item))
+            })
+            .collect::<Vec<_>>();
+        results.extend(batch.into_iter().map(|thread| thread.join().ok()));
+    }
+    results
+}
+
+/// fetches all jira issues matching a jql query, a page at a time
+pub fn search(jira: &Site, query: &Query, retry: Retry) -> Result<Vec<Issue>, FetchError> {
+    found(jira, query, retry, None).map(|found| found.issues)
+}
+
+/// fetches up to `max_results` jira issues matching a jql query, and how many matched
+pub fn found(
+    jira: &Site,
+    query: &Query,
+    retry: Retry,
+    max_results: Option<usize>,
+) -> Result<Found<Issue>, FetchError> {
+    let mut issues = Vec::new();
+    let total = pages(jira, query, None, retry, max_results, |page: Vec<Issue>| {
+        issues.extend(page)
+    })?;
+    Ok(Found { issues, total })
+}
+
+pub fn search_with_changelogs(
+    jira: &Site,
+    query: &Query,
+    retry: Retry,
+) -> Result<Vec<Expanded>, FetchError> {
+    let mut issues = Vec::new();
+    pages(
+        jira,
+        query,
+        Some("changelog"),
+        retry,
+        None,
+        |page: Vec<Expanded>| issues.extend(page),
+    )?;
+    Ok(issues)
+}
+
+/// hands each page of a search's results to `each`, until `max_results` are
+/// fetched, returning how many issues matched
+fn pages<T, F>(
+    jira: &Site,
+    query: &Query,
+    expand: Option<&str>,
+    retry: Retry,
+    max_results: Option<usize>,
+    mut each: F,
+) -> Result<usize, FetchError>
+where
+    T: DeserializeOwned,
+    F: FnMut(Vec<T>),
+{
+    let jql = query.to_string();
+    let mut fetched = 0;
+    let mut total = 0;
+    loop {
+        let remaining = max_results
+            .map(|max| max.saturating_sub(fetched))
+            .unwrap_or(SEARCH_PAGE_SIZE);
+        if remaining == 0 {
+            return Ok(total);
+        }
+        let start_at = fetched.to_string();
+        let page_size = cmp::min(remaining, SEARCH_PAGE_SIZE).to_string();
+        let mut params = vec![
+            ("jql", jql.as_str()),
+            ("startAt", start_at.as_str()),
+            ("maxResults", page_size.as_str()),
+        ];
+        if let Some(expand) = expand {
+            params.push(("expand", expand));
+        }
+        let page = jira
+            .get_json("/rest/api/2/search", &params, retry)
+            .and_then(|page| {
+                ::serde_json::from_value::<SearchResults<T>>(page)
+                    .map_err(|err| FetchError::new("Jira", err.to_string()))
+            })?;
+        total = page.total;
+        let last = page.issues.is_empty() || fetched + page.issues.len() >= page.total;
+        fetched += page.issues.len();
+        each(page.issues);
+        if last {
+            return Ok(cmp::max(total, fetched));
+        }
+    }
+}
+
+fn owner(issue: &Issue, status: &str, users: &Directory, workflow: &Workflow) -> Option<String> {
+    if workflow.is_done(status) {
+        return None; // everyone owns this
+    }
+    Some(
+        issue
+            .field::<JiraUser>("assignee")
+            .and_then(|user| user.ok())
+            .map(|user| users.mention(&user))
+            .unwrap_or_else(|| "@nobody".into()),
+    )
+}
+
+/// orders issues within their groups, ties broken by key
+fn order(
+    issues: &mut Vec<Issue>,
+    order: IssueOrder,
+    entered: &HashMap<String, DateTime<FixedOffset>>,
+    workflow: &Workflow,
+) {
+    issues.sort_by(|a, b| {
+        match order {
+            IssueOrder::Relevance => {
+                relevance(a, entered, workflow).cmp(&relevance(b, entered, workflow))
+            }
+            IssueOrder::Updated => recency(a).cmp(&recency(b)),
+            IssueOrder::Key => Ordering::Equal,
+        }
+        .then_with(|| key(a).cmp(&key(b)))
+    });
+}
+
+/// closed issues most recently resolved first, then in flight issues longest
+/// in their status first. without a changelog, when an issue's status
+/// category last changed stands in for when it entered its status
+fn relevance(
+    issue: &Issue,
+    entered: &HashMap<String, DateTime<FixedOffset>>,
+    workflow: &Workflow,
+) -> (bool, bool, i64) {
+    if workflow.is_done(&status(issue)) {
+        let resolved = timestamp(issue, "resolutiondate");
+        (false, resolved.is_none(), -resolved.unwrap_or_default())
+    } else {
+        let entered = entered
+            .get(&issue.key)
+            .map(|entered| entered.timestamp())
+            .or_else(|| timestamp(issue, "statuscategorychangedate"));
+        (true, entered.is_none(), entered.unwrap_or_default())
+    }
+}
+
+/// most recently updated first
+fn recency(issue: &Issue) -> (bool, i64) {
+    let updated = timestamp(issue, "updated");
+    (updated.is_none(), -updated.unwrap_or_default())
+}
+
+/// seconds since the epoch of a timestamp field
+fn timestamp(issue: &Issue, field: &str) -> Option<i64> {
+    issue
+        .field::<String>(field)
+        .and_then(|value| value.ok())
+        .and_then(|value| DateTime::parse_from_str(&value, JIRA_TIMESTAMP).ok())
+        .map(|value| value.timestamp())
+}
+
+/// an issue's project and number, so `CORE-9` comes before `CORE-10`
+fn key(issue: &Issue) -> (String, u64) {
+    let mut parts = issue.key.rsplitn(2, '-');
+    let number = parts.next().and_then(|number| number.parse().ok());
+    match (parts.next(), number) {
+        (Some(project), Some(number)) => (project.to_owned(), number),
+        _ => (issue.key.clone(), 0),
+    }
+}
+
+/// the issues of a status group, as searched for
+fn group_query(context: &Context, workflow: &Workflow, status: &str) -> Query {
+    if workflow.is_done(status) {
+        shipped_query(context)
+    } else {
+        in_flight_query(&context.config).eq("status", status)
+    }
+}
+
+/// issues resolved since the last standup, from the configured saved filter
+/// or else done issues in the configured projects, with a resolution which
+/// counts as shipping
+fn shipped_query(context: &Context) -> Query {
+    let config = &context.config;
+    // issues are told apart by status category, so filters are held to it too
+    let mut query = match config.jira_shipped_filter {
+        Some(id) => Query::new().filter(id),
+        None => Query::new().is_in("project", &config.jira_projects),
+    }
+    .eq("statusCategory", "Done");
+    if !config.jira_shipped_resolutions.is_empty() {
+        query = query.is_in("resolution", &config.jira_shipped_resolutions);
+    }
+    scoped(query, config).at_least("resolutiondate", &context.since())
+}
+
+/// issues in flight, from the configured saved filter or else issues in the
+/// configured projects and in flight statuses, or any status jira
+/// categorizes as in progress when none are configured
+fn in_flight_query(config: &Config) -> Query {
+    let query = match config.jira_in_flight_filter {
+        Some(id) => Query::new().filter(id).not_eq("statusCategory", "Done"),
+        None if config.jira_in_flight_statuses.is_empty() => Query::new()
+            .is_in("project", &config.jira_projects)
+            .eq("statusCategory", "In Progress"),
+        None => Query::new()
+            .is_in("project", &config.jira_projects)
+            .is_in("status", &config.jira_in_flight_statuses),
+    };
+    scoped(query, config)
+}
+
+/// a query narrowed to the configured labels and components, if any
+fn scoped(mut query: Query, config: &Config) -> Query {
+    if !config.jira_labels.is_empty() {
+        query = query.is_in("labels", &config.jira_labels);
+    }
+    if !config.jira_components.is_empty() {
+        query = query.is_in("component", &config.jira_components);
+    }
+    query
+}
+
+/// a summary of the issues left out of a group, linking to the configured
+/// saved filter or else a search for the group
+fn more(context: &Context, count: usize, query: &Query) -> String {
+    let config = &context.config;
+    context.templates.render(
+        "jira.more",
+        &json!({
+            "count": count,
+            "url": filter_url(&config.jira_host, link_filter(config), query),
+        }),
+    )
+}
+
+/// the saved filter links open, unless issues are searched for through
+/// saved filters already, which links then search through too
+fn link_filter(config: &Config) -> Option<u64> {
+    config
+        .jira_filter_id
+        .filter(|_| config.jira_shipped_filter.is_none() && config.jira_in_flight_filter.is_none())
+}
+
+/// link to a saved filter, or a search, in the jira ui
+fn filter_url(jira_host: &str, filter_id: Option<u64>, query: &Query) -> String {
+    let issues = format!("{}/issues/", jira_host.trim_end_matches('/'));
+    match filter_id {
+        Some(id) => format!("{}?filter={}", issues, id),
+        None => Url::parse_with_params(&issues, &[("jql", query.to_string())])
+            .map(|url| url.to_string())
+            .unwrap_or(issues),
+    }
+}
+
+/// display position of a status, where it's configured or else where it
+/// falls in the workflow. statuses in neither follow alphabetically
+fn rank(config: &Config, workflow: &Workflow, status: &str) -> usize {
+    let configured = config.status_rank(status);
+    if configured < config.jira_status_order.len() {
+        return configured;
+    }
+    workflow
+        .position(status)
+        .map(|position| configured + position)
+        .unwrap_or(usize::max_value())
+}
+
+/// emoji for a status, where it's configured or else for its category
+fn emoji<'a>(
+    status_emoji: &'a HashMap<String, String>,
+    workflow: &Workflow,
+    status: &str,
+) -> &'a str {
+    status_emoji
+        .get(status)
+        .map(String::as_str)
+        .or_else(|| workflow.category(status).map(|category| category.emoji()))
+        .unwrap_or(":shrug:")
+}
+
+/// emoji for an issue's priority and type, where they're mapped
+fn icons(issue: &Issue, config: &Config) -> String {
+    let name = |field: &str| {
+        issue
+            .field::<Value>(field)
+            .and_then(|value| value.ok())
+            .and_then(|value| value["name"].as_str().map(str::to_owned))
+    };
+    let (priorities, types) = (config.priority_emoji(), config.type_emoji());
+    name("priority")
+        .and_then(|priority| priorities.get(&priority).cloned())
+        .into_iter()
+        .chain(name("issuetype").and_then(|issue_type| types.get(&issue_type).cloned()))
+        .collect()
+}
+
+/// link to an issue in the jira ui
+pub fn browse_url(jira_host: &str, key: &str) -> String {
+    format!("{}/browse/{}", jira_host.trim_end_matches('/'), key)
+}
+
+fn issue_blocks(
+    issue: Issue,
+    status: &str,
+    notes: Vec<String>,
+    context: &Context,
+    signals: &Signals,
+) -> Vec<Block> {
+    let mut line = context.templates.render(
+        "jira.issue",
+        &json!({
+            "url": browse_url(&context.config.jira_host, &issue.key),
+            "key": issue.key,
+            "summary": escape(&issue.summary().unwrap_or_else(|| "no summary".into())),
+            "icons": icons(&issue, &context.config),
+        }),
+    );
+    line.extend(nested(&issue, context, signals));
+    let mut blocks = vec![Block::section(line)];
+    let annotations = owner(&issue, status, &context.users, &signals.workflow)
+        .into_iter()
+        .chain(notes)
+        .collect::<Vec<_>>();
+    if !annotations.is_empty() {
+        blocks.push(Block::context(annotations.join(" · ")));
+    }
+    blocks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use config::tests::config;
+    use jira_auth::tests::{site, RETRY};
+    use mockito::{mock, Matcher};
+    use render::Text;
+    use serde_json;
+
+    fn issue(key: &str, status: &str, field: &str, timestamp: &str) -> Issue {
+        let mut fields = json!({
+            "status": {
+                "description": "",
+                "iconUrl": "https://jira/status.png",
+                "id": "1",
+                "name": status,
+                "self": "https://jira/rest/api/2/status/1"
+            }
+        });
+        fields[field] = json!(timestamp);
+        serde_json::from_value(issue_json(key, fields)).unwrap()
+    }
+
+    /// an issue as jira returns it, with `fields`
+    fn issue_json(key: &str, fields: Value) -> Value {
+        json!({
+            "self": "https://jira/rest/api/2/issue/1",
+            "id": "1",
+            "key": key,
+            "fields": fields
+        })
+    }
+
+    fn keys(issues: &[Issue]) -> Vec<&str> {
+        issues.iter().map(|issue| issue.key.as_str()).collect()
+    }
+
+    /// a page of search results, of `total` issues in all
+    fn results(total: usize, keys: &[&str]) -> String {
+        json!({
+            "total": total,
+            "issues": keys
+                .iter()
+                .map(|key| issue_json(key, json!({})))
+                .collect::<Vec<_>>()
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn searches_stop_at_max_results() {
+        let _page = mock("GET", "/rest/api/2/search")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("jql".into(), "project = \"HUGE\"".into()),
+                Matcher::UrlEncoded("startAt".into(), "0".into()),
+                Matcher::UrlEncoded("maxResults".into(), "2".into()),
+            ]))
+            .with_body(results(5000, &["HUGE-1", "HUGE-2"]))
+            .expect(1)
+            .create();
+        let found = found(&site(), &Query::new().eq("project", "HUGE"), RETRY, Some(2)).unwrap();
+        assert_eq!(keys(&found.issues), vec!["HUGE-1", "HUGE-2"]);
+        assert_eq!(found.total, 5000);
+        _page.assert();
+    }
+
+    #[test]
+    fn searches_fail_when_any_page_does() {
+        let _first = mock("GET", "/rest/api/2/search")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("jql".into(), "project = \"FLAKY\"".into()),
+                Matcher::UrlEncoded("startAt".into(), "0".into()),
+            ]))
+            .with_body(results(3, &["FLAKY-1", "FLAKY-2"]))
+            .create();
+        let _second = mock("GET", "/rest/api/2/search")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("jql".into(), "project = \"FLAKY\"".into()),
+                Matcher::UrlEncoded("startAt".into(), "2".into()),
+            ]))
+            .with_status(500)
+            .create();
+        assert!(found(&site(), &Query::new().eq("project", "FLAKY"), RETRY, None).is_err());
+    }
+
+    #[test]
+    fn issues_entered_their_status_at_their_last_status_change() {
+        let expanded = |histories: Value| {
+            let mut issue = issue_json(
+                "CORE-1",
+                json!({ "created": "2019-01-01T10:00:00.000+0000" }),
+            );
+            issue["changelog"] =
+                json!({ "startAt": 0, "maxResults": 2, "total": 2, "histories": histories });
+            serde_json::from_value::<Expanded>(issue).unwrap()
+        };
+        let changed = expanded(json!([
+            { "created": "2019-01-02T10:00:00.000+0000", "items": [{ "field": "status" }] },
+            { "created": "2019-01-03T10:00:00.000+0000", "items": [{ "field": "assignee" }] }
+        ]));
+        assert_eq!(changed.issue.key, "CORE-1");
+        assert_eq!(
+            entered_status(&changed).map(|entered| entered.to_rfc3339()),
+            Some("2019-01-02T10:00:00+00:00".into())
+        );
+        assert_eq!(
+            entered_status(&expanded(json!([]))).map(|entered| entered.to_rfc3339()),
+            Some("2019-01-01T10:00:00+00:00".into())
+        );
+    }
+
+    #[test]
+    fn subtasks_are_nested_under_listed_parents() {
+        let issue = |key: &str, parent: Option<&str>| {
+            serde_json::from_value::<Issue>(issue_json(
+                key,
+                json!({
+                    "issuetype": { "name": "Sub-task", "subtask": parent.is_some() },
+                    "parent": parent.map(|parent| json!({ "key": parent }))
+                }),
+            ))
+            .unwrap()
+        };
+        let (subtasks, rest) = nest(vec![
+            issue("CORE-1", None),
+            issue("CORE-2", Some("CORE-1")),
+            issue("CORE-3", Some("CORE-9")),
+        ]);
+        assert_eq!(keys(&rest), vec!["CORE-1", "CORE-3"]);
+        assert_eq!(keys(&subtasks["CORE-1"]), vec!["CORE-2"]);
+    }
+
+    #[test]
+    fn issues_are_ordered_by_relevance() {
+        let mut issues = vec![
+            issue(
+                "CORE-1",
+                "Closed",
+                "resolutiondate",
+                "2019-01-07T10:00:00.000+0000",
+            ),
+            issue(
+                "CORE-2",
+                "In Review",
+                "statuscategorychangedate",
+                "2019-01-04T10:00:00.000+0000",
+            ),
+            issue(
+                "CORE-3",
+                "Closed",
+                "resolutiondate",
+                "2019-01-08T10:00:00.000+0000",
+            ),
+            issue(
+                "CORE-10",
+                "In Review",
+                "statuscategorychangedate",
+                "2019-01-02T10:00:00.000+0000",
+            ),
+            issue(
+                "CORE-9",
+                "In Review",
+                "updated",
+                "2019-01-08T10:00:00.000+0000",
+            ),
+        ];
+        let mut entered = HashMap::new();
+        entered.insert(
+            "CORE-2".to_owned(),
+            DateTime::parse_from_str("2019-01-01T10:00:00.000+0000", JIRA_TIMESTAMP).unwrap(),
+        );
+        let workflow = Workflow::default();
+        order(&mut issues, IssueOrder::Relevance, &entered, &workflow);
+        assert_eq!(
+            keys(&issues),
+            vec!["CORE-3", "CORE-1", "CORE-2", "CORE-10", "CORE-9"]
+        );
+
+        order(&mut issues, IssueOrder::Key, &entered, &workflow);
+        assert_eq!(
+            keys(&issues),
+            vec!["CORE-1", "CORE-2", "CORE-3", "CORE-9", "CORE-10"]
+        );
+
+        order(&mut issues, IssueOrder::Updated, &entered, &workflow);
+        assert_eq!(keys(&issues)[0], "CORE-9");
+    }
+
+    #[test]
+    fn statuses_over_their_wip_limit_are_flagged() {
+        let context = Context::new(
+            config(&[("JIRA_WIP_LIMITS", "In Review=2,In Progress=1")]),
+            Some(1),
+        );
+        let workflow = Workflow::default();
+        let in_flight = ["CORE-1", "CORE-2", "CORE-3"]
+            .iter()
+            .map(|key| issue(key, "In Review", "updated", "2019-01-08T10:00:00.000+0000"))
+            .chain(vec![issue(
+                "CORE-4",
+                "In Progress",
+                "updated",
+                "2019-01-08T10:00:00.000+0000",
+            )])
+            .collect::<Vec<_>>();
+        assert_eq!(
+            wip_exceeded(&in_flight, &context, &workflow),
+            vec![Block::section(format!(
+                "⚠️ *WIP limit exceeded* {} In Review has 3 issues, the limit is 2",
+                emoji(&context.config.status_emoji(), &workflow, "In Review")
+            ))]
+        );
+    }
+
+    #[test]
+    fn only_unshipped_resolutions_are_noted() {
+        let resolved = |name: &str| {
+            serde_json::from_value::<Issue>(issue_json(
+                "CORE-1",
+                json!({ "resolution": { "name": name } }),
+            ))
+            .unwrap()
+        };
+        let (config, workflow) = (config(&[]), Workflow::default());
+        assert_eq!(
+            resolution(&resolved("Done"), "Closed", &config, &workflow),
+            None
+        );
+        assert_eq!(
+            resolution(&resolved("Won't Do"), "Closed", &config, &workflow),
+            Some("✔️ Won't Do".into())
+        );
+    }
+
+    #[test]
+    fn due_dates_count_down() {
+        let today = NaiveDate::from_ymd(2019, 1, 9);
+        assert_eq!(due(NaiveDate::from_ymd(2019, 1, 7), today), "🔥 overdue 2d");
+        assert_eq!(due(today, today), "📅 due today");
+        assert_eq!(due(NaiveDate::from_ymd(2019, 1, 11), today), "📅 due Fri");
+        assert_eq!(
+            due(NaiveDate::from_ymd(2019, 1, 25), today),
+            "📅 due Jan 25"
+        );
+    }
+
+    #[test]
+    fn field_values_are_shown_as_text() {
+        assert_eq!(field_text(&json!("Payments")), Some("Payments".into()));
+        assert_eq!(field_text(&json!(3)), Some("3".into()));
+        assert_eq!(
+            field_text(&json!({ "self": "https://jira/option/1", "value": "Production" })),
+            Some("Production".into())
+        );
+        assert_eq!(
+            field_text(&json!([{ "value": "iOS" }, { "value": "Android" }])),
+            Some("iOS, Android".into())
+        );
+        assert_eq!(
+            field_text(&json!({ "name": "dana", "displayName": "Dana" })),
+            Some("Dana".into())
+        );
+        assert_eq!(field_text(&json!([])), None);
+        assert_eq!(field_text(&json!(null)), None);
+    }
+
+    #[test]
+    fn records_are_done_by_status_category() {
+        let record = |status: &str, category: Option<&str>| {
+            serde_json::from_value::<IssueRecord>(json!({
+                "key": "CORE-1",
+                "summary": null,
+                "status": status,
+                "assignee": null,
+                "status_category": category,
+            }))
+            .unwrap()
+        };
+        let workflow = Workflow::default();
+        assert!(record("Resolved", Some("done")).done(&workflow));
+        assert!(!record("Closed", Some("indeterminate")).done(&workflow));
+        assert!(record("Closed", None).done(&workflow));
+
+        let issue = serde_json::from_value::<Issue>(issue_json(
+            "CORE-1",
+            json!({ "status": { "name": "Resolved", "statusCategory": { "key": "done" } } }),
+        ))
+        .unwrap();
+        assert!(IssueRecord::from(&issue).shipped(&[]));
+    }
+
+    #[test]
+    fn in_flight_issues_default_to_the_in_progress_category() {
+        assert_eq!(
+            in_flight_query(&config(&[])).to_string(),
+            r#"project in ("CORE") AND statusCategory = "In Progress""#
+        );
+        assert_eq!(
+            in_flight_query(&config(&[("JIRA_IN_FLIGHT_STATUSES", "Doing,QA")])).to_string(),
+            r#"project in ("CORE") AND status in ("Doing", "QA")"#
+        );
+    }
+
+    #[test]
+    fn saved_filters_are_held_to_status_category() {
+        let filtered = config(&[
+            ("JIRA_IN_FLIGHT_FILTER", "10423"),
+            ("JIRA_FILTER_ID", "10500"),
+        ]);
+        assert_eq!(
+            in_flight_query(&filtered).to_string(),
+            r#"filter = 10423 AND statusCategory != "Done""#
+        );
+        assert_eq!(link_filter(&filtered), None);
+        assert_eq!(
+            link_filter(&config(&[("JIRA_FILTER_ID", "10500")])),
+            Some(10500)
+        );
+    }
+
+    #[test]
+    fn filter_urls_link_saved_filters_or_searches() {
+        let query = Query::new()
+            .is_in("project", &["CORE".into()])
+            .eq("status", "In Progress");
+        assert_eq!(
+            filter_url("https://jira.example.com/", Some(10423), &query),
+            "https://jira.example.com/issues/?filter=10423"
+        );
+        assert_eq!(
+            filter_url("https://jira.example.com", None, &query),
+            "https://jira.example.com/issues/?jql=project+in+%28%22CORE%22%29+AND+status+%3D+%22In+Progress%22"
+        );
+    }
+
+    #[test]
+    fn batched_lookups_keep_their_order() {
+        let items = (0..LOOKUP_BATCH_SIZE * 2 + 1).collect::<Vec<_>>();
+        assert_eq!(
+            batched(items.clone(), |item| item * 2),
+            items.iter().map(|item| Some(item * 2)).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn long_groups_are_spread_over_sections() {
+        let lines = (0..40)
+            .map(|n| {
+                format!(
+                    "• <https://jira/browse/CORE-{}|CORE-{}> {}",
+                    n,
+                    n,
+                    "x".repeat(80)
+                )
+            })
+            .chain(Some("…and 3 more".to_owned()))
+            .collect::<Vec<_>>();
+        let texts = sections("*In Progress*".into(), lines.clone())
+            .into_iter()
+            .map(|block| match block {
+                Block::Section {
+                    text: Some(Text::Markdown { text }),
+                    ..
+                } => text,
+                block => panic!("unexpected block {:?}", block),
+            })
+            .collect::<Vec<_>>();
+        assert!(texts.len() > 1);
+        assert!(texts.iter().all(|text| text.len() <= MAX_SECTION_TEXT));
+        assert_eq!(
+            texts.join("\n"),
+            format!("*In Progress*\n{}", lines.join("\n"))
+        );
+    }
+}
@@ -0,0 +1,240 @@
+//! Standup sections
+//!
+//! Each data source contributes a `Section` to the standup. Sections are
+//! fetched concurrently and rendered in the order they are configured in.
+
+// Std lib
+use std::collections::HashMap;
+
+// Third party
+use chrono::{DateTime, Duration, Utc};
+use chrono_tz::Tz;
+use serde_json::Value;
+
+// Ours
+use calendar::{midnight, Holidays};
+use command::{Detail, Grouping};
+use config::Config;
+use error::FetchError;
+use history::Entry;
+use log;
+use metrics::Metric;
+use render::Block;
+use templates::Templates;
+use users::Directory;
+
+mod blocked;
+mod ci;
+mod deploys;
+mod dora;
+mod flow;
+mod github;
+pub mod jira;
+mod release;
+mod sprint;
+pub mod today;
+mod weather;
+mod worklog;
+
+pub use self::blocked::BlockedSection;
+pub use self::ci::CiSection;
+pub use self::deploys::DeploysSection;
+pub use self::dora::DoraSection;
+pub use self::flow::FlowSection;
+pub use self::github::GitHubSection;
+pub use self::jira::{IssueRecord, JiraSection};
+pub use self::release::ReleaseSection;
+pub use self::sprint::SprintSection;
+pub use self::today::TodaySection;
+pub use self::weather::PagerDutySection;
+pub use self::worklog::WorklogSection;
+
+/// Everything a section may need to fetch and render its content
+pub struct Context {
+    pub config: Config,
+    /// when the standup is being run, in the configured timezone
+    pub now: DateTime<Tz>,
+    /// number of days since the last business day
+    pub lookback_days: i64,
+    pub users: Directory,
+    /// the last standup posted to the same channel, when history is enabled
+    pub previous: Option<Entry>,
+    pub grouping: Grouping,
+    /// details which are listed rather than counted
+    pub details: Vec<Detail>,
+    pub templates: Templates,
+}
+
+impl Context {
+    /// `lookback_days` overrides both configured and computed lookback windows
+    pub fn new(config: Config, lookback_days: Option<i64>) -> Self {
+        let now = Utc::now().with_timezone(&config.timezone);
+        let lookback_days = lookback_days.or(config.lookback_days).unwrap_or_else(|| {
+            Holidays::new(
+                &config.holidays,
+                config.holiday_calendar_url.as_ref().map(String::as_str),
+                &config.http_client(),
+            )
+            .lookback_days(now.date().naive_local())
+        });
+        let users = Directory::new(
+            config.slack_bot_token.clone(),
+            &config.slack_user_overrides,
+            config.http_client(),
+        );
+        let templates = Templates::load(&config);
+        Context {
+            config,
+            now,
+            lookback_days,
+            users,
+            previous: None,
+            grouping: Grouping::default(),
+            details: Detail::all(),
+            templates,
+        }
+    }
+
+    pub fn shows(&self, detail: Detail) -> bool {
+        self.details.contains(&detail)
+    }
+
+    /// the start of the lookback window as a `YYYY-MM-DD` date
+    pub fn since(&self) -> String {
+        (self.now - Duration::days(self.lookback_days))
+            .format("%F")
+            .to_string()
+    }
+
+    /// the start of the lookback window, midnight in the configured timezone
+    pub fn since_midnight(&self) -> DateTime<Tz> {
+        midnight(self.now - Duration::days(self.lookback_days))
+    }
+
+    /// the start of the lookback window as a timestamp
+    pub fn since_timestamp(&self) -> String {
+        self.since_midnight().to_rfc3339()
+    }
+}
+
+/// A source of standup content
+pub trait Section: Send + Sync {
+    type Data;
+
+    /// heading displayed above the section
+    fn title(&self) -> String;
+
+    fn fetch(&self, context: &Context) -> Result<Self::Data, FetchError>;
+
+    fn render(&self, data: Self::Data, context: &Context) -> Vec<Block>;
+
+    /// raw data worth keeping in the standup history
+    fn record(&self, _data: &Self::Data) -> Value {
+        Value::Null
+    }
+
+    /// measurements published to cloudwatch
+    fn metrics(&self, _data: &Self::Data) -> Vec<Metric> {
+        Vec::new()
+    }
+
+    /// headline figures, leading the standup
+    fn summary(&self, _data: &Self::Data, _context: &Context) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+/// What a section contributed to a standup
+pub struct Output {
+    pub blocks: Vec<Block>,
+    /// see `Section::record`
+    pub record: Value,
+    /// see `Section::summary`
+    pub summary: Vec<String>,
+    /// see `Section::metrics`
+    pub metrics: Vec<Metric>,
+    /// why the section couldn't be loaded
+    pub error: Option<FetchError>,
+}
+
+/// An object safe `Section` which fetches and renders in one step
+pub trait Report: Send + Sync {
+    fn title(&self) -> String;
+
+    fn report(&self, context: &Context) -> Output;
+}
+
+impl<S> Report for S
+where
+    S: Section,
+{
+    fn title(&self) -> String {
+        Section::title(self)
+    }
+
+    fn report(&self, context: &Context) -> Output {
+        match self.fetch(context) {
+            Ok(data) => {
+                let record = self.record(&data);
+                let summary = self.summary(&data, context);
+                let metrics = self.metrics(&data);
+                Output {
+                    blocks: self.render(data, context),
+                    record,
+                    summary,
+                    metrics,
+                    error: None,
+                }
+            }
+            Err(err) => Output {
+                blocks: vec![warning(&err)],
+                record: Value::Null,
+                summary: Vec::new(),
+                metrics: Vec::new(),
+                error: Some(err),
+            },
+        }
+    }
+}
+
+/// a section which failed to load renders this in place of its content
+pub fn warning(err: &FetchError) -> Block {
+    Block::context(format!("⚠️ {}", err))
+}
+
+/// all known sections keyed by the name they are configured and requested by
+fn available() -> Vec<(&'static str, Box<dyn Report>)> {
+    vec![
+        ("weather", Box::new(PagerDutySection) as Box<dyn Report>),
+        ("blocked", Box::new(BlockedSection) as Box<dyn Report>),
+        ("jira", Box::new(JiraSection) as Box<dyn Report>),
+        ("github", Box::new(GitHubSection) as Box<dyn Report>),
+        ("sprint", Box::new(SprintSection) as Box<dyn Report>),
+        ("ci", Box::new(CiSection) as Box<dyn Report>),
+        ("deploys", Box::new(DeploysSection) as Box<dyn Report>),
+        ("today", Box::new(TodaySection) as Box<dyn Report>),
+        ("release", Box::new(ReleaseSection) as Box<dyn Report>),
+        ("worklog", Box::new(WorklogSection) as Box<dyn Report>),
+        ("flow", Box::new(FlowSection) as Box<dyn Report>),
+        ("dora", Box::new(DoraSection) as Box<dyn Report>),
+    ]
+}
+
+/// the sections enabled by config, in display order
+pub fn registry(config: &Config) -> Vec<(String, Box<dyn Report>)> {
+    let mut available = available().into_iter().collect::<HashMap<_, _>>();
+    config
+        .standup_sections
+        .iter()
+        .filter_map(|name| match available.remove(name.as_str()) {
+            Some(section) => Some((name.clone(), section)),
+            None => {
+                log::warn(
+                    "skipping unknown or duplicate section",
+                    json!({ "section": name }),
+                );
+                None
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,177 @@
+//! Progress toward the next release, tracked by Jira fix version
+
+// Third party
+use goji::Issue;
+use serde_json::Value;
+
+// Ours
+use super::jira::{self, IssueRecord};
+use super::{Context, Section};
+use agile::{Agile, Version};
+use error::FetchError;
+use jira_auth::Site;
+use jql::Query;
+use metrics::Metric;
+use render::{escape, Block};
+use retry::Retry;
+
+pub struct ReleaseSection;
+
+pub struct Readiness {
+    /// absent when the version hasn't been created in jira
+    version: Option<Version>,
+    name: String,
+    done: Vec<Issue>,
+    remaining: Vec<Issue>,
+}
+
+impl Section for ReleaseSection {
+    type Data = Readiness;
+
+    fn title(&self) -> String {
+        "🎁 Release".into()
+    }
+
+    fn fetch(&self, context: &Context) -> Result<Readiness, FetchError> {
+        let config = &context.config;
+        let name = config
+            .jira_fix_version
+            .clone()
+            .ok_or_else(|| FetchError::new("Jira", "no jira_fix_version configured"))?;
+        let agile = Agile::new(Site::configured(config)?, Retry::from(config));
+        let mut version = None;
+        for project in &config.jira_projects {
+            version = agile
+                .versions(project)?
+                .into_iter()
+                .find(|version| version.name == name);
+            if version.is_some() {
+                break;
+            }
+        }
+        let issues = jira::search(
+            &jira::client(config)?,
+            &Query::new()
+                .is_in("project", &config.jira_projects)
+                .eq("fixVersion", &name)
+                .order_by("status")
+                .order_by("key"),
+            Retry::from(config),
+        )?;
+        let (done, remaining) = issues.into_iter().partition(is_done);
+        Ok(Readiness {
+            version,
+            name,
+            done,
+            remaining,
+        })
+    }
+
+    fn record(&self, readiness: &Readiness) -> Value {
+        json!({
+            "version": readiness.version,
+            "done": readiness.done.iter().map(IssueRecord::from).collect::<Vec<_>>(),
+            "remaining": readiness.remaining.iter().map(IssueRecord::from).collect::<Vec<_>>(),
+        })
+    }
+
+    fn metrics(&self, readiness: &Readiness) -> Vec<Metric> {
+        vec![
+            Metric::count("ReleaseDoneIssues", readiness.done.len()),
+            Metric::count("ReleaseRemainingIssues", readiness.remaining.len()),
+        ]
+    }
+
+    fn summary(&self, readiness: &Readiness, _: &Context) -> Vec<String> {
+        vec![format!(
+            "🎁 {} remaining for {}",
+            readiness.remaining.len(),
+            readiness.name
+        )]
+    }
+
+    fn render(&self, readiness: Readiness, context: &Context) -> Vec<Block> {
+        let templates = &context.templates;
+        let release_date = readiness
+            .version
+            .as_ref()
+            .and_then(|version| version.release_date);
+        let days_left = release_date.map(|date| {
+            match date
+                .signed_duration_since(context.now.date().naive_local())
+                .num_days()
+            {
+                days if days < 0 => "overdue".to_owned(),
+                0 => "due today".to_owned(),
+                1 => "1 day left".to_owned(),
+                days => format!("{} days left", days),
+            }
+        });
+        let total = readiness.done.len() + readiness.remaining.len();
+        let mut blocks = vec![
+            Block::section(templates.render(
+                "release.heading",
+                &json!({
+                    "version": escape(&readiness.name),
+                    "date": release_date.map(|date| date.format("%a %b %-d").to_string()),
+                    "days_left": days_left,
+                    "released": readiness.version.as_ref().map(|version| version.released),
+                }),
+            )),
+            Block::context(templates.render(
+                "release.progress",
+                &json!({
+                    "done": readiness.done.len(),
+                    "remaining": readiness.remaining.len(),
+                    "total": total,
+                    "percent": if total == 0 { 0 } else { readiness.done.len() * 100 / total },
+                }),
+            )),
+        ];
+        blocks.extend(readiness.remaining.into_iter().map(|issue| {
+            Block::section(templates.render(
+                "release.issue",
+                &json!({
+                    "url": jira::browse_url(&context.config.jira_host, &issue.key),
+                    "summary": escape(&issue.summary().unwrap_or_default()),
+                    "status": issue.status().map(|status| status.name),
+                    "key": issue.key,
+                }),
+            ))
+        }));
+        blocks
+    }
+}
+
+/// issues in a status jira categorizes as done
+pub fn is_done(issue: &Issue) -> bool {
+    issue
+        .field::<Value>("status")
+        .and_then(|status| status.ok())
+        .map(|status| status["statusCategory"]["key"] == "done")
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json;
+
+    fn issue(category: &str) -> Issue {
+        serde_json::from_value(json!({
+            "self": "https://jira/rest/api/2/issue/1",
+            "id": "1",
+            "key": "CORE-1",
+            "fields": {
+                "status": { "name": "Shipped", "statusCategory": { "key": category } }
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn issues_are_done_by_status_category() {
+        assert!(is_done(&issue("done")));
+        assert!(!is_done(&issue("indeterminate")));
+    }
+}
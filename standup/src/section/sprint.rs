@@ -0,0 +1,87 @@
+//! Progress of the active sprint in Jira Agile
+
+// Third party
+use serde_json::Value;
+
+// Ours
+use super::{Context, Section};
+use agile::{Agile, Progress, Sprint};
+use error::FetchError;
+use jira_auth::Site;
+use render::{escape, Block};
+use retry::Retry;
+
+pub struct SprintSection;
+
+pub struct Sprinting {
+    sprint: Option<Sprint>,
+    progress: Option<Progress>,
+}
+
+impl Section for SprintSection {
+    type Data = Sprinting;
+
+    fn title(&self) -> String {
+        "🏃 Sprint".into()
+    }
+
+    fn fetch(&self, context: &Context) -> Result<Sprinting, FetchError> {
+        let config = &context.config;
+        let board_id = config
+            .jira_board_id
+            .ok_or_else(|| FetchError::new("Jira Agile", "no jira_board_id configured"))?;
+        let agile = Agile::new(Site::configured(config)?, Retry::from(config));
+        let sprint = agile.active_sprint(board_id)?;
+        let progress = match sprint {
+            Some(ref sprint) => Some(agile.progress(board_id, sprint.id)?),
+            None => None,
+        };
+        Ok(Sprinting { sprint, progress })
+    }
+
+    fn record(&self, sprinting: &Sprinting) -> Value {
+        json!({
+            "sprint": sprinting.sprint,
+            "progress": sprinting.progress,
+        })
+    }
+
+    fn render(&self, sprinting: Sprinting, context: &Context) -> Vec<Block> {
+        let sprint = match sprinting.sprint {
+            Some(sprint) => sprint,
+            None => return vec![Block::section("No active sprint")],
+        };
+        let mut heading = format!("*{}*", sprint.name);
+        if let Some(end) = sprint.end_date {
+            let remaining = end
+                .with_timezone(&context.config.timezone)
+                .date()
+                .signed_duration_since(context.now.date())
+                .num_days();
+            heading.push_str(&match remaining {
+                days if days < 0 => " · ended".to_owned(),
+                0 => " · ends today".to_owned(),
+                1 => " · 1 day left".to_owned(),
+                days => format!(" · {} days left", days),
+            });
+        }
+        let mut blocks = vec![Block::section(heading)];
+        if let Some(goal) = sprint.goal.filter(|goal| !goal.is_empty()) {
+            blocks.push(Block::context(format!("🎯 {}", escape(&goal))));
+        }
+        if let Some(progress) = sprinting.progress {
+            blocks.push(Block::fields(vec![
+                format!("*Committed*\n{} pts", progress.committed),
+                format!("*Completed*\n{} pts", progress.completed),
+            ]));
+            if !progress.added.is_empty() || !progress.removed.is_empty() {
+                blocks.push(Block::context(format!(
+                    "Scope change: +{} added, −{} removed",
+                    progress.added.len(),
+                    progress.removed.len()
+                )));
+            }
+        }
+        blocks
+    }
+}
@@ -0,0 +1,186 @@
+//! Who's out and what's on the team's shared Google Calendar today
+
+// Third party
+use chrono::Duration;
+use serde_json::Value;
+
+// Ours
+use super::{Context, Section};
+use calendar::midnight;
+use error::FetchError;
+use google_calendar::{Event, GoogleCalendar};
+use metrics::Metric;
+use render::{escape, Block};
+
+pub struct TodaySection;
+
+pub struct Today {
+    /// who's out of office
+    out: Vec<String>,
+    events: Vec<Event>,
+}
+
+impl Section for TodaySection {
+    type Data = Today;
+
+    fn title(&self) -> String {
+        "📅 Today".into()
+    }
+
+    fn fetch(&self, context: &Context) -> Result<Today, FetchError> {
+        let config = &context.config;
+        let calendar_id = config.google_calendar_id.clone().ok_or_else(|| {
+            FetchError::new("Google Calendar", "no google_calendar_id configured")
+        })?;
+        let calendar = GoogleCalendar::configured(config).ok_or_else(|| {
+            FetchError::new(
+                "Google Calendar",
+                "no google oauth client and refresh token configured",
+            )
+        })?;
+        let today = midnight(context.now);
+        let tomorrow = today + Duration::days(1);
+        let (out, events) = calendar
+            .events(&calendar_id, &today.to_rfc3339(), &tomorrow.to_rfc3339())?
+            .into_iter()
+            .partition::<Vec<_>, _>(|event| is_out(event, &config.calendar_ooo_keywords));
+        Ok(Today {
+            out: out
+                .iter()
+                .map(|event| who(event, &config.calendar_ooo_keywords))
+                .collect(),
+            events,
+        })
+    }
+
+    fn record(&self, today: &Today) -> Value {
+        json!({
+            "out": today.out,
+            "events": today.events,
+        })
+    }
+
+    fn metrics(&self, today: &Today) -> Vec<Metric> {
+        vec![Metric::count("OutOfOffice", today.out.len())]
+    }
+
+    fn summary(&self, today: &Today, _: &Context) -> Vec<String> {
+        match today.out.len() {
+            0 => Vec::new(),
+            out => vec![format!("🌴 {} out", out)],
+        }
+    }
+
+    fn render(&self, today: Today, context: &Context) -> Vec<Block> {
+        let templates = &context.templates;
+        if today.out.is_empty() && today.events.is_empty() {
+            return vec![Block::section(templates.render("today.empty", &json!({})))];
+        }
+        let mut blocks = Vec::new();
+        if !today.out.is_empty() {
+            blocks.push(Block::section(
+                templates.render("today.out", &json!({ "out": today.out.join(", ") })),
+            ));
+        }
+        blocks.extend(today.events.into_iter().map(|event| {
+            let time = match event.start.date_time {
+                Some(start) => start
+                    .with_timezone(&context.config.timezone)
+                    .format("%-I:%M%P")
+                    .to_string(),
+                None => "All day".into(),
+            };
+            Block::section(templates.render(
+                "today.event",
+                &json!({
+                    "time": time,
+                    "url": event.html_link,
+                    "summary": escape(&event.summary.unwrap_or_else(|| "Busy".into())),
+                }),
+            ))
+        }));
+        blocks
+    }
+}
+
+/// out of office events, or all day events mentioning one of `keywords`
+pub fn is_out(event: &Event, keywords: &[String]) -> bool {
+    event.event_type.as_ref().map(String::as_str) == Some("outOfOffice")
+        || (event.is_all_day()
+            && event
+                .summary
+                .as_ref()
+                .map(|summary| !words(summary, keywords).1.is_empty())
+                .unwrap_or(false))
+}
+
+/// who an out of office event is for, i.e. `Dana` for `Dana OOO`
+fn who(event: &Event, keywords: &[String]) -> String {
+    if event.event_type.as_ref().map(String::as_str) == Some("outOfOffice") {
+        if let Some(creator) = event.creator.as_ref().and_then(|creator| {
+            creator
+                .display_name
+                .clone()
+                .or_else(|| creator.email.clone())
+        }) {
+            return creator;
+        }
+    }
+    let summary = event.summary.clone().unwrap_or_default();
+    let (rest, _) = words(&summary, keywords);
+    if rest.is_empty() {
+        summary
+    } else {
+        rest.join(" ")
+    }
+}
+
+/// the words of a summary which aren't `keywords`, and those which are
+fn words<'a>(summary: &'a str, keywords: &[String]) -> (Vec<&'a str>, Vec<&'a str>) {
+    summary
+        .split_whitespace()
+        .map(|word| word.trim_matches(|c: char| !c.is_alphanumeric()))
+        .filter(|word| !word.is_empty())
+        .partition(|word| {
+            !keywords
+                .iter()
+                .any(|keyword| keyword.eq_ignore_ascii_case(word))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use google_calendar::EventTime;
+
+    fn all_day(summary: &str) -> Event {
+        Event {
+            summary: Some(summary.into()),
+            html_link: "https://calendar".into(),
+            start: EventTime {
+                date: Some("2019-01-07".parse().unwrap()),
+                date_time: None,
+            },
+            event_type: None,
+            creator: None,
+        }
+    }
+
+    fn keywords() -> Vec<String> {
+        vec!["OOO".into(), "PTO".into(), "Out".into()]
+    }
+
+    #[test]
+    fn all_day_events_mentioning_keywords_are_out_of_office() {
+        assert!(is_out(&all_day("Dana OOO"), &keywords()));
+        assert!(is_out(&all_day("Sam - out (sick)"), &keywords()));
+        assert!(!is_out(&all_day("Outage review"), &keywords()));
+    }
+
+    #[test]
+    fn keywords_are_stripped_from_who_is_out() {
+        assert_eq!(who(&all_day("Dana OOO"), &keywords()), "Dana");
+        assert_eq!(who(&all_day("PTO: Sam Lee"), &keywords()), "Sam Lee");
+        assert_eq!(who(&all_day("OOO"), &keywords()), "OOO");
+    }
+}
@@ -0,0 +1,353 @@
+//! PagerDuty weather report
+
+// Third party
+use chrono::Duration;
+use serde_json::Value;
+
+// Ours
+use super::{warning, Context, Section};
+use breaker::Breaker;
+use cache::Cache;
+use command::Detail;
+use config::IncidentSource;
+use error::FetchError;
+use metrics::Metric;
+use opsgenie::Opsgenie;
+use pagerduty::{Incident, IncidentMetrics, OnCall, PagerDuty, Scope};
+use render::{escape, Block};
+use retry::Retry;
+
+pub struct PagerDutySection;
+
+pub struct Weather {
+    oncalls: Result<Vec<OnCall>, FetchError>,
+    open: Vec<Incident>,
+    resolved: Result<Vec<Incident>, FetchError>,
+    /// only fetched when `pd_analytics` is enabled
+    analytics: Option<Result<Analytics, FetchError>>,
+}
+
+/// incident metrics for the past week and the week before it
+#[derive(Serialize, Debug)]
+pub struct Analytics {
+    this_week: IncidentMetrics,
+    last_week: IncidentMetrics,
+}
+
+impl Section for PagerDutySection {
+    type Data = Weather;
+
+    fn title(&self) -> String {
+        "⛅ Weather Report".into()
+    }
+
+    fn fetch(&self, context: &Context) -> Result<Weather, FetchError> {
+        if context.config.incident_source == IncidentSource::Opsgenie {
+            return fetch_opsgenie(context);
+        }
+        let config = &context.config;
+        let pagerduty = PagerDuty::new(
+            config.pd_token.clone(),
+            config.http_client(),
+            Retry::from(config),
+        )
+        .with_cache(Cache::from(config))
+        .with_breaker(Breaker::from(config));
+        let since = context.since_timestamp();
+        let scope = Scope {
+            team_ids: &config.pd_team_ids,
+            service_ids: &config.pd_service_ids,
+            urgencies: &config.pd_urgencies,
+        };
+        Ok(Weather {
+            open: pagerduty.open_incidents(&scope, &since)?,
+            oncalls: pagerduty.oncalls(&config.pd_escalation_policy_ids, &config.pd_team_ids),
+            resolved: pagerduty.resolved_incidents(&scope, &since),
+            analytics: if config.pd_analytics {
+                Some(analytics(&pagerduty, &scope, context))
+            } else {
+                None
+            },
+        })
+    }
+
+    fn record(&self, weather: &Weather) -> Value {
+        json!({
+            "oncalls": weather.oncalls.as_ref().ok(),
+            "open": weather.open,
+            "resolved": weather.resolved.as_ref().ok(),
+            "analytics": weather
+                .analytics
+                .as_ref()
+                .and_then(|analytics| analytics.as_ref().ok()),
+        })
+    }
+
+    fn metrics(&self, weather: &Weather) -> Vec<Metric> {
+        let mut metrics = vec![Metric::count("OpenIncidents", weather.open.len())];
+        if let Ok(ref resolved) = weather.resolved {
+            metrics.push(Metric::count("ResolvedIncidents", resolved.len()));
+        }
+        metrics
+    }
+
+    fn summary(&self, weather: &Weather, _: &Context) -> Vec<String> {
+        match weather.open.len() {
+            0 => Vec::new(),
+            1 => vec!["🚨 1 open incident".into()],
+            open => vec![format!("🚨 {} open incidents", open)],
+        }
+    }
+
+    fn render(&self, weather: Weather, context: &Context) -> Vec<Block> {
+        let mut blocks = Vec::new();
+        match weather.oncalls {
+            Ok(ref oncalls) if oncalls.is_empty() => (),
+            Ok(oncalls) => {
+                let oncall = oncalls
+                    .into_iter()
+                    .map(|oncall| match oncall.end {
+                        Some(end) => format!(
+                            "{} (until {})",
+                            escape(&oncall.user.summary),
+                            end.with_timezone(&context.config.timezone)
+                                .format("%a %-I:%M%P")
+                        ),
+                        None => escape(&oncall.user.summary),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                blocks.push(Block::section(
+                    context
+                        .templates
+                        .render("weather.oncall", &json!({ "oncall": oncall })),
+                ))
+            }
+            Err(err) => blocks.push(warning(&err)),
+        }
+        if weather.open.is_empty() {
+            blocks.push(Block::section(
+                context.templates.render("weather.empty", &json!({})),
+            ));
+        }
+        blocks.extend(
+            weather
+                .open
+                .into_iter()
+                .map(|incident| incident_block(incident, context)),
+        );
+        match weather.resolved {
+            Ok(ref resolved) if resolved.is_empty() => (),
+            Ok(ref resolved) if !context.shows(Detail::Resolved) => {
+                blocks.push(Block::context(context.templates.render(
+                    "weather.resolved.collapsed",
+                    &json!({ "count": resolved.len() }),
+                )))
+            }
+            Ok(resolved) => {
+                blocks.push(Block::section(
+                    context
+                        .templates
+                        .render("weather.resolved.heading", &json!({})),
+                ));
+                blocks.extend(
+                    resolved
+                        .into_iter()
+                        .map(|incident| incident_block(incident, context)),
+                );
+            }
+            Err(err) => blocks.push(warning(&err)),
+        }
+        match weather.analytics {
+            Some(Ok(ref analytics)) => blocks.push(Block::context(
+                context
+                    .templates
+                    .render("weather.analytics", &analytics_context(analytics)),
+            )),
+            Some(Err(ref err)) => blocks.push(warning(err)),
+            None => (),
+        }
+        blocks
+    }
+}
+
+fn fetch_opsgenie(context: &Context) -> Result<Weather, FetchError> {
+    let config = &context.config;
+    let api_key = config
+        .opsgenie_api_key
+        .clone()
+        .ok_or_else(|| FetchError::new("Opsgenie", "no opsgenie_api_key configured"))?;
+    let mut opsgenie = Opsgenie::new(api_key, config.http_client(), Retry::from(config));
+    if let Some(ref api) = config.opsgenie_api_url {
+        opsgenie = opsgenie.with_api(api.clone());
+    }
+    let since = context.since_midnight().timestamp_millis();
+    Ok(Weather {
+        open: opsgenie.open_alerts(&config.opsgenie_teams, since)?,
+        oncalls: opsgenie.oncalls(&config.opsgenie_schedules),
+        resolved: opsgenie.closed_alerts(&config.opsgenie_teams, since),
+        // opsgenie's analytics aren't available through its api
+        analytics: None,
+    })
+}
+
+fn analytics(
+    pagerduty: &PagerDuty,
+    scope: &Scope,
+    context: &Context,
+) -> Result<Analytics, FetchError> {
+    let week_ago = context.now - Duration::weeks(1);
+    let fortnight_ago = week_ago - Duration::weeks(1);
+    Ok(Analytics {
+        this_week: pagerduty.incident_metrics(
+            scope,
+            &week_ago.to_rfc3339(),
+            &context.now.to_rfc3339(),
+        )?,
+        last_week: pagerduty.incident_metrics(
+            scope,
+            &fortnight_ago.to_rfc3339(),
+            &week_ago.to_rfc3339(),
+        )?,
+    })
+}
+
+fn analytics_context(analytics: &Analytics) -> Value {
+    let (this_week, last_week) = (&analytics.this_week, &analytics.last_week);
+    json!({
+        "incidents": this_week.total_incident_count,
+        "incidents_change": change(
+            Some(this_week.total_incident_count as f64),
+            Some(last_week.total_incident_count as f64),
+            |count| count.to_string(),
+        ),
+        "mtta": this_week
+            .mean_seconds_to_first_ack
+            .map(duration)
+            .unwrap_or_else(|| "n/a".into()),
+        "mtta_change": change(
+            this_week.mean_seconds_to_first_ack,
+            last_week.mean_seconds_to_first_ack,
+            duration,
+        ),
+        "mttr": this_week
+            .mean_seconds_to_resolve
+            .map(duration)
+            .unwrap_or_else(|| "n/a".into()),
+        "mttr_change": change(
+            this_week.mean_seconds_to_resolve,
+            last_week.mean_seconds_to_resolve,
+            duration,
+        ),
+    })
+}
+
+/// the difference from last week, i.e. `+2` or `-5m`, if both weeks have a value
+fn change<F>(this_week: Option<f64>, last_week: Option<f64>, format: F) -> Option<String>
+where
+    F: Fn(f64) -> String,
+{
+    match (this_week, last_week) {
+        (Some(this_week), Some(last_week)) if this_week > last_week => {
+            Some(format!("+{}", format(this_week - last_week)))
+        }
+        (Some(this_week), Some(last_week)) if this_week < last_week => {
+            Some(format!("-{}", format(last_week - this_week)))
+        }
+        (Some(_), Some(_)) => Some("±0".into()),
+        _ => None,
+    }
+}
+
+/// a mean number of seconds, roughly
+pub fn duration(seconds: f64) -> String {
+    let seconds = seconds.round() as i64;
+    if seconds < 60 {
+        format!("{}s", seconds)
+    } else if seconds < 60 * 60 {
+        format!("{}m", seconds / 60)
+    } else {
+        format!("{}h {}m", seconds / (60 * 60), seconds % (60 * 60) / 60)
+    }
+}
+
+fn incident_block(incident: Incident, context: &Context) -> Block {
+    let age = incident
+        .created_at
+        .map(|created_at| age(context.now.signed_duration_since(created_at)));
+    let assignees = incident
+        .assignments
+        .iter()
+        .map(|assignment| format!("@{}", escape(&assignment.assignee.summary)))
+        .collect::<Vec<_>>();
+    // i.e. `HIGH, 7h old, @dana`
+    let tags = incident
+        .urgency
+        .as_ref()
+        .map(|urgency| urgency.to_uppercase())
+        .into_iter()
+        .chain(age.clone())
+        .chain(assignees.iter().cloned())
+        .collect::<Vec<_>>()
+        .join(", ");
+    Block::fields(vec![
+        context.templates.render(
+            "weather.incident",
+            &json!({
+                "url": incident.html_url,
+                "number": incident.incident_number,
+                "title": escape(&incident.title),
+                "urgency": incident.urgency,
+                "age": age,
+                "assignees": assignees,
+                "tags": tags,
+            }),
+        ),
+        context.templates.render(
+            "weather.incident.status",
+            &json!({ "status": incident.status }),
+        ),
+    ])
+}
+
+/// how long ago something happened, roughly
+fn age(elapsed: Duration) -> String {
+    if elapsed.num_hours() < 1 {
+        format!("{}m old", elapsed.num_minutes().max(0))
+    } else if elapsed.num_hours() < 48 {
+        format!("{}h old", elapsed.num_hours())
+    } else {
+        format!("{}d old", elapsed.num_days())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ages_are_rounded_down_to_a_readable_unit() {
+        assert_eq!(age(Duration::minutes(42)), "42m old");
+        assert_eq!(age(Duration::minutes(7 * 60 + 59)), "7h old");
+        assert_eq!(age(Duration::hours(50)), "2d old");
+    }
+
+    #[test]
+    fn durations_are_rounded_to_a_readable_unit() {
+        assert_eq!(duration(42.4), "42s");
+        assert_eq!(duration(150.0), "2m");
+        assert_eq!(duration(3.0 * 60.0 * 60.0 + 125.0), "3h 2m");
+    }
+
+    #[test]
+    fn changes_are_relative_to_last_week() {
+        let count = |count: f64| count.to_string();
+        assert_eq!(change(Some(5.0), Some(3.0), count), Some("+2".into()));
+        assert_eq!(
+            change(Some(90.0), Some(400.0), duration),
+            Some("-5m".into())
+        );
+        assert_eq!(change(Some(3.0), Some(3.0), count), Some("±0".into()));
+        assert_eq!(change(Some(90.0), None, duration), None);
+    }
+}
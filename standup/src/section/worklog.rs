@@ -0,0 +1,207 @@
+//! Time logged in Jira
+
+// Std lib
+use std::collections::HashMap;
+
+// Third party
+use chrono::{DateTime, FixedOffset, TimeZone};
+use serde_json::{self, Value};
+
+// Ours
+use super::jira::{self, JIRA_TIMESTAMP};
+use super::{Context, Section};
+use calendar::midnight;
+use error::FetchError;
+use jira_auth::Site;
+use jql::Query;
+use metrics::Metric;
+use render::Block;
+use retry::Retry;
+use users::JiraUser;
+
+pub struct WorklogSection;
+
+/// Seconds logged per person, most first
+pub struct Timesheet {
+    logged: Vec<(JiraUser, u64)>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Worklogs {
+    worklogs: Vec<Worklog>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct Worklog {
+    author: JiraUser,
+    started: String,
+    time_spent_seconds: u64,
+}
+
+impl Section for WorklogSection {
+    type Data = Timesheet;
+
+    fn title(&self) -> String {
+        "⏱ Time Logged".into()
+    }
+
+    fn fetch(&self, context: &Context) -> Result<Timesheet, FetchError> {
+        let config = &context.config;
+        let jira = jira::client(config)?;
+        let retry = Retry::from(config);
+        let issues = jira::search(
+            &jira,
+            &Query::new()
+                .is_in("project", &config.jira_projects)
+                .at_least("worklogDate", &context.since()),
+            retry,
+        )?;
+
+        // worklogs are logged against issues, so they're gathered issue by issue
+        let since = context.since_midnight();
+        let until = midnight(context.now);
+        let started_after = since.timestamp_millis();
+        let lookups = jira::batched(issues, move |issue| {
+            worklogs(&jira, &issue.key, started_after, retry)
+        });
+        let mut logged = HashMap::new();
+        for lookup in lookups {
+            let worklogs = lookup.unwrap_or_else(|| Err(FetchError::new("Jira", "panicked")))?;
+            for worklog in worklogs {
+                if within(&worklog, &since, &until) {
+                    logged
+                        .entry(worklog.author.name.clone())
+                        .or_insert((worklog.author, 0))
+                        .1 += worklog.time_spent_seconds;
+                }
+            }
+        }
+        let mut logged = logged
+            .into_iter()
+            .map(|(_, logged)| logged)
+            .collect::<Vec<_>>();
+        logged.sort_by(|(a, a_seconds), (b, b_seconds)| {
+            b_seconds.cmp(a_seconds).then(a.name.cmp(&b.name))
+        });
+        Ok(Timesheet { logged })
+    }
+
+    fn record(&self, timesheet: &Timesheet) -> Value {
+        json!(timesheet
+            .logged
+            .iter()
+            .map(|(user, seconds)| (user.name.clone(), seconds))
+            .collect::<HashMap<_, _>>())
+    }
+
+    fn metrics(&self, timesheet: &Timesheet) -> Vec<Metric> {
+        vec![Metric::count(
+            "LoggedHours",
+            total(timesheet) as usize / 3600,
+        )]
+    }
+
+    fn summary(&self, timesheet: &Timesheet, _: &Context) -> Vec<String> {
+        vec![format!("⏱ {} logged", hours(total(timesheet)))]
+    }
+
+    fn render(&self, timesheet: Timesheet, context: &Context) -> Vec<Block> {
+        if timesheet.logged.is_empty() {
+            return vec![Block::section(
+                context.templates.render("worklog.empty", &json!({})),
+            )];
+        }
+        vec![Block::section(
+            timesheet
+                .logged
+                .iter()
+                .map(|(user, seconds)| {
+                    format!("• {} {}", context.users.mention(user), hours(*seconds))
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+        )]
+    }
+}
+
+/// worklogs of an issue started after a time, in milliseconds since the epoch
+fn worklogs(
+    jira: &Site,
+    key: &str,
+    started_after: i64,
+    retry: Retry,
+) -> Result<Vec<Worklog>, FetchError> {
+    let path = format!("/rest/api/2/issue/{}/worklog", key);
+    let started_after = started_after.to_string();
+    jira.get_json(&path, &[("startedAfter", started_after.as_str())], retry)
+        .and_then(|worklogs| {
+            serde_json::from_value::<Worklogs>(worklogs)
+                .map_err(|err| FetchError::new("Jira", err.to_string()))
+        })
+        .map(|worklogs| worklogs.worklogs)
+}
+
+/// whether a worklog was started from `since` up to, but not including, `until`
+fn within<Tz: TimeZone>(worklog: &Worklog, since: &DateTime<Tz>, until: &DateTime<Tz>) -> bool {
+    DateTime::parse_from_str(&worklog.started, JIRA_TIMESTAMP)
+        .map(|started: DateTime<FixedOffset>| started >= *since && started < *until)
+        .unwrap_or(false)
+}
+
+fn total(timesheet: &Timesheet) -> u64 {
+    timesheet.logged.iter().map(|(_, seconds)| seconds).sum()
+}
+
+/// seconds as hours, i.e. `7.5h`
+fn hours(seconds: u64) -> String {
+    format!("{}h", (seconds as f64 / 360.0).round() / 10.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    #[test]
+    fn hours_are_rounded_to_tenths() {
+        assert_eq!(hours(27000), "7.5h");
+        assert_eq!(hours(3600), "1h");
+        assert_eq!(hours(1000), "0.3h");
+    }
+
+    #[test]
+    fn worklogs_count_within_the_window() {
+        let since = Utc.ymd(2019, 1, 7).and_hms(0, 0, 0);
+        let until = Utc.ymd(2019, 1, 8).and_hms(0, 0, 0);
+        let worklog = |started: &str| Worklog {
+            author: JiraUser {
+                name: "jdoe".into(),
+                email_address: None,
+            },
+            started: started.into(),
+            time_spent_seconds: 3600,
+        };
+        assert!(within(
+            &worklog("2019-01-07T00:00:00.000+0000"),
+            &since,
+            &until
+        ));
+        assert!(within(
+            &worklog("2019-01-07T18:30:00.000-0500"),
+            &since,
+            &until
+        ));
+        assert!(!within(
+            &worklog("2019-01-06T23:59:59.000+0000"),
+            &since,
+            &until
+        ));
+        assert!(!within(
+            &worklog("2019-01-07T19:00:00.000-0500"),
+            &since,
+            &until
+        ));
+        assert!(!within(&worklog("yesterday"), &since, &until));
+    }
+}
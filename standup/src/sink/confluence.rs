@@ -0,0 +1,282 @@
+//! Confluence delivery, as pages in a space or sections of a running page
+//!
+//! see https://developer.atlassian.com/cloud/confluence/rest/v1/api-group-content/#api-wiki-rest-api-content-post
+//! and https://developer.atlassian.com/cloud/confluence/rest/v1/api-group-content/#api-wiki-rest-api-content-id-put
+
+// Third party
+use reqwest::Client;
+use serde_json::Value;
+
+// Ours
+use super::markup;
+use super::Sink;
+use config::Config;
+use render::Message;
+
+/// A page's current content, as needed to update it
+#[derive(Deserialize, Debug)]
+struct Content {
+    title: String,
+    version: Version,
+    body: Body,
+}
+
+#[derive(Deserialize, Debug)]
+struct Version {
+    number: u64,
+}
+
+#[derive(Deserialize, Debug)]
+struct Body {
+    storage: Storage,
+}
+
+#[derive(Deserialize, Debug)]
+struct Storage {
+    value: String,
+}
+
+/// A Confluence space, created a page titled after each message,
+/// or a running page each message is appended to
+pub struct Confluence {
+    /// i.e. https://example.atlassian.net/wiki
+    url: String,
+    user: String,
+    token: String,
+    space: String,
+    /// pages are created under this page when set
+    parent_id: Option<String>,
+    /// title of created pages, or appended sections, in favor of the message's text
+    title: Option<String>,
+    /// messages are appended to this page, instead of being created as pages, when set
+    page_id: Option<String>,
+    client: Client,
+}
+
+impl Confluence {
+    pub fn new(
+        url: String,
+        user: String,
+        token: String,
+        space: String,
+        parent_id: Option<String>,
+        client: Client,
+    ) -> Self {
+        Confluence {
+            url: url.trim_end_matches('/').into(),
+            user,
+            token,
+            space,
+            parent_id,
+            title: None,
+            page_id: None,
+            client,
+        }
+    }
+
+    /// confluence, when a site, credentials and space are configured
+    pub fn configured(config: &Config) -> Option<Confluence> {
+        match (
+            config.confluence_url.clone(),
+            config.confluence_user.clone(),
+            config.confluence_token.clone(),
+            config.confluence_space.clone(),
+        ) {
+            (Some(url), Some(user), Some(token), Some(space)) => Some(Confluence::new(
+                url,
+                user,
+                token,
+                space,
+                config.confluence_parent_id.clone(),
+                config.http_client(),
+            )),
+            _ => None,
+        }
+    }
+
+    /// titles pages, or appended sections, i.e. with the date
+    pub fn titled(self, title: String) -> Self {
+        Confluence {
+            title: Some(title),
+            ..self
+        }
+    }
+
+    /// appends messages to a running page rather than creating a page for each
+    pub fn appending_to(self, page_id: String) -> Self {
+        Confluence {
+            page_id: Some(page_id),
+            ..self
+        }
+    }
+
+    fn title(&self, message: &Message) -> String {
+        self.title.clone().unwrap_or_else(|| message.text.clone())
+    }
+
+    fn page(&self, message: &Message) -> Value {
+        let mut page = json!({
+            "type": "page",
+            "title": self.title(message),
+            "space": { "key": self.space },
+            "body": {
+                "storage": {
+                    "value": storage(message),
+                    "representation": "storage",
+                }
+            }
+        });
+        if let Some(ref parent_id) = self.parent_id {
+            page["ancestors"] = json!([{ "id": parent_id }]);
+        }
+        page
+    }
+
+    fn create(&self, message: &Message) -> Result<(), String> {
+        self.client
+            .post(&format!("{}/rest/api/content", self.url))
+            .basic_auth(self.user.as_str(), Some(self.token.as_str()))
+            .json(&self.page(message))
+            .send()
+            .and_then(|response| response.error_for_status())
+            .map(|_| ())
+            .map_err(|err| format!("failed to create confluence page: {}", err))
+    }
+
+    /// adds a message to the end of a page under a heading, as a new version of the page
+    fn append(&self, page_id: &str, message: &Message) -> Result<(), String> {
+        let url = format!("{}/rest/api/content/{}", self.url, page_id);
+        let current = self
+            .client
+            .get(&url)
+            .query(&[("expand", "body.storage,version")])
+            .basic_auth(self.user.as_str(), Some(self.token.as_str()))
+            .send()
+            .and_then(|response| response.error_for_status())
+            .and_then(|mut response| response.json::<Content>())
+            .map_err(|err| format!("failed to read confluence page: {}", err))?;
+        self.client
+            .put(&url)
+            .basic_auth(self.user.as_str(), Some(self.token.as_str()))
+            .json(&json!({
+                "id": page_id,
+                "type": "page",
+                "title": current.title,
+                "version": { "number": current.version.number + 1 },
+                "body": {
+                    "storage": {
+                        "value": format!(
+                            "{}<h2>{}</h2>{}",
+                            current.body.storage.value,
+                            markup::escape(&self.title(message)),
+                            storage(message)
+                        ),
+                        "representation": "storage",
+                    }
+                }
+            }))
+            .send()
+            .and_then(|response| response.error_for_status())
+            .map(|_| ())
+            .map_err(|err| format!("failed to update confluence page: {}", err))
+    }
+}
+
+impl Sink for Confluence {
+    fn send(&self, message: &Message) -> Result<(), String> {
+        match self.page_id {
+            Some(ref page_id) => self.append(page_id, message),
+            None => self.create(message),
+        }
+    }
+}
+
+/// confluence's storage format is xhtml, so tags must be closed
+fn storage(message: &Message) -> String {
+    markup::html(message).replace("<br>", "<br/>")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::{self, mock, Matcher};
+    use render::Block;
+
+    #[test]
+    fn messages_are_created_as_child_pages() {
+        let _page = mock("POST", "/wiki/rest/api/content")
+            .match_header("authorization", "Basic ZGFuYTp0b2tlbg==")
+            .match_body(Matcher::Json(json!({
+                "type": "page",
+                "title": "Release notes for 2.4.0",
+                "space": { "key": "ENG" },
+                "ancestors": [{ "id": "123" }],
+                "body": {
+                    "storage": {
+                        "value": "<h3>🐛 Bug fixes</h3><p><b>fixed</b><br/>things</p>",
+                        "representation": "storage",
+                    }
+                }
+            })))
+            .create();
+        Confluence::new(
+            format!("{}/wiki/", mockito::server_url()),
+            "dana".into(),
+            "token".into(),
+            "ENG".into(),
+            Some("123".into()),
+            Client::new(),
+        )
+        .send(&Message {
+            text: "Release notes for 2.4.0".into(),
+            blocks: vec![
+                Block::header("🐛 Bug fixes"),
+                Block::section("*fixed*\nthings"),
+            ],
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn messages_are_appended_to_running_pages() {
+        let _current = mock("GET", "/rest/api/content/42")
+            .match_query(Matcher::UrlEncoded(
+                "expand".into(),
+                "body.storage,version".into(),
+            ))
+            .with_body(
+                r#"{"id":"42","title":"Standups","version":{"number":7},"body":{"storage":{"value":"<p>earlier</p>","representation":"storage"}}}"#,
+            )
+            .create();
+        let update = mock("PUT", "/rest/api/content/42")
+            .match_body(Matcher::Json(json!({
+                "id": "42",
+                "type": "page",
+                "title": "Standups",
+                "version": { "number": 8 },
+                "body": {
+                    "storage": {
+                        "value": "<p>earlier</p><h2>Standup 2019-01-07</h2><p>🚫 1 blocked</p>",
+                        "representation": "storage",
+                    }
+                }
+            })))
+            .create();
+        Confluence::new(
+            mockito::server_url(),
+            "dana".into(),
+            "token".into(),
+            "ENG".into(),
+            None,
+            Client::new(),
+        )
+        .titled("Standup 2019-01-07".into())
+        .appending_to("42".into())
+        .send(&Message {
+            text: "🚫 1 blocked".into(),
+            blocks: vec![Block::section("🚫 1 blocked")],
+        })
+        .unwrap();
+        update.assert();
+    }
+}
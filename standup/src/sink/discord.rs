@@ -0,0 +1,110 @@
+//! Discord delivery
+//!
+//! see https://discord.com/developers/docs/resources/webhook#execute-webhook
+
+// Third party
+use reqwest::Client;
+use serde_json::Value;
+
+// Ours
+use super::markup::{sections, truncated, Markup};
+use super::Sink;
+use render::Message;
+
+/// most embeds discord accepts in one message
+const MAX_EMBEDS: usize = 10;
+
+/// longest embed description discord accepts
+const MAX_DESCRIPTION: usize = 4096;
+
+/// longest message content discord accepts
+const MAX_CONTENT: usize = 2000;
+
+/// A Discord webhook, posted a message with an embed per section
+pub struct Discord {
+    url: String,
+    client: Client,
+}
+
+impl Discord {
+    pub fn new(url: String, client: Client) -> Self {
+        Discord { url, client }
+    }
+}
+
+impl Sink for Discord {
+    fn send(&self, message: &Message) -> Result<(), String> {
+        payloads(message)
+            .iter()
+            .map(|payload| {
+                self.client
+                    .post(&self.url)
+                    .json(payload)
+                    .send()
+                    .and_then(|response| response.error_for_status())
+                    .map(|_| ())
+                    .map_err(|err| format!("failed to post to discord: {}", err))
+            })
+            .collect()
+    }
+}
+
+/// renders a message as as few webhook payloads as discord allows
+///
+/// untitled content leads the first payload, followed by an embed per section
+fn payloads(message: &Message) -> Vec<Value> {
+    let mut content = Vec::new();
+    let mut embeds = Vec::new();
+    for section in sections(message, Markup::Markdown) {
+        match section.title {
+            Some(title) => embeds.push(json!({
+                "title": title,
+                "description": truncated(&section.lines.join("\n"), MAX_DESCRIPTION),
+            })),
+            None => content.extend(section.lines),
+        }
+    }
+    let content = if content.is_empty() {
+        message.text.clone()
+    } else {
+        content.join("\n")
+    };
+    let mut payloads = embeds
+        .chunks(MAX_EMBEDS)
+        .map(|embeds| json!({ "embeds": embeds }))
+        .collect::<Vec<_>>();
+    match payloads.first_mut() {
+        Some(first) => first["content"] = json!(truncated(&content, MAX_CONTENT)),
+        None => payloads.push(json!({ "content": truncated(&content, MAX_CONTENT) })),
+    }
+    payloads
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use render::Block;
+
+    #[test]
+    fn renders_sections_as_embeds() {
+        let mut blocks = vec![Block::section("🚫 1 blocked")];
+        for section in 0..11 {
+            blocks.push(Block::header(format!("section {}", section)));
+            blocks.push(Block::section("<https://jira/browse/A-1|A-1> *stuck*"));
+        }
+        let payloads = payloads(&Message {
+            text: "🚫 1 blocked".into(),
+            blocks,
+        });
+        assert_eq!(payloads.len(), 2);
+        assert_eq!(payloads[0]["content"], json!("🚫 1 blocked"));
+        assert_eq!(
+            payloads[0]["embeds"][0],
+            json!({
+                "title": "section 0",
+                "description": "[A-1](https://jira/browse/A-1) **stuck**",
+            })
+        );
+        assert_eq!(payloads[1]["embeds"].as_array().map(Vec::len), Some(1));
+    }
+}
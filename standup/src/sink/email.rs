@@ -0,0 +1,104 @@
+//! Email delivery through SES
+//!
+//! see https://docs.aws.amazon.com/ses/latest/APIReference/API_SendEmail.html
+
+// Third party
+use rusoto_core::Region;
+use rusoto_ses::{Body, Content, Destination, SendEmailRequest, Ses, SesClient};
+
+// Ours
+use super::markup::{self, sections, Markup};
+use super::Sink;
+use render::Message;
+
+/// A distribution list, sent each standup as an html email
+pub struct Email {
+    sender: String,
+    recipients: Vec<String>,
+}
+
+impl Email {
+    pub fn new(sender: String, recipients: Vec<String>) -> Self {
+        Email { sender, recipients }
+    }
+}
+
+impl Sink for Email {
+    fn send(&self, message: &Message) -> Result<(), String> {
+        SesClient::new(Region::default())
+            .send_email(SendEmailRequest {
+                source: self.sender.clone(),
+                destination: Destination {
+                    to_addresses: Some(self.recipients.clone()),
+                    ..Default::default()
+                },
+                message: ::rusoto_ses::Message {
+                    subject: content(format!("Standup: {}", message.text)),
+                    body: Body {
+                        html: Some(content(html(message))),
+                        text: Some(content(text(message))),
+                    },
+                },
+                ..Default::default()
+            })
+            .sync()
+            .map(|_| ())
+            .map_err(|err| format!("failed to send email: {}", err))
+    }
+}
+
+fn content(data: String) -> Content {
+    Content {
+        charset: Some("UTF-8".into()),
+        data,
+    }
+}
+
+fn html(message: &Message) -> String {
+    format!(
+        r#"<html><body style="font-family: sans-serif">{}</body></html>"#,
+        markup::html(message)
+    )
+}
+
+/// plain text alternative for mail clients without html
+fn text(message: &Message) -> String {
+    sections(message, Markup::Markdown)
+        .into_iter()
+        .map(|section| {
+            section
+                .title
+                .into_iter()
+                .chain(section.lines)
+                .collect::<Vec<_>>()
+                .join("\n")
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use render::Block;
+
+    #[test]
+    fn renders_sections_as_html() {
+        let message = Message {
+            text: "🚫 1 blocked".into(),
+            blocks: vec![
+                Block::section("🚫 1 blocked"),
+                Block::header("🚫 Blocked"),
+                Block::section("<https://jira/browse/A-1|A-1> *stuck*"),
+            ],
+        };
+        assert_eq!(
+            html(&message),
+            r#"<html><body style="font-family: sans-serif"><p>🚫 1 blocked</p><h3>🚫 Blocked</h3><p><a href="https://jira/browse/A-1">A-1</a> <b>stuck</b></p></body></html>"#
+        );
+        assert_eq!(
+            text(&message),
+            "🚫 1 blocked\n\n🚫 Blocked\n[A-1](https://jira/browse/A-1) **stuck**"
+        );
+    }
+}
@@ -0,0 +1,288 @@
+//! Slack mrkdwn translation, for sinks outside of slack
+//!
+//! see https://api.slack.com/reference/surfaces/formatting
+
+// Ours
+use render::{Block, Message, Text};
+
+/// stands in for links while emphasis is translated, so urls are left alone
+const PLACEHOLDER: char = '\u{0}';
+
+/// What mrkdwn is translated into
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Markup {
+    Markdown,
+    Html,
+}
+
+/// translates slack mrkdwn, escaping text in html
+fn translate(mrkdwn: &str, markup: Markup) -> String {
+    let (text, links) = extract_links(mrkdwn, markup);
+    let (bold, italic, strike) = match markup {
+        Markup::Markdown => (("**", "**"), ("*", "*"), ("~~", "~~")),
+        Markup::Html => (("<b>", "</b>"), ("<i>", "</i>"), ("<s>", "</s>")),
+    };
+    let text = emphasis(&text, '*', bold);
+    let text = emphasis(&text, '_', italic);
+    let text = emphasis(&text, '~', strike);
+    let mut links = links.into_iter();
+    let translated = text
+        .split(PLACEHOLDER)
+        .enumerate()
+        .map(|(index, part)| {
+            if index % 2 == 1 {
+                links.next().unwrap_or_default()
+            } else {
+                part.to_owned()
+            }
+        })
+        .collect::<String>();
+    match markup {
+        Markup::Markdown => translated,
+        Markup::Html => translated.replace('\n', "<br>"),
+    }
+}
+
+/// A message's content under one of its headers
+pub struct Section {
+    pub title: Option<String>,
+    pub lines: Vec<String>,
+}
+
+/// a message's content, split at its headers
+///
+/// content before the first header has no title. Interactive elements,
+/// which only work in slack, are dropped.
+pub fn sections(message: &Message, markup: Markup) -> Vec<Section> {
+    let text_markup = |text: &Text| match *text {
+        Text::Plain { ref text, .. } if markup == Markup::Html => escape(text),
+        Text::Plain { ref text, .. } => text.clone(),
+        Text::Markdown { ref text } => translate(text, markup),
+    };
+    let mut sections = vec![Section {
+        title: None,
+        lines: Vec::new(),
+    }];
+    for block in &message.blocks {
+        let line = match *block {
+            Block::Header { ref text } => {
+                sections.push(Section {
+                    title: Some(text_markup(text)),
+                    lines: Vec::new(),
+                });
+                continue;
+            }
+            Block::Section {
+                ref text,
+                ref fields,
+            } => text
+                .iter()
+                .map(text_markup)
+                .chain(if fields.is_empty() {
+                    None
+                } else {
+                    Some(
+                        fields
+                            .iter()
+                            .map(text_markup)
+                            .collect::<Vec<_>>()
+                            .join(" · "),
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+            Block::Context { ref elements } => elements
+                .iter()
+                .map(text_markup)
+                .collect::<Vec<_>>()
+                .join(" "),
+            Block::Divider | Block::Actions { .. } => continue,
+        };
+        if let Some(section) = sections.last_mut() {
+            section.lines.push(line);
+        }
+    }
+    sections
+        .into_iter()
+        .filter(|section| section.title.is_some() || !section.lines.is_empty())
+        .collect()
+}
+
+/// a message's sections as html paragraphs under `h3` headings
+pub fn html(message: &Message) -> String {
+    sections(message, Markup::Html)
+        .into_iter()
+        .map(|section| {
+            let title = section
+                .title
+                .map(|title| format!("<h3>{}</h3>", title))
+                .unwrap_or_default();
+            let lines = section
+                .lines
+                .into_iter()
+                .map(|line| format!("<p>{}</p>", line))
+                .collect::<String>();
+            title + &lines
+        })
+        .collect()
+}
+
+/// text cut short with an ellipsis when longer than `max` characters
+pub fn truncated(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        text.to_owned()
+    } else {
+        text.chars().take(max - 1).chain(Some('…')).collect()
+    }
+}
+
+/// escapes text for html
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// replaces `<...>` references with placeholders, returning them in markdown
+fn extract_links(mrkdwn: &str, markup: Markup) -> (String, Vec<String>) {
+    let mut text = String::new();
+    let mut links = Vec::new();
+    let mut rest = mrkdwn;
+    while let Some(start) = rest.find('<') {
+        let end = match rest[start..].find('>') {
+            Some(end) => start + end,
+            None => break,
+        };
+        text.push_str(&plain(&rest[..start], markup));
+        text.push(PLACEHOLDER);
+        text.push(PLACEHOLDER);
+        links.push(link(&rest[start + 1..end], markup));
+        rest = &rest[end + 1..];
+    }
+    text.push_str(&plain(rest, markup));
+    (text, links)
+}
+
+/// mrkdwn text outside of links, unescaped and, for html, escaped again
+fn plain(mrkdwn: &str, markup: Markup) -> String {
+    let text = unescape(mrkdwn);
+    match markup {
+        Markup::Markdown => text,
+        Markup::Html => escape(&text),
+    }
+}
+
+/// undoes slack's escaping, see `render::escape`
+fn unescape(mrkdwn: &str) -> String {
+    mrkdwn
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+fn link(reference: &str, markup: Markup) -> String {
+    let mut parts = reference.splitn(2, '|');
+    let target = parts.next().unwrap_or_default();
+    let label = parts.next().map(|label| plain(label, markup));
+    match target.chars().next() {
+        // users, channels and special mentions don't translate outside of slack
+        Some('@') | Some('#') | Some('!') => match label {
+            Some(label) => format!("@{}", label.trim_start_matches('@')),
+            None => format!(
+                "@{}",
+                target.trim_start_matches(|c| c == '@' || c == '#' || c == '!')
+            ),
+        },
+        _ => match (label, markup) {
+            (Some(label), Markup::Markdown) => format!("[{}]({})", label, target),
+            (None, Markup::Markdown) => format!("<{}>", target),
+            (label, Markup::Html) => {
+                let target = plain(target, markup);
+                format!(
+                    r#"<a href="{}">{}</a>"#,
+                    target,
+                    label.unwrap_or_else(|| target.clone())
+                )
+            }
+        },
+    }
+}
+
+/// translates `marker` delimited emphasis into `opening` and `closing` delimited emphasis
+///
+/// like slack, emphasis starts and ends at word boundaries and doesn't span lines
+fn emphasis(text: &str, marker: char, (opening, closing): (&str, &str)) -> String {
+    let chars = text.chars().collect::<Vec<_>>();
+    let boundary = |index: Option<usize>| {
+        index
+            .and_then(|index| chars.get(index))
+            .map(|c| !c.is_alphanumeric())
+            .unwrap_or(true)
+    };
+    let mut translated = String::new();
+    let mut index = 0;
+    while index < chars.len() {
+        if chars[index] == marker && boundary(index.checked_sub(1)) {
+            let close = (index + 2..chars.len())
+                .take_while(|&close| chars[close] != '\n')
+                .find(|&close| chars[close] == marker && boundary(Some(close + 1)));
+            if let Some(close) = close {
+                translated.push_str(opening);
+                translated.extend(&chars[index + 1..close]);
+                translated.push_str(closing);
+                index = close + 1;
+                continue;
+            }
+        }
+        translated.push(chars[index]);
+        index += 1;
+    }
+    translated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn translates_links_and_mentions() {
+        assert_eq!(
+            translate(
+                "<https://jira/browse/CORE_1|CORE_1> fix it · <@U1> <https://example.com>",
+                Markup::Markdown
+            ),
+            "[CORE_1](https://jira/browse/CORE_1) fix it · @U1 <https://example.com>"
+        );
+    }
+
+    #[test]
+    fn translates_to_escaped_html() {
+        assert_eq!(
+            translate(
+                "<https://jira/browse/A-1?a=1&b=2|A-1> *fix* R&D\n_soon_",
+                Markup::Html
+            ),
+            r#"<a href="https://jira/browse/A-1?a=1&amp;b=2">A-1</a> <b>fix</b> R&amp;D<br><i>soon</i>"#
+        );
+    }
+
+    #[test]
+    fn unescapes_slack_entities() {
+        assert_eq!(
+            translate("<https://jira|A-1> R&amp;D &lt;3", Markup::Markdown),
+            "[A-1](https://jira) R&D <3"
+        );
+        assert_eq!(translate("R&amp;D &lt;3", Markup::Html), "R&amp;D &lt;3");
+    }
+
+    #[test]
+    fn translates_emphasis() {
+        assert_eq!(
+            translate(
+                "🙋 *<@U1|ada>* _stale 5d_ ~gone~ snake_case_name",
+                Markup::Markdown
+            ),
+            "🙋 **@ada** *stale 5d* ~~gone~~ snake_case_name"
+        );
+    }
+}
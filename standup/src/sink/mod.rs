@@ -0,0 +1,602 @@
+//! Destinations standups are delivered to
+
+// Std lib
+use std::cell::{Cell, RefCell};
+
+// Third party
+use reqwest::header::AUTHORIZATION;
+use reqwest::Client;
+use serde_json;
+
+// Ours
+use chart::Chart;
+use http;
+use log;
+use render::{Block, Message};
+
+mod confluence;
+mod discord;
+mod email;
+pub mod markup;
+mod teams;
+
+pub use self::confluence::Confluence;
+pub use self::discord::Discord;
+pub use self::email::Email;
+pub use self::teams::Teams;
+
+const API: &str = "https://slack.com/api";
+
+/// Somewhere to deliver a rendered standup
+pub trait Sink {
+    fn send(&self, message: &Message) -> Result<(), String>;
+
+    /// Delivers replies in a thread under a parent message
+    ///
+    /// Sinks without threads deliver them merged into one message.
+    fn thread(&self, parent: &Message, replies: &[Message]) -> Result<(), String> {
+        self.send(&merged(parent, replies))
+    }
+
+    /// whether replies are delivered in a thread rather than as messages of their own
+    fn threads(&self) -> bool {
+        false
+    }
+
+    /// Attaches a chart to the last message delivered
+    ///
+    /// Sinks without files skip them.
+    fn upload(&self, _chart: &Chart) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// where the last message was delivered, for sinks which know
+    fn posted(&self) -> Option<Link> {
+        None
+    }
+}
+
+/// A slack incoming webhook or slash command `response_url`
+pub struct Webhook {
+    url: String,
+    client: Client,
+    /// whether the next message replaces the one an interaction came from
+    replace: Cell<bool>,
+    /// whether the next message deletes the one an interaction came from
+    delete: Cell<bool>,
+    /// who slash command responses are shown to, `ephemeral` or `in_channel`
+    response_type: Option<&'static str>,
+}
+
+impl Webhook {
+    pub fn new(url: String) -> Self {
+        Webhook {
+            url,
+            client: http::default_client(),
+            replace: Cell::new(false),
+            delete: Cell::new(false),
+            response_type: None,
+        }
+    }
+
+    /// a slash command's `response_url`, messages to which only its invoker sees
+    pub fn ephemeral(url: String) -> Self {
+        Webhook {
+            response_type: Some("ephemeral"),
+            ..Webhook::new(url)
+        }
+    }
+
+    /// an interaction's `response_url`, messages to which are posted to the
+    /// channel, the first deleting the message the interaction came from
+    pub fn posting(url: String) -> Self {
+        Webhook {
+            delete: Cell::new(true),
+            response_type: Some("in_channel"),
+            ..Webhook::new(url)
+        }
+    }
+
+    /// an interaction's `response_url`, the first message to which replaces
+    /// the message the interaction came from
+    pub fn replacing(url: String) -> Self {
+        Webhook {
+            replace: Cell::new(true),
+            ..Webhook::new(url)
+        }
+    }
+}
+
+impl Sink for Webhook {
+    fn send(&self, message: &Message) -> Result<(), String> {
+        parts(message)
+            .iter()
+            .map(|part| {
+                let mut body = json!(part);
+                if self.replace.replace(false) {
+                    body["replace_original"] = json!(true);
+                }
+                if self.delete.replace(false) {
+                    body["delete_original"] = json!(true);
+                }
+                if let Some(response_type) = self.response_type {
+                    body["response_type"] = json!(response_type);
+                }
+                self.client
+                    .post(&self.url)
+                    .json(&body)
+                    .send()
+                    .and_then(|response| response.error_for_status())
+                    .map(|_| ())
+                    .map_err(|err| format!("failed to post to slack: {}", err))
+            })
+            .collect()
+    }
+}
+
+/// A delivered message
+#[derive(Debug, PartialEq)]
+pub struct Link {
+    pub ts: String,
+    pub permalink: String,
+}
+
+/// A channel posted to with a slack bot token
+///
+/// see https://api.slack.com/methods/chat.postMessage
+pub struct Chat {
+    api: String,
+    token: String,
+    channel: String,
+    client: Client,
+    /// `ts` of the last message posted, which uploads are threaded under
+    last_ts: RefCell<Option<String>>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Posted {
+    ok: bool,
+    ts: Option<String>,
+    error: Option<String>,
+}
+
+/// Where to upload a file's content to
+#[derive(Deserialize, Debug)]
+struct UploadUrl {
+    ok: bool,
+    upload_url: Option<String>,
+    file_id: Option<String>,
+    error: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Permalink {
+    ok: bool,
+    permalink: Option<String>,
+    error: Option<String>,
+}
+
+impl Chat {
+    pub fn new(token: String, channel: String, client: Client) -> Self {
+        Chat {
+            api: API.into(),
+            token,
+            channel,
+            client,
+            last_ts: RefCell::new(None),
+        }
+    }
+
+    /// points posts at another api host, i.e. a mock server
+    pub fn with_api(self, api: String) -> Self {
+        Chat { api, ..self }
+    }
+
+    /// posts a message, returning its `ts`
+    fn post(&self, message: &Message, thread_ts: Option<&str>) -> Result<String, String> {
+        let mut body = json!({
+            "channel": self.channel,
+            "text": message.text,
+            "blocks": message.blocks,
+        });
+        if let Some(ts) = thread_ts {
+            body["thread_ts"] = json!(ts);
+        }
+        let posted = self
+            .client
+            .post(&format!("{}/chat.postMessage", self.api))
+            .header(AUTHORIZATION, format!("Bearer {}", self.token))
+            .json(&body)
+            .send()
+            .and_then(|response| response.error_for_status())
+            .and_then(|mut response| response.json::<Posted>())
+            .map_err(|err| format!("failed to post to slack: {}", err))?;
+        match posted {
+            Posted {
+                ok: true,
+                ts: Some(ts),
+                ..
+            } => {
+                if thread_ts.is_none() {
+                    *self.last_ts.borrow_mut() = Some(ts.clone());
+                }
+                Ok(ts)
+            }
+            Posted { error, .. } => Err(format!(
+                "failed to post to slack: {}",
+                error.unwrap_or_else(|| "unknown error".into())
+            )),
+        }
+    }
+}
+
+impl Sink for Chat {
+    fn send(&self, message: &Message) -> Result<(), String> {
+        parts(message)
+            .iter()
+            .map(|part| self.post(part, None).map(|_| ()))
+            .collect()
+    }
+
+    fn thread(&self, parent: &Message, replies: &[Message]) -> Result<(), String> {
+        let ts = self.post(parent, None)?;
+        replies
+            .iter()
+            .flat_map(parts)
+            .map(|reply| self.post(&reply, Some(&ts)).map(|_| ()))
+            .collect()
+    }
+
+    fn threads(&self) -> bool {
+        true
+    }
+
+    /// uploads the chart's content, then shares the file in the channel
+    ///
+    /// see https://api.slack.com/messaging/files#uploading_files
+    fn upload(&self, chart: &Chart) -> Result<(), String> {
+        let failed = |err: String| format!("failed to upload to slack: {}", err);
+        let length = chart.png.len().to_string();
+        let filename = format!("{}.png", chart.title);
+        let url = self
+            .client
+            .post(&format!("{}/files.getUploadURLExternal", self.api))
+            .header(AUTHORIZATION, format!("Bearer {}", self.token))
+            .form(&[("filename", filename.as_str()), ("length", length.as_str())])
+            .send()
+            .and_then(|response| response.error_for_status())
+            .and_then(|mut response| response.json::<UploadUrl>())
+            .map_err(|err| failed(err.to_string()))?;
+        let (upload_url, file_id) = match url {
+            UploadUrl {
+                ok: true,
+                upload_url: Some(upload_url),
+                file_id: Some(file_id),
+                ..
+            } => (upload_url, file_id),
+            UploadUrl { error, .. } => {
+                return Err(failed(error.unwrap_or_else(|| "unknown error".into())))
+            }
+        };
+        self.client
+            .post(&upload_url)
+            .body(chart.png.clone())
+            .send()
+            .and_then(|response| response.error_for_status())
+            .map_err(|err| failed(err.to_string()))?;
+        let mut body = json!({
+            "files": [{ "id": file_id, "title": chart.title }],
+            "channel_id": self.channel,
+        });
+        if let Some(ts) = self.last_ts.borrow().clone() {
+            body["thread_ts"] = json!(ts);
+        }
+        let completed = self
+            .client
+            .post(&format!("{}/files.completeUploadExternal", self.api))
+            .header(AUTHORIZATION, format!("Bearer {}", self.token))
+            .json(&body)
+            .send()
+            .and_then(|response| response.error_for_status())
+            .and_then(|mut response| response.json::<Posted>())
+            .map_err(|err| failed(err.to_string()))?;
+        if completed.ok {
+            Ok(())
+        } else {
+            Err(failed(
+                completed.error.unwrap_or_else(|| "unknown error".into()),
+            ))
+        }
+    }
+
+    /// see https://api.slack.com/methods/chat.getPermalink
+    fn posted(&self) -> Option<Link> {
+        let ts = self.last_ts.borrow().clone()?;
+        let linked = self
+            .client
+            .get(&format!("{}/chat.getPermalink", self.api))
+            .header(AUTHORIZATION, format!("Bearer {}", self.token))
+            .query(&[
+                ("channel", self.channel.as_str()),
+                ("message_ts", ts.as_str()),
+            ])
+            .send()
+            .and_then(|response| response.error_for_status())
+            .and_then(|mut response| response.json::<Permalink>())
+            .map_err(|err| err.to_string());
+        match linked {
+            Ok(Permalink {
+                ok: true,
+                permalink: Some(permalink),
+                ..
+            }) => Some(Link { ts, permalink }),
+            Ok(Permalink { error, .. }) => {
+                log::warn(
+                    "failed to link to standup",
+                    json!({ "error": error.unwrap_or_else(|| "unknown error".into()) }),
+                );
+                None
+            }
+            Err(err) => {
+                log::warn("failed to link to standup", json!({ "error": err }));
+                None
+            }
+        }
+    }
+}
+
+/// Delivers to several sinks, i.e. slack and email
+///
+/// Every sink is tried, failing if any did.
+pub struct Fanout {
+    sinks: Vec<Box<dyn Sink>>,
+}
+
+impl Fanout {
+    pub fn new(sinks: Vec<Box<dyn Sink>>) -> Self {
+        Fanout { sinks }
+    }
+
+    fn each<F>(&self, deliver: F) -> Result<(), String>
+    where
+        F: Fn(&dyn Sink) -> Result<(), String>,
+    {
+        let errors = self
+            .sinks
+            .iter()
+            .filter_map(|sink| deliver(sink.as_ref()).err())
+            .collect::<Vec<_>>();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors.join(", "))
+        }
+    }
+}
+
+impl Sink for Fanout {
+    fn send(&self, message: &Message) -> Result<(), String> {
+        self.each(|sink| sink.send(message))
+    }
+
+    fn thread(&self, parent: &Message, replies: &[Message]) -> Result<(), String> {
+        self.each(|sink| sink.thread(parent, replies))
+    }
+
+    fn threads(&self) -> bool {
+        self.sinks.iter().any(|sink| sink.threads())
+    }
+
+    fn upload(&self, chart: &Chart) -> Result<(), String> {
+        self.each(|sink| sink.upload(chart))
+    }
+
+    fn posted(&self) -> Option<Link> {
+        self.sinks.iter().filter_map(|sink| sink.posted()).next()
+    }
+}
+
+/// a parent and its replies as one message, for sinks without threads
+///
+/// the parent's context, which points to the thread, is left out
+fn merged(parent: &Message, replies: &[Message]) -> Message {
+    let mut blocks = parent
+        .blocks
+        .iter()
+        .filter(|block| match **block {
+            Block::Context { .. } => false,
+            _ => true,
+        })
+        .cloned()
+        .collect::<Vec<_>>();
+    for reply in replies {
+        blocks.extend(reply.blocks.iter().cloned());
+    }
+    Message {
+        text: parent.text.clone(),
+        blocks,
+    }
+}
+
+/// a message in as many parts as slack needs, in order
+fn parts(message: &Message) -> Vec<Message> {
+    let parts = message.split();
+    if parts.len() > 1 {
+        log::info(
+            "splitting oversized message",
+            json!({ "blocks": message.blocks.len(), "parts": parts.len() }),
+        );
+    }
+    parts
+}
+
+/// Prints messages as json, for trying out changes locally
+pub struct Stdout;
+
+impl Sink for Stdout {
+    fn send(&self, message: &Message) -> Result<(), String> {
+        serde_json::to_string_pretty(message)
+            .map(|json| println!("{}", json))
+            .map_err(|err| err.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::{self, mock, Matcher};
+    use render::Block;
+
+    #[test]
+    fn posting_replaces_a_preview_in_channel() {
+        let posted = mock("POST", "/response")
+            .match_body(Matcher::Json(json!({
+                "text": "🚫 1 blocked",
+                "blocks": [],
+                "delete_original": true,
+                "response_type": "in_channel",
+            })))
+            .create();
+        let webhook = Webhook::posting(format!("{}/response", mockito::server_url()));
+        assert_eq!(
+            webhook.send(&Message {
+                text: "🚫 1 blocked".into(),
+                blocks: Vec::new(),
+            }),
+            Ok(())
+        );
+        posted.assert();
+    }
+
+    #[test]
+    fn webhooks_merge_replies_into_one_message() {
+        let merged = mock("POST", "/merged")
+            .match_body(Matcher::Json(json!({
+                "text": "🚫 1 blocked",
+                "blocks": [{ "type": "header", "text": { "type": "plain_text", "text": "Blocked", "emoji": true } }],
+            })))
+            .expect(1)
+            .create();
+        let webhook = Webhook::new(format!("{}/merged", mockito::server_url()));
+        assert!(!webhook.threads());
+        assert_eq!(
+            webhook.thread(
+                &Message {
+                    text: "🚫 1 blocked".into(),
+                    blocks: vec![Block::context("🧵 Details in the thread")],
+                },
+                &[Message {
+                    text: "Blocked".into(),
+                    blocks: vec![Block::header("Blocked")],
+                }],
+            ),
+            Ok(())
+        );
+        merged.assert();
+    }
+
+    #[test]
+    fn replies_are_threaded_under_the_parent() {
+        let parent = mock("POST", "/chat.postMessage")
+            .match_body(Matcher::Json(json!({
+                "channel": "C1",
+                "text": "🚫 1 blocked",
+                "blocks": [],
+            })))
+            .with_body(r#"{"ok":true,"ts":"1.2"}"#)
+            .create();
+        let reply = mock("POST", "/chat.postMessage")
+            .match_body(Matcher::Json(json!({
+                "channel": "C1",
+                "text": "Blocked",
+                "blocks": [{ "type": "header", "text": { "type": "plain_text", "text": "Blocked", "emoji": true } }],
+                "thread_ts": "1.2",
+            })))
+            .with_body(r#"{"ok":true,"ts":"1.3"}"#)
+            .create();
+        let chat =
+            Chat::new("xoxb".into(), "C1".into(), Client::new()).with_api(mockito::server_url());
+        assert_eq!(
+            chat.thread(
+                &Message {
+                    text: "🚫 1 blocked".into(),
+                    blocks: Vec::new(),
+                },
+                &[Message {
+                    text: "Blocked".into(),
+                    blocks: vec![Block::header("Blocked")],
+                }],
+            ),
+            Ok(())
+        );
+        parent.assert();
+        reply.assert();
+    }
+
+    #[test]
+    fn charts_are_uploaded_then_shared() {
+        let _url = mock("POST", "/files.getUploadURLExternal")
+            .match_body(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("filename".into(), "Closed issues.png".into()),
+                Matcher::UrlEncoded("length".into(), "3".into()),
+            ]))
+            .with_body(
+                json!({
+                    "ok": true,
+                    "upload_url": format!("{}/upload/F1", mockito::server_url()),
+                    "file_id": "F1",
+                })
+                .to_string(),
+            )
+            .create();
+        let uploaded = mock("POST", "/upload/F1").match_body("png").create();
+        let shared = mock("POST", "/files.completeUploadExternal")
+            .match_body(Matcher::Json(json!({
+                "files": [{ "id": "F1", "title": "Closed issues" }],
+                "channel_id": "C3",
+            })))
+            .with_body(r#"{"ok":true}"#)
+            .create();
+        let chat =
+            Chat::new("xoxb".into(), "C3".into(), Client::new()).with_api(mockito::server_url());
+        assert_eq!(
+            chat.upload(&Chart {
+                title: "Closed issues".into(),
+                png: b"png".to_vec(),
+            }),
+            Ok(())
+        );
+        uploaded.assert();
+        shared.assert();
+    }
+
+    #[test]
+    fn chat_links_to_what_it_posted() {
+        let _posted = mock("POST", "/chat.postMessage")
+            .match_body(Matcher::Regex(r#""channel":"C2""#.into()))
+            .with_body(r#"{"ok":true,"ts":"2.1"}"#)
+            .create();
+        let _linked = mock("GET", "/chat.getPermalink")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("channel".into(), "C2".into()),
+                Matcher::UrlEncoded("message_ts".into(), "2.1".into()),
+            ]))
+            .with_body(r#"{"ok":true,"permalink":"https://acme.slack.com/archives/C2/p21"}"#)
+            .create();
+        let chat =
+            Chat::new("xoxb".into(), "C2".into(), Client::new()).with_api(mockito::server_url());
+        assert_eq!(chat.posted(), None);
+        chat.send(&Message {
+            text: "standup".into(),
+            blocks: Vec::new(),
+        })
+        .unwrap();
+        assert_eq!(
+            chat.posted(),
+            Some(Link {
+                ts: "2.1".into(),
+                permalink: "https://acme.slack.com/archives/C2/p21".into(),
+            })
+        );
+    }
+}
@@ -0,0 +1,90 @@
+//! Microsoft Teams delivery
+//!
+//! see https://docs.microsoft.com/en-us/outlook/actionable-messages/message-card-reference
+
+// Third party
+use reqwest::Client;
+use serde_json::Value;
+
+// Ours
+use super::markup::{sections, Markup};
+use super::Sink;
+use render::Message;
+
+/// A Teams incoming webhook, posted MessageCards
+pub struct Teams {
+    url: String,
+    client: Client,
+}
+
+impl Teams {
+    pub fn new(url: String, client: Client) -> Self {
+        Teams { url, client }
+    }
+}
+
+impl Sink for Teams {
+    fn send(&self, message: &Message) -> Result<(), String> {
+        self.client
+            .post(&self.url)
+            .json(&card(message))
+            .send()
+            .and_then(|response| response.error_for_status())
+            .map(|_| ())
+            .map_err(|err| format!("failed to post to teams: {}", err))
+    }
+}
+
+/// renders a message as a MessageCard
+fn card(message: &Message) -> Value {
+    json!({
+        "@type": "MessageCard",
+        "@context": "https://schema.org/extensions",
+        "summary": message.text,
+        "sections": sections(message, Markup::Markdown)
+            .into_iter()
+            .map(|section| {
+                let mut card_section = json!({
+                    // teams needs blank lines to break lines
+                    "text": section.lines.join("\n\n"),
+                    "markdown": true,
+                });
+                if let Some(title) = section.title {
+                    card_section["title"] = json!(title);
+                }
+                card_section
+            })
+            .collect::<Vec<_>>(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use render::Block;
+
+    #[test]
+    fn renders_sections_as_card_sections() {
+        let message = Message {
+            text: "🚫 1 blocked".into(),
+            blocks: vec![
+                Block::section("🚫 1 blocked"),
+                Block::header("🚫 Blocked"),
+                Block::section("<https://jira/browse/A-1|A-1> *stuck*"),
+                Block::context("@ada · In Progress"),
+                Block::Divider,
+            ],
+        };
+        assert_eq!(
+            card(&message)["sections"],
+            json!([
+                { "text": "🚫 1 blocked", "markdown": true },
+                {
+                    "title": "🚫 Blocked",
+                    "text": "[A-1](https://jira/browse/A-1) **stuck**\n\n@ada · In Progress",
+                    "markdown": true
+                }
+            ])
+        );
+    }
+}
@@ -0,0 +1,184 @@
+//! Slack request verification and authorization
+//!
+//! see https://api.slack.com/docs/verifying-requests-from-slack
+
+// Third party
+use hex;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+/// requests signed longer ago than this are treated as replays
+const REPLAY_WINDOW_SECS: i64 = 60 * 5;
+
+/// the only signature version slack currently issues
+const SIGNATURE_VERSION: &str = "v0";
+
+/// distinguishes our countersignatures from slack's signatures, which share a secret
+const COUNTERSIGNATURE_PREFIX: &str = "standup:";
+
+#[derive(Debug, Fail, PartialEq)]
+pub enum VerificationError {
+    #[fail(display = "missing slack signature headers")]
+    MissingHeaders,
+    #[fail(display = "request timestamp is outside of the replay window")]
+    Expired,
+    #[fail(display = "invalid slack signature")]
+    InvalidSignature,
+}
+
+/// Verifies a request body was signed by slack with our signing secret
+///
+/// `now` is the current unix time in seconds
+pub fn verify(
+    secret: &str,
+    timestamp: Option<&str>,
+    signature: Option<&str>,
+    body: &[u8],
+    now: i64,
+) -> Result<(), VerificationError> {
+    let (timestamp, signature) = match (timestamp, signature) {
+        (Some(timestamp), Some(signature)) => (timestamp, signature),
+        _ => return Err(VerificationError::MissingHeaders),
+    };
+    let signed_at = timestamp
+        .parse::<i64>()
+        .map_err(|_| VerificationError::InvalidSignature)?;
+    if (now - signed_at).abs() > REPLAY_WINDOW_SECS {
+        return Err(VerificationError::Expired);
+    }
+    let prefix = format!("{}=", SIGNATURE_VERSION);
+    if !signature.starts_with(&prefix) {
+        return Err(VerificationError::InvalidSignature);
+    }
+    let expected =
+        hex::decode(&signature[prefix.len()..]).map_err(|_| VerificationError::InvalidSignature)?;
+    let mut mac = Hmac::<Sha256>::new_varkey(secret.as_bytes())
+        .map_err(|_| VerificationError::InvalidSignature)?;
+    mac.input(format!("{}:{}:", SIGNATURE_VERSION, timestamp).as_bytes());
+    mac.input(body);
+    mac.verify(&expected)
+        .map_err(|_| VerificationError::InvalidSignature)
+}
+
+/// Countersigns a verified request body, so a copy of it handed back to us
+/// can be told apart from one sent by anyone else with slack's signature
+pub fn countersign(secret: &str, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_varkey(secret.as_bytes()).expect("hmac takes any key");
+    mac.input(COUNTERSIGNATURE_PREFIX.as_bytes());
+    mac.input(body);
+    hex::encode(mac.result().code())
+}
+
+/// Returns true if `countersignature` is our countersignature of `body`
+pub fn countersigned(secret: &str, countersignature: Option<&str>, body: &[u8]) -> bool {
+    let expected = match countersignature.and_then(|value| hex::decode(value).ok()) {
+        Some(expected) => expected,
+        None => return false,
+    };
+    Hmac::<Sha256>::new_varkey(secret.as_bytes())
+        .map(|mut mac| {
+            mac.input(COUNTERSIGNATURE_PREFIX.as_bytes());
+            mac.input(body);
+            mac.verify(&expected).is_ok()
+        })
+        .unwrap_or(false)
+}
+
+/// Returns true if a command may be run by a user in a channel
+///
+/// When neither channels nor users are listed, anyone may run the command.
+/// Otherwise the channel or the user must be listed.
+pub fn authorized(channels: &[String], users: &[String], channel_id: &str, user_id: &str) -> bool {
+    (channels.is_empty() && users.is_empty())
+        || channels.iter().any(|channel| channel == channel_id)
+        || users.iter().any(|user| user == user_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // example from https://api.slack.com/docs/verifying-requests-from-slack
+    const SECRET: &str = "8f742231b10e8888abcd99yyyzzz85a5";
+    const TIMESTAMP: &str = "1531420618";
+    const SIGNATURE: &str = "v0=a2114d57b48eac39b9ad189dd8316235a7b4a8d21a10bd27519666489c69b503";
+    const BODY: &str = "token=xyzz0WbapA4vBCDEFasx0q6G&team_id=T1DC2JH3J&team_domain=testteamnow&channel_id=G8PSS9T3V&channel_name=foobar&user_id=U2CERLKJA&user_name=roadrunner&command=%2Fwebhook-collect&text=&response_url=https%3A%2F%2Fhooks.slack.com%2Fcommands%2FT1DC2JH3J%2F397700885554%2F96rGlfmibIGlgcZRskXaIFfN&trigger_id=398738663015.47445629121.803a0bc887a14d10d2c447fce8b6703c";
+
+    #[test]
+    fn verifies_signed_requests() {
+        assert_eq!(
+            verify(
+                SECRET,
+                Some(TIMESTAMP),
+                Some(SIGNATURE),
+                BODY.as_bytes(),
+                1_531_420_618
+            ),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn rejects_tampered_requests() {
+        assert_eq!(
+            verify(
+                SECRET,
+                Some(TIMESTAMP),
+                Some(SIGNATURE),
+                b"text=all",
+                1_531_420_618
+            ),
+            Err(VerificationError::InvalidSignature)
+        );
+    }
+
+    #[test]
+    fn rejects_replayed_requests() {
+        assert_eq!(
+            verify(
+                SECRET,
+                Some(TIMESTAMP),
+                Some(SIGNATURE),
+                BODY.as_bytes(),
+                1_531_420_618 + REPLAY_WINDOW_SECS + 1
+            ),
+            Err(VerificationError::Expired)
+        );
+    }
+
+    #[test]
+    fn rejects_unsigned_requests() {
+        assert_eq!(
+            verify(SECRET, None, None, BODY.as_bytes(), 1_531_420_618),
+            Err(VerificationError::MissingHeaders)
+        );
+    }
+
+    #[test]
+    fn only_our_countersignatures_are_accepted() {
+        let countersignature = countersign(SECRET, BODY.as_bytes());
+        assert!(countersigned(
+            SECRET,
+            Some(&countersignature),
+            BODY.as_bytes()
+        ));
+        assert!(!countersigned(SECRET, Some(&countersignature), b"text=all"));
+        assert!(!countersigned(SECRET, Some("true"), BODY.as_bytes()));
+        assert!(!countersigned(
+            SECRET,
+            Some(&SIGNATURE["v0=".len()..]),
+            BODY.as_bytes()
+        ));
+        assert!(!countersigned(SECRET, None, BODY.as_bytes()));
+    }
+
+    #[test]
+    fn authorizes_listed_channels_and_users() {
+        let channels = vec!["C1".to_owned()];
+        let users = vec!["U1".to_owned()];
+        assert!(authorized(&[], &[], "C2", "U2"));
+        assert!(authorized(&channels, &users, "C1", "U2"));
+        assert!(authorized(&channels, &users, "C2", "U1"));
+        assert!(!authorized(&channels, &users, "C2", "U2"));
+    }
+}
@@ -0,0 +1,230 @@
+//! Participation statistics
+//!
+//! Summarizes the past month of standup history per person: how often they
+//! checked in, from the participation recorded with each check-in summary,
+//! and how many jira issues they moved along, from the issues each standup
+//! reported.
+
+// Std lib
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+// Third party
+use chrono_tz::Tz;
+use serde_json;
+
+// Ours
+use checkin::Participation;
+use digest::records;
+use history::Entry;
+use render::{Block, Message};
+use section::IssueRecord;
+use users::{Directory, JiraUser};
+
+/// days of history summarized
+pub const DAYS: i64 = 30;
+
+/// renders participation over check-in and standup history, both oldest
+/// first. issues count as closed with any of the `shipped_resolutions`
+pub fn stats(
+    checkins: &[Entry],
+    standups: &[Entry],
+    timezone: Tz,
+    shipped_resolutions: &[String],
+    users: &Directory,
+) -> Message {
+    let text = format!("Participation over the past {} days", DAYS);
+    let mut blocks = vec![Block::header(format!("📊 {}", text))];
+
+    let submissions = submissions(checkins, timezone);
+    blocks.push(Block::section(if submissions.is_empty() {
+        "*Check-ins*\nNo check-ins were recorded".to_owned()
+    } else {
+        format!(
+            "*Check-ins*\n{}",
+            submissions
+                .into_iter()
+                .map(|(user, responded, expected)| format!(
+                    "• <@{}> {}/{} days ({:.0}%)",
+                    user,
+                    responded,
+                    expected,
+                    100.0 * responded as f64 / expected as f64
+                ))
+                .collect::<Vec<_>>()
+                .join("\n")
+        )
+    }));
+
+    let movement = movement(standups, shipped_resolutions);
+    blocks.push(Block::section(if movement.is_empty() {
+        "*Issue movement*\nNo issues moved".to_owned()
+    } else {
+        format!(
+            "*Issue movement*\n{}",
+            movement
+                .into_iter()
+                .map(|(name, moved, closed)| format!(
+                    "• {} {} moved, {} closed",
+                    users.mention(&JiraUser {
+                        name,
+                        email_address: None,
+                    }),
+                    moved,
+                    closed
+                ))
+                .collect::<Vec<_>>()
+                .join("\n")
+        )
+    }));
+
+    Message { text, blocks }
+}
+
+/// days each user checked in and was expected to, best participation first
+fn submissions(entries: &[Entry], timezone: Tz) -> Vec<(String, usize, usize)> {
+    // the last summary of a day has the most check-ins
+    let days = entries
+        .iter()
+        .filter_map(|entry| {
+            entry
+                .sections
+                .get("checkins")
+                .cloned()
+                .and_then(|record| serde_json::from_value::<Participation>(record).ok())
+                .map(|participation| {
+                    (
+                        entry
+                            .posted_at
+                            .with_timezone(&timezone)
+                            .date()
+                            .naive_local(),
+                        participation,
+                    )
+                })
+        })
+        .collect::<BTreeMap<_, _>>();
+    let mut counts = HashMap::new();
+    for participation in days.values() {
+        for user in &participation.responded {
+            let count = counts.entry(user.clone()).or_insert((0, 0));
+            count.0 += 1;
+            count.1 += 1;
+        }
+        for user in &participation.missing {
+            counts.entry(user.clone()).or_insert((0, 0)).1 += 1;
+        }
+    }
+    let mut submissions = counts
+        .into_iter()
+        .map(|(user, (responded, expected))| (user, responded, expected))
+        .collect::<Vec<_>>();
+    submissions.sort_by(
+        |(a, a_responded, a_expected), (b, b_responded, b_expected)| {
+            (b_responded * a_expected)
+                .cmp(&(a_responded * b_expected))
+                .then(a.cmp(b))
+        },
+    );
+    submissions
+}
+
+/// status changes and closures of each assignee's issues between standups, busiest first
+fn movement(entries: &[Entry], shipped_resolutions: &[String]) -> Vec<(String, usize, usize)> {
+    let mut statuses = HashMap::new();
+    let mut closed = HashSet::new();
+    let mut counts = HashMap::new();
+    for issue in entries
+        .iter()
+        .flat_map(|entry| records::<IssueRecord>(entry, "jira", None))
+    {
+        let assignee = match issue.assignee {
+            Some(ref assignee) => assignee.clone(),
+            None => continue,
+        };
+        let moved = statuses
+            .insert(issue.key.clone(), issue.status.clone())
+            .map(|previous| previous != issue.status)
+            .unwrap_or(false);
+        let first_closed = issue.shipped(shipped_resolutions) && closed.insert(issue.key.clone());
+        if moved || first_closed {
+            let count = counts.entry(assignee).or_insert((0, 0));
+            if moved {
+                count.0 += 1;
+            }
+            if first_closed {
+                count.1 += 1;
+            }
+        }
+    }
+    let mut movement = counts
+        .into_iter()
+        .map(|(name, (moved, closed))| (name, moved, closed))
+        .collect::<Vec<_>>();
+    movement.sort_by(|(a, a_moved, a_closed), (b, b_moved, b_closed)| {
+        (b_moved + b_closed)
+            .cmp(&(a_moved + a_closed))
+            .then(a.cmp(b))
+    });
+    movement
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+    use serde_json::Value;
+
+    fn entry(day: u32, section: &str, record: Value) -> Entry {
+        let mut sections = BTreeMap::new();
+        sections.insert(section.to_owned(), record);
+        Entry {
+            channel: "C1".into(),
+            posted_at: Utc.ymd(2019, 1, day).and_hms(15, 0, 0),
+            sections,
+            message: Value::Null,
+            ts: None,
+            permalink: None,
+            command: None,
+        }
+    }
+
+    #[test]
+    fn submissions_count_the_last_summary_of_each_day() {
+        let entries = vec![
+            entry(
+                7,
+                "checkins",
+                json!({ "responded": [], "missing": ["U1", "U2"] }),
+            ),
+            entry(
+                7,
+                "checkins",
+                json!({ "responded": ["U1"], "missing": ["U2"] }),
+            ),
+            entry(
+                8,
+                "checkins",
+                json!({ "responded": ["U1", "U2"], "missing": [] }),
+            ),
+        ];
+        assert_eq!(
+            submissions(&entries, Tz::UTC),
+            vec![("U1".into(), 2, 2), ("U2".into(), 1, 2)]
+        );
+    }
+
+    #[test]
+    fn movement_counts_status_changes_and_closures() {
+        let issue = |status: &str| json!([{ "key": "CORE-1", "summary": null, "status": status, "assignee": "dana" }]);
+        let entries = vec![
+            entry(7, "jira", issue("To Do")),
+            entry(8, "jira", issue("In Progress")),
+            entry(9, "jira", issue("Closed")),
+            entry(10, "jira", issue("Closed")),
+        ];
+        assert_eq!(
+            movement(&entries, &["Done".to_owned()]),
+            vec![("dana".into(), 2, 1)]
+        );
+    }
+}
@@ -0,0 +1,279 @@
+//! Overridable message wording
+//!
+//! Lines of the standup are rendered from handlebars templates. Teams may
+//! override any of the defaults below, and section titles as `{section}.title`,
+//! with a json object of template names to templates, either inline in config
+//! or as a document fetched from S3 or over https.
+
+// Std lib
+use std::collections::HashMap;
+use std::io::Read;
+
+// Third party
+use handlebars::{no_escape, Handlebars};
+use rusoto_core::Region;
+use rusoto_s3::{GetObjectRequest, S3Client, S3};
+use serde_json::{self, Value};
+
+// Ours
+use config::Config;
+use log;
+
+const DEFAULTS: &[(&str, &str)] = &[
+    ("jira.status", "{{emoji}} *{{status}}*"),
+    (
+        "jira.issue",
+        "{{#if icons}}{{icons}} {{/if}}<{{url}}|{{key}}> {{summary}}",
+    ),
+    ("jira.assignee", "🙋 *{{assignee}}*"),
+    ("jira.assignee.status", "{{emoji}} {{status}}"),
+    (
+        "jira.subtask",
+        "    ↳ {{emoji}} <{{url}}|{{key}}> {{summary}}",
+    ),
+    (
+        "jira.epic",
+        "🏔 *<{{url}}|{{name}}>*{{#if total}} · {{done}}/{{total}} done{{/if}}",
+    ),
+    ("jira.epic.none", "🏔 *No epic*"),
+    (
+        "jira.wip",
+        "⚠️ *WIP limit exceeded* {{emoji}} {{status}} has {{count}} issues, the limit is {{limit}}",
+    ),
+    ("jira.closed.collapsed", "✅ {{count}} closed"),
+    ("jira.closed.empty", "Nothing shipped since the last standup"),
+    ("jira.empty", "Nothing in flight"),
+    ("jira.due.heading", "📅 *Due this week*"),
+    ("jira.more", "…and {{count}} more <{{url}}|view in Jira>"),
+    (
+        "jira.truncated",
+        "Showing {{count}} of {{total}} {{kind}} issues <{{url}}|see all in Jira>",
+    ),
+    ("jira.dropped.heading", "👋 *No longer in flight*"),
+    (
+        "jira.dropped",
+        "<{{url}}|{{key}}> {{summary}} (was {{status}})",
+    ),
+    ("blocked.issue", "<{{url}}|{{key}}> {{summary}}"),
+    ("blocked.owner", "{{assignee}} · {{status}}"),
+    ("blocked.empty", "Nothing is blocked"),
+    ("weather.oncall", "👮 *On call today* {{oncall}}"),
+    (
+        "weather.incident",
+        "<{{url}}|#{{number}}> {{#if tags}}[{{tags}}] {{/if}}{{title}}",
+    ),
+    ("weather.incident.status", "_{{status}}_"),
+    ("weather.empty", "☀️ No incidents — clear skies!"),
+    ("weather.resolved.heading", "✅ *Resolved*"),
+    ("weather.resolved.collapsed", "✅ {{count}} resolved"),
+    (
+        "weather.analytics",
+        "📈 *Past week* {{incidents}} incidents{{#if incidents_change}} ({{incidents_change}}){{/if}} · MTTA {{mtta}}{{#if mtta_change}} ({{mtta_change}}){{/if}} · MTTR {{mttr}}{{#if mttr_change}} ({{mttr_change}}){{/if}}",
+    ),
+    ("github.awaiting_review.heading", "👀 *Awaiting review*"),
+    ("github.merged.heading", "🚢 *Merged*"),
+    ("github.empty", "No pull requests awaiting review or merged"),
+    (
+        "github.pull",
+        "<{{url}}|{{repository}}#{{number}}> {{title}}",
+    ),
+    (
+        "ci.repo",
+        "{{#if passing}}✅{{else}}🔴{{/if}} *{{repository}}* `{{branch}}` {{#if passing}}passing{{else}}failing {{failing}}{{/if}}",
+    ),
+    ("ci.red.heading", "🚨 *Red for more than {{hours}}h*"),
+    ("ci.red", "<{{url}}|{{repository}} {{workflow}}> red for {{hours}}h"),
+    (
+        "deploys.deploy",
+        "{{#if url}}<{{url}}|{{service}} {{version}}>{{else}}{{service}} {{version}}{{/if}}{{#if deployer}} by {{deployer}}{{/if}}",
+    ),
+    ("deploys.deployed_at", "_{{time}}_"),
+    ("deploys.empty", "Nothing deployed since the last standup"),
+    ("today.out", "🌴 *Out today* {{out}}"),
+    ("today.event", "{{time}} <{{url}}|{{summary}}>"),
+    ("today.empty", "Nothing on the calendar today"),
+    ("worklog.empty", "No time was logged"),
+    (
+        "release.heading",
+        "*{{version}}*{{#if released}} · released{{else}}{{#if date}} · {{date}} · {{days_left}}{{/if}}{{/if}}",
+    ),
+    (
+        "release.progress",
+        "{{done}} of {{total}} done ({{percent}}%) · {{remaining}} remaining",
+    ),
+    ("release.issue", "<{{url}}|{{key}}> {{summary}} _{{status}}_"),
+    ("github.author", "@{{login}}"),
+];
+
+const S3_PREFIX: &str = "s3://";
+
+pub struct Templates {
+    registry: Handlebars,
+}
+
+impl Templates {
+    /// the default templates with the given overrides
+    pub fn new(overrides: &HashMap<String, String>) -> Self {
+        let mut registry = Handlebars::new();
+        // this is slack mrkdwn, not html
+        registry.register_escape_fn(no_escape);
+        for (name, template) in DEFAULTS {
+            if let Err(err) = registry.register_template_string(name, template) {
+                log::error(
+                    "invalid default template",
+                    json!({ "template": name, "error": err.to_string() }),
+                );
+            }
+        }
+        for (name, template) in overrides {
+            if let Err(err) = registry.register_template_string(name, template) {
+                log::warn(
+                    "skipping invalid template",
+                    json!({ "template": name, "error": err.to_string() }),
+                );
+            }
+        }
+        Templates { registry }
+    }
+
+    /// the default templates with those of `template_url` and `template_overrides` applied, in that order
+    pub fn load(config: &Config) -> Self {
+        let mut overrides = HashMap::new();
+        let sources = config
+            .template_url
+            .as_ref()
+            .map(|url| fetch(url, config))
+            .into_iter()
+            .chain(
+                config
+                    .template_overrides
+                    .as_ref()
+                    .map(|overrides| Ok(overrides.clone())),
+            );
+        for source in sources {
+            match source.and_then(|json| {
+                serde_json::from_str::<HashMap<String, String>>(&json)
+                    .map_err(|err| err.to_string())
+            }) {
+                Ok(templates) => overrides.extend(templates),
+                Err(err) => log::error("failed to load templates", json!({ "error": err })),
+            }
+        }
+        Templates::new(&overrides)
+    }
+
+    pub fn render(&self, name: &str, data: &Value) -> String {
+        self.registry.render(name, data).unwrap_or_else(|err| {
+            log::error(
+                "failed to render template",
+                json!({ "template": name, "error": err.to_string() }),
+            );
+            String::new()
+        })
+    }
+
+    /// a section's title, unless overridden
+    pub fn title(&self, section: &str, default: String) -> String {
+        let name = format!("{}.title", section);
+        if self.registry.get_template(&name).is_some() {
+            self.render(&name, &json!({}))
+        } else {
+            default
+        }
+    }
+}
+
+/// fetches a template document from S3 or over http
+fn fetch(url: &str, config: &Config) -> Result<String, String> {
+    if url.starts_with(S3_PREFIX) {
+        let mut location = url[S3_PREFIX.len()..].splitn(2, '/');
+        let (bucket, key) = match (location.next(), location.next()) {
+            (Some(bucket), Some(key)) => (bucket.to_owned(), key.to_owned()),
+            _ => return Err(format!("invalid S3 location {}", url)),
+        };
+        let mut body = String::new();
+        S3Client::new(Region::default())
+            .get_object(GetObjectRequest {
+                bucket,
+                key,
+                ..Default::default()
+            })
+            .sync()
+            .map_err(|err| format!("failed to fetch templates from {}: {}", url, err))?
+            .body
+            .ok_or_else(|| format!("no templates at {}", url))?
+            .into_blocking_read()
+            .read_to_string(&mut body)
+            .map_err(|err| err.to_string())?;
+        Ok(body)
+    } else {
+        config
+            .http_client()
+            .get(url)
+            .send()
+            .and_then(|response| response.error_for_status())
+            .and_then(|mut response| response.text())
+            .map_err(|err| format!("failed to fetch templates from {}: {}", url, err))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overrides_replace_defaults() {
+        let mut overrides = HashMap::new();
+        overrides.insert("blocked.empty".to_owned(), "All clear 🎉".to_owned());
+        let templates = Templates::new(&overrides);
+        assert_eq!(
+            templates.render("blocked.empty", &json!({})),
+            "All clear 🎉"
+        );
+        assert_eq!(
+            templates.render("github.author", &json!({ "login": "octocat" })),
+            "@octocat"
+        );
+    }
+
+    #[test]
+    fn empty_states_have_defaults() {
+        let templates = Templates::new(&HashMap::new());
+        assert_eq!(
+            templates.render("weather.empty", &json!({})),
+            "☀️ No incidents — clear skies!"
+        );
+        assert_eq!(
+            templates.render("jira.closed.empty", &json!({})),
+            "Nothing shipped since the last standup"
+        );
+    }
+
+    #[test]
+    fn does_not_escape_markup() {
+        let templates = Templates::new(&HashMap::new());
+        assert_eq!(
+            templates.render(
+                "jira.issue",
+                &json!({ "url": "https://jira/browse/A-1", "key": "A-1", "summary": "<b> & co" })
+            ),
+            "<https://jira/browse/A-1|A-1> <b> & co"
+        );
+        assert_eq!(
+            templates.render(
+                "jira.issue",
+                &json!({ "url": "https://jira/browse/A-1", "key": "A-1", "summary": "fix", "icons": "🔴🐞" })
+            ),
+            "🔴🐞 <https://jira/browse/A-1|A-1> fix"
+        );
+    }
+
+    #[test]
+    fn titles_default_unless_overridden() {
+        let mut overrides = HashMap::new();
+        overrides.insert("jira.title".to_owned(), "In flight".to_owned());
+        let templates = Templates::new(&overrides);
+        assert_eq!(templates.title("jira", "Jira".into()), "In flight");
+        assert_eq!(templates.title("github", "GitHub".into()), "GitHub");
+    }
+}
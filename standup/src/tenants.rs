@@ -0,0 +1,85 @@
+//! Per channel configuration persisted to DynamoDB
+//!
+//! Channels may override any of the deployment's environment configuration,
+//! so one deployment can serve many teams. Overrides are stored as a json
+//! document of env variable names to values, keyed by slack team and channel.
+
+// Std lib
+use std::collections::{BTreeMap, HashMap};
+
+// Third party
+use rusoto_core::Region;
+use rusoto_dynamodb::{AttributeValue, DynamoDb, DynamoDbClient, GetItemInput, PutItemInput};
+use serde_json;
+
+// Ours
+use dynamodb::string;
+
+/// env variable names to values
+pub type Overrides = BTreeMap<String, String>;
+
+pub struct Tenants {
+    table: String,
+    client: DynamoDbClient,
+}
+
+impl Tenants {
+    pub fn new(table: String) -> Self {
+        Tenants {
+            table,
+            client: DynamoDbClient::new(Region::default()),
+        }
+    }
+
+    /// a channel's overrides, empty if it has none
+    pub fn overrides(&self, team_id: &str, channel_id: &str) -> Result<Overrides, String> {
+        let output = self
+            .client
+            .get_item(GetItemInput {
+                table_name: self.table.clone(),
+                key: key(team_id, channel_id),
+                ..Default::default()
+            })
+            .sync()
+            .map_err(|err| format!("failed to load channel config: {}", err))?;
+        match output
+            .item
+            .as_ref()
+            .and_then(|item| item.get("document"))
+            .and_then(|document| document.s.as_ref())
+        {
+            Some(document) => serde_json::from_str(document)
+                .map_err(|err| format!("invalid channel config: {}", err)),
+            None => Ok(Overrides::new()),
+        }
+    }
+
+    pub fn save(
+        &self,
+        team_id: &str,
+        channel_id: &str,
+        overrides: &Overrides,
+    ) -> Result<(), String> {
+        let mut item = key(team_id, channel_id);
+        item.insert(
+            "document".into(),
+            string(serde_json::to_string(overrides).map_err(|err| err.to_string())?),
+        );
+        self.client
+            .put_item(PutItemInput {
+                table_name: self.table.clone(),
+                item,
+                ..Default::default()
+            })
+            .sync()
+            .map(|_| ())
+            .map_err(|err| format!("failed to save channel config: {}", err))
+    }
+}
+
+fn key(team_id: &str, channel_id: &str) -> HashMap<String, AttributeValue> {
+    let mut key = HashMap::new();
+    key.insert("team_id".into(), string(team_id.to_owned()));
+    key.insert("channel_id".into(), string(channel_id.to_owned()));
+    key
+}
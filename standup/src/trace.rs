@@ -0,0 +1,278 @@
+//! X-Ray tracing of sections and upstream requests
+//!
+//! With active tracing, lambda traces each invocation and hands us its trace
+//! header. Sections and the upstream requests they make are then timed as
+//! subsegments of the invocation, sent to the X-Ray daemon over udp, so
+//! traces show which upstream a slow standup waited on. Without a sampled
+//! trace header, nothing is traced.
+//!
+//! The trace is kept per thread, like log correlation, so requests handled
+//! concurrently are traced apart. Threads an invocation spawns carry both on.
+//!
+//! see https://docs.aws.amazon.com/xray/latest/devguide/xray-api-sendingdata.html
+
+// Std lib
+use std::cell::RefCell;
+use std::env;
+use std::net::UdpSocket;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread::{self, JoinHandle};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Third party
+use hex;
+use reqwest;
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+
+// Ours
+use log;
+
+/// prefixes every document sent to the daemon
+const DAEMON_HEADER: &str = r#"{"format": "json", "version": 1}"#;
+
+const DEFAULT_DAEMON_ADDRESS: &str = "127.0.0.1:2000";
+
+thread_local! {
+    /// the trace of the invocation this thread is working on, if it's sampled
+    static TRACE: RefCell<Option<Trace>> = RefCell::new(None);
+
+    /// the innermost span of this thread, which new spans are nested in
+    static CURRENT: RefCell<Option<String>> = RefCell::new(None);
+}
+
+/// distinguishes ids generated within the same nanosecond
+static GENERATED: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug, Clone, PartialEq)]
+struct Trace {
+    root: String,
+    /// the invocation's segment
+    parent: String,
+}
+
+/// traces the current invocation under an X-Ray trace header, i.e.
+/// `Root=1-5759e988-bd862e3fe1be46a994272793;Parent=53995c3f42cd8ad8;Sampled=1`
+pub fn start(header: Option<&str>) {
+    TRACE.with(|trace| *trace.borrow_mut() = header.and_then(parse));
+}
+
+/// spawns a thread which carries on this one's trace, innermost span and
+/// log correlation
+pub fn spawn<T, F>(f: F) -> JoinHandle<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    let trace = TRACE.with(|trace| trace.borrow().clone());
+    let current = CURRENT.with(|current| current.borrow().clone());
+    let correlation = log::correlation();
+    thread::spawn(move || {
+        TRACE.with(|carried| *carried.borrow_mut() = trace);
+        CURRENT.with(|carried| *carried.borrow_mut() = current);
+        log::correlate(correlation);
+        f()
+    })
+}
+
+/// calls `f` within a span named `name`, which `describe` may add fields to
+/// once `f` returns, i.e. `error`
+pub fn span<T, F, D>(name: &str, f: F, describe: D) -> T
+where
+    F: FnOnce() -> T,
+    D: FnOnce(&T, &mut Map<String, Value>),
+{
+    let trace = match TRACE.with(|trace| trace.borrow().clone()) {
+        Some(trace) => trace,
+        None => return f(),
+    };
+    let id = generate_id();
+    let previous = CURRENT.with(|current| current.replace(Some(id.clone())));
+    let parent = previous.clone().unwrap_or_else(|| trace.parent.clone());
+    let start_time = epoch_secs();
+    let result = f();
+    let end_time = epoch_secs();
+    CURRENT.with(|current| *current.borrow_mut() = previous);
+    let mut segment = Map::new();
+    segment.insert("name".into(), json!(name));
+    segment.insert("id".into(), json!(id));
+    segment.insert("trace_id".into(), json!(trace.root));
+    segment.insert("parent_id".into(), json!(parent));
+    segment.insert("type".into(), json!("subsegment"));
+    segment.insert("start_time".into(), json!(start_time));
+    segment.insert("end_time".into(), json!(end_time));
+    describe(&result, &mut segment);
+    send(&Value::Object(segment));
+    result
+}
+
+/// calls `f` within a span for a request to `path` of an upstream
+pub fn request<T, F>(upstream: &str, path: &str, f: F) -> Result<T, reqwest::Error>
+where
+    F: FnOnce() -> Result<T, reqwest::Error>,
+{
+    span(upstream, f, |result, segment| {
+        segment.insert("namespace".into(), json!("remote"));
+        let status = match *result {
+            Ok(_) => None,
+            Err(ref err) => err.status().map(|status| status.as_u16()),
+        };
+        segment.insert(
+            "http".into(),
+            json!({
+                "request": { "url": path },
+                "response": { "status": status },
+            }),
+        );
+        match (result, status) {
+            (&Ok(_), _) => (),
+            (&Err(_), Some(status)) if status < 500 => {
+                segment.insert("error".into(), json!(true));
+            }
+            (&Err(_), _) => {
+                segment.insert("fault".into(), json!(true));
+            }
+        }
+    })
+}
+
+fn parse(header: &str) -> Option<Trace> {
+    let field = |name: &str| {
+        header
+            .split(';')
+            .filter_map(|field| {
+                let mut parts = field.trim().splitn(2, '=');
+                match (parts.next(), parts.next()) {
+                    (Some(key), Some(value)) if key == name => Some(value.to_owned()),
+                    _ => None,
+                }
+            })
+            .next()
+    };
+    if field("Sampled").as_ref().map(String::as_str) != Some("1") {
+        return None;
+    }
+    Some(Trace {
+        root: field("Root")?,
+        parent: field("Parent")?,
+    })
+}
+
+/// a 64 bit id, as 16 hex digits
+fn generate_id() -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|now| now.as_secs() * 1_000_000_000 + u64::from(now.subsec_nanos()))
+        .unwrap_or_default();
+    let seed = format!(
+        "{}:{}:{:?}",
+        nanos,
+        GENERATED.fetch_add(1, Ordering::SeqCst),
+        thread::current().id()
+    );
+    hex::encode(&Sha256::digest(seed.as_bytes())[..8])
+}
+
+fn epoch_secs() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|now| now.as_secs() as f64 + f64::from(now.subsec_nanos()) / 1e9)
+        .unwrap_or_default()
+}
+
+/// the daemon's udp address, from `AWS_XRAY_DAEMON_ADDRESS`, which is either
+/// `host:port` or `tcp:host:port udp:host:port`
+fn daemon_address(configured: Option<&str>) -> String {
+    match configured {
+        Some(configured) => configured
+            .split_whitespace()
+            .find(|address| address.starts_with("udp:"))
+            .map(|address| address.trim_start_matches("udp:"))
+            .unwrap_or(configured)
+            .to_owned(),
+        None => DEFAULT_DAEMON_ADDRESS.into(),
+    }
+}
+
+fn send(segment: &Value) {
+    let address = daemon_address(
+        env::var("AWS_XRAY_DAEMON_ADDRESS")
+            .ok()
+            .as_ref()
+            .map(String::as_str),
+    );
+    let sent = UdpSocket::bind("0.0.0.0:0").and_then(|socket| {
+        socket.send_to(
+            format!("{}\n{}", DAEMON_HEADER, segment).as_bytes(),
+            address.as_str(),
+        )
+    });
+    if let Err(err) = sent {
+        log::warn(
+            "failed to send trace segment",
+            json!({ "error": err.to_string() }),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sampled_trace_headers_are_traced() {
+        assert_eq!(
+            parse("Root=1-5759e988-bd862e3fe1be46a994272793;Parent=53995c3f42cd8ad8;Sampled=1"),
+            Some(Trace {
+                root: "1-5759e988-bd862e3fe1be46a994272793".into(),
+                parent: "53995c3f42cd8ad8".into(),
+            })
+        );
+        assert_eq!(
+            parse("Root=1-5759e988-bd862e3fe1be46a994272793;Parent=53995c3f42cd8ad8;Sampled=0"),
+            None
+        );
+        assert_eq!(parse("Root=1-5759e988-bd862e3fe1be46a994272793"), None);
+    }
+
+    #[test]
+    fn spawned_threads_carry_on_the_trace() {
+        start(Some(
+            "Root=1-5759e988-bd862e3fe1be46a994272793;Parent=53995c3f42cd8ad8;Sampled=1",
+        ));
+        log::correlate(json!({ "request_id": "r1" }));
+        let carried = spawn(|| {
+            (
+                TRACE.with(|trace| trace.borrow().clone()),
+                log::correlation(),
+            )
+        })
+        .join()
+        .unwrap();
+        assert_eq!(
+            carried,
+            (
+                TRACE.with(|trace| trace.borrow().clone()),
+                json!({ "request_id": "r1" })
+            )
+        );
+        assert!(
+            thread::spawn(|| TRACE.with(|trace| trace.borrow().is_none()))
+                .join()
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn daemon_addresses_prefer_udp() {
+        assert_eq!(daemon_address(None), "127.0.0.1:2000");
+        assert_eq!(
+            daemon_address(Some("169.254.79.129:2000")),
+            "169.254.79.129:2000"
+        );
+        assert_eq!(
+            daemon_address(Some("tcp:169.254.79.129:2000 udp:169.254.79.129:2001")),
+            "169.254.79.129:2001"
+        );
+    }
+}
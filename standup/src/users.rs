@@ -0,0 +1,214 @@
+//! Jira to Slack user resolution
+
+// Std lib
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+// Third party
+use reqwest::header::AUTHORIZATION;
+use reqwest::Client;
+
+// Ours
+use config;
+
+const API: &str = "https://slack.com/api";
+
+/// The subset of a jira user we need to find them in slack
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct JiraUser {
+    pub name: String,
+    pub email_address: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Lookup {
+    ok: bool,
+    user: Option<SlackUser>,
+}
+
+#[derive(Deserialize, Debug)]
+struct SlackUser {
+    id: String,
+    #[serde(default)]
+    profile: Profile,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct Profile {
+    #[serde(default)]
+    display_name: String,
+    #[serde(default)]
+    real_name: String,
+}
+
+/// Resolves jira users to slack mentions
+///
+/// Static overrides, keyed by jira name or email, take precedence over
+/// looking users up by email with a slack bot token. Users we can't
+/// resolve fall back on their jira name.
+pub struct Directory {
+    api: String,
+    token: Option<String>,
+    client: Client,
+    overrides: HashMap<String, String>,
+    lookups: Mutex<HashMap<String, Option<String>>>,
+}
+
+impl Directory {
+    /// `overrides` are `jira name or email=slack user id` pairs
+    pub fn new(token: Option<String>, overrides: &[String], client: Client) -> Self {
+        Directory {
+            api: API.into(),
+            token,
+            client,
+            overrides: config::pairs(overrides),
+            lookups: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// points lookups at another api host, i.e. a mock server
+    pub fn with_api(self, api: String) -> Self {
+        Directory { api, ..self }
+    }
+
+    /// returns a slack mention for a jira user
+    pub fn mention(&self, user: &JiraUser) -> String {
+        self.overrides
+            .get(&user.name)
+            .cloned()
+            .or_else(|| user.email_address.as_ref().and_then(|email| self.id(email)))
+            .map(|id| format!("<@{}>", id))
+            .unwrap_or_else(|| format!("@{}", user.name))
+    }
+
+    /// the slack user id of the user with an email address
+    pub fn id(&self, email: &str) -> Option<String> {
+        self.overrides
+            .get(email)
+            .cloned()
+            .or_else(|| self.lookup(email))
+    }
+
+    /// a slack user's display name, for mentioning someone without notifying them
+    ///
+    /// users we can't look up fall back on a mention
+    pub fn name(&self, id: &str) -> String {
+        self.token
+            .as_ref()
+            .and_then(|token| {
+                self.client
+                    .get(&format!("{}/users.info", self.api))
+                    .query(&[("user", id)])
+                    .header(AUTHORIZATION, format!("Bearer {}", token))
+                    .send()
+                    .and_then(|mut response| response.json::<Lookup>())
+                    .ok()
+            })
+            .and_then(|lookup| if lookup.ok { lookup.user } else { None })
+            .map(|user| {
+                if user.profile.display_name.is_empty() {
+                    user.profile.real_name
+                } else {
+                    user.profile.display_name
+                }
+            })
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| format!("<@{}>", id))
+    }
+
+    fn lookup(&self, email: &str) -> Option<String> {
+        let token = self.token.as_ref()?;
+        let cached = self
+            .lookups
+            .lock()
+            .ok()
+            .and_then(|lookups| lookups.get(email).cloned());
+        if let Some(cached) = cached {
+            return cached;
+        }
+        let id = self
+            .client
+            .get(&format!("{}/users.lookupByEmail", self.api))
+            .query(&[("email", email)])
+            .header(AUTHORIZATION, format!("Bearer {}", token))
+            .send()
+            .and_then(|mut response| response.json::<Lookup>())
+            .ok()
+            .and_then(|lookup| if lookup.ok { lookup.user } else { None })
+            .map(|user| user.id);
+        if let Ok(mut lookups) = self.lookups.lock() {
+            lookups.insert(email.to_owned(), id.clone());
+        }
+        id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::{self, mock, Matcher};
+
+    fn user(name: &str, email: Option<&str>) -> JiraUser {
+        JiraUser {
+            name: name.into(),
+            email_address: email.map(String::from),
+        }
+    }
+
+    #[test]
+    fn overrides_take_precedence() {
+        let directory = Directory::new(
+            None,
+            &["ada=U1".into(), "grace@example.com=U2".into()],
+            Client::new(),
+        );
+        assert_eq!(directory.mention(&user("ada", None)), "<@U1>");
+        assert_eq!(
+            directory.mention(&user("grace", Some("grace@example.com"))),
+            "<@U2>"
+        );
+    }
+
+    #[test]
+    fn unresolved_users_fall_back_on_their_jira_name() {
+        let directory = Directory::new(None, &[], Client::new());
+        assert_eq!(
+            directory.mention(&user("ada", Some("ada@example.com"))),
+            "@ada"
+        );
+    }
+
+    #[test]
+    fn users_are_looked_up_by_email_once() {
+        let lookup = mock("GET", "/users.lookupByEmail")
+            .match_query(Matcher::UrlEncoded(
+                "email".into(),
+                "linus@example.com".into(),
+            ))
+            .match_header("authorization", "Bearer xoxb")
+            .with_body(r#"{"ok":true,"user":{"id":"U3"}}"#)
+            .expect(1)
+            .create();
+        let directory =
+            Directory::new(Some("xoxb".into()), &[], Client::new()).with_api(mockito::server_url());
+        let linus = user("linus", Some("linus@example.com"));
+        assert_eq!(directory.mention(&linus), "<@U3>");
+        assert_eq!(directory.mention(&linus), "<@U3>");
+        lookup.assert();
+    }
+
+    #[test]
+    fn names_prefer_display_names() {
+        let _info = mock("GET", "/users.info")
+            .match_query(Matcher::UrlEncoded("user".into(), "U4".into()))
+            .with_body(
+                r#"{"ok":true,"user":{"id":"U4","profile":{"display_name":"Dana","real_name":"Dana Lee"}}}"#,
+            )
+            .create();
+        let directory =
+            Directory::new(Some("xoxb".into()), &[], Client::new()).with_api(mockito::server_url());
+        assert_eq!(directory.name("U4"), "Dana");
+        assert_eq!(Directory::new(None, &[], Client::new()).name("U5"), "<@U5>");
+    }
+}
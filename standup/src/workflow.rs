@@ -0,0 +1,208 @@
+//! Jira workflow statuses
+//!
+//! Every status belongs to one of jira's three status categories, `To Do`,
+//! `In Progress` and `Done`. Discovering a project's statuses along with
+//! their categories lets custom workflows be ordered and marked sensibly
+//! without configuring each status by name.
+//!
+//! Statuses are discovered per project, by project key, i.e. `CORE`.
+
+// Third party
+use serde_json;
+
+// Ours
+use error::FetchError;
+use jira_auth::Site;
+use retry::Retry;
+
+/// Statuses an issue type moves through
+#[derive(Deserialize, Debug)]
+struct IssueTypeStatuses {
+    #[serde(default)]
+    statuses: Vec<Status>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct Status {
+    name: String,
+    status_category: StatusCategory,
+}
+
+#[derive(Deserialize, Debug)]
+struct StatusCategory {
+    /// `new`, `indeterminate` or `done`
+    key: String,
+}
+
+/// A status category, in workflow order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Category {
+    ToDo,
+    InProgress,
+    Done,
+}
+
+impl Category {
+    fn from_key(key: &str) -> Option<Category> {
+        match key {
+            "new" => Some(Category::ToDo),
+            "indeterminate" => Some(Category::InProgress),
+            "done" => Some(Category::Done),
+            _ => None,
+        }
+    }
+
+    /// what statuses in the category are marked with, unless configured otherwise
+    pub fn emoji(self) -> &'static str {
+        match self {
+            Category::ToDo => "📋",
+            Category::InProgress => "👩🏻‍💻",
+            Category::Done => "🎉",
+        }
+    }
+}
+
+/// Statuses of projects' workflows, ordered by category and then by
+/// where they first appear in them
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Workflow {
+    statuses: Vec<(String, Category)>,
+}
+
+impl Workflow {
+    fn new(mut statuses: Vec<(String, Category)>) -> Self {
+        let mut seen = Vec::<String>::new();
+        statuses.retain(|(name, _)| {
+            let lower = name.to_lowercase();
+            if seen.contains(&lower) {
+                false
+            } else {
+                seen.push(lower);
+                true
+            }
+        });
+        // a stable sort, so statuses keep their order within a category
+        statuses.sort_by_key(|(_, category)| *category);
+        Workflow { statuses }
+    }
+
+    pub fn category(&self, status: &str) -> Option<Category> {
+        self.statuses
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(status))
+            .map(|(_, category)| *category)
+    }
+
+    /// whether a status is in the done category. without a discovered
+    /// workflow, only `Closed` is
+    pub fn is_done(&self, status: &str) -> bool {
+        if self.statuses.is_empty() {
+            return status == "Closed";
+        }
+        self.category(status) == Some(Category::Done)
+    }
+
+    /// statuses in a category, in workflow order
+    pub fn statuses(&self, category: Category) -> Vec<String> {
+        self.statuses
+            .iter()
+            .filter(|(_, of)| *of == category)
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+
+    /// where a status falls in the workflow
+    pub fn position(&self, status: &str) -> Option<usize> {
+        self.statuses
+            .iter()
+            .position(|(name, _)| name.eq_ignore_ascii_case(status))
+    }
+}
+
+/// the statuses of projects' workflows, across their issue types, by project key
+pub fn discover(site: &Site, retry: Retry, projects: &[String]) -> Result<Workflow, FetchError> {
+    let mut statuses = Vec::new();
+    for project in projects {
+        let path = format!("/rest/api/2/project/{}/statuses", segment(project));
+        let issue_types = site.get_json(&path, &[], retry).and_then(|issue_types| {
+            serde_json::from_value::<Vec<IssueTypeStatuses>>(issue_types)
+                .map_err(|err| FetchError::new("Jira", err.to_string()))
+        })?;
+        statuses.extend(
+            issue_types
+                .into_iter()
+                .flat_map(|issue_type| issue_type.statuses)
+                .filter_map(|status| {
+                    Category::from_key(&status.status_category.key)
+                        .map(|category| (status.name, category))
+                }),
+        );
+    }
+    Ok(Workflow::new(statuses))
+}
+
+/// a percent encoded path segment, so a project named rather than keyed
+/// can't change the path
+fn segment(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jira_auth::tests::{site, RETRY};
+    use mockito::mock;
+
+    #[test]
+    fn statuses_are_ordered_by_category_then_workflow() {
+        let _mock = mock("GET", "/rest/api/2/project/CORE/statuses")
+            .with_body(
+                r#"[
+                    {"name":"Bug","statuses":[
+                        {"name":"Backlog","statusCategory":{"key":"new"}},
+                        {"name":"Shipped","statusCategory":{"key":"done"}},
+                        {"name":"Doing","statusCategory":{"key":"indeterminate"}}
+                    ]},
+                    {"name":"Story","statuses":[
+                        {"name":"Doing","statusCategory":{"key":"indeterminate"}},
+                        {"name":"QA","statusCategory":{"key":"indeterminate"}}
+                    ]}
+                ]"#,
+            )
+            .create();
+        let workflow = discover(&site(), RETRY, &["CORE".into()]).unwrap();
+        assert_eq!(workflow.position("Backlog"), Some(0));
+        assert_eq!(workflow.position("doing"), Some(1));
+        assert_eq!(workflow.position("QA"), Some(2));
+        assert_eq!(workflow.position("Shipped"), Some(3));
+        assert_eq!(workflow.category("Shipped"), Some(Category::Done));
+        assert_eq!(workflow.category("Closed"), None);
+        assert!(workflow.is_done("shipped"));
+        assert!(!workflow.is_done("Closed"));
+        assert!(Workflow::default().is_done("Closed"));
+        assert_eq!(
+            workflow.statuses(Category::InProgress),
+            vec!["Doing".to_owned(), "QA".to_owned()]
+        );
+    }
+
+    #[test]
+    fn projects_are_encoded_into_the_path() {
+        let _mock = mock("GET", "/rest/api/2/project/Core%20Services%2FAPI/statuses")
+            .with_body("[]")
+            .create();
+        assert_eq!(
+            discover(&site(), RETRY, &["Core Services/API".into()]).unwrap(),
+            Workflow::default()
+        );
+    }
+}